@@ -1,21 +1,169 @@
-use clap::{value_parser, ArgAction, Args, Parser};
+use clap::{Args, Parser, Subcommand};
 use shared::cli::Logging;
+use std::net::IpAddr;
 use std::path::PathBuf;
 
-use shared::logging::LevelFilter;
+use shared::logging::LogOptions;
+
+use crate::config::{Config, ConfigError};
+use crate::store::StoreKind;
 
 /// The command line args for the server
 #[derive(Debug, Parser)]
 pub struct AppArgs {
     #[clap(flatten)]
     logging: Logging,
+    /// A TOML file to read the configuration from
+    #[clap(long, env = "EVENTIDE_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    #[clap(flatten)]
+    overrides: Overrides,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Values overriding the ones from the config file, from either the environment or a flag
+#[derive(Debug, Default, Args)]
+struct Overrides {
+    /// The address to listen on
+    #[clap(long, env = "EVENTIDE_ADDRESS", global = true)]
+    address: Option<IpAddr>,
+    /// The port to listen on
+    #[clap(long, env = "EVENTIDE_PORT", global = true)]
+    port: Option<u16>,
     /// Extra paths to static content to serve
-    #[clap(long = "static", default_value = "static")]
-    pub static_content: PathBuf,
+    #[clap(long = "static", env = "EVENTIDE_STATIC", global = true)]
+    static_content: Option<PathBuf>,
+    /// Where started games are stored, so they survive a restart
+    #[clap(long, value_enum, env = "EVENTIDE_STORE", global = true)]
+    store: Option<StoreKind>,
+    /// The directory stored games are kept in
+    #[clap(long, env = "EVENTIDE_DATA_DIR", global = true)]
+    data_dir: Option<PathBuf>,
+    /// How many seconds a disconnected player may take to resume their session
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        env = "EVENTIDE_GRACE_PERIOD",
+        global = true
+    )]
+    grace_period: Option<u64>,
+    /// How many games may be waiting or in progress at once
+    #[clap(long, env = "EVENTIDE_MAX_GAMES", global = true)]
+    max_games: Option<usize>,
+    /// The most players a game may be created for
+    #[clap(long, env = "EVENTIDE_MAX_PLAYERS", global = true)]
+    max_players: Option<u8>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Inspects the configuration
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Prints the effective configuration, after merging the file, environment and flags
+    Print,
 }
 
 impl AppArgs {
-    pub fn level_filter(&self) -> LevelFilter {
-        self.logging.level_filter()
+    pub fn log_options(&self) -> LogOptions {
+        self.logging.options()
+    }
+
+    /// Builds the effective configuration from the config file, the environment and the flags
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        let mut config = match &self.config {
+            Some(path) => Config::read(path)?,
+            None => Config::default(),
+        };
+        self.overrides.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl Overrides {
+    fn apply(&self, config: &mut Config) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
+        }
+
+        set(&mut config.server.address, &self.address);
+        set(&mut config.server.port, &self.port);
+        set(&mut config.server.static_dir, &self.static_content);
+        set(&mut config.store.backend, &self.store);
+        set(&mut config.store.data_dir, &self.data_dir);
+        set(&mut config.sessions.grace_period_secs, &self.grace_period);
+        set(&mut config.games.max_games, &self.max_games);
+        set(&mut config.games.max_players, &self.max_players);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    use clap::Parser;
+
+    use crate::args::{AppArgs, Command, ConfigCommand};
+
+    /// Guards the environment, which clap reads every time arguments are parsed
+    static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    fn lock_environment() -> MutexGuard<'static, ()> {
+        ENVIRONMENT.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Parses arguments without racing a test that changes the environment
+    fn parse(args: &[&str]) -> Result<AppArgs, clap::Error> {
+        let _environment = lock_environment();
+        AppArgs::try_parse_from(args)
+    }
+
+    #[test]
+    fn flags_override_environment_override_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "[server]\nport = 4000\naddress = \"127.0.0.1\"").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let args = parse(&["server", "--config", path]).unwrap();
+        assert_eq!(args.config().unwrap().server.port, 4000);
+
+        // every parse reads the environment, so the variable is only set while no other test
+        // is parsing
+        let environment = lock_environment();
+        std::env::set_var("EVENTIDE_PORT", "5000");
+        let from_env = AppArgs::try_parse_from(["server", "--config", path]);
+        let from_flag = AppArgs::try_parse_from(["server", "--config", path, "--port", "6000"]);
+        std::env::remove_var("EVENTIDE_PORT");
+        drop(environment);
+
+        let config = from_env.unwrap().config().unwrap();
+        assert_eq!(config.server.port, 5000);
+        assert_eq!(config.server.address.to_string(), "127.0.0.1");
+        assert_eq!(from_flag.unwrap().config().unwrap().server.port, 6000);
+    }
+
+    #[test]
+    fn config_print_accepts_overrides() {
+        let args = parse(&["server", "config", "print", "--max-players", "4"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Config(ConfigCommand::Print))
+        ));
+        assert_eq!(args.config().unwrap().games.max_players, 4);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let args = parse(&["server", "--max-players", "12"]).unwrap();
+        assert!(args.config().is_err());
     }
 }
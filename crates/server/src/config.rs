@@ -0,0 +1,190 @@
+//! The configuration of the server.
+//!
+//! The configuration is layered: values read from a TOML file are overridden by `EVENTIDE_*`
+//! environment variables, which are in turn overridden by command line flags. Anything left
+//! unset keeps its default.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use shared::lobby::PLAYER_LIMITS;
+use thiserror::Error;
+
+use crate::lobby::GameLimits;
+use crate::session::DEFAULT_GRACE_PERIOD;
+use crate::store::StoreKind;
+
+/// An error occurred while loading the configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not read config file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("could not parse config file {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid value for {key}: {reason}")]
+    Invalid { key: &'static str, reason: String },
+}
+
+/// The effective configuration of the server
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub store: StoreConfig,
+    pub sessions: SessionConfig,
+    pub games: GameLimits,
+}
+
+/// Where the server listens and what it serves
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The address to listen on
+    pub address: IpAddr,
+    /// The port to listen on
+    pub port: u16,
+    /// The directory static content is served from
+    pub static_dir: PathBuf,
+}
+
+impl ServerConfig {
+    /// Gets the socket address to listen on
+    pub fn listen_address(&self) -> SocketAddr {
+        SocketAddr::new(self.address, self.port)
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 3000,
+            static_dir: PathBuf::from("static"),
+        }
+    }
+}
+
+/// Where started games are stored
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    /// The kind of store games are kept in
+    pub backend: StoreKind,
+    /// The directory stored games are kept in
+    pub data_dir: PathBuf,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreKind::Files,
+            data_dir: PathBuf::from("data"),
+        }
+    }
+}
+
+/// How long sessions live
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// How many seconds a detached session may be resumed for
+    pub grace_period_secs: u64,
+}
+
+impl SessionConfig {
+    /// Gets how long a detached session may be resumed for
+    pub fn grace_period(&self) -> Duration {
+        Duration::from_secs(self.grace_period_secs)
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_secs: DEFAULT_GRACE_PERIOD.as_secs(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the configuration from a TOML file. Missing values keep their defaults.
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Checks that every value makes sense
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !PLAYER_LIMITS.contains(&self.games.max_players) {
+            return Err(ConfigError::Invalid {
+                key: "games.max_players",
+                reason: format!(
+                    "must be between {} and {}",
+                    PLAYER_LIMITS.start(),
+                    PLAYER_LIMITS.end()
+                ),
+            });
+        }
+        if self.games.max_games == 0 {
+            return Err(ConfigError::Invalid {
+                key: "games.max_games",
+                reason: "must allow at least one game".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Renders the configuration as TOML
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("config is always representable as toml")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::store::StoreKind;
+
+    #[test]
+    fn partial_file_keeps_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            port = 8080
+
+            [store]
+            backend = "sqlite"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.store.backend, StoreKind::Sqlite);
+        assert_eq!(config.sessions, Default::default());
+        assert_eq!(config.games, Default::default());
+    }
+
+    #[test]
+    fn printed_config_reads_back() {
+        let config = Config::default();
+        assert_eq!(toml::from_str::<Config>(&config.to_toml()).unwrap(), config);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("[server]\nprot = 8080").is_err());
+    }
+}
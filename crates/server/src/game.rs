@@ -0,0 +1,318 @@
+//! Running games.
+//!
+//! Every game is owned by its own actor task, which holds the authoritative [`GameState`] and
+//! receives commands over a channel. Everything else only ever talks to a game through its
+//! [`GameHandle`]. Every command is written to the game's store before it takes effect, so
+//! that a game can always be recovered from its store.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use shared::game::{CommandError, GameCommand, GameState, GameUpdate};
+use shared::lobby::{GameId, PlayerId};
+use shared::protocol::{ErrorCode, ErrorFrame};
+use thiserror::Error;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::error;
+
+use crate::game::actor::{supervise, ActorMessage};
+use crate::store::{blocking, GameRecord, GameStore};
+
+mod actor;
+
+/// How many messages may wait in a game's inbox before it's considered busy
+const INBOX_SIZE: usize = 64;
+/// How many updates may be buffered for a slow subscriber before it starts missing them
+const UPDATE_BUFFER: usize = 256;
+
+/// An error occurred while talking to a game
+#[derive(Debug, Error)]
+pub enum GameError {
+    #[error("no game with id {0}")]
+    NoSuchGame(GameId),
+    #[error("not playing in a game")]
+    NotPlaying,
+    #[error("game {0} is too busy to accept commands, try again later")]
+    Busy(GameId),
+    #[error("game {0} is unavailable")]
+    Unavailable(GameId),
+    #[error(transparent)]
+    Command(#[from] CommandError),
+}
+
+impl From<GameError> for ErrorFrame {
+    fn from(value: GameError) -> Self {
+        let code = match value {
+            GameError::Unavailable(_) => ErrorCode::Internal,
+            _ => ErrorCode::Rejected,
+        };
+        let violation = match &value {
+            GameError::Command(error) => error.violation(),
+            _ => None,
+        };
+        let frame = ErrorFrame::new(code, value);
+        match violation {
+            Some(violation) => frame.with_violation(violation),
+            None => frame,
+        }
+    }
+}
+
+/// A handle to a running game. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct GameHandle {
+    id: GameId,
+    inbox: mpsc::Sender<ActorMessage>,
+    updates: broadcast::Sender<GameUpdate>,
+}
+
+impl GameHandle {
+    /// Subscribes to every update made to the game
+    pub fn subscribe(&self) -> broadcast::Receiver<GameUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Issues a command to the game, returning the sequence number the game is at afterwards
+    pub async fn execute(&self, player: PlayerId, command: GameCommand) -> Result<u64, GameError> {
+        self.request(|reply| ActorMessage::Execute {
+            player,
+            command,
+            reply,
+        })
+        .await?
+    }
+
+    /// Gets a copy of the current state of the game
+    pub async fn snapshot(&self) -> Result<GameState, GameError> {
+        self.request(|reply| ActorMessage::Snapshot { reply }).await
+    }
+
+    /// Gets every update made after the given sequence number.
+    ///
+    /// Returns `None` if the game no longer remembers all of them, in which case the full
+    /// state should be fetched instead.
+    pub async fn updates_since(&self, sequence: u64) -> Result<Option<Vec<GameUpdate>>, GameError> {
+        self.request(|reply| ActorMessage::UpdatesSince { sequence, reply })
+            .await
+    }
+
+    async fn request<T>(
+        &self,
+        message: impl FnOnce(oneshot::Sender<T>) -> ActorMessage,
+    ) -> Result<T, GameError> {
+        let (reply, response) = oneshot::channel();
+        self.inbox.try_send(message(reply)).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => GameError::Busy(self.id),
+            mpsc::error::TrySendError::Closed(_) => GameError::Unavailable(self.id),
+        })?;
+        // the reply is only dropped if the actor crashed while handling the message
+        response.await.map_err(|_| GameError::Unavailable(self.id))
+    }
+}
+
+/// Every running game. Cheap to clone, every clone refers to the same games.
+#[derive(Debug, Clone)]
+pub struct Games {
+    handles: Arc<Mutex<HashMap<GameId, GameHandle>>>,
+    store: Arc<dyn GameStore>,
+}
+
+impl Games {
+    /// Creates an empty set of games, which are stored in the given store
+    pub fn new(store: Arc<dyn GameStore>) -> Self {
+        Self {
+            handles: Default::default(),
+            store,
+        }
+    }
+
+    /// Gets the store games are kept in
+    pub fn store(&self) -> &Arc<dyn GameStore> {
+        &self.store
+    }
+
+    /// Stores a newly started game and spawns the actor that runs it
+    pub fn start(&self, record: GameRecord, state: GameState) -> GameHandle {
+        self.spawn(state, Some(record))
+    }
+
+    /// Spawns the actor for a game recovered from the store
+    pub fn resume(&self, state: GameState) -> GameHandle {
+        self.spawn(state, None)
+    }
+
+    /// Spawns an actor for a game, which will own the given state. If a record is given, the
+    /// game is stored before the actor starts.
+    fn spawn(&self, state: GameState, record: Option<GameRecord>) -> GameHandle {
+        let id = state.id;
+        let (inbox, receiver) = mpsc::channel(INBOX_SIZE);
+        let (updates, _) = broadcast::channel(UPDATE_BUFFER);
+        let handle = GameHandle {
+            id,
+            inbox,
+            updates: updates.clone(),
+        };
+        self.lock().insert(id, handle.clone());
+
+        let games = self.clone();
+        tokio::spawn(async move {
+            if let Some(record) = record {
+                let initial = state.clone();
+                let created =
+                    blocking(&games.store, move |store| store.create(&record, &initial)).await;
+                if let Err(e) = created {
+                    error!("could not store game {id}: {e}");
+                    games.lock().remove(&id);
+                    return;
+                }
+            }
+            supervise(state, receiver, updates, games.store.clone()).await;
+            games.lock().remove(&id);
+        });
+        handle
+    }
+
+    /// Gets the handle to a running game
+    pub fn get(&self, id: GameId) -> Result<GameHandle, GameError> {
+        self.lock()
+            .get(&id)
+            .cloned()
+            .ok_or(GameError::NoSuchGame(id))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<GameId, GameHandle>> {
+        self.handles.lock().expect("games lock poisoned")
+    }
+}
+
+/// Picks a command the game accepts right now, for tests that only need the game to move on
+#[cfg(test)]
+pub(crate) fn next_command(state: &GameState) -> (PlayerId, GameCommand) {
+    use shared::game::combat::Hit;
+    use shared::game::round::DecisionKind;
+    use shared::game::strategy::{PrimaryAbility, StrategyCard};
+
+    let decision = state
+        .pending()
+        .into_iter()
+        .next()
+        .expect("game waits on a decision");
+    // prefer cards whose primary ability doesn't need choosing anything on the board
+    let simple = [
+        StrategyCard::Leadership,
+        StrategyCard::Politics,
+        StrategyCard::Trade,
+        StrategyCard::Imperial,
+    ];
+    let command = match decision.kind {
+        DecisionKind::PickStartingTechs { count, options } => GameCommand::PickStartingTechs {
+            techs: options.into_iter().take(count.into()).collect(),
+        },
+        DecisionKind::PickStrategyCard { available } => GameCommand::PickStrategyCard {
+            card: available
+                .iter()
+                .copied()
+                .find(|card| simple.contains(card))
+                .unwrap_or(available[0]),
+        },
+        DecisionKind::TakeAction { can_pass: true } => GameCommand::Pass,
+        DecisionKind::TakeAction { can_pass: false } => {
+            let card = state
+                .round
+                .cards_of(decision.player)
+                .into_iter()
+                .find(|card| !state.round.strategy_cards[card].exhausted)
+                .expect("player holds an unused card");
+            let ability = match card {
+                StrategyCard::Leadership => PrimaryAbility::Leadership {
+                    tokens: shared::game::CommandTokens {
+                        tactic: 3,
+                        fleet: 0,
+                        strategy: 0,
+                    },
+                    payment: Default::default(),
+                },
+                StrategyCard::Politics => PrimaryAbility::Politics {
+                    speaker: state
+                        .seats()
+                        .into_iter()
+                        .find(|seat| *seat != state.round.speaker)
+                        .expect("game has another player"),
+                },
+                StrategyCard::Trade => PrimaryAbility::Trade {
+                    replenish: Default::default(),
+                },
+                StrategyCard::Imperial => PrimaryAbility::Imperial {
+                    objective: None,
+                    payment: Default::default(),
+                },
+                card => panic!("{card} needs choices on the board"),
+            };
+            GameCommand::StrategicAction { ability }
+        }
+        DecisionKind::FollowSecondary { .. } => GameCommand::DeclineSecondary,
+        DecisionKind::AssignHits {
+            system,
+            planet,
+            hits,
+        } => {
+            let area = state.board.system(system);
+            let units = match &planet {
+                Some(planet) => area.planets[planet]
+                    .iter()
+                    .filter(|unit| unit.kind.is_ground_force())
+                    .collect::<Vec<_>>(),
+                None => area
+                    .space
+                    .iter()
+                    .filter(|unit| unit.kind.is_ship())
+                    .collect(),
+            };
+            GameCommand::AssignHits {
+                hits: units
+                    .into_iter()
+                    .filter(|unit| unit.owner == decision.player)
+                    .take(hits as usize)
+                    .map(|unit| Hit {
+                        unit: unit.kind,
+                        sustain: false,
+                    })
+                    .collect(),
+            }
+        }
+        DecisionKind::Bombard { .. } => GameCommand::Bombard { targets: vec![] },
+        DecisionKind::CommitGroundForces { .. } => {
+            GameCommand::CommitGroundForces { landings: vec![] }
+        }
+        DecisionKind::Produce { .. } => GameCommand::Produce {
+            units: vec![],
+            payment: Default::default(),
+        },
+        DecisionKind::AnnounceRetreat { .. } => GameCommand::AnnounceRetreat { to: None },
+        DecisionKind::ScoreObjectives => GameCommand::ScoreObjectives,
+        DecisionKind::DiscardSecretObjective { objectives } => {
+            GameCommand::DiscardSecretObjective {
+                objective: objectives[0].clone(),
+            }
+        }
+        DecisionKind::RedistributeCommandTokens { total } => {
+            GameCommand::RedistributeCommandTokens {
+                tokens: shared::game::CommandTokens {
+                    tactic: total - 5,
+                    fleet: 3,
+                    strategy: 2,
+                },
+            }
+        }
+        DecisionKind::PlaceRider { .. } => GameCommand::PlaceRider { prediction: None },
+        DecisionKind::Vote { .. } => GameCommand::Vote {
+            outcome: None,
+            planets: vec![],
+        },
+        DecisionKind::BreakTie { outcomes, .. } => GameCommand::BreakTie {
+            outcome: outcomes[0].clone(),
+        },
+        DecisionKind::PlayActionCard { .. } => GameCommand::PassActionCard,
+    };
+    (decision.player, command)
+}
@@ -0,0 +1,299 @@
+//! The actor task owning a game's state
+
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::FutureExt;
+use shared::game::{GameCommand, GameState, GameUpdate};
+use shared::lobby::PlayerId;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{debug, error, info, warn};
+
+use crate::game::GameError;
+use crate::store::{blocking, GameStore, LogEntry};
+
+/// How many times a game may crash before it's given up on
+const MAX_RESTARTS: u32 = 5;
+/// How long to wait before restarting a crashed game
+const RESTART_DELAY: Duration = Duration::from_millis(100);
+/// How many of the most recent updates are kept for clients catching up after a reconnect
+const HISTORY_SIZE: usize = 1024;
+/// How many commands are applied between two snapshots
+const SNAPSHOT_INTERVAL: u64 = 64;
+
+/// A message sent to a game's actor
+#[derive(Debug)]
+pub enum ActorMessage {
+    Execute {
+        player: PlayerId,
+        command: GameCommand,
+        reply: oneshot::Sender<Result<u64, GameError>>,
+    },
+    Snapshot {
+        reply: oneshot::Sender<GameState>,
+    },
+    /// Gets every update after the given sequence number, or `None` if some of them are no
+    /// longer kept
+    UpdatesSince {
+        sequence: u64,
+        reply: oneshot::Sender<Option<Vec<GameUpdate>>>,
+    },
+    #[cfg(test)]
+    Crash,
+}
+
+/// The state owned by a game's actor
+struct Actor {
+    state: GameState,
+    history: VecDeque<GameUpdate>,
+    updates: broadcast::Sender<GameUpdate>,
+    store: Arc<dyn GameStore>,
+}
+
+/// Runs a game's actor until every handle to it is dropped, restarting it whenever it panics.
+///
+/// A restarted actor resumes from the state last written to the store.
+pub async fn supervise(
+    state: GameState,
+    mut inbox: mpsc::Receiver<ActorMessage>,
+    updates: broadcast::Sender<GameUpdate>,
+    store: Arc<dyn GameStore>,
+) {
+    let id = state.id;
+    let mut actor = Actor {
+        state,
+        history: VecDeque::with_capacity(HISTORY_SIZE),
+        updates,
+        store,
+    };
+    let mut restarts = 0;
+    loop {
+        match AssertUnwindSafe(actor.run(&mut inbox)).catch_unwind().await {
+            Ok(()) => {
+                info!("game {id} stopped");
+                return;
+            }
+            Err(_) if restarts >= MAX_RESTARTS => {
+                error!("game {id} crashed {restarts} times, giving up");
+                return;
+            }
+            Err(_) => {
+                restarts += 1;
+                tokio::time::sleep(RESTART_DELAY).await;
+                actor.reload().await;
+                warn!(
+                    "game {id} crashed, restarted from sequence {} ({restarts}/{MAX_RESTARTS})",
+                    actor.state.sequence
+                );
+            }
+        }
+    }
+}
+
+impl Actor {
+    /// Handles messages until the inbox is closed.
+    ///
+    /// Commands are applied to a copy of the state, which only replaces the state once the
+    /// command has been written to the store, so a panic never leaves the state half changed.
+    async fn run(&mut self, inbox: &mut mpsc::Receiver<ActorMessage>) {
+        while let Some(message) = inbox.recv().await {
+            match message {
+                ActorMessage::Execute {
+                    player,
+                    command,
+                    reply,
+                } => {
+                    let result = self.execute(player, command).await;
+                    let _ = reply.send(result);
+                }
+                ActorMessage::Snapshot { reply } => {
+                    let _ = reply.send(self.state.clone());
+                }
+                ActorMessage::UpdatesSince { sequence, reply } => {
+                    let _ = reply.send(updates_since(&self.state, &self.history, sequence));
+                }
+                #[cfg(test)]
+                ActorMessage::Crash => panic!("crash requested"),
+            }
+        }
+    }
+
+    async fn execute(&mut self, player: PlayerId, command: GameCommand) -> Result<u64, GameError> {
+        let id = self.state.id;
+        debug!("game {id}: {player} issued {command:?}");
+        let mut next = self.state.clone();
+        let events = next.apply(player, command.clone())?;
+
+        let entry = LogEntry {
+            sequence: next.sequence,
+            player,
+            command,
+        };
+        if let Err(e) = blocking(&self.store, move |store| store.append(id, &entry)).await {
+            error!(
+                "could not store command {} of game {id}: {e}",
+                next.sequence
+            );
+            return Err(GameError::Unavailable(id));
+        }
+        self.state = next;
+
+        if self.state.sequence.is_multiple_of(SNAPSHOT_INTERVAL) {
+            let snapshot = self.state.clone();
+            if let Err(e) = blocking(&self.store, move |store| store.snapshot(&snapshot)).await {
+                warn!("could not snapshot game {id}: {e}");
+            }
+        }
+
+        let update = GameUpdate {
+            game: id,
+            sequence: self.state.sequence,
+            events,
+        };
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(update.clone());
+        let _ = self.updates.send(update);
+        Ok(self.state.sequence)
+    }
+
+    /// Replaces the state with the one in the store, keeping the current state if the store
+    /// can't be read
+    async fn reload(&mut self) {
+        let id = self.state.id;
+        let stored = blocking(&self.store, move |store| store.load(id)).await;
+        match stored.and_then(|stored| stored.map(|stored| stored.recover()).transpose()) {
+            Ok(Some((_, state))) => self.state = state,
+            Ok(None) => warn!("game {id} is missing from the store"),
+            Err(e) => error!("could not reload game {id}: {e}"),
+        }
+    }
+}
+
+fn updates_since(
+    state: &GameState,
+    history: &VecDeque<GameUpdate>,
+    sequence: u64,
+) -> Option<Vec<GameUpdate>> {
+    let oldest = history
+        .front()
+        .map(|update| update.sequence)
+        .unwrap_or(state.sequence + 1);
+    if sequence + 1 < oldest {
+        return None;
+    }
+    Some(
+        history
+            .iter()
+            .filter(|update| update.sequence > sequence)
+            .cloned()
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::actor::ActorMessage;
+    use crate::game::{next_command, GameError, GameHandle, Games};
+    use crate::store::{GameRecord, MemoryStore};
+    use shared::game::strategy::StrategyCard;
+    use shared::game::{GameCommand, GameState};
+    use shared::lobby::{GameId, GameSettings, GameStatus, GameSummary, PlayerId, Seat};
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    fn games() -> Games {
+        Games::new(Arc::new(MemoryStore::default()))
+    }
+
+    fn start_game(games: &Games) -> (GameHandle, Vec<PlayerId>) {
+        let players = (0..3).map(|_| PlayerId(Uuid::new_v4())).collect::<Vec<_>>();
+        let summary = GameSummary {
+            id: GameId(Uuid::new_v4()),
+            name: "test".to_string(),
+            host: players[0],
+            settings: GameSettings::default(),
+            status: GameStatus::InProgress,
+            seats: players
+                .iter()
+                .map(|&player| {
+                    Some(Seat {
+                        player,
+                        name: player.to_string(),
+                        disconnected: false,
+                    })
+                })
+                .collect(),
+        };
+        let state = GameState::new(&summary, 0);
+        let record = GameRecord {
+            summary,
+            sessions: vec![],
+        };
+        (games.start(record, state), players)
+    }
+
+    #[tokio::test]
+    async fn crashed_game_restarts_from_last_state() {
+        let games = games();
+        let (handle, players) = start_game(&games);
+
+        let (player, command) = next_command(&handle.snapshot().await.unwrap());
+        assert_eq!(handle.execute(player, command).await.unwrap(), 1);
+        handle.inbox.send(ActorMessage::Crash).await.unwrap();
+
+        let restored = handle.snapshot().await.unwrap();
+        assert_eq!(restored.sequence, 1);
+        assert_eq!(restored.pending()[0].player, players[1]);
+    }
+
+    #[tokio::test]
+    async fn crashing_game_does_not_affect_others() {
+        let games = games();
+        let (crashing, _) = start_game(&games);
+        let (healthy, _) = start_game(&games);
+
+        crashing.inbox.send(ActorMessage::Crash).await.unwrap();
+        let state = healthy.snapshot().await.unwrap();
+        assert_eq!(state.sequence, 0);
+    }
+
+    #[tokio::test]
+    async fn rejected_commands_leave_state_untouched() {
+        let games = games();
+        let (handle, players) = start_game(&games);
+
+        assert!(matches!(
+            handle
+                .execute(
+                    players[1],
+                    GameCommand::PickStrategyCard {
+                        card: StrategyCard::Leadership
+                    }
+                )
+                .await,
+            Err(GameError::Command(_))
+        ));
+        assert_eq!(handle.snapshot().await.unwrap().sequence, 0);
+    }
+
+    #[tokio::test]
+    async fn missed_updates_are_replayed() {
+        let games = games();
+        let (handle, players) = start_game(&games);
+        for _ in &players {
+            let (player, command) = next_command(&handle.snapshot().await.unwrap());
+            handle.execute(player, command).await.unwrap();
+        }
+
+        let missed = handle.updates_since(1).await.unwrap().unwrap();
+        assert_eq!(
+            missed.iter().map(|u| u.sequence).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert!(handle.updates_since(3).await.unwrap().unwrap().is_empty());
+    }
+}
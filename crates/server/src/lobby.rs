@@ -0,0 +1,532 @@
+//! The lobby, where games are created and wait for players to join
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+use shared::lobby::{
+    GameId, GameSettings, GameStatus, GameSummary, LobbyEvent, PlayerId, Seat, PLAYER_LIMITS,
+};
+use shared::protocol::{ErrorCode, ErrorFrame};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+pub mod routes;
+
+/// How many lobby events may be buffered for a slow subscriber before it starts missing them
+const EVENT_BUFFER: usize = 256;
+
+/// An error occurred while changing the lobby
+#[derive(Debug, Error)]
+pub enum LobbyError {
+    #[error("no game with id {0}")]
+    NoSuchGame(GameId),
+    #[error("player limit must be between {} and {max}, got {limit}", PLAYER_LIMITS.start())]
+    InvalidPlayerLimit { limit: u8, max: u8 },
+    #[error("the server is already hosting as many games as it can ({0})")]
+    TooManyGames(usize),
+    #[error("{0} must not be empty")]
+    EmptyName(&'static str),
+    #[error("the game is full")]
+    GameFull,
+    #[error("seat {0} does not exist")]
+    NoSuchSeat(usize),
+    #[error("seat {0} is already taken")]
+    SeatTaken(usize),
+    #[error("a valid invite code is required to join this game")]
+    InviteRequired,
+    #[error("already seated in game {0}")]
+    AlreadySeated(GameId),
+    #[error("not seated in a game")]
+    NotSeated,
+    #[error("only the host may do that")]
+    NotHost,
+    #[error("at least {needed} players are needed to start, but only {seated} are seated")]
+    NotEnoughPlayers { needed: u8, seated: usize },
+    #[error("the game has already started")]
+    AlreadyStarted,
+}
+
+impl From<LobbyError> for ErrorFrame {
+    fn from(value: LobbyError) -> Self {
+        ErrorFrame::new(ErrorCode::Rejected, value)
+    }
+}
+
+/// Limits on the games hosted by the lobby
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameLimits {
+    /// How many games may be waiting or in progress at once
+    pub max_games: usize,
+    /// The most players a game may be created for
+    pub max_players: u8,
+}
+
+impl Default for GameLimits {
+    fn default() -> Self {
+        Self {
+            max_games: 256,
+            max_players: *PLAYER_LIMITS.end(),
+        }
+    }
+}
+
+/// A game waiting in, or started from, the lobby
+#[derive(Debug)]
+struct Room {
+    summary: GameSummary,
+    invite_code: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct LobbyState {
+    rooms: HashMap<GameId, Room>,
+    seated: HashMap<PlayerId, GameId>,
+}
+
+/// The shared lobby. Cheap to clone, every clone refers to the same lobby.
+#[derive(Debug, Clone)]
+pub struct Lobby {
+    state: Arc<Mutex<LobbyState>>,
+    events: broadcast::Sender<LobbyEvent>,
+    limits: GameLimits,
+}
+
+impl Lobby {
+    /// Creates a new, empty lobby
+    pub fn new(limits: GameLimits) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            state: Default::default(),
+            events,
+            limits,
+        }
+    }
+
+    /// Subscribes to every change made to the lobby
+    pub fn subscribe(&self) -> broadcast::Receiver<LobbyEvent> {
+        self.events.subscribe()
+    }
+
+    /// Lists every game that is shown in the lobby
+    pub fn list(&self) -> Vec<GameSummary> {
+        let state = self.lock();
+        let mut games = state
+            .rooms
+            .values()
+            .map(|room| &room.summary)
+            .filter(|summary| summary.settings.visibility.is_listed())
+            .cloned()
+            .collect::<Vec<_>>();
+        games.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        games
+    }
+
+    /// Gets a game by its id
+    pub fn get(&self, id: GameId) -> Result<GameSummary, LobbyError> {
+        self.lock()
+            .rooms
+            .get(&id)
+            .map(|room| room.summary.clone())
+            .ok_or(LobbyError::NoSuchGame(id))
+    }
+
+    /// Gets the game a player is seated in
+    pub fn game_of(&self, player: PlayerId) -> Option<GameId> {
+        self.lock().seated.get(&player).copied()
+    }
+
+    /// Checks whether a player is seated in a game that has started
+    pub fn is_playing(&self, player: PlayerId) -> bool {
+        let state = self.lock();
+        state
+            .seated
+            .get(&player)
+            .and_then(|id| state.rooms.get(id))
+            .is_some_and(|room| room.summary.status == GameStatus::InProgress)
+    }
+
+    /// Puts a started game recovered from storage back into the lobby, with every player
+    /// marked as disconnected until they resume their session
+    pub fn restore(&self, mut summary: GameSummary) {
+        for seat in summary.seats.iter_mut().flatten() {
+            seat.disconnected = true;
+        }
+        let mut state = self.lock();
+        for seat in summary.seats.iter().flatten() {
+            state.seated.insert(seat.player, summary.id);
+        }
+        state.rooms.insert(
+            summary.id,
+            Room {
+                summary: summary.clone(),
+                invite_code: None,
+            },
+        );
+        drop(state);
+
+        self.publish(LobbyEvent::GameUpdated { game: summary });
+    }
+
+    /// Creates a new game with the given player seated as its host.
+    ///
+    /// Returns the game and, if the game isn't open, the code needed to join it.
+    pub fn create(
+        &self,
+        host: PlayerId,
+        host_name: &str,
+        name: &str,
+        settings: GameSettings,
+    ) -> Result<(GameSummary, Option<String>), LobbyError> {
+        let max = self.limits.max_players;
+        if !(*PLAYER_LIMITS.start()..=max).contains(&settings.player_limit) {
+            return Err(LobbyError::InvalidPlayerLimit {
+                limit: settings.player_limit,
+                max,
+            });
+        }
+        let name = non_empty(name, "game name")?;
+        let host_name = non_empty(host_name, "player name")?;
+
+        let mut state = self.lock();
+        if let Some(&game) = state.seated.get(&host) {
+            return Err(LobbyError::AlreadySeated(game));
+        }
+        if state.rooms.len() >= self.limits.max_games {
+            return Err(LobbyError::TooManyGames(self.limits.max_games));
+        }
+
+        let id = GameId(Uuid::new_v4());
+        let invite_code = settings
+            .visibility
+            .requires_invite()
+            .then(|| Uuid::new_v4().simple().to_string()[..8].to_string());
+        let mut seats = vec![None; settings.player_limit as usize];
+        seats[0] = Some(Seat {
+            player: host,
+            name: host_name,
+            disconnected: false,
+        });
+        let summary = GameSummary {
+            id,
+            name,
+            host,
+            settings,
+            status: GameStatus::Waiting,
+            seats,
+        };
+        state.seated.insert(host, id);
+        state.rooms.insert(
+            id,
+            Room {
+                summary: summary.clone(),
+                invite_code: invite_code.clone(),
+            },
+        );
+        drop(state);
+
+        info!("{host} created game {id} ({:?})", summary.name);
+        self.publish(LobbyEvent::GameUpdated {
+            game: summary.clone(),
+        });
+        Ok((summary, invite_code))
+    }
+
+    /// Seats a player in a game
+    pub fn join(
+        &self,
+        id: GameId,
+        player: PlayerId,
+        player_name: &str,
+        seat: Option<usize>,
+        invite_code: Option<&str>,
+    ) -> Result<GameSummary, LobbyError> {
+        let player_name = non_empty(player_name, "player name")?;
+
+        let mut state = self.lock();
+        if let Some(&game) = state.seated.get(&player) {
+            return Err(LobbyError::AlreadySeated(game));
+        }
+        let room = state.rooms.get_mut(&id).ok_or(LobbyError::NoSuchGame(id))?;
+        if room.summary.status != GameStatus::Waiting {
+            return Err(LobbyError::AlreadyStarted);
+        }
+        if room.invite_code.is_some() && room.invite_code.as_deref() != invite_code {
+            return Err(LobbyError::InviteRequired);
+        }
+        let seats = &mut room.summary.seats;
+        let index = match seat {
+            Some(index) => match seats.get(index) {
+                None => return Err(LobbyError::NoSuchSeat(index)),
+                Some(Some(_)) => return Err(LobbyError::SeatTaken(index)),
+                Some(None) => index,
+            },
+            None => seats
+                .iter()
+                .position(Option::is_none)
+                .ok_or(LobbyError::GameFull)?,
+        };
+        seats[index] = Some(Seat {
+            player,
+            name: player_name,
+            disconnected: false,
+        });
+        let summary = room.summary.clone();
+        state.seated.insert(player, id);
+        drop(state);
+
+        debug!("{player} joined game {id} in seat {index}");
+        self.publish(LobbyEvent::GameUpdated {
+            game: summary.clone(),
+        });
+        Ok(summary)
+    }
+
+    /// Removes a player from the game they're seated in.
+    ///
+    /// If the host leaves, the next seated player becomes the host. If nobody is left, the
+    /// game is removed from the lobby.
+    pub fn leave(&self, player: PlayerId) -> Result<GameId, LobbyError> {
+        let mut state = self.lock();
+        let id = *state.seated.get(&player).ok_or(LobbyError::NotSeated)?;
+        let room = state.rooms.get_mut(&id).ok_or(LobbyError::NoSuchGame(id))?;
+        if room.summary.status != GameStatus::Waiting {
+            return Err(LobbyError::AlreadyStarted);
+        }
+        let summary = &mut room.summary;
+        if let Some(index) = summary.seat_of(player) {
+            summary.seats[index] = None;
+        }
+        let event = match summary.seats.iter().flatten().next() {
+            Some(next) => {
+                if summary.host == player {
+                    summary.host = next.player;
+                }
+                LobbyEvent::GameUpdated {
+                    game: summary.clone(),
+                }
+            }
+            None => {
+                state.rooms.remove(&id);
+                LobbyEvent::GameRemoved { id }
+            }
+        };
+        state.seated.remove(&player);
+        drop(state);
+
+        debug!("{player} left game {id}");
+        self.publish(event);
+        Ok(id)
+    }
+
+    /// Marks whether a seated player currently has a connection to the server
+    pub fn set_connected(&self, player: PlayerId, connected: bool) {
+        let mut state = self.lock();
+        let Some(id) = state.seated.get(&player).copied() else {
+            return;
+        };
+        let Some(room) = state.rooms.get_mut(&id) else {
+            return;
+        };
+        let summary = &mut room.summary;
+        let Some(seat) = summary
+            .seats
+            .iter_mut()
+            .flatten()
+            .find(|s| s.player == player)
+        else {
+            return;
+        };
+        if seat.disconnected != connected {
+            return;
+        }
+        seat.disconnected = !connected;
+        let summary = summary.clone();
+        drop(state);
+
+        self.publish(LobbyEvent::GameUpdated { game: summary });
+    }
+
+    /// Starts the game hosted by the given player.
+    ///
+    /// `launch` is called with the started game before anyone is notified that it started.
+    pub fn start(
+        &self,
+        player: PlayerId,
+        launch: impl FnOnce(&GameSummary),
+    ) -> Result<GameSummary, LobbyError> {
+        let mut state = self.lock();
+        let id = *state.seated.get(&player).ok_or(LobbyError::NotSeated)?;
+        let room = state.rooms.get_mut(&id).ok_or(LobbyError::NoSuchGame(id))?;
+        let summary = &mut room.summary;
+        if summary.host != player {
+            return Err(LobbyError::NotHost);
+        }
+        if summary.status != GameStatus::Waiting {
+            return Err(LobbyError::AlreadyStarted);
+        }
+        let needed = *PLAYER_LIMITS.start();
+        let seated = summary.player_count();
+        if seated < needed as usize {
+            return Err(LobbyError::NotEnoughPlayers { needed, seated });
+        }
+        summary.status = GameStatus::InProgress;
+        let summary = summary.clone();
+        drop(state);
+
+        launch(&summary);
+        info!("game {id} started with {seated} players");
+        self.publish(LobbyEvent::GameUpdated {
+            game: summary.clone(),
+        });
+        Ok(summary)
+    }
+
+    fn publish(&self, event: LobbyEvent) {
+        // an error only means there are currently no subscribers
+        let _ = self.events.send(event);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LobbyState> {
+        self.state.lock().expect("lobby lock poisoned")
+    }
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        Self::new(GameLimits::default())
+    }
+}
+
+/// Checks whether a lobby event should be shown to a player
+pub fn is_visible_to(event: &LobbyEvent, player: PlayerId) -> bool {
+    match event {
+        LobbyEvent::GameUpdated { game } => {
+            game.settings.visibility.is_listed() || game.seat_of(player).is_some()
+        }
+        LobbyEvent::GameRemoved { .. } => true,
+    }
+}
+
+fn non_empty(value: &str, what: &'static str) -> Result<String, LobbyError> {
+    let value = value.trim();
+    if value.is_empty() {
+        Err(LobbyError::EmptyName(what))
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lobby::{GameLimits, Lobby, LobbyError};
+    use shared::lobby::{GameSettings, GameStatus, PlayerId, Visibility};
+    use uuid::Uuid;
+
+    fn player() -> PlayerId {
+        PlayerId(Uuid::new_v4())
+    }
+
+    #[test]
+    fn player_limit_is_validated() {
+        let lobby = Lobby::new(GameLimits::default());
+        let settings = GameSettings {
+            player_limit: 9,
+            ..Default::default()
+        };
+        assert!(matches!(
+            lobby.create(player(), "host", "game", settings),
+            Err(LobbyError::InvalidPlayerLimit { limit: 9, max: 8 })
+        ));
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        let lobby = Lobby::new(GameLimits {
+            max_games: 1,
+            max_players: 4,
+        });
+        let settings = GameSettings {
+            player_limit: 5,
+            ..Default::default()
+        };
+        assert!(matches!(
+            lobby.create(player(), "host", "game", settings),
+            Err(LobbyError::InvalidPlayerLimit { limit: 5, max: 4 })
+        ));
+
+        let settings = GameSettings {
+            player_limit: 4,
+            ..Default::default()
+        };
+        lobby
+            .create(player(), "host", "first", settings.clone())
+            .unwrap();
+        assert!(matches!(
+            lobby.create(player(), "host", "second", settings),
+            Err(LobbyError::TooManyGames(1))
+        ));
+    }
+
+    #[test]
+    fn host_passes_on_leave() {
+        let lobby = Lobby::new(GameLimits::default());
+        let (host, guest) = (player(), player());
+        let (game, _) = lobby
+            .create(host, "host", "game", GameSettings::default())
+            .unwrap();
+        lobby.join(game.id, guest, "guest", Some(3), None).unwrap();
+        lobby.leave(host).unwrap();
+        let game = lobby.get(game.id).unwrap();
+        assert_eq!(game.host, guest);
+        assert_eq!(game.seat_of(guest), Some(3));
+
+        lobby.leave(guest).unwrap();
+        assert!(lobby.get(game.id).is_err());
+    }
+
+    #[test]
+    fn private_games_need_invites_and_are_unlisted() {
+        let lobby = Lobby::new(GameLimits::default());
+        let settings = GameSettings {
+            visibility: Visibility::Private,
+            ..Default::default()
+        };
+        let (game, code) = lobby.create(player(), "host", "secret", settings).unwrap();
+        let code = code.expect("private games have an invite code");
+        assert!(lobby.list().is_empty());
+        assert!(matches!(
+            lobby.join(game.id, player(), "guest", None, None),
+            Err(LobbyError::InviteRequired)
+        ));
+        lobby
+            .join(game.id, player(), "guest", None, Some(&code))
+            .unwrap();
+    }
+
+    #[test]
+    fn start_requires_host_and_three_players() {
+        let lobby = Lobby::new(GameLimits::default());
+        let (host, guest) = (player(), player());
+        let (game, _) = lobby
+            .create(host, "host", "game", GameSettings::default())
+            .unwrap();
+        lobby.join(game.id, guest, "guest", None, None).unwrap();
+        assert!(matches!(
+            lobby.start(guest, |_| {}),
+            Err(LobbyError::NotHost)
+        ));
+        assert!(matches!(
+            lobby.start(host, |_| {}),
+            Err(LobbyError::NotEnoughPlayers { .. })
+        ));
+        lobby.join(game.id, player(), "third", None, None).unwrap();
+        assert_eq!(
+            lobby.start(host, |_| {}).unwrap().status,
+            GameStatus::InProgress
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! REST endpoints for the lobby.
+//!
+//! These mirror the lobby requests of the web socket protocol. As there are no accounts yet,
+//! callers take a seat with a player id of their choosing, such as one obtained from
+//! `/random_uuid`. Taking a seat opens a session, whose token a web socket client can use to
+//! play from that seat. Requests made from a seat, such as leaving or starting the game, send
+//! that token as a bearer token in the `Authorization` header.
+
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Path, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use shared::lobby::{GameId, GameSettings, GameSummary, PlayerId, SessionToken};
+use shared::protocol::ErrorFrame;
+
+use uuid::Uuid;
+
+use crate::lobby::LobbyError;
+use crate::session::SessionError;
+use crate::state::AppState;
+
+/// Creates the router for the lobby endpoints
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/games", get(list_games).post(create_game))
+        .route("/games/:id", get(get_game))
+        .route("/games/:id/join", post(join_game))
+        .route("/games/:id/leave", post(leave_game))
+        .route("/games/:id/start", post(start_game))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateGame {
+    player: PlayerId,
+    player_name: String,
+    name: String,
+    #[serde(default)]
+    settings: GameSettings,
+}
+
+#[derive(Debug, Serialize)]
+struct CreatedGame {
+    game: GameSummary,
+    invite_code: Option<String>,
+    session: SessionToken,
+}
+
+#[derive(Debug, Serialize)]
+struct JoinedGame {
+    game: GameSummary,
+    session: SessionToken,
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinGame {
+    player: PlayerId,
+    player_name: String,
+    #[serde(default)]
+    seat: Option<usize>,
+    #[serde(default)]
+    invite_code: Option<String>,
+}
+
+/// The player making a request, as authenticated by the token of their session
+#[derive(Debug, Copy, Clone)]
+struct Caller(PlayerId);
+
+#[async_trait]
+impl FromRequestParts<AppState> for Caller {
+    type Rejection = SessionError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| token.trim().parse::<Uuid>().ok())
+            .map(SessionToken)
+            .ok_or(SessionError::MissingToken)?;
+        let player = state
+            .sessions
+            .player_of(token)
+            .ok_or(SessionError::Expired(token))?;
+        Ok(Caller(player))
+    }
+}
+
+async fn list_games(State(state): State<AppState>) -> Json<Vec<GameSummary>> {
+    Json(state.lobby.list())
+}
+
+async fn get_game(
+    State(state): State<AppState>,
+    Path(id): Path<GameId>,
+) -> Result<Json<GameSummary>, LobbyError> {
+    state.lobby.get(id).map(Json)
+}
+
+async fn create_game(
+    State(state): State<AppState>,
+    Json(body): Json<CreateGame>,
+) -> Result<(StatusCode, Json<CreatedGame>), LobbyError> {
+    let (game, invite_code) =
+        state
+            .lobby
+            .create(body.player, &body.player_name, &body.name, body.settings)?;
+    let session = state.open_session(body.player);
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedGame {
+            game,
+            invite_code,
+            session,
+        }),
+    ))
+}
+
+async fn join_game(
+    State(state): State<AppState>,
+    Path(id): Path<GameId>,
+    Json(body): Json<JoinGame>,
+) -> Result<Json<JoinedGame>, LobbyError> {
+    let game = state.lobby.join(
+        id,
+        body.player,
+        &body.player_name,
+        body.seat,
+        body.invite_code.as_deref(),
+    )?;
+    let session = state.open_session(body.player);
+    Ok(Json(JoinedGame { game, session }))
+}
+
+async fn leave_game(
+    State(state): State<AppState>,
+    Path(id): Path<GameId>,
+    Caller(player): Caller,
+) -> Result<StatusCode, LobbyError> {
+    ensure_seated_in(&state, id, player)?;
+    state.lobby.leave(player)?;
+    state.end_session(player);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn start_game(
+    State(state): State<AppState>,
+    Path(id): Path<GameId>,
+    Caller(player): Caller,
+) -> Result<Json<GameSummary>, LobbyError> {
+    ensure_seated_in(&state, id, player)?;
+    state.start_game(player).map(Json)
+}
+
+/// Makes sure the player is seated in the game named in the path
+fn ensure_seated_in(state: &AppState, id: GameId, player: PlayerId) -> Result<(), LobbyError> {
+    state.lobby.get(id)?;
+    match state.lobby.game_of(player) {
+        Some(game) if game == id => Ok(()),
+        _ => Err(LobbyError::NotSeated),
+    }
+}
+
+impl IntoResponse for LobbyError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            LobbyError::NoSuchGame(_) => StatusCode::NOT_FOUND,
+            LobbyError::NotHost | LobbyError::InviteRequired => StatusCode::FORBIDDEN,
+            LobbyError::InvalidPlayerLimit { .. } | LobbyError::EmptyName(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            LobbyError::TooManyGames(_) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::CONFLICT,
+        };
+        (status, Json(ErrorFrame::from(self))).into_response()
+    }
+}
+
+impl IntoResponse for SessionError {
+    fn into_response(self) -> Response {
+        (StatusCode::UNAUTHORIZED, Json(ErrorFrame::from(self))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+
+    use axum::http::StatusCode;
+    use serde_json::{json, Value};
+    use shared::lobby::{GameId, PlayerId};
+    use uuid::Uuid;
+
+    use crate::lobby::GameLimits;
+    use crate::session::Sessions;
+    use crate::state::AppState;
+    use crate::store::{self, StoreKind};
+
+    /// Serves the lobby endpoints on a free local port
+    fn serve() -> SocketAddr {
+        let directory = std::env::temp_dir();
+        let state = AppState::new(
+            store::open(StoreKind::Memory, &directory).unwrap(),
+            Sessions::default(),
+            GameLimits::default(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = super::routes().with_state(state);
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+        address
+    }
+
+    #[tokio::test]
+    async fn seated_requests_need_the_session_of_the_seat() {
+        let address = serve();
+        let client = reqwest::Client::new();
+        let created: Value = client
+            .post(format!("http://{address}/games"))
+            .json(&json!({
+                "player": PlayerId(Uuid::new_v4()),
+                "player_name": "host",
+                "name": "guarded",
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let game: GameId = serde_json::from_value(created["game"]["id"].clone()).unwrap();
+        let session = created["session"].as_str().unwrap();
+        let leave = format!("http://{address}/games/{game}/leave");
+
+        let missing = client.post(&leave).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+        let error: Value = missing.json().await.unwrap();
+        assert_eq!(error["code"], "unauthenticated");
+
+        let wrong = client
+            .post(&leave)
+            .bearer_auth(Uuid::new_v4())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        let error: Value = wrong.json().await.unwrap();
+        assert_eq!(error["code"], "session_expired");
+
+        let left = client
+            .post(&leave)
+            .bearer_auth(session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(left.status(), StatusCode::NO_CONTENT);
+    }
+}
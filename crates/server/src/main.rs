@@ -1,35 +1,79 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
-use axum::body::HttpBody;
 use axum::response::IntoResponse;
 use axum::routing::get;
-use axum::{Json, Router, ServiceExt};
+use axum::{Json, Router};
 use clap::Parser;
-use futures::SinkExt;
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{error, info};
 use uuid::Uuid;
 
-use args::AppArgs;
+use args::{AppArgs, Command, ConfigCommand};
 use shared::logging::init_logging;
 
+use crate::session::Sessions;
 use crate::socket::ws_handler;
+use crate::state::AppState;
 
 mod args;
+mod config;
+mod game;
+mod lobby;
+mod session;
 mod socket;
+mod state;
+mod store;
 
 #[tokio::main]
 async fn main() {
     let args = AppArgs::parse();
+    let config = match args.config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    if let Some(Command::Config(ConfigCommand::Print)) = args.command {
+        print!("{}", config.to_toml());
+        return;
+    }
+
+    let _logging = match init_logging(&args.log_options()) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
 
-    init_logging(args.level_filter());
+    let store = match store::open(config.store.backend, &config.store.data_dir) {
+        Ok(store) => store,
+        Err(e) => {
+            error!(
+                "could not open {:?} store in {:?}: {e}",
+                config.store.backend, config.store.data_dir
+            );
+            std::process::exit(1);
+        }
+    };
+    let state = AppState::new(
+        store,
+        Sessions::new(config.sessions.grace_period()),
+        config.games,
+    );
+    match state.restore_games().await {
+        Ok(restored) => info!("restored {restored} games from the store"),
+        Err(e) => error!("could not restore games: {e}"),
+    }
 
     let mut router = Router::new();
-    let ref asset = args.static_content;
+    let asset = &config.server.static_dir;
     info!("serving static content from {asset:?}");
-    if let Ok(dir) = std::fs::read_dir(&asset) {
-        let mut root_index_html: Option<PathBuf> = dir
+    if let Ok(dir) = std::fs::read_dir(asset) {
+        let root_index_html: Option<PathBuf> = dir
             .into_iter()
             .filter_map(|child| child.ok())
             .find(|entry| entry.file_name() == "index.html")
@@ -52,10 +96,14 @@ async fn main() {
     let router = router
         .route("/ws", get(ws_handler))
         .route("/random_uuid", get(generate_uuid))
-        .layer(TraceLayer::new_for_http());
+        .merge(lobby::routes::routes())
+        .layer(TraceLayer::new_for_http())
+        .with_state(state);
 
-    axum::Server::bind(&"0.0.0.0:3000".parse().unwrap())
-        .serve(router.into_make_service())
+    let address = config.server.listen_address();
+    info!("listening on {address}");
+    axum::Server::bind(&address)
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap()
 }
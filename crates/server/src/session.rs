@@ -0,0 +1,227 @@
+//! Player sessions, which outlive the connections they're used from.
+//!
+//! A session is opened when a player takes a seat. While the player is connected, the session
+//! is attached to their connection. Once the connection drops, the session is detached and the
+//! player has a grace period to resume it from a new connection before it expires.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use shared::lobby::{PlayerId, SessionToken};
+use shared::protocol::{ErrorCode, ErrorFrame};
+use thiserror::Error;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// How long a detached session may be resumed by default
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(120);
+
+/// An error occurred while resuming a session
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("session {0} has expired or never existed")]
+    Expired(SessionToken),
+    #[error("a session token must be sent as a bearer token")]
+    MissingToken,
+}
+
+impl From<SessionError> for ErrorFrame {
+    fn from(value: SessionError) -> Self {
+        let code = match value {
+            SessionError::Expired(_) => ErrorCode::SessionExpired,
+            SessionError::MissingToken => ErrorCode::Unauthenticated,
+        };
+        ErrorFrame::new(code, value)
+    }
+}
+
+#[derive(Debug)]
+struct Session {
+    player: PlayerId,
+    /// Bumped every time the session is attached or detached, so that a pending expiry can
+    /// tell whether the session was resumed in the meantime
+    epoch: u64,
+    /// Tells the attached connection that it has been replaced, if there is one
+    attached: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Debug, Default)]
+struct SessionsState {
+    sessions: HashMap<SessionToken, Session>,
+    by_player: HashMap<PlayerId, SessionToken>,
+}
+
+/// Every open session. Cheap to clone, every clone refers to the same sessions.
+#[derive(Debug, Clone)]
+pub struct Sessions {
+    state: Arc<Mutex<SessionsState>>,
+    grace_period: Duration,
+}
+
+impl Sessions {
+    /// Creates a new set of sessions, which may be resumed for the given time after detaching
+    pub fn new(grace_period: Duration) -> Self {
+        Self {
+            state: Default::default(),
+            grace_period,
+        }
+    }
+
+    /// Gets how long a detached session may be resumed
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    /// Opens a detached session for a player, or gets the one they already have.
+    ///
+    /// Returns the token and epoch of the session.
+    pub fn open(&self, player: PlayerId) -> (SessionToken, u64) {
+        let mut state = self.lock();
+        if let Some(&token) = state.by_player.get(&player) {
+            return (token, state.sessions[&token].epoch);
+        }
+        let token = SessionToken(Uuid::new_v4());
+        state.sessions.insert(
+            token,
+            Session {
+                player,
+                epoch: 0,
+                attached: None,
+            },
+        );
+        state.by_player.insert(player, token);
+        (token, 0)
+    }
+
+    /// Restores a detached session recovered from storage
+    pub fn restore(&self, token: SessionToken, player: PlayerId) {
+        let mut state = self.lock();
+        state.sessions.insert(
+            token,
+            Session {
+                player,
+                epoch: 0,
+                attached: None,
+            },
+        );
+        state.by_player.insert(player, token);
+    }
+
+    /// Gets the session of a player
+    pub fn token_of(&self, player: PlayerId) -> Option<SessionToken> {
+        self.lock().by_player.get(&player).copied()
+    }
+
+    /// Gets the player of a session
+    pub fn player_of(&self, token: SessionToken) -> Option<PlayerId> {
+        self.lock()
+            .sessions
+            .get(&token)
+            .map(|session| session.player)
+    }
+
+    /// Attaches a session to a connection, which is told through `replaced` once another
+    /// connection attaches to the same session.
+    pub fn attach(
+        &self,
+        token: SessionToken,
+        replaced: oneshot::Sender<()>,
+    ) -> Result<PlayerId, SessionError> {
+        let mut state = self.lock();
+        let session = state
+            .sessions
+            .get_mut(&token)
+            .ok_or(SessionError::Expired(token))?;
+        if let Some(previous) = session.attached.replace(replaced) {
+            let _ = previous.send(());
+        }
+        session.epoch += 1;
+        Ok(session.player)
+    }
+
+    /// Detaches a session from its connection, returning the new epoch of the session
+    pub fn detach(&self, token: SessionToken) -> Option<u64> {
+        let mut state = self.lock();
+        let session = state.sessions.get_mut(&token)?;
+        session.attached = None;
+        session.epoch += 1;
+        Some(session.epoch)
+    }
+
+    /// Ends a session if it is still detached at the given epoch, returning its player
+    pub fn expire(&self, token: SessionToken, epoch: u64) -> Option<PlayerId> {
+        let mut state = self.lock();
+        let session = state.sessions.get(&token)?;
+        if session.epoch != epoch || session.attached.is_some() {
+            return None;
+        }
+        let player = session.player;
+        state.sessions.remove(&token);
+        state.by_player.remove(&player);
+        Some(player)
+    }
+
+    /// Ends the session of a player, whether it's attached or not
+    pub fn end(&self, player: PlayerId) {
+        let mut state = self.lock();
+        if let Some(token) = state.by_player.remove(&player) {
+            state.sessions.remove(&token);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SessionsState> {
+        self.state.lock().expect("sessions lock poisoned")
+    }
+}
+
+impl Default for Sessions {
+    fn default() -> Self {
+        Self::new(DEFAULT_GRACE_PERIOD)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::session::Sessions;
+    use shared::lobby::PlayerId;
+    use tokio::sync::oneshot;
+    use uuid::Uuid;
+
+    #[test]
+    fn resumed_session_does_not_expire() {
+        let sessions = Sessions::default();
+        let player = PlayerId(Uuid::new_v4());
+        let (token, _) = sessions.open(player);
+        let (replaced, _) = oneshot::channel();
+        sessions.attach(token, replaced).unwrap();
+        let epoch = sessions.detach(token).unwrap();
+
+        let (replaced, _) = oneshot::channel();
+        assert_eq!(sessions.attach(token, replaced).unwrap(), player);
+        assert_eq!(sessions.expire(token, epoch), None);
+    }
+
+    #[test]
+    fn detached_session_expires() {
+        let sessions = Sessions::default();
+        let player = PlayerId(Uuid::new_v4());
+        let (token, _) = sessions.open(player);
+        let epoch = sessions.detach(token).unwrap();
+        assert_eq!(sessions.expire(token, epoch), Some(player));
+
+        let (replaced, _) = oneshot::channel();
+        assert!(sessions.attach(token, replaced).is_err());
+    }
+
+    #[test]
+    fn attaching_replaces_previous_connection() {
+        let sessions = Sessions::default();
+        let (token, _) = sessions.open(PlayerId(Uuid::new_v4()));
+        let (first, mut first_replaced) = oneshot::channel();
+        sessions.attach(token, first).unwrap();
+        let (second, _) = oneshot::channel();
+        sessions.attach(token, second).unwrap();
+        assert!(first_replaced.try_recv().is_ok());
+    }
+}
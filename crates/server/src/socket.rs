@@ -1,14 +1,39 @@
-use axum::extract::ws::WebSocket;
-use axum::extract::{ConnectInfo, WebSocketUpgrade};
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use axum::{headers, TypedHeader};
+use futures::stream::SplitStream;
+use futures::{Future, SinkExt, StreamExt};
+use shared::game::GameUpdate;
+use shared::lobby::{GameId, GameStatus, GameSummary, LobbyEvent, PlayerId, SessionToken};
+use shared::protocol::{
+    decode, encode, ClientMessage, ErrorCode, ErrorFrame, Request, RequestId, Response,
+    ServerMessage, PROTOCOL_VERSION,
+};
 use std::net::SocketAddr;
-use tracing::{info, instrument};
+use std::ops::ControlFlow;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::Instant;
+use tracing::{debug, error, field, info, info_span, instrument, warn, Instrument, Span};
+use uuid::Uuid;
+
+use crate::game::{GameError, GameHandle};
+use crate::lobby::is_visible_to;
+use crate::state::AppState;
+
+/// How often the server pings an idle client
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// How long a client may stay silent before the connection is dropped
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(45);
 
 /// Web socket handler
-#[instrument]
+#[instrument(skip(ws, state))]
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
+    State(state): State<AppState>,
     user_agent: Option<TypedHeader<headers::UserAgent>>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
 ) -> impl IntoResponse {
@@ -18,7 +43,518 @@ pub async fn ws_handler(
         String::from("unknown browser")
     };
     info!("`{user_agent}` at {addr} connected.");
-    ws.on_upgrade(move |socket| handle_socket(socket, addr))
+    ws.on_upgrade(move |socket| handle_socket(socket, addr, state))
+}
+
+async fn handle_socket(socket: WebSocket, who: SocketAddr, state: AppState) {
+    let (mut sink, stream) = socket.split();
+    let (outgoing, mut queued) = unbounded_channel::<ServerMessage>();
+
+    let connection = Connection::new(who, outgoing, state);
+    let span = connection.span.clone();
+    let writer = tokio::spawn(
+        async move {
+            while let Some(message) = queued.recv().await {
+                let frame = match encode(&message) {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        error!("could not encode {message:?}: {e}");
+                        continue;
+                    }
+                };
+                if sink.send(Message::Text(frame)).await.is_err() {
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        }
+        .instrument(span.clone()),
+    );
+
+    async move {
+        let mut connection = connection;
+        connection.run(stream).await;
+        connection.close();
+        let _ = writer.await;
+        info!("{who} disconnected.");
+    }
+    .instrument(span)
+    .await
+}
+
+/// The state of a connection's handshake
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Handshake {
+    AwaitingHello,
+    Complete,
+}
+
+/// A single client connection
+struct Connection {
+    who: SocketAddr,
+    player: PlayerId,
+    state: AppState,
+    outgoing: UnboundedSender<ServerMessage>,
+    handshake: Handshake,
+    last_seen: Instant,
+    next_nonce: u64,
+    /// The session attached to this connection, if the player has taken a seat
+    session: Option<SessionToken>,
+    /// Resolves once another connection resumed the session
+    replaced: Option<oneshot::Receiver<()>>,
+    game_updates: Option<broadcast::Receiver<GameUpdate>>,
+    /// The sequence number of the last game update sent to the client
+    game_sequence: u64,
+    /// The span every event of this connection is logged in, carrying its player and game ids
+    span: Span,
+}
+
+/// How a request is replied to
+enum Reply {
+    /// The response is ready, boxed as a game state makes it large
+    Now(Box<Response>),
+    /// The response will be sent once it's ready
+    Later,
+}
+
+impl Connection {
+    fn new(who: SocketAddr, outgoing: UnboundedSender<ServerMessage>, state: AppState) -> Self {
+        let player = PlayerId(Uuid::new_v4());
+        Self {
+            who,
+            player,
+            state,
+            outgoing,
+            handshake: Handshake::AwaitingHello,
+            last_seen: Instant::now(),
+            next_nonce: 0,
+            session: None,
+            replaced: None,
+            game_updates: None,
+            game_sequence: 0,
+            span: info_span!("connection", %who, %player, game = field::Empty),
+        }
+    }
+
+    /// Reads from the socket until either side closes the connection
+    async fn run(&mut self, mut stream: SplitStream<WebSocket>) {
+        let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+        keepalive.tick().await;
+        let mut lobby_events = self.state.lobby.subscribe();
+        loop {
+            tokio::select! {
+                frame = stream.next() => {
+                    let message = match frame {
+                        Some(Ok(message)) => message,
+                        Some(Err(e)) => {
+                            warn!("error reading from {}: {e}", self.who);
+                            break;
+                        }
+                        None => break,
+                    };
+                    self.last_seen = Instant::now();
+                    let flow = match message {
+                        Message::Text(text) => self.on_frame(&text).await,
+                        Message::Binary(_) => self.send_error(ErrorFrame::new(
+                            ErrorCode::Malformed,
+                            "binary frames are not supported",
+                        )),
+                        Message::Close(_) => ControlFlow::Break(()),
+                        Message::Ping(_) | Message::Pong(_) => ControlFlow::Continue(()),
+                    };
+                    if flow.is_break() {
+                        break;
+                    }
+                }
+                event = lobby_events.recv() => match event {
+                    Ok(event) => self.on_lobby_event(event).await,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("{} missed {missed} lobby events", self.who);
+                    }
+                    Err(RecvError::Closed) => {}
+                },
+                update = next_update(&mut self.game_updates) => match update {
+                    Ok(update) => self.send_update(update),
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("{} missed {missed} game updates, catching up", self.who);
+                        self.resynchronize().await;
+                    }
+                    Err(RecvError::Closed) => self.game_updates = None,
+                },
+                replaced = next_replacement(&mut self.replaced) => match replaced {
+                    Ok(()) => {
+                        info!("session of {} was resumed elsewhere", self.player);
+                        self.session = None;
+                        self.send(ServerMessage::Error(ErrorFrame::new(
+                            ErrorCode::SessionReplaced,
+                            "the session was resumed by another connection",
+                        )));
+                        break;
+                    }
+                    Err(_) => self.replaced = None,
+                },
+                _ = keepalive.tick() => {
+                    if self.last_seen.elapsed() > KEEPALIVE_TIMEOUT {
+                        info!("{} timed out", self.who);
+                        break;
+                    }
+                    let nonce = self.next_nonce;
+                    self.next_nonce += 1;
+                    self.send(ServerMessage::Ping { nonce });
+                }
+            }
+        }
+    }
+
+    /// Detaches the session from this connection, so it can be resumed from another one
+    fn close(&mut self) {
+        if let Some(session) = self.session.take() {
+            self.state.detach_session(session, self.player);
+        }
+    }
+
+    /// Handles a single text frame
+    async fn on_frame(&mut self, frame: &str) -> ControlFlow<()> {
+        let message = match decode::<ClientMessage>(frame) {
+            Ok(message) => message,
+            Err(e) => {
+                debug!("malformed frame from {}: {e}", self.who);
+                return self.send_error(ErrorFrame::new(ErrorCode::Malformed, e));
+            }
+        };
+
+        match (self.handshake, message) {
+            (
+                Handshake::AwaitingHello,
+                ClientMessage::Hello {
+                    version,
+                    client,
+                    session,
+                    last_sequence,
+                },
+            ) => {
+                if version != PROTOCOL_VERSION {
+                    return self.send_error(ErrorFrame::new(
+                        ErrorCode::UnsupportedVersion,
+                        format!(
+                            "client speaks version {version} but server speaks version {PROTOCOL_VERSION}"
+                        ),
+                    ));
+                }
+                if let Some(session) = session {
+                    if let Err(e) = self.attach(session) {
+                        return self.send_error(e);
+                    }
+                }
+                info!("{} identified as `{client}`", self.who);
+                self.handshake = Handshake::Complete;
+                self.send(ServerMessage::Welcome {
+                    version: PROTOCOL_VERSION,
+                    server: concat!("eventide-dominion/", env!("CARGO_PKG_VERSION")).to_string(),
+                    player: self.player,
+                    resumed: session.is_some(),
+                });
+                if session.is_some() {
+                    self.catch_up(last_sequence).await;
+                }
+                ControlFlow::Continue(())
+            }
+            (Handshake::AwaitingHello, _) => self.send_error(ErrorFrame::new(
+                ErrorCode::HandshakeRequired,
+                "expected a hello message",
+            )),
+            (Handshake::Complete, ClientMessage::Hello { .. }) => self.send_error(ErrorFrame::new(
+                ErrorCode::Rejected,
+                "handshake already completed",
+            )),
+            (Handshake::Complete, ClientMessage::Request { id, request }) => {
+                match self.dispatch(id, request).await {
+                    Ok(Reply::Now(response)) => {
+                        self.send(ServerMessage::Response {
+                            id,
+                            response: *response,
+                        });
+                        ControlFlow::Continue(())
+                    }
+                    Ok(Reply::Later) => ControlFlow::Continue(()),
+                    Err(error) => self.send_error(error.with_request_id(id)),
+                }
+            }
+            (Handshake::Complete, ClientMessage::Ping { nonce }) => {
+                self.send(ServerMessage::Pong { nonce });
+                ControlFlow::Continue(())
+            }
+            (Handshake::Complete, ClientMessage::Pong { .. }) => ControlFlow::Continue(()),
+        }
+    }
+
+    /// Carries out a request
+    async fn dispatch(&mut self, id: RequestId, request: Request) -> Result<Reply, ErrorFrame> {
+        debug!("{} requested {id}: {request:?}", self.who);
+        let lobby = self.state.lobby.clone();
+        let response = match request {
+            Request::Echo { message } => Response::Echo { message },
+            Request::ListGames => Response::Games {
+                games: lobby.list(),
+            },
+            Request::CreateGame {
+                name,
+                player_name,
+                settings,
+            } => {
+                let (game, invite_code) =
+                    lobby.create(self.player, &player_name, &name, settings)?;
+                self.record_game(game.id);
+                let session = self.open_session()?;
+                Response::GameCreated {
+                    game,
+                    invite_code,
+                    session,
+                }
+            }
+            Request::JoinGame {
+                game,
+                player_name,
+                seat,
+                invite_code,
+            } => {
+                let game = lobby.join(
+                    game,
+                    self.player,
+                    &player_name,
+                    seat,
+                    invite_code.as_deref(),
+                )?;
+                self.record_game(game.id);
+                let session = self.open_session()?;
+                Response::Joined { game, session }
+            }
+            Request::LeaveGame => {
+                let game = lobby.leave(self.player)?;
+                self.state.end_session(self.player);
+                self.session = None;
+                self.replaced = None;
+                self.game_updates = None;
+                Response::Left { game }
+            }
+            Request::StartGame => {
+                let game = self.state.start_game(self.player)?;
+                self.follow_game(&game, None).await;
+                Response::Game { game }
+            }
+            Request::Command { command } => {
+                let player = self.player;
+                return self.reply_from_game(id, move |game| async move {
+                    let sequence = game.execute(player, command).await?;
+                    Ok(Response::CommandApplied { sequence })
+                });
+            }
+            Request::GetGameState => {
+                let player = self.player;
+                return self.reply_from_game(id, move |game| async move {
+                    let state = game.snapshot().await?;
+                    Ok(Response::GameState {
+                        state: Box::new(state.view_for(player)),
+                    })
+                });
+            }
+            Request::SuggestPayment { cost, currency } => {
+                let player = self.player;
+                return self.reply_from_game(id, move |game| async move {
+                    let state = game.snapshot().await?;
+                    let payment = state
+                        .player(player)
+                        .and_then(|state| state.suggest_payment(cost, currency));
+                    Ok(Response::PaymentSuggested { payment })
+                });
+            }
+            Request::QualifyingObjectives => {
+                let player = self.player;
+                return self.reply_from_game(id, move |game| async move {
+                    let state = game.snapshot().await?;
+                    let objectives = state.qualifying_objectives(player);
+                    Ok(Response::QualifyingObjectives { objectives })
+                });
+            }
+        };
+        Ok(Reply::Now(Box::new(response)))
+    }
+
+    /// Runs a request against the game the client is playing, without waiting for the game
+    /// to reply
+    fn reply_from_game<F, R>(&self, id: RequestId, request: F) -> Result<Reply, ErrorFrame>
+    where
+        F: FnOnce(GameHandle) -> R,
+        R: Future<Output = Result<Response, GameError>> + Send + 'static,
+    {
+        let game = self
+            .state
+            .lobby
+            .game_of(self.player)
+            .ok_or(GameError::NotPlaying)?;
+        let response = request(self.state.games.get(game)?);
+        let outgoing = self.outgoing.clone();
+        tokio::spawn(
+            async move {
+                let message = match response.await {
+                    Ok(response) => ServerMessage::Response { id, response },
+                    Err(error) => ServerMessage::Error(ErrorFrame::from(error).with_request_id(id)),
+                };
+                let _ = outgoing.send(message);
+            }
+            .instrument(self.span.clone()),
+        );
+        Ok(Reply::Later)
+    }
+
+    /// Opens a session for the player, who just took a seat, and attaches it to this connection
+    fn open_session(&mut self) -> Result<SessionToken, ErrorFrame> {
+        let session = self.state.open_session(self.player);
+        self.attach(session)?;
+        Ok(session)
+    }
+
+    /// Attaches a session to this connection, making this connection play as its player
+    fn attach(&mut self, session: SessionToken) -> Result<(), ErrorFrame> {
+        let (replaced, on_replaced) = oneshot::channel();
+        self.player = self.state.resume_session(session, replaced)?;
+        self.span.record("player", field::display(self.player));
+        self.session = Some(session);
+        self.replaced = Some(on_replaced);
+        Ok(())
+    }
+
+    /// Sends a client that resumed its session everything it missed while it was disconnected
+    async fn catch_up(&mut self, last_sequence: Option<u64>) {
+        let Some(game) = self.state.lobby.game_of(self.player) else {
+            return;
+        };
+        let Ok(game) = self.state.lobby.get(game) else {
+            return;
+        };
+        self.record_game(game.id);
+        self.send(ServerMessage::Lobby {
+            event: LobbyEvent::GameUpdated { game: game.clone() },
+        });
+        self.follow_game(&game, last_sequence).await;
+    }
+
+    /// Starts forwarding updates of a game to the client, if the client is playing in it.
+    ///
+    /// If `since` is set, every update after that sequence number is sent first.
+    async fn follow_game(&mut self, game: &GameSummary, since: Option<u64>) {
+        if self.game_updates.is_some()
+            || game.status != GameStatus::InProgress
+            || game.seat_of(self.player).is_none()
+        {
+            return;
+        }
+        let handle = match self.state.games.get(game.id) {
+            Ok(handle) => handle,
+            Err(e) => {
+                warn!("can not follow game {}: {e}", game.id);
+                return;
+            }
+        };
+        // subscribe before fetching missed updates, so that none fall in between
+        self.game_updates = Some(handle.subscribe());
+        self.game_sequence = 0;
+        if let Some(since) = since {
+            self.send_missed_updates(&handle, since).await;
+        }
+    }
+
+    /// Catches up on the updates of the followed game the client missed while lagging behind
+    async fn resynchronize(&mut self) {
+        let Some(game) = self.state.lobby.game_of(self.player) else {
+            return;
+        };
+        match self.state.games.get(game) {
+            Ok(handle) => self.send_missed_updates(&handle, self.game_sequence).await,
+            Err(e) => warn!("can not resynchronize {}: {e}", self.player),
+        }
+    }
+
+    /// Sends every update of a game after the given sequence number, or what the player may see
+    /// of the game if some of them are no longer kept
+    async fn send_missed_updates(&mut self, handle: &GameHandle, since: u64) {
+        match handle.updates_since(since).await {
+            Ok(Some(missed)) => {
+                self.game_sequence = since;
+                for update in missed {
+                    self.send_update(update);
+                }
+            }
+            Ok(None) => match handle.snapshot().await {
+                Ok(state) => {
+                    self.game_sequence = state.sequence;
+                    self.send(ServerMessage::GameState {
+                        state: Box::new(state.view_for(self.player)),
+                    });
+                }
+                Err(e) => warn!("can not resynchronize {}: {e}", self.player),
+            },
+            Err(e) => warn!("can not catch {} up: {e}", self.player),
+        }
+    }
+
+    /// Records the game the player took a seat in on the span of this connection
+    fn record_game(&self, game: GameId) {
+        self.span.record("game", field::display(game));
+    }
+
+    /// Sends a game update to the client, unless it was already sent
+    fn send_update(&mut self, update: GameUpdate) {
+        if update.sequence > self.game_sequence {
+            self.game_sequence = update.sequence;
+            self.send(ServerMessage::Game { update });
+        }
+    }
+
+    /// Forwards a lobby event to the client, if the client is allowed to see it
+    async fn on_lobby_event(&mut self, event: LobbyEvent) {
+        if let LobbyEvent::GameUpdated { game } = &event {
+            self.follow_game(game, None).await;
+        }
+        if self.handshake == Handshake::Complete && is_visible_to(&event, self.player) {
+            self.send(ServerMessage::Lobby { event });
+        }
+    }
+
+    /// Queues a message to be sent to the client
+    fn send(&self, message: ServerMessage) {
+        let _ = self.outgoing.send(message);
+    }
+
+    /// Queues an error to be sent to the client, closing the connection if the error is fatal
+    fn send_error(&self, error: ErrorFrame) -> ControlFlow<()> {
+        let fatal = error.is_fatal();
+        self.send(ServerMessage::Error(error));
+        if fatal {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Waits for the next game update, or forever if the client isn't following a game
+async fn next_update(
+    updates: &mut Option<broadcast::Receiver<GameUpdate>>,
+) -> Result<GameUpdate, RecvError> {
+    match updates {
+        Some(updates) => updates.recv().await,
+        None => futures::future::pending().await,
+    }
 }
 
-async fn handle_socket(mut socket: WebSocket, who: SocketAddr) {}
+/// Waits until the session is resumed by another connection, or forever if there's no
+/// session attached
+async fn next_replacement(
+    replaced: &mut Option<oneshot::Receiver<()>>,
+) -> Result<(), oneshot::error::RecvError> {
+    match replaced {
+        Some(replaced) => replaced.await,
+        None => futures::future::pending().await,
+    }
+}
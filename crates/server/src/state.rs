@@ -0,0 +1,238 @@
+//! State shared between every request handler
+
+use std::sync::Arc;
+
+use shared::game::GameState;
+use shared::lobby::{GameSummary, PlayerId, SessionToken};
+use tokio::sync::oneshot;
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::game::Games;
+use crate::lobby::{GameLimits, Lobby, LobbyError};
+use crate::session::{SessionError, Sessions};
+use crate::store::{blocking, GameRecord, GameStore, StoreError};
+
+/// The state of the server, available to every handler
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub lobby: Lobby,
+    pub games: Games,
+    pub sessions: Sessions,
+}
+
+impl AppState {
+    /// Creates the state of a server storing its games in the given store
+    pub fn new(store: Arc<dyn GameStore>, sessions: Sessions, limits: GameLimits) -> Self {
+        Self {
+            lobby: Lobby::new(limits),
+            games: Games::new(store),
+            sessions,
+        }
+    }
+
+    /// Recovers every game in the store, putting them back into the lobby and restarting
+    /// their actors.
+    ///
+    /// Returns the number of recovered games. Games that can't be recovered are skipped. There
+    /// is no way for a game to end yet, so every game in the store is recovered.
+    pub async fn restore_games(&self) -> Result<usize, StoreError> {
+        let store = self.games.store();
+        let ids = blocking(store, |store| store.games()).await?;
+        let mut restored = 0;
+        for id in ids {
+            let stored = blocking(store, move |store| store.load(id)).await;
+            let (record, state) =
+                match stored.and_then(|stored| stored.map(|stored| stored.recover()).transpose()) {
+                    Ok(Some(recovered)) => recovered,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        error!("could not recover game {id}: {e}");
+                        continue;
+                    }
+                };
+            info!("recovered game {id} at sequence {}", state.sequence);
+            for (player, token) in record.sessions {
+                self.sessions.restore(token, player);
+            }
+            self.lobby.restore(record.summary);
+            self.games.resume(state);
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// Starts the game hosted by the given player, spawning the actor that runs it
+    pub fn start_game(&self, host: PlayerId) -> Result<GameSummary, LobbyError> {
+        self.lobby.start(host, |game| {
+            let sessions = game
+                .seats
+                .iter()
+                .flatten()
+                .filter_map(|seat| Some((seat.player, self.sessions.token_of(seat.player)?)))
+                .collect();
+            let record = GameRecord {
+                summary: game.clone(),
+                sessions,
+            };
+            // Version 4 UUIDs are read from the randomness of the operating system
+            let seed = Uuid::new_v4().as_u64_pair().0;
+            self.games.start(record, GameState::new(game, seed));
+        })
+    }
+
+    /// Opens a session for a player who just took a seat.
+    ///
+    /// The session starts out detached, and expires unless a connection attaches to it within
+    /// the grace period.
+    pub fn open_session(&self, player: PlayerId) -> SessionToken {
+        let (token, epoch) = self.sessions.open(player);
+        self.expire_session_later(token, epoch);
+        token
+    }
+
+    /// Attaches a session to a connection, marking its player as connected again
+    pub fn resume_session(
+        &self,
+        token: SessionToken,
+        replaced: oneshot::Sender<()>,
+    ) -> Result<PlayerId, SessionError> {
+        let player = self.sessions.attach(token, replaced)?;
+        self.lobby.set_connected(player, true);
+        Ok(player)
+    }
+
+    /// Detaches a session from its dropped connection, marking its player as disconnected
+    pub fn detach_session(&self, token: SessionToken, player: PlayerId) {
+        if let Some(epoch) = self.sessions.detach(token) {
+            self.lobby.set_connected(player, false);
+            self.expire_session_later(token, epoch);
+        }
+    }
+
+    /// Ends a player's session, because they left their seat
+    pub fn end_session(&self, player: PlayerId) {
+        self.sessions.end(player);
+    }
+
+    /// Expires a session once the grace period is over, unless it was resumed in the meantime.
+    ///
+    /// Players whose session expired lose their seat. Sessions of players in a started game
+    /// never expire, as their seat is kept for them until they return.
+    fn expire_session_later(&self, token: SessionToken, epoch: u64) {
+        let state = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(state.sessions.grace_period()).await;
+            let Some(player) = state.sessions.player_of(token) else {
+                return;
+            };
+            if state.lobby.is_playing(player) {
+                return;
+            }
+            if state.sessions.expire(token, epoch).is_none() {
+                return;
+            }
+            info!("session of {player} expired");
+            match state.lobby.leave(player) {
+                Ok(_) | Err(LobbyError::NotSeated) => {}
+                Err(e) => debug!("{player} keeps their seat after their session expired: {e}"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use shared::lobby::{GameSettings, GameStatus, PlayerId};
+    use tokio::runtime::Runtime;
+    use uuid::Uuid;
+
+    use crate::game::next_command;
+    use crate::lobby::GameLimits;
+    use crate::session::Sessions;
+    use crate::state::AppState;
+    use crate::store::{self, StoreKind};
+
+    fn runtime() -> Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    fn server(kind: StoreKind, directory: &Path) -> AppState {
+        AppState::new(
+            store::open(kind, directory).unwrap(),
+            Sessions::default(),
+            GameLimits::default(),
+        )
+    }
+
+    /// Plays a few turns, kills the server in the middle of the game, then checks a new server
+    /// picks the game up where it was left
+    fn survives_restart(kind: StoreKind) {
+        let directory = tempfile::tempdir().unwrap();
+        let players = (0..3).map(|_| PlayerId(Uuid::new_v4())).collect::<Vec<_>>();
+
+        let first = runtime();
+        let (game, tokens, played) = first.block_on(async {
+            let state = server(kind, directory.path());
+            let (game, _) = state
+                .lobby
+                .create(players[0], "host", "durable", GameSettings::default())
+                .unwrap();
+            for &player in &players[1..] {
+                state
+                    .lobby
+                    .join(game.id, player, "guest", None, None)
+                    .unwrap();
+            }
+            let tokens = players
+                .iter()
+                .map(|&player| state.open_session(player))
+                .collect::<Vec<_>>();
+            state.start_game(players[0]).unwrap();
+
+            let handle = state.games.get(game.id).unwrap();
+            for _ in 0..5 {
+                let (player, command) = next_command(&handle.snapshot().await.unwrap());
+                handle.execute(player, command).await.unwrap();
+            }
+            (game, tokens, handle.snapshot().await.unwrap())
+        });
+        // drop every task without letting it finish, as a killed server would
+        first.shutdown_background();
+
+        let second = runtime();
+        second.block_on(async {
+            let state = server(kind, directory.path());
+            assert_eq!(state.restore_games().await.unwrap(), 1);
+
+            let summary = state.lobby.get(game.id).unwrap();
+            assert_eq!(summary.status, GameStatus::InProgress);
+            assert!(summary.seats.iter().flatten().all(|seat| seat.disconnected));
+            for (&player, &token) in players.iter().zip(&tokens) {
+                assert_eq!(state.sessions.player_of(token), Some(player));
+            }
+
+            let handle = state.games.get(game.id).unwrap();
+            let recovered = handle.snapshot().await.unwrap();
+            assert_eq!(recovered, played);
+            assert_eq!(recovered.sequence, 5);
+            let (player, command) = next_command(&recovered);
+            assert_eq!(handle.execute(player, command).await.unwrap(), 6);
+        });
+    }
+
+    #[test]
+    fn file_store_survives_restart() {
+        survives_restart(StoreKind::Files);
+    }
+
+    #[test]
+    fn sqlite_store_survives_restart() {
+        survives_restart(StoreKind::Sqlite);
+    }
+}
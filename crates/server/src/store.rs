@@ -0,0 +1,231 @@
+//! Durable storage for started games.
+//!
+//! Every game is stored as a snapshot of its state plus an append-only log of the commands
+//! applied since that snapshot. Every so often a new snapshot is taken, after which the log
+//! entries it covers are dropped. A game is recovered by replaying its log on top of its
+//! snapshot.
+
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use shared::game::{GameCommand, GameState};
+use shared::lobby::{GameId, GameSummary, PlayerId, SessionToken};
+use thiserror::Error;
+
+pub use files::FileStore;
+pub use memory::MemoryStore;
+pub use sqlite::SqliteStore;
+
+mod files;
+mod memory;
+mod sqlite;
+
+/// An error occurred while reading or writing stored games
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("stored game {game} is corrupt: {reason}")]
+    Corrupt { game: GameId, reason: String },
+    #[error("storage task was interrupted")]
+    Interrupted,
+}
+
+/// Where games are stored
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    /// Games are kept in memory and lost when the server stops
+    Memory,
+    /// Games are stored as files in a directory
+    Files,
+    /// Games are stored in an embedded sqlite database
+    Sqlite,
+}
+
+/// Opens the store of the given kind, keeping its data in the given directory
+pub fn open(kind: StoreKind, directory: &Path) -> Result<Arc<dyn GameStore>, StoreError> {
+    Ok(match kind {
+        StoreKind::Memory => Arc::new(MemoryStore::default()),
+        StoreKind::Files => Arc::new(FileStore::open(directory)?),
+        StoreKind::Sqlite => {
+            std::fs::create_dir_all(directory)?;
+            Arc::new(SqliteStore::open(&directory.join("games.sqlite3"))?)
+        }
+    })
+}
+
+/// Everything about a game that lives outside of its state
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// The game as it was shown in the lobby when it started
+    pub summary: GameSummary,
+    /// The sessions of the players, so they can resume them after a restart
+    pub sessions: Vec<(PlayerId, SessionToken)>,
+}
+
+/// A command applied to a game
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// The sequence number of the game after the command was applied
+    pub sequence: u64,
+    pub player: PlayerId,
+    pub command: GameCommand,
+}
+
+/// A game as it was read from a store
+#[derive(Debug, Clone)]
+pub struct StoredGame {
+    pub record: GameRecord,
+    pub snapshot: GameState,
+    /// Every command applied after the snapshot was taken, in order
+    pub log: Vec<LogEntry>,
+}
+
+impl StoredGame {
+    /// Rebuilds the state of the game by replaying its log on top of its snapshot
+    pub fn recover(self) -> Result<(GameRecord, GameState), StoreError> {
+        let mut state = self.snapshot;
+        for entry in self.log {
+            if entry.sequence <= state.sequence {
+                continue;
+            }
+            let game = state.id;
+            let corrupt = |reason: String| StoreError::Corrupt { game, reason };
+            if entry.sequence != state.sequence + 1 {
+                return Err(corrupt(format!(
+                    "expected command {} but found command {}",
+                    state.sequence + 1,
+                    entry.sequence
+                )));
+            }
+            if let Err(e) = state.apply(entry.player, entry.command) {
+                return Err(corrupt(format!("command {} failed: {e}", entry.sequence)));
+            }
+        }
+        Ok((self.record, state))
+    }
+}
+
+/// Somewhere games can be stored.
+///
+/// Implementations are blocking, so they should be called from a blocking task.
+pub trait GameStore: Debug + Send + Sync + 'static {
+    /// Stores a newly started game with its initial state
+    fn create(&self, record: &GameRecord, initial: &GameState) -> Result<(), StoreError>;
+
+    /// Appends a command to the log of a game
+    fn append(&self, game: GameId, entry: &LogEntry) -> Result<(), StoreError>;
+
+    /// Replaces the snapshot of a game, dropping every log entry the snapshot covers
+    fn snapshot(&self, state: &GameState) -> Result<(), StoreError>;
+
+    /// Reads a game
+    fn load(&self, game: GameId) -> Result<Option<StoredGame>, StoreError>;
+
+    /// Lists every stored game
+    fn games(&self) -> Result<Vec<GameId>, StoreError>;
+}
+
+/// Runs a blocking operation against a store without blocking the async runtime
+pub async fn blocking<T, F>(store: &Arc<dyn GameStore>, operation: F) -> Result<T, StoreError>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn GameStore) -> Result<T, StoreError> + Send + 'static,
+{
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || operation(store.as_ref()))
+        .await
+        .map_err(|_| StoreError::Interrupted)?
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::next_command;
+    use crate::store::{FileStore, GameRecord, GameStore, LogEntry, MemoryStore, SqliteStore};
+    use shared::game::GameState;
+    use shared::lobby::{GameId, GameSettings, GameStatus, GameSummary, PlayerId, Seat};
+    use uuid::Uuid;
+
+    fn new_game() -> (GameRecord, GameState) {
+        let players = (0..3).map(|_| PlayerId(Uuid::new_v4())).collect::<Vec<_>>();
+        let summary = GameSummary {
+            id: GameId(Uuid::new_v4()),
+            name: "stored".to_string(),
+            host: players[0],
+            settings: GameSettings::default(),
+            status: GameStatus::InProgress,
+            seats: players
+                .iter()
+                .map(|&player| {
+                    Some(Seat {
+                        player,
+                        name: player.to_string(),
+                        disconnected: false,
+                    })
+                })
+                .collect(),
+        };
+        let state = GameState::new(&summary, 0);
+        let record = GameRecord {
+            summary,
+            sessions: vec![],
+        };
+        (record, state)
+    }
+
+    /// Plays a few turns, snapshotting half way through, then checks the game recovers
+    fn round_trip(store: &dyn GameStore) {
+        let (record, mut state) = new_game();
+        store.create(&record, &state).unwrap();
+        for turn in 0..6 {
+            let (player, command) = next_command(&state);
+            state.apply(player, command.clone()).unwrap();
+            store
+                .append(
+                    state.id,
+                    &LogEntry {
+                        sequence: state.sequence,
+                        player,
+                        command,
+                    },
+                )
+                .unwrap();
+            if turn == 3 {
+                store.snapshot(&state).unwrap();
+            }
+        }
+
+        assert_eq!(store.games().unwrap(), vec![state.id]);
+        let stored = store.load(state.id).unwrap().unwrap();
+        assert_eq!(stored.snapshot.sequence, 4);
+        assert_eq!(stored.log.len(), 2, "snapshot should compact the log");
+        let (recovered_record, recovered) = stored.recover().unwrap();
+        assert_eq!(recovered_record, record);
+        assert_eq!(recovered, state);
+    }
+
+    #[test]
+    fn memory_round_trip() {
+        round_trip(&MemoryStore::default());
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let directory = tempfile::tempdir().unwrap();
+        round_trip(&FileStore::open(directory.path()).unwrap());
+    }
+
+    #[test]
+    fn sqlite_round_trip() {
+        let directory = tempfile::tempdir().unwrap();
+        round_trip(&SqliteStore::open(&directory.path().join("games.sqlite3")).unwrap());
+    }
+}
@@ -0,0 +1,161 @@
+//! Stores games as files in a directory.
+//!
+//! Every game gets its own directory, named after its id, containing:
+//! - `game.json`, the [`GameRecord`]
+//! - `snapshot.json`, the latest snapshot of its state
+//! - `commands.jsonl`, the commands applied since the snapshot, one per line
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use shared::game::GameState;
+use shared::lobby::GameId;
+use uuid::Uuid;
+
+use crate::store::{GameRecord, GameStore, LogEntry, StoreError, StoredGame};
+
+const RECORD: &str = "game.json";
+const SNAPSHOT: &str = "snapshot.json";
+const LOG: &str = "commands.jsonl";
+
+/// A store keeping games as files in a directory
+#[derive(Debug)]
+pub struct FileStore {
+    directory: PathBuf,
+    /// Serializes writes, so that compacting a log never races an append to it
+    writing: Mutex<()>,
+}
+
+impl FileStore {
+    /// Opens a store in the given directory, creating it if necessary
+    pub fn open(directory: &Path) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(directory)?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            writing: Mutex::new(()),
+        })
+    }
+
+    fn game_directory(&self, game: GameId) -> PathBuf {
+        self.directory.join(game.to_string())
+    }
+}
+
+impl GameStore for FileStore {
+    fn create(&self, record: &GameRecord, initial: &GameState) -> Result<(), StoreError> {
+        let _writing = self.writing.lock().expect("file store lock poisoned");
+        let directory = self.game_directory(initial.id);
+        std::fs::create_dir_all(&directory)?;
+        write_atomically(&directory.join(SNAPSHOT), initial)?;
+        File::create(directory.join(LOG))?;
+        // the record is written last, as it marks the game as complete
+        write_atomically(&directory.join(RECORD), record)?;
+        Ok(())
+    }
+
+    fn append(&self, game: GameId, entry: &LogEntry) -> Result<(), StoreError> {
+        let _writing = self.writing.lock().expect("file store lock poisoned");
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(self.game_directory(game).join(LOG))?;
+        log.write_all(&line)?;
+        log.sync_data()?;
+        Ok(())
+    }
+
+    fn snapshot(&self, state: &GameState) -> Result<(), StoreError> {
+        let _writing = self.writing.lock().expect("file store lock poisoned");
+        let directory = self.game_directory(state.id);
+        write_atomically(&directory.join(SNAPSHOT), state)?;
+
+        let remaining = read_log(&directory.join(LOG))?
+            .into_iter()
+            .filter(|entry| entry.sequence > state.sequence)
+            .map(|entry| serde_json::to_string(&entry).map(|line| line + "\n"))
+            .collect::<Result<String, _>>()?;
+        write_bytes_atomically(&directory.join(LOG), remaining.as_bytes())?;
+        Ok(())
+    }
+
+    fn load(&self, game: GameId) -> Result<Option<StoredGame>, StoreError> {
+        let directory = self.game_directory(game);
+        let record = match File::open(directory.join(RECORD)) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let snapshot =
+            serde_json::from_reader(BufReader::new(File::open(directory.join(SNAPSHOT))?))?;
+        let log = read_log(&directory.join(LOG))?;
+        Ok(Some(StoredGame {
+            record,
+            snapshot,
+            log,
+        }))
+    }
+
+    fn games(&self) -> Result<Vec<GameId>, StoreError> {
+        let mut games = vec![];
+        for entry in std::fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| Uuid::parse_str(name).ok())
+            else {
+                continue;
+            };
+            if entry.path().join(RECORD).exists() {
+                games.push(GameId(id));
+            }
+        }
+        Ok(games)
+    }
+}
+
+/// Reads every entry of a log.
+///
+/// A torn last line, left behind by a crash in the middle of an append, is ignored.
+fn read_log(path: &Path) -> Result<Vec<LogEntry>, StoreError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let lines = BufReader::new(file)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()?;
+    let last = lines.len().saturating_sub(1);
+    let mut entries = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if index == last => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(entries)
+}
+
+fn write_atomically<T: Serialize>(path: &Path, value: &T) -> Result<(), StoreError> {
+    write_bytes_atomically(path, &serde_json::to_vec(value)?)
+}
+
+/// Writes a file by writing to a temporary file first and renaming it over the original, so
+/// that a crash never leaves a half written file behind
+fn write_bytes_atomically(path: &Path, bytes: &[u8]) -> Result<(), StoreError> {
+    let temporary = path.with_extension("tmp");
+    let mut file = File::create(&temporary)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Keeps games in memory
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use shared::game::GameState;
+use shared::lobby::GameId;
+
+use crate::store::{GameRecord, GameStore, LogEntry, StoreError, StoredGame};
+
+/// A store that forgets everything once dropped
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    games: Mutex<HashMap<GameId, StoredGame>>,
+}
+
+impl MemoryStore {
+    fn lock(&self) -> MutexGuard<'_, HashMap<GameId, StoredGame>> {
+        self.games.lock().expect("memory store lock poisoned")
+    }
+}
+
+impl GameStore for MemoryStore {
+    fn create(&self, record: &GameRecord, initial: &GameState) -> Result<(), StoreError> {
+        self.lock().insert(
+            initial.id,
+            StoredGame {
+                record: record.clone(),
+                snapshot: initial.clone(),
+                log: vec![],
+            },
+        );
+        Ok(())
+    }
+
+    fn append(&self, game: GameId, entry: &LogEntry) -> Result<(), StoreError> {
+        let mut games = self.lock();
+        let stored = games.get_mut(&game).ok_or_else(|| StoreError::Corrupt {
+            game,
+            reason: "game was never created".to_string(),
+        })?;
+        stored.log.push(entry.clone());
+        Ok(())
+    }
+
+    fn snapshot(&self, state: &GameState) -> Result<(), StoreError> {
+        let mut games = self.lock();
+        let stored = games
+            .get_mut(&state.id)
+            .ok_or_else(|| StoreError::Corrupt {
+                game: state.id,
+                reason: "game was never created".to_string(),
+            })?;
+        stored.snapshot = state.clone();
+        stored.log.retain(|entry| entry.sequence > state.sequence);
+        Ok(())
+    }
+
+    fn load(&self, game: GameId) -> Result<Option<StoredGame>, StoreError> {
+        Ok(self.lock().get(&game).cloned())
+    }
+
+    fn games(&self) -> Result<Vec<GameId>, StoreError> {
+        Ok(self.lock().keys().copied().collect())
+    }
+}
@@ -0,0 +1,142 @@
+//! Stores games in an embedded sqlite database
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use shared::game::GameState;
+use shared::lobby::GameId;
+use uuid::Uuid;
+
+use crate::store::{GameRecord, GameStore, LogEntry, StoreError, StoredGame};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS games (
+        id TEXT PRIMARY KEY,
+        record TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS snapshots (
+        game TEXT PRIMARY KEY REFERENCES games(id),
+        sequence INTEGER NOT NULL,
+        state TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS commands (
+        game TEXT NOT NULL REFERENCES games(id),
+        sequence INTEGER NOT NULL,
+        entry TEXT NOT NULL,
+        PRIMARY KEY (game, sequence)
+    );
+";
+
+/// A store keeping games in a sqlite database
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database at the given path, creating it if necessary
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().expect("sqlite store lock poisoned")
+    }
+}
+
+impl GameStore for SqliteStore {
+    fn create(&self, record: &GameRecord, initial: &GameState) -> Result<(), StoreError> {
+        let mut connection = self.lock();
+        let transaction = connection.transaction()?;
+        let id = initial.id.to_string();
+        transaction.execute(
+            "INSERT INTO games (id, record) VALUES (?1, ?2)",
+            params![id, serde_json::to_string(record)?],
+        )?;
+        transaction.execute(
+            "INSERT INTO snapshots (game, sequence, state) VALUES (?1, ?2, ?3)",
+            params![id, initial.sequence, serde_json::to_string(initial)?],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn append(&self, game: GameId, entry: &LogEntry) -> Result<(), StoreError> {
+        self.lock().execute(
+            "INSERT INTO commands (game, sequence, entry) VALUES (?1, ?2, ?3)",
+            params![
+                game.to_string(),
+                entry.sequence,
+                serde_json::to_string(entry)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn snapshot(&self, state: &GameState) -> Result<(), StoreError> {
+        let mut connection = self.lock();
+        let transaction = connection.transaction()?;
+        let id = state.id.to_string();
+        transaction.execute(
+            "UPDATE snapshots SET sequence = ?2, state = ?3 WHERE game = ?1",
+            params![id, state.sequence, serde_json::to_string(state)?],
+        )?;
+        transaction.execute(
+            "DELETE FROM commands WHERE game = ?1 AND sequence <= ?2",
+            params![id, state.sequence],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn load(&self, game: GameId) -> Result<Option<StoredGame>, StoreError> {
+        let connection = self.lock();
+        let id = game.to_string();
+        let Some(record) = connection
+            .query_row(
+                "SELECT record FROM games WHERE id = ?1",
+                params![id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let snapshot = connection.query_row(
+            "SELECT state FROM snapshots WHERE game = ?1",
+            params![id],
+            |row| row.get::<_, String>(0),
+        )?;
+        let mut statement =
+            connection.prepare("SELECT entry FROM commands WHERE game = ?1 ORDER BY sequence")?;
+        let log = statement
+            .query_map(params![id], |row| row.get::<_, String>(0))?
+            .map(|entry| Ok(serde_json::from_str(&entry?)?))
+            .collect::<Result<Vec<LogEntry>, StoreError>>()?;
+        Ok(Some(StoredGame {
+            record: serde_json::from_str(&record)?,
+            snapshot: serde_json::from_str(&snapshot)?,
+            log,
+        }))
+    }
+
+    fn games(&self) -> Result<Vec<GameId>, StoreError> {
+        let connection = self.lock();
+        let mut statement = connection.prepare("SELECT id FROM games")?;
+        let ids = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let games = ids
+            .iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .map(GameId)
+            .collect();
+        Ok(games)
+    }
+}
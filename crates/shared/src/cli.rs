@@ -1,7 +1,9 @@
 //! Command line interface shared happiness
 
-use crate::logging::LevelFilter;
+use crate::logging::{LevelFilter, LogFile, LogFormat, LogOptions, LogRotation};
 use clap::{value_parser, ArgAction, Args};
+use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, Args)]
 pub struct Logging {
@@ -25,6 +27,21 @@ pub struct Logging {
     #[clap(action = ArgAction::Count)]
     #[clap(value_parser = value_parser!(u8).range(0..=3))]
     q: u8,
+    /// Sets the level of specific modules, such as `server::socket=trace,axum=warn`
+    ///
+    /// Modules without a directive log at the level set by the other options
+    #[clap(long, value_name = "DIRECTIVES")]
+    #[clap(value_parser = parse_directives)]
+    log_filter: Option<String>,
+    /// The format logs are written in
+    #[clap(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    /// Also writes logs to this file, rotating it as set by `--log-rotation`
+    #[clap(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// How often the log file is rotated
+    #[clap(long, value_enum, default_value_t = LogRotation::Daily)]
+    log_rotation: LogRotation,
 }
 
 impl Logging {
@@ -44,4 +61,24 @@ impl Logging {
             }
         }
     }
+
+    /// Gets the options to initialize logging with
+    pub fn options(&self) -> LogOptions {
+        LogOptions {
+            level: self.level_filter(),
+            directives: self.log_filter.clone(),
+            format: self.log_format,
+            file: self.log_file.clone().map(|path| LogFile {
+                path,
+                rotation: self.log_rotation,
+            }),
+        }
+    }
+}
+
+fn parse_directives(directives: &str) -> Result<String, String> {
+    EnvFilter::builder()
+        .parse(directives)
+        .map(|_| directives.to_string())
+        .map_err(|e| e.to_string())
 }
@@ -0,0 +1,925 @@
+//! The authoritative state of a running game and the commands that change it
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::game::action_cards::{
+    ActionCardError, ActionCardId, ActionDeck, CardTarget, OpenWindow,
+};
+use crate::game::agenda::{AgendaDeck, AgendaId, Prediction, Rider};
+use crate::game::board::Board;
+use crate::game::combat::{Hit, RollKind};
+use crate::game::dice::{Dice, SeededDice};
+use crate::game::effects::EffectId;
+use crate::game::factions::FactionId;
+use crate::game::hex::Hex;
+use crate::game::invasion::{Bombardment, Landing};
+use crate::game::movement::{MoveError, Movement, RiftRoll};
+use crate::game::objectives::{ObjectiveDecks, ObjectiveError, ObjectiveId, ObjectivePayment};
+use crate::game::payment::Payment;
+use crate::game::production::ProductionError;
+use crate::game::round::{PhaseKind, Round};
+use crate::game::strategy::{PrimaryAbility, SecondaryAbility, StrategyCard};
+use crate::game::tech::{self as technologies, Research, ResearchError, TechColor, TechId};
+use crate::game::transactions::{
+    Bundle, PromissoryNote, Transaction, TransactionError, TransactionId, Transactions,
+};
+use crate::game::units::{UnitDefinition, UnitKind};
+use crate::lobby::{GameId, GameSummary, PlayerId};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub mod action_cards;
+pub mod agenda;
+pub mod board;
+pub mod combat;
+pub mod dice;
+pub mod effects;
+pub mod factions;
+pub mod galaxy;
+pub mod hex;
+pub mod invasion;
+pub mod movement;
+pub mod objectives;
+pub mod payment;
+pub mod production;
+pub mod round;
+pub mod setup;
+pub mod strategy;
+pub mod tactical;
+pub mod tech;
+pub mod tiles;
+pub mod transactions;
+pub mod units;
+pub mod view;
+pub mod violations;
+
+/// A player taking part in a game
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    pub id: PlayerId,
+    pub name: String,
+    /// The faction played, once picked
+    #[serde(default)]
+    pub faction: Option<FactionId>,
+    /// Every technology the player owns
+    #[serde(default)]
+    pub technologies: BTreeSet<TechId>,
+    #[serde(default)]
+    pub command_tokens: CommandTokens,
+    /// Every planet the player controls, by name
+    #[serde(default)]
+    pub planets: BTreeMap<String, PlanetCard>,
+    #[serde(default)]
+    pub trade_goods: u8,
+    #[serde(default)]
+    pub commodities: u8,
+    #[serde(default)]
+    pub victory_points: u8,
+    /// The secret objectives the player holds and hasn't scored yet
+    #[serde(default)]
+    pub secret_objectives: Vec<ObjectiveId>,
+    /// Every objective the player scored, public or secret
+    #[serde(default)]
+    pub scored_objectives: Vec<ObjectiveId>,
+    /// The action cards in the player's hand
+    #[serde(default)]
+    pub action_cards: Vec<ActionCardId>,
+    /// The promissory notes the player holds, their own and other players'
+    #[serde(default)]
+    pub promissory_notes: Vec<PromissoryNote>,
+    /// The starting technologies the player's faction still lets them pick
+    #[serde(default)]
+    pub tech_picks: u8,
+}
+
+/// The card of a planet a player controls
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlanetCard {
+    /// Whether the planet's resources or influence have been spent this round
+    #[serde(default)]
+    pub exhausted: bool,
+}
+
+/// The command tokens on a player's command sheet
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct CommandTokens {
+    /// Spent to activate systems
+    pub tactic: u8,
+    /// Limits the number of ships in each system
+    pub fleet: u8,
+    /// Spent to perform secondary abilities of strategy cards
+    pub strategy: u8,
+}
+
+impl CommandTokens {
+    /// Counts the tokens in every pool
+    pub fn total(self) -> u8 {
+        self.tactic + self.fleet + self.strategy
+    }
+}
+
+impl Default for CommandTokens {
+    /// The tokens every player starts the game with
+    fn default() -> Self {
+        Self {
+            tactic: 3,
+            fleet: 3,
+            strategy: 2,
+        }
+    }
+}
+
+impl Player {
+    /// Gets the number of commodities this player's faction can hold
+    pub fn commodity_limit(&self) -> u8 {
+        self.faction
+            .as_ref()
+            .and_then(|faction| factions::catalog().get(&faction.0))
+            .map(|faction| faction.commodities)
+            .unwrap_or_default()
+    }
+
+    /// Gets the version of a unit this player uses, if they can have it at all
+    pub fn unit(&self, kind: UnitKind) -> Option<UnitDefinition> {
+        let mut unit = units::catalog().resolve(kind, self.faction.as_ref(), &self.technologies)?;
+        self.modify_unit(&mut unit);
+        Some(unit)
+    }
+
+    /// Checks whether this player can research a technology right now, given the specialties
+    /// of the ready planets they control
+    pub fn can_research(
+        &self,
+        tech: &str,
+        specialties: &[TechColor],
+    ) -> Result<Research, ResearchError> {
+        technologies::catalog().check(tech, self.faction.as_ref(), &self.technologies, specialties)
+    }
+
+    /// Researches a technology, if this player can research it right now.
+    ///
+    /// The planets whose specialties were used as skips still need to be exhausted.
+    pub fn research(
+        &mut self,
+        tech: &str,
+        specialties: &[TechColor],
+    ) -> Result<Research, ResearchError> {
+        let research = self.can_research(tech, specialties)?;
+        self.technologies.insert(research.tech.clone());
+        Ok(research)
+    }
+}
+
+/// The full state of a running game
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameState {
+    pub id: GameId,
+    /// Every player, in seat order
+    pub players: Vec<Player>,
+    /// The number of commands applied to this game so far
+    pub sequence: u64,
+    pub round: Round,
+    /// The player who removed the custodians token from Mecatol Rex, after which every round
+    /// ends with an agenda phase
+    #[serde(default)]
+    pub custodians: Option<PlayerId>,
+    #[serde(default)]
+    pub board: Board,
+    /// The trade goods placed on strategy cards nobody picked in previous rounds
+    #[serde(default)]
+    pub strategy_card_trade_goods: BTreeMap<StrategyCard, u8>,
+    /// The dice rolled by commands, which also shuffle decks. They are kept on the server, and
+    /// left out of what players see of the game.
+    #[serde(default)]
+    pub dice: SeededDice,
+    #[serde(default)]
+    pub agenda_deck: AgendaDeck,
+    /// The laws in play, with the outcome elected by each law that elects one
+    #[serde(default)]
+    pub laws: BTreeMap<AgendaId, Option<String>>,
+    #[serde(default)]
+    pub objectives: ObjectiveDecks,
+    #[serde(default)]
+    pub action_deck: ActionDeck,
+    /// The timing windows the game is paused in, innermost last
+    #[serde(default)]
+    pub windows: Vec<OpenWindow>,
+    #[serde(default)]
+    pub transactions: Transactions,
+}
+
+impl GameState {
+    /// Creates the initial state of a game started from the lobby, rolling dice from the seed
+    ///
+    /// The host starts as the speaker, or the first seated player if the host left. The seed
+    /// decides every die rolled and deck shuffled, so it must not be guessable by players.
+    pub fn new(game: &GameSummary, seed: u64) -> Self {
+        let players = game
+            .seats
+            .iter()
+            .flatten()
+            .map(|seat| Player {
+                id: seat.player,
+                name: seat.name.clone(),
+                faction: None,
+                technologies: BTreeSet::new(),
+                command_tokens: CommandTokens::default(),
+                planets: BTreeMap::new(),
+                trade_goods: 0,
+                commodities: 0,
+                victory_points: 0,
+                secret_objectives: vec![],
+                scored_objectives: vec![],
+                action_cards: vec![],
+                promissory_notes: transactions::starting_notes(seat.player, None),
+                tech_picks: 0,
+            })
+            .collect::<Vec<_>>();
+        let seats = players.iter().map(|player| player.id).collect::<Vec<_>>();
+        let speaker = if seats.contains(&game.host) {
+            game.host
+        } else {
+            seats[0]
+        };
+        let mut state = Self {
+            id: game.id,
+            players,
+            sequence: 0,
+            round: Round::new(1, speaker, &seats),
+            custodians: None,
+            board: Board::default(),
+            strategy_card_trade_goods: BTreeMap::new(),
+            dice: SeededDice::new(seed),
+            agenda_deck: AgendaDeck::default(),
+            laws: BTreeMap::new(),
+            objectives: ObjectiveDecks::default(),
+            action_deck: ActionDeck::default(),
+            windows: vec![],
+            transactions: Transactions::default(),
+        };
+        state.set_up_factions();
+        state.set_up_objectives();
+        state.set_up_action_cards();
+        state
+    }
+
+    /// Gets a player taking part in this game
+    pub fn player(&self, player: PlayerId) -> Option<&Player> {
+        self.players.iter().find(|p| p.id == player)
+    }
+
+    pub(crate) fn player_mut(&mut self, player: PlayerId) -> Option<&mut Player> {
+        self.players.iter_mut().find(|p| p.id == player)
+    }
+
+    /// Checks whether a player takes part in this game
+    pub fn is_player(&self, player: PlayerId) -> bool {
+        self.players.iter().any(|p| p.id == player)
+    }
+
+    /// Applies a command issued by a player, rolling the game's own dice.
+    ///
+    /// The state is left untouched if the command is rejected.
+    pub fn apply(
+        &mut self,
+        player: PlayerId,
+        command: GameCommand,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let mut dice = self.dice.clone();
+        let events = self.apply_with(player, command, &mut dice)?;
+        self.dice = dice;
+        Ok(events)
+    }
+
+    /// Applies a command issued by a player, rolling the given dice
+    pub fn apply_with(
+        &mut self,
+        player: PlayerId,
+        command: GameCommand,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        if !self.is_player(player) {
+            return Err(CommandError::NotAPlayer(player));
+        }
+        // commands are resolved on a copy, so that a command rejected halfway through doesn't
+        // leave a partially resolved state behind
+        let mut next = self.clone();
+        let events = next.dispatch(player, command, dice)?;
+        next.sequence += 1;
+        *self = next;
+        Ok(events)
+    }
+
+    fn dispatch(
+        &mut self,
+        player: PlayerId,
+        command: GameCommand,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        // the game starts once every faction picked its starting technologies
+        let picking = self.players.iter().any(|state| state.tech_picks > 0);
+        if picking && !matches!(command, GameCommand::PickStartingTechs { .. }) {
+            return Err(CommandError::NotNow);
+        }
+        let over_limit = self
+            .player(player)
+            .is_some_and(|state| state.is_over_secret_limit());
+        if over_limit && !matches!(command, GameCommand::DiscardSecretObjective { .. }) {
+            return Err(ObjectiveError::OverHandLimit.into());
+        }
+        // nothing else happens until every open timing window is closed, though players can
+        // keep negotiating
+        let in_window = matches!(
+            command,
+            GameCommand::PlayActionCard { .. }
+                | GameCommand::UseWindowOption { .. }
+                | GameCommand::PassActionCard
+                | GameCommand::ProposeTransaction { .. }
+                | GameCommand::CounterTransaction { .. }
+                | GameCommand::CancelTransaction { .. }
+        );
+        if !self.windows.is_empty() && !in_window {
+            return Err(CommandError::NotNow);
+        }
+        match command {
+            GameCommand::PickStartingTechs { techs } => self.pick_starting_techs(player, techs),
+            GameCommand::PickStrategyCard { card } => self.pick_strategy_card(player, card),
+            GameCommand::StrategicAction { ability } => self.strategic_action(player, ability),
+            GameCommand::FollowSecondary { ability } => self.follow_secondary(player, ability),
+            GameCommand::DeclineSecondary => self.decline_secondary(player),
+            GameCommand::TacticalAction { system, movement } => {
+                self.tactical_action(player, system, movement, dice)
+            }
+            GameCommand::AssignHits { hits } if self.invasion().is_some() => {
+                self.assign_ground_hits(player, hits, dice)
+            }
+            GameCommand::AssignHits { hits } => self.assign_hits(player, hits, dice),
+            GameCommand::Bombard { targets } => self.bombard(player, targets, dice),
+            GameCommand::CommitGroundForces { landings } => {
+                self.commit_ground_forces(player, landings, dice)
+            }
+            GameCommand::AnnounceRetreat { to } => self.announce_retreat(player, to, dice),
+            GameCommand::Produce { units, payment } => self.production_step(player, units, payment),
+            GameCommand::Pass => self.pass(player),
+            GameCommand::ScoreObjective { objective, payment } => {
+                self.score_objective(player, objective, payment)
+            }
+            GameCommand::ScoreObjectives => self.score_objectives(player),
+            GameCommand::DiscardSecretObjective { objective } => {
+                self.discard_secret_objective(player, objective)
+            }
+            GameCommand::RedistributeCommandTokens { tokens } => {
+                self.redistribute_command_tokens(player, tokens)
+            }
+            GameCommand::PlaceRider { prediction } => self.place_rider(player, prediction),
+            GameCommand::Vote { outcome, planets } => self.vote(player, outcome, planets),
+            GameCommand::BreakTie { outcome } => self.break_tie(player, outcome),
+            GameCommand::PlayActionCard { card, target } => {
+                self.play_action_card(player, card, target, dice)
+            }
+            GameCommand::UseWindowOption { option } => self.use_window_option(player, option, dice),
+            GameCommand::PassActionCard => self.pass_action_card(player, dice),
+            GameCommand::ProposeTransaction { to, give, receive } => {
+                self.propose_transaction(player, to, give, receive)
+            }
+            GameCommand::CounterTransaction {
+                transaction,
+                give,
+                receive,
+            } => self.counter_transaction(player, transaction, give, receive),
+            GameCommand::AcceptTransaction { transaction } => {
+                self.accept_transaction(player, transaction)
+            }
+            GameCommand::CancelTransaction { transaction } => {
+                self.cancel_transaction(player, transaction)
+            }
+        }
+    }
+}
+
+/// A command issued by a player to change the game
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum GameCommand {
+    /// Picks the starting technologies the player's faction lets them choose
+    PickStartingTechs { techs: Vec<TechId> },
+    /// Picks a strategy card during the strategy phase
+    PickStrategyCard { card: StrategyCard },
+    /// Performs the strategic action of one of the player's strategy cards, resolving its
+    /// primary ability
+    StrategicAction { ability: PrimaryAbility },
+    /// Follows the secondary ability of the strategy card another player used
+    FollowSecondary { ability: SecondaryAbility },
+    /// Declines to follow the secondary ability of the strategy card another player used
+    DeclineSecondary,
+    /// Activates a system with a tactic token, moving units into it
+    TacticalAction {
+        system: Hex,
+        #[serde(default)]
+        movement: Movement,
+    },
+    /// Assigns the hits the player's units took during combat
+    AssignHits { hits: Vec<Hit> },
+    /// Bombards planets in the active system with the player's ships, or none
+    Bombard { targets: Vec<Bombardment> },
+    /// Commits ground forces from the space area of the active system to its planets, or none
+    CommitGroundForces { landings: Vec<Landing> },
+    /// Announces a retreat to an adjacent system at the start of a combat round, or stays
+    AnnounceRetreat {
+        #[serde(default)]
+        to: Option<Hex>,
+    },
+    /// Produces units in the active system at the end of a tactical action, or none
+    Produce {
+        #[serde(default)]
+        units: Vec<UnitKind>,
+        #[serde(default)]
+        payment: Payment,
+    },
+    /// Passes for the rest of the action phase
+    Pass,
+    /// Scores a public or secret objective during the status phase, spending what it asks for
+    ScoreObjective {
+        objective: ObjectiveId,
+        #[serde(default)]
+        payment: ObjectivePayment,
+    },
+    /// Finishes scoring objectives during the status phase
+    ScoreObjectives,
+    /// Discards an unscored secret objective, having more than the limit
+    DiscardSecretObjective { objective: ObjectiveId },
+    /// Places the command tokens gained during the status phase
+    RedistributeCommandTokens { tokens: CommandTokens },
+    /// Places a rider predicting the outcome of the current agenda, or none
+    PlaceRider {
+        #[serde(default)]
+        prediction: Option<Prediction>,
+    },
+    /// Votes on the current agenda with the influence of the planets exhausted, or abstains
+    /// without an outcome
+    Vote {
+        #[serde(default)]
+        outcome: Option<String>,
+        #[serde(default)]
+        planets: Vec<String>,
+    },
+    /// Breaks a tie between outcomes of the current agenda, as the speaker
+    BreakTie { outcome: String },
+    /// Plays an action card in the open timing window, or as an action on the player's turn
+    PlayActionCard {
+        card: ActionCardId,
+        #[serde(default)]
+        target: Option<CardTarget>,
+    },
+    /// Uses an option offered by the player's abilities or technologies in the open timing
+    /// window
+    UseWindowOption { option: EffectId },
+    /// Passes on playing an action card in the open timing window
+    PassActionCard,
+    /// Proposes a transaction to a neighbor, giving and receiving the given bundles
+    ProposeTransaction {
+        to: PlayerId,
+        #[serde(default)]
+        give: Bundle,
+        #[serde(default)]
+        receive: Bundle,
+    },
+    /// Replaces the terms of a transaction proposed to the player
+    CounterTransaction {
+        transaction: TransactionId,
+        #[serde(default)]
+        give: Bundle,
+        #[serde(default)]
+        receive: Bundle,
+    },
+    /// Accepts the terms of a transaction proposed to the player, exchanging both bundles
+    AcceptTransaction { transaction: TransactionId },
+    /// Withdraws from a transaction being negotiated
+    CancelTransaction { transaction: TransactionId },
+}
+
+/// Something that happened in a game as the result of a command
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GameEvent {
+    /// A new round started, with its strategy phase
+    RoundStarted { round: u32, speaker: PlayerId },
+    /// A phase of the round started
+    PhaseStarted { phase: PhaseKind },
+    /// A player picked a strategy card
+    StrategyCardPicked {
+        player: PlayerId,
+        card: StrategyCard,
+    },
+    /// It is now a player's turn of the action phase
+    TurnStarted { player: PlayerId },
+    /// A player performed the strategic action of a strategy card
+    StrategicActionTaken {
+        player: PlayerId,
+        card: StrategyCard,
+    },
+    /// A player followed the secondary ability of a strategy card
+    SecondaryFollowed {
+        player: PlayerId,
+        card: StrategyCard,
+    },
+    /// A player declined to follow the secondary ability of a strategy card
+    SecondaryDeclined {
+        player: PlayerId,
+        card: StrategyCard,
+    },
+    /// A player passed for the rest of the action phase
+    Passed { player: PlayerId },
+    /// A player activated a system with a tactic token
+    SystemActivated { player: PlayerId, system: Hex },
+    /// A ship leaving a gravity rift rolled to survive
+    GravityRiftRolled { player: PlayerId, roll: RiftRoll },
+    /// A player's units arrived in a system
+    UnitsMoved {
+        player: PlayerId,
+        system: Hex,
+        units: Vec<UnitKind>,
+    },
+    /// A player's units were destroyed
+    UnitsDestroyed {
+        player: PlayerId,
+        system: Hex,
+        units: Vec<UnitKind>,
+    },
+    /// A player placed units on a planet without producing them
+    UnitsPlaced {
+        player: PlayerId,
+        planet: String,
+        units: Vec<UnitKind>,
+    },
+    /// A player's units were damaged, cancelling hits with sustain damage
+    UnitsDamaged {
+        player: PlayerId,
+        system: Hex,
+        units: Vec<UnitKind>,
+    },
+    /// A player rolled dice for their units
+    DiceRolled {
+        player: PlayerId,
+        kind: RollKind,
+        results: Vec<u8>,
+        hits: u8,
+    },
+    /// Space combat started between the active player and the player with ships in the system
+    SpaceCombatStarted {
+        system: Hex,
+        attacker: PlayerId,
+        defender: PlayerId,
+    },
+    /// A player announced retreating to a system at the end of the combat round
+    RetreatAnnounced { player: PlayerId, to: Hex },
+    /// Space combat ended, won by the player with ships left, if any
+    SpaceCombatEnded {
+        system: Hex,
+        winner: Option<PlayerId>,
+    },
+    /// A player committed ground forces to a planet
+    GroundForcesCommitted {
+        player: PlayerId,
+        planet: String,
+        units: Vec<UnitKind>,
+    },
+    /// A player gained control of a planet, capturing it from the player who controlled it
+    PlanetControlGained {
+        player: PlayerId,
+        planet: String,
+        from: Option<PlayerId>,
+    },
+    /// A player produced units in a system
+    UnitsProduced {
+        player: PlayerId,
+        system: Hex,
+        units: Vec<UnitKind>,
+    },
+    /// A player's damaged units were repaired
+    UnitsRepaired {
+        player: PlayerId,
+        units: Vec<UnitKind>,
+    },
+    /// A trade good was placed on each strategy card nobody picked
+    TradeGoodsPlaced { cards: Vec<StrategyCard> },
+    /// A player gained trade goods
+    TradeGoodsGained { player: PlayerId, amount: u8 },
+    /// A player spent trade goods
+    TradeGoodsSpent { player: PlayerId, amount: u8 },
+    /// A player returned trade goods to the supply without spending them
+    TradeGoodsReturned { player: PlayerId, amount: u8 },
+    /// A player's commodities were refilled up to their faction's limit
+    CommoditiesReplenished { player: PlayerId, commodities: u8 },
+    /// A player gained command tokens, placed in the given pools
+    CommandTokensGained {
+        player: PlayerId,
+        tokens: CommandTokens,
+    },
+    /// A player spent command tokens from their pools
+    CommandTokensSpent {
+        player: PlayerId,
+        tokens: CommandTokens,
+    },
+    /// A player returned command tokens from their pools to their reinforcements
+    CommandTokensLost {
+        player: PlayerId,
+        tokens: CommandTokens,
+    },
+    /// A player placed a command token in a system
+    CommandTokenPlaced { player: PlayerId, system: Hex },
+    /// A player's command token was removed from a system
+    CommandTokenRemoved { player: PlayerId, system: Hex },
+    /// A player exhausted planets they control
+    PlanetsExhausted {
+        player: PlayerId,
+        planets: Vec<String>,
+    },
+    /// A player readied planets they control
+    PlanetsReadied {
+        player: PlayerId,
+        planets: Vec<String>,
+    },
+    /// Another player gained the speaker token
+    SpeakerChanged { speaker: PlayerId },
+    /// A player placed a PDS or space dock on a planet
+    StructurePlaced {
+        player: PlayerId,
+        unit: UnitKind,
+        planet: String,
+    },
+    /// A player researched a technology
+    TechnologyResearched { player: PlayerId, tech: TechId },
+    /// A player gained victory points
+    VictoryPointsGained {
+        player: PlayerId,
+        points: u8,
+        /// The player's victory points afterwards
+        total: u8,
+    },
+    /// A player lost victory points
+    VictoryPointsLost {
+        player: PlayerId,
+        points: u8,
+        /// The player's victory points afterwards
+        total: u8,
+    },
+    /// A public objective was revealed
+    ObjectiveRevealed { objective: ObjectiveId },
+    /// A player drew a secret objective
+    SecretObjectiveDrawn { player: PlayerId },
+    /// A player was to draw a secret objective, but the deck had run out
+    SecretObjectiveDeckEmpty { player: PlayerId },
+    /// A player discarded an unscored secret objective
+    SecretObjectiveDiscarded { player: PlayerId },
+    /// A player scored an objective
+    ObjectiveScored {
+        player: PlayerId,
+        objective: ObjectiveId,
+    },
+    /// A player placed the command tokens gained during the status phase
+    CommandTokensRedistributed {
+        player: PlayerId,
+        tokens: CommandTokens,
+    },
+    /// An agenda was revealed for players to vote on, or none if the deck ran out of agendas
+    /// anyone could vote for, in which case the agenda is skipped
+    AgendaRevealed {
+        agenda: u8,
+        #[serde(default)]
+        card: Option<AgendaId>,
+    },
+    /// A player placed a rider predicting the outcome of the current agenda
+    RiderPlaced {
+        player: PlayerId,
+        rider: Rider,
+        outcome: String,
+    },
+    /// A player voted on the current agenda
+    VoteCast {
+        player: PlayerId,
+        outcome: Option<String>,
+        votes: u32,
+    },
+    /// An agenda resolved with the outcome that won the vote, or none if it had no outcome
+    AgendaResolved {
+        card: AgendaId,
+        outcome: Option<String>,
+    },
+    /// A law was put into play, electing the given outcome if it elects one
+    LawEnacted {
+        card: AgendaId,
+        elected: Option<String>,
+    },
+    /// A law was discarded from play
+    LawRepealed { card: AgendaId },
+    /// A player drew action cards
+    ActionCardsDrawn { player: PlayerId, count: u8 },
+    /// A player discarded action cards from their hand without playing them
+    ActionCardsDiscarded { player: PlayerId, count: u8 },
+    /// A player played an action card
+    ActionCardPlayed {
+        player: PlayerId,
+        card: ActionCardId,
+        #[serde(default)]
+        target: Option<CardTarget>,
+    },
+    /// An action card was canceled before it resolved
+    ActionCardCanceled {
+        player: PlayerId,
+        card: ActionCardId,
+    },
+    /// A player used an option their abilities or technologies offer them in a timing window
+    WindowOptionUsed { player: PlayerId, option: EffectId },
+    /// A transaction was proposed, waiting on the other player
+    TransactionProposed { transaction: Transaction },
+    /// The terms of a transaction were replaced, now waiting on the other player
+    TransactionCountered { transaction: Transaction },
+    /// A player withdrew from a transaction
+    TransactionCanceled {
+        transaction: TransactionId,
+        player: PlayerId,
+    },
+    /// Both players agreed to a transaction and exchanged its terms
+    TransactionResolved { transaction: Transaction },
+    /// A promissory note went back to the player who owns it
+    PromissoryNoteReturned {
+        note: PromissoryNote,
+        from: PlayerId,
+    },
+}
+
+/// The events produced by a single command, sent to every player in the game
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameUpdate {
+    pub game: GameId,
+    /// The sequence number of the game after the command was applied
+    pub sequence: u64,
+    pub events: Vec<GameEvent>,
+}
+
+/// A command was rejected
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+pub enum CommandError {
+    #[error("{0} is not a player in this game")]
+    NotAPlayer(PlayerId),
+    #[error("exactly {expected} different starting technologies must be picked")]
+    WrongStartingTechs { expected: u8 },
+    #[error("{0} is not one of your starting technologies")]
+    NotAStartingTech(TechId),
+    #[error("it is not your turn")]
+    NotYourTurn,
+    #[error("that can't be done during this phase")]
+    NotNow,
+    #[error("{0} has already been picked")]
+    CardTaken(StrategyCard),
+    #[error("you don't hold {0}")]
+    NotYourCard(StrategyCard),
+    #[error("the strategic action of {0} has already been performed")]
+    CardExhausted(StrategyCard),
+    #[error("you must perform the strategic action of your strategy cards before passing")]
+    MustUseStrategyCard,
+    #[error("command tokens must add up to {expected}")]
+    InvalidCommandTokens { expected: u8 },
+    #[error("you have no strategy token to spend")]
+    NoStrategyToken,
+    #[error("you don't control {0}")]
+    NotYourPlanet(String),
+    #[error("{0} is already exhausted")]
+    PlanetExhausted(String),
+    #[error("{0} is already ready")]
+    PlanetReady(String),
+    #[error("{0} has no technology specialty")]
+    NoSpecialty(String),
+    #[error("you only have {have} trade goods")]
+    NotEnoughTradeGoods { have: u8 },
+    #[error("that costs {cost}, but only {paid} was paid")]
+    CannotAfford { cost: u8, paid: u8 },
+    #[error(transparent)]
+    Research(#[from] ResearchError),
+    #[error("{0} can't become the speaker")]
+    InvalidSpeaker(PlayerId),
+    #[error("a {0} is not a structure")]
+    NotAStructure(UnitKind),
+    #[error("{0} is not on the board")]
+    PlanetNotOnBoard(String),
+    #[error("you have no command token in {0}")]
+    NoCommandToken(Hex),
+    #[error("{0} can't be chosen")]
+    CannotTarget(String),
+    #[error("at most {max} planets can be chosen")]
+    TooManyPlanets { max: usize },
+    #[error("that ability belongs to another strategy card")]
+    WrongCard,
+    #[error("you have no tactic token to spend")]
+    NoTacticToken,
+    #[error(transparent)]
+    Movement(#[from] MoveError),
+    #[error("exactly {expected} hits must be assigned")]
+    WrongHits { expected: u8 },
+    #[error("no undamaged {0} can sustain damage")]
+    CannotSustain(UnitKind),
+    #[error("you have no {0} to assign a hit to")]
+    NoUnitToHit(UnitKind),
+    #[error("you can't retreat to {0}")]
+    InvalidRetreat(Hex),
+    #[error("you have no {0} available")]
+    NoSuchUnit(UnitKind),
+    #[error("{0} can't be bombarded")]
+    CannotBombard(String),
+    #[error("ground forces can't be committed to {0}")]
+    CannotLand(String),
+    #[error("your home system is not on the board")]
+    NoHomeSystem,
+    #[error(transparent)]
+    Production(#[from] ProductionError),
+    #[error("{0} is not an outcome of this agenda")]
+    InvalidOutcome(String),
+    #[error("voting for an outcome takes exhausting planets with influence")]
+    NoVotes,
+    #[error("the {0} has already been placed on this agenda")]
+    RiderTaken(Rider),
+    #[error("your fleet pool can hold at most {max} command tokens")]
+    FleetPoolLimit { max: u8 },
+    #[error(transparent)]
+    Objective(#[from] ObjectiveError),
+    #[error(transparent)]
+    ActionCard(#[from] ActionCardError),
+    #[error(transparent)]
+    Transaction(#[from] TransactionError),
+}
+
+/// Creates a game where each player controls a planet in a system next to Mecatol Rex
+#[cfg(test)]
+pub(crate) fn test_game(players: usize) -> (GameState, Vec<PlayerId>) {
+    use crate::game::galaxy::{Galaxy, MapTile};
+    use crate::lobby::{GameSettings, GameStatus, Seat};
+
+    let ids = (0..players)
+        .map(|_| PlayerId(uuid::Uuid::new_v4()))
+        .collect::<Vec<_>>();
+    let summary = GameSummary {
+        id: GameId(uuid::Uuid::new_v4()),
+        name: "test".to_string(),
+        host: ids[0],
+        settings: GameSettings::default(),
+        status: GameStatus::InProgress,
+        seats: ids
+            .iter()
+            .map(|&player| {
+                Some(Seat {
+                    player,
+                    name: player.to_string(),
+                    disconnected: false,
+                })
+            })
+            .collect(),
+    };
+    let mut state = GameState::new(&summary, 0);
+    // players start without a faction, so that tests only deal with the planets given here
+    for player in &mut state.players {
+        player.faction = None;
+        player.technologies.clear();
+        player.planets.clear();
+        player.tech_picks = 0;
+        player.promissory_notes = transactions::starting_notes(player.id, None);
+    }
+    let mut galaxy = Galaxy::new();
+    galaxy.place(Hex::ORIGIN, MapTile::system("18"));
+    for (index, (hex, player)) in Hex::ORIGIN
+        .spiral(2)
+        .into_iter()
+        .skip(1)
+        .zip(&mut state.players)
+        .enumerate()
+    {
+        let tile = tiles::catalog().get(&(19 + index).to_string()).unwrap();
+        galaxy.place(hex, MapTile::system(tile.id.clone()));
+        for planet in &tile.planets {
+            player
+                .planets
+                .insert(planet.name.clone(), PlanetCard::default());
+        }
+    }
+    state.board = Board::new(galaxy);
+    (state, ids)
+}
+
+/// Creates a [test_game] in its action phase, each player having picked strategy cards in order
+#[cfg(test)]
+pub(crate) fn test_action_phase(players: usize) -> (GameState, Vec<PlayerId>) {
+    use crate::game::round::PhaseKind;
+    use crate::game::strategy::StrategyCard;
+
+    let (mut state, ids) = test_game(players);
+    for card in StrategyCard::ALL {
+        if state.round.phase.kind() != PhaseKind::Strategy {
+            break;
+        }
+        let player = state.pending()[0].player;
+        state
+            .apply(player, GameCommand::PickStrategyCard { card })
+            .unwrap();
+    }
+    (state, ids)
+}
@@ -0,0 +1,996 @@
+//! The action card deck, and the timing windows action cards are played in.
+//!
+//! Every action card is read from `data/action_cards.json`, which is embedded into the crate.
+//! It only holds a sample of the cards of the game, so the deck is smaller than the real one.
+//! Once it runs out, the discard pile is shuffled into a new deck, and players draw fewer cards
+//! if every card is already in someone's hand.
+//!
+//! Each card names the [Window] it is played in, such as the start of a combat round or right
+//! after a system is activated. Whenever the engine reaches a window, it opens it on a stack
+//! of [OpenWindow]s and pauses, offering it clockwise from the player whose action opened it
+//! to every player holding action cards who could play one there, and to every player whose
+//! abilities or technologies offer them an option there, as described in
+//! [crate::game::effects]. Whether they actually hold
+//! such a card isn't taken into account, so that being offered a window gives nothing away.
+//! Each of them plays a card or passes in turn, and the engine carries on where it stopped
+//! once everyone has decided.
+//!
+//! Playing a card opens a window of its own, where other players may cancel it with
+//! "Sabotage" before it resolves. Cards played as an action on a player's turn end that turn
+//! once they resolve, even if they were canceled.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::board::Unit;
+use crate::game::dice::Dice;
+use crate::game::effects::EffectId;
+use crate::game::hex::Hex;
+use crate::game::movement::Movement;
+use crate::game::payment::{planet_value, Currency};
+use crate::game::round::{clockwise_from, Decision, DecisionKind, Phase};
+use crate::game::units::UnitKind;
+use crate::game::{CommandError, CommandTokens, GameEvent, GameState};
+use crate::lobby::PlayerId;
+
+const ACTION_CARDS: &str = include_str!("../../data/action_cards.json");
+
+/// The number of action cards every player draws during the status phase
+pub const STATUS_PHASE_ACTION_CARDS: u8 = 1;
+
+/// Gets the catalog of every action card
+pub fn catalog() -> &'static ActionCardCatalog {
+    static CATALOG: OnceLock<ActionCardCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let cards: Vec<ActionCard> =
+            serde_json::from_str(ACTION_CARDS).expect("embedded action card catalog is valid");
+        ActionCardCatalog {
+            order: cards.iter().map(|card| card.id.clone()).collect(),
+            cards: cards
+                .into_iter()
+                .map(|card| (card.id.clone(), card))
+                .collect(),
+        }
+    })
+}
+
+/// Identifies an action card, such as `sabotage`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ActionCardId(pub String);
+
+impl Borrow<str> for ActionCardId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for ActionCardId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Display for ActionCardId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A moment of the game where action cards can be played
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Window {
+    /// As an action on the player's turn of the action phase
+    Action,
+    /// Right after a player activates a system, before moving units into it
+    AfterSystemActivated,
+    /// At the start of each round of space combat, before retreats are announced
+    StartOfCombatRound,
+    /// When an agenda is revealed, before riders are placed
+    AgendaRevealed,
+    /// When another player plays an action card, before it resolves
+    ActionCardPlayed,
+}
+
+/// An action card, as printed on its card
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ActionCard {
+    pub id: ActionCardId,
+    pub name: String,
+    /// The number of copies of the card in the deck
+    pub count: u8,
+    pub window: Window,
+    pub text: String,
+    #[serde(flatten)]
+    pub effect: ActionEffect,
+}
+
+/// What an action card does when it resolves
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum ActionEffect {
+    /// Cancels the action card another player just played
+    CancelActionCard,
+    /// Returns the player's command token in the activated system to their tactic pool
+    ReturnCommandToken,
+    /// Discards the agenda just revealed and reveals another in its place
+    ReplaceAgenda,
+    /// Gains trade goods equal to the resources of the targeted planet
+    GainPlanetResources,
+    /// Places infantry on the targeted planet
+    PlaceInfantry { infantry: u8 },
+    /// Removes a token from the targeted player's tactic pool
+    RemoveTacticToken,
+    /// Resolves an effect described in the effect catalog
+    Scripted { script: EffectId },
+}
+
+impl ActionEffect {
+    /// Checks whether an action card with this effect is played on a target
+    fn needs_target(&self) -> bool {
+        matches!(
+            self,
+            ActionEffect::GainPlanetResources
+                | ActionEffect::PlaceInfantry { .. }
+                | ActionEffect::RemoveTacticToken
+        )
+    }
+}
+
+/// What an action card is played on
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "target", rename_all = "snake_case")]
+pub enum CardTarget {
+    Planet { planet: String },
+    Player { player: PlayerId },
+}
+
+/// Every action card, by id
+#[derive(Debug)]
+pub struct ActionCardCatalog {
+    cards: BTreeMap<ActionCardId, ActionCard>,
+    /// Ids in the order they are listed in
+    order: Vec<ActionCardId>,
+}
+
+impl ActionCardCatalog {
+    /// Gets an action card by its id
+    pub fn get(&self, id: &str) -> Option<&ActionCard> {
+        self.cards.get(id)
+    }
+
+    /// Gets every action card, once however many copies the deck holds
+    pub fn cards(&self) -> impl Iterator<Item = &ActionCard> {
+        self.order.iter().map(|id| &self.cards[id])
+    }
+}
+
+/// The action cards not in anyone's hand
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ActionDeck {
+    /// The cards left to draw, drawn from the end
+    #[serde(default)]
+    pub draw: Vec<ActionCardId>,
+    /// The cards played or discarded, shuffled back into the deck once it runs out
+    #[serde(default)]
+    pub discard: Vec<ActionCardId>,
+}
+
+/// An action card a player played, waiting to resolve
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlayedCard {
+    pub player: PlayerId,
+    pub card: ActionCardId,
+    #[serde(default)]
+    pub target: Option<CardTarget>,
+}
+
+/// A timing window the game is paused in, offering players to play action cards
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OpenWindow {
+    pub window: Window,
+    /// The player whose action opened the window, such as the active player
+    pub player: PlayerId,
+    /// The system the window is about, if any
+    #[serde(default)]
+    pub system: Option<Hex>,
+    /// The players still to decide whether to play a card, in order
+    #[serde(default)]
+    pub deciding: VecDeque<PlayerId>,
+    /// The card this window was opened for, which resolves once it closes
+    #[serde(default)]
+    pub played: Option<PlayedCard>,
+    /// Whether that card was canceled
+    #[serde(default)]
+    pub canceled: bool,
+    /// What the game carries on with once the window closes
+    #[serde(default)]
+    pub resume: Option<Resume>,
+}
+
+impl OpenWindow {
+    /// Creates a window nobody has been offered yet
+    pub fn new(window: Window, player: PlayerId, system: Option<Hex>) -> Self {
+        Self {
+            window,
+            player,
+            system,
+            deciding: VecDeque::new(),
+            played: None,
+            canceled: false,
+            resume: None,
+        }
+    }
+
+    fn resume(self, resume: Resume) -> Self {
+        Self {
+            resume: Some(resume),
+            ..self
+        }
+    }
+}
+
+/// Where the game stopped to open a window
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "resume", rename_all = "snake_case")]
+pub enum Resume {
+    /// Moving units into the system the active player activated
+    Movement { system: Hex, movement: Movement },
+    /// Announcing retreats at the start of a combat round
+    CombatRound { attacker: PlayerId, system: Hex },
+    /// Ending the turn of the player who played an action card as their action
+    EndTurn,
+}
+
+/// Playing an action card breaks the rules
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum ActionCardError {
+    #[error("{card} is not an action card")]
+    Unknown { card: ActionCardId },
+    #[error("{card} is not in your hand")]
+    NotInHand { card: ActionCardId },
+    #[error("{card} can't be played right now")]
+    NotPlayable { card: ActionCardId },
+    #[error("{card} has to be played on a target")]
+    MissingTarget { card: ActionCardId },
+    #[error("{card} can't be played on that target")]
+    InvalidTarget { card: ActionCardId },
+    #[error("{option} can't be used right now")]
+    OptionNotOffered { option: EffectId },
+}
+
+impl GameState {
+    /// Shuffles every copy of every action card into the deck at the start of the game
+    pub(super) fn set_up_action_cards(&mut self) {
+        let mut draw = catalog()
+            .cards()
+            .flat_map(|card| std::iter::repeat_n(card.id.clone(), usize::from(card.count)))
+            .collect::<Vec<_>>();
+        self.dice.shuffle(&mut draw);
+        self.action_deck = ActionDeck {
+            draw,
+            discard: vec![],
+        };
+    }
+
+    /// Draws action cards into a player's hand, shuffling the discard pile back into the deck
+    /// once it runs out
+    pub(super) fn draw_action_cards(&mut self, player: PlayerId, count: u8) -> Vec<GameEvent> {
+        let mut drawn = 0;
+        for _ in 0..count {
+            if self.action_deck.draw.is_empty() {
+                let mut draw = std::mem::take(&mut self.action_deck.discard);
+                self.dice.shuffle(&mut draw);
+                self.action_deck.draw = draw;
+            }
+            let Some(card) = self.action_deck.draw.pop() else {
+                break;
+            };
+            let Some(state) = self.player_mut(player) else {
+                self.action_deck.draw.push(card);
+                break;
+            };
+            state.action_cards.push(card);
+            drawn += 1;
+        }
+        if drawn == 0 {
+            return vec![];
+        }
+        vec![GameEvent::ActionCardsDrawn {
+            player,
+            count: drawn,
+        }]
+    }
+
+    /// Discards every action card in a player's hand
+    pub(super) fn discard_action_cards(&mut self, player: PlayerId) -> Vec<GameEvent> {
+        let Some(state) = self.player_mut(player) else {
+            return vec![];
+        };
+        let cards = std::mem::take(&mut state.action_cards);
+        if cards.is_empty() {
+            return vec![];
+        }
+        let count = cards.len() as u8;
+        self.action_deck.discard.extend(cards);
+        vec![GameEvent::ActionCardsDiscarded { player, count }]
+    }
+
+    /// Gets the cards in a player's hand they can play in a window
+    pub fn playable_cards(&self, player: PlayerId, window: &OpenWindow) -> Vec<ActionCardId> {
+        let Some(state) = self.player(player) else {
+            return vec![];
+        };
+        let mut cards = state
+            .action_cards
+            .iter()
+            .filter(|card| {
+                catalog()
+                    .get(&card.0)
+                    .is_some_and(|card| self.can_play(player, card, window))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        cards.dedup();
+        cards
+    }
+
+    /// Checks whether a player can play an action card in a window, whether or not they hold
+    /// it
+    fn can_play(&self, player: PlayerId, card: &ActionCard, window: &OpenWindow) -> bool {
+        if card.window != window.window {
+            return false;
+        }
+        // cards played at the start of a combat round only help the players fighting it
+        let fighting = self
+            .combat()
+            .is_some_and(|combat| combat.attacker == player || combat.defender == Some(player));
+        if card.window == Window::StartOfCombatRound && !fighting {
+            return false;
+        }
+        match card.effect {
+            // "Sabotage" can't cancel another "Sabotage", or the player's own cards
+            ActionEffect::CancelActionCard => window.played.as_ref().is_some_and(|played| {
+                played.player != player
+                    && catalog()
+                        .get(&played.card.0)
+                        .is_some_and(|played| played.effect != ActionEffect::CancelActionCard)
+            }),
+            ActionEffect::ReturnCommandToken => {
+                player != window.player
+                    && window.system.is_some_and(|system| {
+                        self.board.system(system).command_tokens.contains(&player)
+                    })
+            }
+            _ => true,
+        }
+    }
+
+    /// Checks that an action card is played on a target it accepts
+    fn check_target(
+        &self,
+        player: PlayerId,
+        card: &ActionCard,
+        target: Option<&CardTarget>,
+    ) -> Result<(), ActionCardError> {
+        let controls = |planet: &str| {
+            self.player(player)
+                .is_some_and(|state| state.planets.contains_key(planet))
+        };
+        let valid = match (&card.effect, target) {
+            (effect, None) if effect.needs_target() => {
+                return Err(ActionCardError::MissingTarget {
+                    card: card.id.clone(),
+                });
+            }
+            (ActionEffect::GainPlanetResources, Some(CardTarget::Planet { planet })) => {
+                controls(planet)
+            }
+            (ActionEffect::PlaceInfantry { .. }, Some(CardTarget::Planet { planet })) => {
+                controls(planet) && self.board.find_planet(planet).is_some()
+            }
+            (ActionEffect::RemoveTacticToken, Some(CardTarget::Player { player: other })) => {
+                *other != player && self.is_player(*other)
+            }
+            (_, None) => true,
+            (_, Some(_)) => false,
+        };
+        if !valid {
+            return Err(ActionCardError::InvalidTarget {
+                card: card.id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Gets the decision the open window is waiting on, if any
+    pub(super) fn window_decision(&self) -> Option<Decision> {
+        let window = self.windows.last()?;
+        let player = *window.deciding.front()?;
+        Some(Decision {
+            player,
+            kind: DecisionKind::PlayActionCard {
+                window: window.window,
+                cards: self.playable_cards(player, window),
+                options: self.window_options(player, window),
+            },
+        })
+    }
+
+    /// Gets the options a player's abilities and technologies offer them in a window
+    pub fn window_options(&self, player: PlayerId, window: &OpenWindow) -> Vec<EffectId> {
+        // like action cards, only the players fighting use options at the start of a combat round
+        let fighting = self
+            .combat()
+            .is_some_and(|combat| combat.attacker == player || combat.defender == Some(player));
+        if window.window == Window::StartOfCombatRound && !fighting {
+            return vec![];
+        }
+        self.player(player)
+            .map(|state| state.window_options(window.window))
+            .unwrap_or_default()
+    }
+
+    /// Checks whether a player is offered a window, judging only from what every player can
+    /// see: they hold action cards and some action card can be played by them there, or their
+    /// abilities and technologies offer them an option there. Whether they hold a card they can
+    /// play stays hidden until they play it or pass.
+    fn is_offered(&self, player: PlayerId, window: &OpenWindow) -> bool {
+        let card = self
+            .player(player)
+            .is_some_and(|state| !state.action_cards.is_empty())
+            && catalog()
+                .cards()
+                .any(|card| self.can_play(player, card, window));
+        card || !self.window_options(player, window).is_empty()
+    }
+
+    /// Opens a window, offering it to every player who might play a card in it. Returns
+    /// whether the game waits on anyone; if not, the window isn't opened at all.
+    pub(super) fn open_window(&mut self, mut window: OpenWindow) -> bool {
+        window.deciding = clockwise_from(&self.seats(), window.player)
+            .into_iter()
+            .filter(|player| self.is_offered(*player, &window))
+            .collect();
+        if window.deciding.is_empty() {
+            return false;
+        }
+        self.windows.push(window);
+        true
+    }
+
+    /// Opens the window of a card just played, resolving it straight away if nobody can
+    /// cancel it
+    fn react_to(
+        &mut self,
+        played: PlayedCard,
+        system: Option<Hex>,
+        resume: Option<Resume>,
+        dice: &mut dyn Dice,
+    ) -> Vec<GameEvent> {
+        let window = OpenWindow {
+            played: Some(played.clone()),
+            resume,
+            ..OpenWindow::new(Window::ActionCardPlayed, played.player, system)
+        };
+        if self.open_window(window.clone()) {
+            return vec![];
+        }
+        self.close_window(window, dice)
+    }
+
+    /// Plays an action card from the player's hand, either in the open window or as their
+    /// action
+    pub(super) fn play_action_card(
+        &mut self,
+        player: PlayerId,
+        card: ActionCardId,
+        target: Option<CardTarget>,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Some(action_card) = catalog().get(&card.0) else {
+            return Err(ActionCardError::Unknown { card }.into());
+        };
+        let state = self.player_state(player)?;
+        let Some(index) = state.action_cards.iter().position(|held| *held == card) else {
+            return Err(ActionCardError::NotInHand { card }.into());
+        };
+        let playable = match self.windows.last() {
+            Some(window) => {
+                if window.deciding.front() != Some(&player) {
+                    return Err(CommandError::NotYourTurn);
+                }
+                self.can_play(player, action_card, window)
+            }
+            None => {
+                self.check_turn(player)?;
+                action_card.window == Window::Action
+            }
+        };
+        if !playable {
+            return Err(ActionCardError::NotPlayable { card }.into());
+        }
+        self.check_target(player, action_card, target.as_ref())?;
+
+        self.player_state_mut(player)?.action_cards.remove(index);
+        let mut events = vec![GameEvent::ActionCardPlayed {
+            player,
+            card: card.clone(),
+            target: target.clone(),
+        }];
+        let played = PlayedCard {
+            player,
+            card,
+            target,
+        };
+        match self.windows.last_mut() {
+            Some(window) if action_card.effect == ActionEffect::CancelActionCard => {
+                // nobody else needs to decide once the card is canceled
+                window.deciding.clear();
+                window.canceled = true;
+                self.action_deck.discard.push(played.card);
+                if let Some(canceled) = window.played.clone() {
+                    events.push(GameEvent::ActionCardCanceled {
+                        player: canceled.player,
+                        card: canceled.card,
+                    });
+                }
+            }
+            Some(window) => {
+                window.deciding.pop_front();
+                let system = window.system;
+                events.extend(self.react_to(played, system, None, dice));
+            }
+            None => events.extend(self.react_to(played, None, Some(Resume::EndTurn), dice)),
+        }
+        events.extend(self.advance_windows(dice));
+        Ok(events)
+    }
+
+    /// Passes on playing an action card in the open window
+    pub(super) fn pass_action_card(
+        &mut self,
+        player: PlayerId,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Some(window) = self.windows.last_mut() else {
+            return Err(CommandError::NotNow);
+        };
+        if window.deciding.front() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        window.deciding.pop_front();
+        Ok(self.advance_windows(dice))
+    }
+
+    /// Uses an option the player's abilities or technologies offer them in the open window,
+    /// resolving its effect straight away
+    pub(super) fn use_window_option(
+        &mut self,
+        player: PlayerId,
+        option: EffectId,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Some(window) = self.windows.last() else {
+            return Err(CommandError::NotNow);
+        };
+        if window.deciding.front() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        if !self.window_options(player, window).contains(&option) {
+            return Err(ActionCardError::OptionNotOffered { option }.into());
+        }
+        if let Some(window) = self.windows.last_mut() {
+            window.deciding.pop_front();
+        }
+        let mut events = vec![GameEvent::WindowOptionUsed {
+            player,
+            option: option.clone(),
+        }];
+        events.extend(self.play_effect(player, &option));
+        events.extend(self.advance_windows(dice));
+        Ok(events)
+    }
+
+    /// Closes every window nobody is left to decide in, from the top of the stack down,
+    /// skipping players who can no longer play anything
+    fn advance_windows(&mut self, dice: &mut dyn Dice) -> Vec<GameEvent> {
+        let mut events = vec![];
+        while let Some(window) = self.windows.last() {
+            let skipped = window
+                .deciding
+                .iter()
+                .take_while(|player| !self.is_offered(**player, window))
+                .count();
+            let Some(window) = self.windows.last_mut() else {
+                break;
+            };
+            window.deciding.drain(..skipped);
+            if !window.deciding.is_empty() {
+                break;
+            }
+            let window = self.windows.pop().expect("checked above");
+            events.extend(self.close_window(window, dice));
+        }
+        events
+    }
+
+    /// Resolves the card of a window unless it was canceled, then carries on where the game
+    /// stopped
+    fn close_window(&mut self, window: OpenWindow, dice: &mut dyn Dice) -> Vec<GameEvent> {
+        let mut events = vec![];
+        if let Some(played) = window.played {
+            if !window.canceled {
+                events.extend(self.resolve_action_card(&played, window.system));
+            }
+            self.action_deck.discard.push(played.card);
+        }
+        match window.resume {
+            Some(Resume::Movement { system, movement }) => {
+                events.extend(self.move_into(window.player, system, &movement, dice));
+            }
+            Some(Resume::CombatRound { attacker, system }) => {
+                let (_, more) = self.announce_retreats(dice);
+                events.extend(more);
+                events.extend(self.after_combat(attacker, system, dice));
+            }
+            Some(Resume::EndTurn) => events.extend(self.end_turn()),
+            None => {}
+        }
+        events
+    }
+
+    /// Applies the effect of an action card that was not canceled
+    fn resolve_action_card(&mut self, played: &PlayedCard, system: Option<Hex>) -> Vec<GameEvent> {
+        let Some(card) = catalog().get(&played.card.0) else {
+            return vec![];
+        };
+        let player = played.player;
+        let mut events = vec![];
+        match (&card.effect, &played.target) {
+            (ActionEffect::CancelActionCard, _) => {}
+            (ActionEffect::ReturnCommandToken, _) => {
+                let Some(system) = system else {
+                    return events;
+                };
+                if self.board.remove_command_token(system, player) {
+                    let tokens = CommandTokens {
+                        tactic: 1,
+                        fleet: 0,
+                        strategy: 0,
+                    };
+                    if let Some(state) = self.player_mut(player) {
+                        state.command_tokens.tactic += 1;
+                    }
+                    events.push(GameEvent::CommandTokenRemoved { player, system });
+                    events.push(GameEvent::CommandTokensGained { player, tokens });
+                }
+            }
+            (ActionEffect::ReplaceAgenda, _) => events.extend(self.replace_agenda()),
+            (ActionEffect::GainPlanetResources, Some(CardTarget::Planet { planet })) => {
+                let amount = planet_value(planet, Currency::Resources);
+                if let Some(state) = self.player_mut(player) {
+                    state.trade_goods += amount;
+                    events.push(GameEvent::TradeGoodsGained { player, amount });
+                }
+            }
+            (ActionEffect::PlaceInfantry { infantry }, Some(CardTarget::Planet { planet })) => {
+                let Some(system) = self.board.find_planet(planet) else {
+                    return events;
+                };
+                self.board
+                    .system_mut(system)
+                    .planets
+                    .entry(planet.clone())
+                    .or_default()
+                    .extend((0..*infantry).map(|_| Unit::new(player, UnitKind::Infantry)));
+                events.push(GameEvent::UnitsPlaced {
+                    player,
+                    planet: planet.clone(),
+                    units: vec![UnitKind::Infantry; usize::from(*infantry)],
+                });
+            }
+            (ActionEffect::Scripted { script }, _) => {
+                events.extend(self.play_effect(player, script));
+            }
+            (ActionEffect::RemoveTacticToken, Some(CardTarget::Player { player: other })) => {
+                let Some(state) = self.player_mut(*other) else {
+                    return events;
+                };
+                if state.command_tokens.tactic > 0 {
+                    state.command_tokens.tactic -= 1;
+                    events.push(GameEvent::CommandTokensLost {
+                        player: *other,
+                        tokens: CommandTokens {
+                            tactic: 1,
+                            fleet: 0,
+                            strategy: 0,
+                        },
+                    });
+                }
+            }
+            // targets are checked when the card is played
+            (_, _) => {}
+        }
+        events
+    }
+
+    /// Opens the window right after a player activated a system, before they move units into
+    /// it. Returns whether the game waits on anyone.
+    pub(super) fn after_system_activated(
+        &mut self,
+        player: PlayerId,
+        system: Hex,
+        movement: &Movement,
+    ) -> bool {
+        let window = OpenWindow::new(Window::AfterSystemActivated, player, Some(system));
+        self.open_window(window.resume(Resume::Movement {
+            system,
+            movement: movement.clone(),
+        }))
+    }
+
+    /// Opens the window at the start of a round of space combat, before retreats are
+    /// announced. Returns whether the game waits on anyone.
+    pub(super) fn start_of_combat_round(&mut self, attacker: PlayerId, system: Hex) -> bool {
+        let window = OpenWindow::new(Window::StartOfCombatRound, attacker, Some(system));
+        self.open_window(window.resume(Resume::CombatRound { attacker, system }))
+    }
+
+    /// Opens the window once an agenda is revealed, offered starting with the speaker
+    pub(super) fn agenda_revealed(&mut self) {
+        if matches!(self.round.phase, Phase::Agenda(_)) {
+            let window = OpenWindow::new(Window::AgendaRevealed, self.round.speaker, None);
+            self.open_window(window);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::action_cards::{ActionCardError, CardTarget, Window};
+    use crate::game::board::Unit;
+    use crate::game::dice::LoadedDice;
+    use crate::game::movement::{Movement, ShipMove};
+    use crate::game::round::DecisionKind;
+    use crate::game::units::UnitKind;
+    use crate::game::{
+        test_action_phase, test_game, CommandError, GameCommand, GameEvent, GameState,
+    };
+    use crate::lobby::PlayerId;
+
+    /// Creates a three player game in its action phase, where the players hold the given
+    /// action cards
+    fn holding(hands: [&[&str]; 3]) -> (GameState, Vec<PlayerId>) {
+        let (mut state, players) = test_action_phase(3);
+        for (player, hand) in players.iter().zip(hands) {
+            state.player_mut(*player).unwrap().action_cards =
+                hand.iter().map(|&card| card.into()).collect();
+        }
+        (state, players)
+    }
+
+    fn play(card: &str, target: Option<CardTarget>) -> GameCommand {
+        GameCommand::PlayActionCard {
+            card: card.into(),
+            target,
+        }
+    }
+
+    #[test]
+    fn sabotage_cancels_an_action_card_played_as_an_action() {
+        let (mut state, players) = holding([&["mining_initiative"], &["sabotage"], &["sabotage"]]);
+        let target = || {
+            Some(CardTarget::Planet {
+                planet: "Saudor".to_string(),
+            })
+        };
+        assert_eq!(
+            state.apply(players[0], play("mining_initiative", target())),
+            Err(CommandError::ActionCard(ActionCardError::InvalidTarget {
+                card: "mining_initiative".into()
+            }))
+        );
+        state
+            .player_mut(players[0])
+            .unwrap()
+            .planets
+            .insert("Saudor".to_string(), Default::default());
+        state
+            .apply(players[0], play("mining_initiative", target()))
+            .unwrap();
+
+        // the other players may cancel it, clockwise from the player who played it
+        let decision = &state.pending()[0];
+        assert_eq!(decision.player, players[1]);
+        assert_eq!(
+            decision.kind,
+            DecisionKind::PlayActionCard {
+                window: Window::ActionCardPlayed,
+                cards: vec!["sabotage".into()],
+                options: vec![],
+            }
+        );
+        assert_eq!(
+            state.apply(players[0], GameCommand::Pass),
+            Err(CommandError::NotNow)
+        );
+        state
+            .apply(players[1], GameCommand::PassActionCard)
+            .unwrap();
+        let events = state.apply(players[2], play("sabotage", None)).unwrap();
+
+        assert!(events.contains(&GameEvent::ActionCardCanceled {
+            player: players[0],
+            card: "mining_initiative".into(),
+        }));
+        assert!(events.contains(&GameEvent::TurnStarted { player: players[1] }));
+        assert_eq!(state.player(players[0]).unwrap().trade_goods, 0);
+        assert!(state.windows.is_empty());
+        assert_eq!(state.action_deck.discard.len(), 2);
+    }
+
+    #[test]
+    fn cards_resolve_when_nobody_can_cancel_them() {
+        let (mut state, players) = holding([&["insubordination", "sabotage"], &[], &[]]);
+        assert_eq!(
+            state.apply(players[0], play("sabotage", None)),
+            Err(CommandError::ActionCard(ActionCardError::NotPlayable {
+                card: "sabotage".into()
+            }))
+        );
+        assert_eq!(
+            state.apply(players[0], play("insubordination", None)),
+            Err(CommandError::ActionCard(ActionCardError::MissingTarget {
+                card: "insubordination".into()
+            }))
+        );
+        let target = Some(CardTarget::Player { player: players[2] });
+        state
+            .apply(players[0], play("insubordination", target))
+            .unwrap();
+
+        assert_eq!(state.player(players[2]).unwrap().command_tokens.tactic, 2);
+        assert_eq!(state.pending()[0].player, players[1]);
+    }
+
+    #[test]
+    fn windows_pause_a_tactical_action_and_combat() {
+        let (mut state, players) = holding([&["morale_boost"], &["counterstroke"], &["sabotage"]]);
+        let home = state.board.find_planet("Wellon").unwrap();
+        let target = state.board.find_planet("Vefut II").unwrap();
+        state
+            .board
+            .place_in_space(home, Unit::new(players[0], UnitKind::Cruiser));
+        state
+            .board
+            .place_in_space(target, Unit::new(players[1], UnitKind::Destroyer));
+        state.board.place_command_token(target, players[1]);
+
+        let movement = Movement {
+            ships: vec![ShipMove {
+                unit: UnitKind::Cruiser,
+                from: home,
+                through: vec![],
+            }],
+            transported: vec![],
+        };
+        let events = state
+            .apply(
+                players[0],
+                GameCommand::TacticalAction {
+                    system: target,
+                    movement,
+                },
+            )
+            .unwrap();
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::UnitsMoved { .. })));
+        assert_eq!(state.pending()[0].player, players[1]);
+
+        // nobody sabotages Counterstroke, then the cruiser moves in and combat starts
+        state
+            .apply(players[1], play("counterstroke", None))
+            .unwrap();
+        state
+            .apply(players[2], GameCommand::PassActionCard)
+            .unwrap();
+        // players[0] holds a card, so they are offered the window too
+        assert_eq!(state.pending()[0].player, players[0]);
+        let events = state
+            .apply(players[0], GameCommand::PassActionCard)
+            .unwrap();
+        assert!(events.contains(&GameEvent::CommandTokenRemoved {
+            player: players[1],
+            system: target,
+        }));
+        assert_eq!(state.player(players[1]).unwrap().command_tokens.tactic, 4);
+        assert!(events.contains(&GameEvent::SpaceCombatStarted {
+            system: target,
+            attacker: players[0],
+            defender: players[1],
+        }));
+
+        // the cruiser hits on 7, and on 6 with Morale Boost
+        let decision = &state.pending()[0];
+        assert_eq!(decision.player, players[0]);
+        assert!(matches!(
+            decision.kind,
+            DecisionKind::PlayActionCard {
+                window: Window::StartOfCombatRound,
+                ..
+            }
+        ));
+        state.apply(players[0], play("morale_boost", None)).unwrap();
+        assert_eq!(state.pending()[0].player, players[2]);
+        state
+            .apply(players[2], GameCommand::PassActionCard)
+            .unwrap();
+        assert!(matches!(
+            state.pending()[0].kind,
+            DecisionKind::AnnounceRetreat { .. }
+        ));
+        let mut dice = LoadedDice::new([6, 1]);
+        let events = state
+            .apply_with(
+                players[0],
+                GameCommand::AnnounceRetreat { to: None },
+                &mut dice,
+            )
+            .unwrap();
+        assert!(events.contains(&GameEvent::DiceRolled {
+            player: players[0],
+            kind: crate::game::combat::RollKind::SpaceCombat,
+            results: vec![6],
+            hits: 1,
+        }));
+    }
+
+    #[test]
+    fn veto_replaces_the_revealed_agenda() {
+        let (mut state, players) = test_game(3);
+        state.agenda_deck.draw = vec!["mutiny".into(), "fleet_regulations".into()];
+        state.player_mut(players[1]).unwrap().action_cards = vec!["veto".into()];
+        state.start_agenda_phase();
+        assert_eq!(state.pending()[0].player, players[1]);
+        assert_eq!(
+            state.apply(players[1], GameCommand::PlaceRider { prediction: None }),
+            Err(CommandError::NotNow)
+        );
+
+        let events = state.apply(players[1], play("veto", None)).unwrap();
+        assert!(events.contains(&GameEvent::AgendaRevealed {
+            agenda: 1,
+            card: Some("mutiny".into()),
+        }));
+        assert_eq!(state.agenda_deck.discard, vec!["fleet_regulations".into()]);
+        assert!(matches!(
+            state.pending()[0].kind,
+            DecisionKind::PlaceRider { agenda: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn players_draw_what_is_left_once_every_card_is_drawn() {
+        let (mut state, players) = test_game(3);
+        state.action_deck.draw = vec!["veto".into()];
+        state.action_deck.discard = vec!["sabotage".into()];
+        assert_eq!(
+            state.draw_action_cards(players[0], 3),
+            vec![GameEvent::ActionCardsDrawn {
+                player: players[0],
+                count: 2,
+            }]
+        );
+        assert_eq!(state.draw_action_cards(players[1], 1), vec![]);
+        assert_eq!(state.player(players[0]).unwrap().action_cards.len(), 2);
+    }
+}
@@ -0,0 +1,1357 @@
+//! The agenda deck, and voting on agendas during the agenda phase.
+//!
+//! Every agenda of the base game is read from `data/agendas.json`, which is embedded into the
+//! crate. Once the deck runs out, it is shuffled again from every agenda not in play, and once none
+//! is left that anyone could vote for, the agendas of the phase are revealed empty and skipped.
+//! Otherwise, each agenda of the phase is revealed, then every player in voting order may place a
+//! rider predicting its outcome, and then votes for an outcome with the influence of the planets
+//! they exhaust. The outcome with the most votes wins, and the speaker breaks ties. Players may
+//! play action cards as soon as an agenda is revealed, before anyone places a rider.
+//!
+//! Directives resolve once and are discarded. Laws that pass stay in play, and the rules they
+//! change are declared as [LawRule]s. The rest of the engine asks the hooks at the bottom of
+//! this module, such as [GameState::produced_per_cost], instead of looking for specific laws.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::board::{Unit, MECATOL_REX};
+use crate::game::hex::Hex;
+use crate::game::objectives::{self, Stage};
+use crate::game::payment::{planet_value, Currency};
+use crate::game::round::{clockwise_from, Decision, DecisionKind, Phase, PhaseKind};
+use crate::game::tiles::{self, PlanetTrait, TileKind};
+use crate::game::units::UnitKind;
+use crate::game::{CommandError, CommandTokens, GameEvent, GameState};
+use crate::lobby::PlayerId;
+
+const AGENDAS: &str = include_str!("../../data/agendas.json");
+
+/// The number of agendas voted on during every agenda phase
+pub const AGENDAS_PER_PHASE: u8 = 2;
+
+/// The outcome of agendas voted on for or against that puts them into effect
+pub const FOR: &str = "for";
+
+/// The outcome of agendas voted on for or against that rejects them
+pub const AGAINST: &str = "against";
+
+/// Gets the catalog of every agenda
+pub fn catalog() -> &'static AgendaCatalog {
+    static CATALOG: OnceLock<AgendaCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let agendas: Vec<Agenda> =
+            serde_json::from_str(AGENDAS).expect("embedded agenda catalog is valid");
+        AgendaCatalog {
+            order: agendas.iter().map(|agenda| agenda.id.clone()).collect(),
+            agendas: agendas
+                .into_iter()
+                .map(|agenda| (agenda.id.clone(), agenda))
+                .collect(),
+        }
+    })
+}
+
+/// Identifies an agenda, such as `fleet_regulations`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AgendaId(pub String);
+
+impl Borrow<str> for AgendaId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for AgendaId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Display for AgendaId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Whether an agenda stays in play once resolved
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgendaKind {
+    /// Stays in play, changing the rules, unless voted against
+    Law,
+    /// Resolves once and is discarded
+    Directive,
+}
+
+/// What players vote for on an agenda
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "elect", rename_all = "snake_case")]
+pub enum Election {
+    /// Either "for" or "against"
+    ForAgainst,
+    /// A player in the game
+    Player,
+    /// A planet controlled by a player, with the given trait if any
+    Planet {
+        #[serde(rename = "trait", default)]
+        planet_trait: Option<PlanetTrait>,
+    },
+    /// A law in play
+    Law,
+    /// A secret objective a player scored
+    SecretObjective,
+    /// A planet controlled by a player, outside of every home system and other than Mecatol Rex
+    NonHomePlanet,
+}
+
+/// An agenda, as printed on its card
+///
+/// Its effects and rules are what the engine resolves, and players follow the rest of its text
+/// themselves.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Agenda {
+    pub id: AgendaId,
+    pub name: String,
+    pub kind: AgendaKind,
+    #[serde(flatten)]
+    pub election: Election,
+    pub text: String,
+    /// Resolved when "for" or an elected outcome wins
+    #[serde(default)]
+    pub effects: Vec<AgendaEffect>,
+    /// Resolved when "against" wins
+    #[serde(default)]
+    pub against: Vec<AgendaEffect>,
+    /// The rules this agenda changes while it is in play as a law
+    #[serde(default)]
+    pub rules: Vec<LawRule>,
+}
+
+/// Something that happens when an agenda resolves
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum AgendaEffect {
+    /// Players gain victory points, or lose them if negative
+    VictoryPoints { players: Recipients, points: i8 },
+    /// Players gain trade goods
+    TradeGoods { players: Recipients, amount: u8 },
+    /// Players return all of their trade goods to the supply
+    ReturnTradeGoods { players: Recipients },
+    /// The elected law is discarded from play
+    DiscardElectedLaw,
+    /// Players destroy half of their infantry on each planet they control, rounded up, and gain
+    /// a trade good for each
+    DisarmInfantry,
+    /// Players place an infantry on each planet they control
+    PlaceInfantry,
+    /// Players holding the speaker token give it to the player on their left
+    PassSpeaker { players: Recipients },
+    /// Players can't vote for the rest of the agenda phase
+    ForbidVoting { players: Recipients },
+    /// Players discard every action card in their hand
+    DiscardActionCards { players: Recipients },
+    /// Players draw action cards
+    DrawActionCards { players: Recipients, count: u8 },
+    /// Players draw a secret objective
+    DrawSecretObjective { players: Recipients },
+    /// The next public objective of a stage is revealed
+    RevealObjective { stage: Stage },
+    /// Every ground force on the elected planet is destroyed, and its controller gains a trade
+    /// good for each
+    DestroyGroundForces,
+    /// Every law in play is discarded
+    DiscardLaws,
+}
+
+/// The players an [AgendaEffect] applies to
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recipients {
+    Everyone,
+    /// Players who cast votes for "for", whichever outcome won
+    VotedFor,
+    /// Players who cast votes for "against", whichever outcome won
+    VotedAgainst,
+    /// The elected player, or the controller of the elected planet
+    Elected,
+    MostPoints,
+    FewestPoints,
+}
+
+/// A rule changed by a law while it is in play
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum LawRule {
+    /// Fighters and infantry are produced one for their cost, rather than two
+    SingleUnitProduction,
+    /// Fleet pools hold at most this many command tokens
+    FleetPoolLimit { max: u8 },
+    /// Units on the elected planet can't use production
+    NoProductionOnElectedPlanet,
+    /// Gaining control of the elected planet gains victory points, and losing it loses them
+    ElectedPlanetPoints { points: u8 },
+}
+
+/// Every agenda, by id
+#[derive(Debug)]
+pub struct AgendaCatalog {
+    agendas: BTreeMap<AgendaId, Agenda>,
+    /// Ids in the order they are listed in
+    order: Vec<AgendaId>,
+}
+
+impl AgendaCatalog {
+    /// Gets an agenda by its id
+    pub fn get(&self, id: &str) -> Option<&Agenda> {
+        self.agendas.get(id)
+    }
+
+    /// Gets every agenda
+    pub fn agendas(&self) -> impl Iterator<Item = &Agenda> {
+        self.order.iter().map(|id| &self.agendas[id])
+    }
+}
+
+/// The agendas not in play as laws
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AgendaDeck {
+    /// The agendas left to reveal, drawn from the end
+    #[serde(default)]
+    pub draw: Vec<AgendaId>,
+    /// The agendas resolved, in the order they were discarded
+    #[serde(default)]
+    pub discard: Vec<AgendaId>,
+}
+
+/// A rider, predicting the outcome of an agenda for a reward
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rider {
+    /// Gains a victory point
+    Imperial,
+    /// Gains 5 trade goods
+    Trade,
+}
+
+impl Rider {
+    pub const ALL: [Rider; 2] = [Rider::Imperial, Rider::Trade];
+}
+
+impl Display for Rider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Rider::Imperial => "Imperial Rider",
+            Rider::Trade => "Trade Rider",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The trade goods gained for predicting an outcome with the Trade Rider
+const TRADE_RIDER_GOODS: u8 = 5;
+
+/// A rider placed on an outcome
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Prediction {
+    pub rider: Rider,
+    pub outcome: String,
+}
+
+/// A player's prediction on the agenda being voted on
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlacedRider {
+    pub player: PlayerId,
+    #[serde(flatten)]
+    pub prediction: Prediction,
+}
+
+/// Votes a player cast for an outcome
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CastVote {
+    pub player: PlayerId,
+    pub outcome: String,
+    pub votes: u32,
+}
+
+/// The agenda phase, where players vote on agendas
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AgendaPhase {
+    /// The agenda being voted on, starting from 1
+    pub agenda: u8,
+    /// The card of the agenda being voted on
+    #[serde(default)]
+    pub card: Option<AgendaId>,
+    #[serde(default)]
+    pub step: AgendaStep,
+    /// The players still to decide during this step, in voting order
+    pub voting: VecDeque<PlayerId>,
+    #[serde(default)]
+    pub riders: Vec<PlacedRider>,
+    #[serde(default)]
+    pub votes: Vec<CastVote>,
+    /// The players who can't vote for the rest of the phase
+    #[serde(default)]
+    pub silenced: BTreeSet<PlayerId>,
+}
+
+/// The steps of voting on a single agenda
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum AgendaStep {
+    /// Players place riders before anyone votes
+    Riders,
+    #[default]
+    Voting,
+    /// The speaker chooses between the outcomes tied for the most votes
+    BreakTie { outcomes: Vec<String> },
+}
+
+impl GameState {
+    /// Gets the outcomes players can vote for on an agenda
+    pub fn outcomes(&self, agenda: &Agenda) -> Vec<String> {
+        match agenda.election {
+            Election::ForAgainst => vec![FOR.to_string(), AGAINST.to_string()],
+            Election::Player => self.seats().iter().map(ToString::to_string).collect(),
+            Election::Planet { planet_trait } => self
+                .players
+                .iter()
+                .flat_map(|player| player.planets.keys())
+                .filter(|planet| {
+                    planet_trait.is_none_or(|planet_trait| {
+                        tiles::catalog()
+                            .planet(planet)
+                            .is_some_and(|(_, planet)| planet.planet_trait == Some(planet_trait))
+                    })
+                })
+                .cloned()
+                .collect(),
+            Election::Law => self.laws.keys().map(ToString::to_string).collect(),
+            Election::SecretObjective => self
+                .players
+                .iter()
+                .flat_map(|player| &player.scored_objectives)
+                .filter(|objective| {
+                    objectives::catalog()
+                        .get(&objective.0)
+                        .is_some_and(|objective| objective.stage == Stage::Secret)
+                })
+                .map(ToString::to_string)
+                .collect(),
+            Election::NonHomePlanet => self
+                .players
+                .iter()
+                .flat_map(|player| player.planets.keys())
+                .filter(|planet| {
+                    planet.as_str() != MECATOL_REX
+                        && tiles::catalog()
+                            .planet(planet)
+                            .is_some_and(|(tile, _)| tile.kind != TileKind::Home)
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Gets the agenda being voted on
+    fn current_agenda(&self) -> Option<&'static Agenda> {
+        let Phase::Agenda(phase) = &self.round.phase else {
+            return None;
+        };
+        catalog().get(&phase.card.as_ref()?.0)
+    }
+
+    /// Gets the players who vote on the current agenda, in order
+    fn voters(&self) -> Vec<PlayerId> {
+        let silenced = match &self.round.phase {
+            Phase::Agenda(phase) => phase.silenced.clone(),
+            _ => BTreeSet::new(),
+        };
+        self.voting_order()
+            .into_iter()
+            .filter(|player| !silenced.contains(player))
+            .collect()
+    }
+
+    /// Gets the decisions the agenda phase is waiting on
+    pub(super) fn agenda_decisions(&self, phase: &AgendaPhase) -> Vec<Decision> {
+        let outcomes = self
+            .current_agenda()
+            .map(|agenda| self.outcomes(agenda))
+            .unwrap_or_default();
+        let agenda = phase.agenda;
+        let kind = match &phase.step {
+            AgendaStep::Riders => DecisionKind::PlaceRider {
+                agenda,
+                riders: Rider::ALL
+                    .into_iter()
+                    .filter(|rider| phase.riders.iter().all(|r| r.prediction.rider != *rider))
+                    .collect(),
+                outcomes,
+            },
+            AgendaStep::Voting => DecisionKind::Vote { agenda, outcomes },
+            AgendaStep::BreakTie { outcomes } => {
+                return vec![Decision {
+                    player: self.round.speaker,
+                    kind: DecisionKind::BreakTie {
+                        agenda,
+                        outcomes: outcomes.clone(),
+                    },
+                }];
+            }
+        };
+        phase
+            .voting
+            .front()
+            .map(|&player| Decision { player, kind })
+            .into_iter()
+            .collect()
+    }
+
+    /// Starts the agenda phase, revealing its first agenda
+    pub(super) fn start_agenda_phase(&mut self) -> Vec<GameEvent> {
+        self.round.phase = Phase::Agenda(AgendaPhase {
+            agenda: 0,
+            card: None,
+            step: AgendaStep::Riders,
+            voting: VecDeque::new(),
+            riders: vec![],
+            votes: vec![],
+            silenced: BTreeSet::new(),
+        });
+        let mut events = vec![GameEvent::PhaseStarted {
+            phase: PhaseKind::Agenda,
+        }];
+        events.extend(self.reveal_agenda());
+        events
+    }
+
+    /// Draws an agenda, shuffling every agenda not in play back into the deck once it runs out
+    fn draw_agenda(&mut self) -> Option<AgendaId> {
+        if self.agenda_deck.draw.is_empty() {
+            let mut draw = catalog()
+                .agendas()
+                .map(|agenda| agenda.id.clone())
+                .filter(|id| !self.laws.contains_key(id))
+                .collect::<Vec<_>>();
+            self.dice.shuffle(&mut draw);
+            self.agenda_deck = AgendaDeck {
+                draw,
+                discard: vec![],
+            };
+        }
+        self.agenda_deck.draw.pop()
+    }
+
+    /// Reveals the next agenda of the phase
+    fn reveal_agenda(&mut self) -> Vec<GameEvent> {
+        let Phase::Agenda(phase) = &mut self.round.phase else {
+            return vec![];
+        };
+        phase.agenda += 1;
+        self.reveal_card()
+    }
+
+    /// Discards the agenda being voted on and reveals another in its place
+    pub(super) fn replace_agenda(&mut self) -> Vec<GameEvent> {
+        let Phase::Agenda(phase) = &mut self.round.phase else {
+            return vec![];
+        };
+        if let Some(card) = phase.card.take() {
+            self.agenda_deck.discard.push(card);
+        }
+        self.reveal_card()
+    }
+
+    /// Reveals the card of the current agenda, discarding those nobody could vote for, then
+    /// lets players play action cards on it
+    fn reveal_card(&mut self) -> Vec<GameEvent> {
+        let mut card = None;
+        for _ in 0..=catalog().agendas().count() {
+            let Some(id) = self.draw_agenda() else {
+                break;
+            };
+            let agenda = catalog()
+                .get(&id.0)
+                .expect("agenda deck only holds cataloged ids");
+            if self.outcomes(agenda).is_empty() {
+                self.agenda_deck.discard.push(id);
+                continue;
+            }
+            card = Some(id);
+            break;
+        }
+        let order = self.voting_order();
+        let Phase::Agenda(phase) = &mut self.round.phase else {
+            return vec![];
+        };
+        phase.card = card.clone();
+        phase.step = AgendaStep::Riders;
+        phase.voting = order.into();
+        phase.riders.clear();
+        phase.votes.clear();
+        let mut events = vec![GameEvent::AgendaRevealed {
+            agenda: phase.agenda,
+            card: card.clone(),
+        }];
+        if card.is_none() {
+            events.extend(self.resolve_agenda(None));
+            return events;
+        }
+        self.agenda_revealed();
+        events
+    }
+
+    /// Places a rider on an outcome of the current agenda, or none
+    pub(super) fn place_rider(
+        &mut self,
+        player: PlayerId,
+        prediction: Option<Prediction>,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let outcomes = self
+            .current_agenda()
+            .map(|agenda| self.outcomes(agenda))
+            .unwrap_or_default();
+        let voters = self.voters();
+        let Phase::Agenda(phase) = &mut self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        if phase.step != AgendaStep::Riders {
+            return Err(CommandError::NotNow);
+        }
+        if phase.voting.front() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        let mut events = vec![];
+        if let Some(prediction) = prediction {
+            if !outcomes.contains(&prediction.outcome) {
+                return Err(CommandError::InvalidOutcome(prediction.outcome));
+            }
+            if phase
+                .riders
+                .iter()
+                .any(|placed| placed.prediction.rider == prediction.rider)
+            {
+                return Err(CommandError::RiderTaken(prediction.rider));
+            }
+            events.push(GameEvent::RiderPlaced {
+                player,
+                rider: prediction.rider,
+                outcome: prediction.outcome.clone(),
+            });
+            phase.riders.push(PlacedRider { player, prediction });
+        }
+        phase.voting.pop_front();
+        if phase.voting.is_empty() {
+            phase.step = AgendaStep::Voting;
+            phase.voting = voters.into();
+            if phase.voting.is_empty() {
+                events.extend(self.tally_votes());
+            }
+        }
+        Ok(events)
+    }
+
+    /// Votes for an outcome of the current agenda with the influence of the exhausted planets,
+    /// or abstains without an outcome
+    pub(super) fn vote(
+        &mut self,
+        player: PlayerId,
+        outcome: Option<String>,
+        planets: Vec<String>,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let outcomes = self
+            .current_agenda()
+            .map(|agenda| self.outcomes(agenda))
+            .unwrap_or_default();
+        let Phase::Agenda(phase) = &self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        if phase.step != AgendaStep::Voting {
+            return Err(CommandError::NotNow);
+        }
+        if phase.voting.front() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        let mut events = vec![];
+        let votes = match &outcome {
+            Some(outcome) => {
+                if !outcomes.contains(outcome) {
+                    return Err(CommandError::InvalidOutcome(outcome.clone()));
+                }
+                let votes = planets
+                    .iter()
+                    .map(|planet| u32::from(planet_value(planet, Currency::Influence)))
+                    .sum::<u32>();
+                if votes == 0 {
+                    return Err(CommandError::NoVotes);
+                }
+                self.player_state_mut(player)?.exhaust_planets(&planets)?;
+                events.push(GameEvent::PlanetsExhausted { player, planets });
+                votes
+            }
+            None if planets.is_empty() => 0,
+            None => return Err(CommandError::NoVotes),
+        };
+        let Phase::Agenda(phase) = &mut self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        phase.voting.pop_front();
+        if let Some(outcome) = outcome.clone() {
+            phase.votes.push(CastVote {
+                player,
+                outcome,
+                votes,
+            });
+        }
+        events.push(GameEvent::VoteCast {
+            player,
+            outcome,
+            votes,
+        });
+        if phase.voting.is_empty() {
+            events.extend(self.tally_votes());
+        }
+        Ok(events)
+    }
+
+    /// Breaks a tie between outcomes, as the speaker
+    pub(super) fn break_tie(
+        &mut self,
+        player: PlayerId,
+        outcome: String,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Phase::Agenda(AgendaPhase {
+            step: AgendaStep::BreakTie { outcomes },
+            ..
+        }) = &self.round.phase
+        else {
+            return Err(CommandError::NotNow);
+        };
+        if player != self.round.speaker {
+            return Err(CommandError::NotYourTurn);
+        }
+        if !outcomes.contains(&outcome) {
+            return Err(CommandError::InvalidOutcome(outcome));
+        }
+        Ok(self.resolve_agenda(Some(outcome)))
+    }
+
+    /// Counts the votes once everyone voted, resolving the agenda unless the speaker has to
+    /// break a tie
+    fn tally_votes(&mut self) -> Vec<GameEvent> {
+        let outcomes = self
+            .current_agenda()
+            .map(|agenda| self.outcomes(agenda))
+            .unwrap_or_default();
+        let Phase::Agenda(phase) = &mut self.round.phase else {
+            return vec![];
+        };
+        let mut totals = BTreeMap::<&str, u32>::new();
+        for vote in &phase.votes {
+            *totals.entry(&vote.outcome).or_default() += vote.votes;
+        }
+        let most = totals.values().copied().max().unwrap_or_default();
+        let mut tied = outcomes
+            .into_iter()
+            .filter(|outcome| totals.get(outcome.as_str()).copied().unwrap_or_default() == most)
+            .collect::<Vec<_>>();
+        if tied.len() > 1 {
+            phase.step = AgendaStep::BreakTie { outcomes: tied };
+            return vec![];
+        }
+        self.resolve_agenda(tied.pop())
+    }
+
+    /// Resolves the current agenda with the winning outcome, then reveals the next agenda or
+    /// ends the phase
+    fn resolve_agenda(&mut self, outcome: Option<String>) -> Vec<GameEvent> {
+        let Phase::Agenda(phase) = &self.round.phase else {
+            return vec![];
+        };
+        let phase = phase.clone();
+        let mut events = vec![];
+        if let Some(card) = phase.card.clone() {
+            events.push(GameEvent::AgendaResolved {
+                card: card.clone(),
+                outcome: outcome.clone(),
+            });
+            if let Some(outcome) = &outcome {
+                events.extend(self.resolve_outcome(&phase, &card, outcome));
+            }
+        }
+        for placed in &phase.riders {
+            if Some(&placed.prediction.outcome) == outcome.as_ref() {
+                events.extend(self.reward_rider(placed.player, placed.prediction.rider));
+            }
+        }
+        if phase.agenda < AGENDAS_PER_PHASE {
+            events.extend(self.reveal_agenda());
+        } else {
+            events.extend(self.ready_planets());
+            events.extend(self.start_round());
+        }
+        events
+    }
+
+    /// Resolves the effects of an agenda's outcome, putting it into play if it is a law
+    fn resolve_outcome(
+        &mut self,
+        phase: &AgendaPhase,
+        card: &AgendaId,
+        outcome: &str,
+    ) -> Vec<GameEvent> {
+        let Some(agenda) = catalog().get(&card.0) else {
+            return vec![];
+        };
+        let against = agenda.election == Election::ForAgainst && outcome == AGAINST;
+        let mut events = vec![];
+        if agenda.kind == AgendaKind::Law && !against {
+            let elected = (agenda.election != Election::ForAgainst).then(|| outcome.to_string());
+            self.laws.insert(card.clone(), elected.clone());
+            events.push(GameEvent::LawEnacted {
+                card: card.clone(),
+                elected,
+            });
+        } else {
+            self.agenda_deck.discard.push(card.clone());
+        }
+        let effects = if against {
+            &agenda.against
+        } else {
+            &agenda.effects
+        };
+        for effect in effects {
+            events.extend(self.apply_agenda_effect(phase, outcome, *effect));
+        }
+        events
+    }
+
+    fn apply_agenda_effect(
+        &mut self,
+        phase: &AgendaPhase,
+        outcome: &str,
+        effect: AgendaEffect,
+    ) -> Vec<GameEvent> {
+        let mut events = vec![];
+        match effect {
+            AgendaEffect::VictoryPoints { players, points } => {
+                for player in self.recipients(phase, outcome, players) {
+                    events.extend(match u8::try_from(points) {
+                        Ok(points) => self.gain_victory_points(player, points),
+                        Err(_) => self.lose_victory_points(player, points.unsigned_abs()),
+                    });
+                }
+            }
+            AgendaEffect::TradeGoods { players, amount } => {
+                for player in self.recipients(phase, outcome, players) {
+                    if let Some(state) = self.player_mut(player) {
+                        state.trade_goods += amount;
+                        events.push(GameEvent::TradeGoodsGained { player, amount });
+                    }
+                }
+            }
+            AgendaEffect::ReturnTradeGoods { players } => {
+                for player in self.recipients(phase, outcome, players) {
+                    if let Some(state) = self.player_mut(player) {
+                        let amount = std::mem::take(&mut state.trade_goods);
+                        if amount > 0 {
+                            events.push(GameEvent::TradeGoodsReturned { player, amount });
+                        }
+                    }
+                }
+            }
+            AgendaEffect::DiscardElectedLaw => {
+                let card = AgendaId::from(outcome);
+                if self.laws.remove(&card).is_some() {
+                    self.agenda_deck.discard.push(card.clone());
+                    events.push(GameEvent::LawRepealed { card });
+                }
+            }
+            AgendaEffect::DisarmInfantry => {
+                for player in self.seats() {
+                    events.extend(self.disarm_infantry(player));
+                }
+            }
+            AgendaEffect::PlaceInfantry => {
+                for player in self.seats() {
+                    events.extend(self.place_infantry(player));
+                }
+            }
+            AgendaEffect::PassSpeaker { players } => {
+                let recipients = self.recipients(phase, outcome, players);
+                if recipients.contains(&self.round.speaker) {
+                    let speaker = clockwise_from(&self.seats(), self.round.speaker)
+                        .get(1)
+                        .copied()
+                        .unwrap_or(self.round.speaker);
+                    self.round.speaker = speaker;
+                    events.push(GameEvent::SpeakerChanged { speaker });
+                }
+            }
+            AgendaEffect::ForbidVoting { players } => {
+                let recipients = self.recipients(phase, outcome, players);
+                if let Phase::Agenda(phase) = &mut self.round.phase {
+                    phase.silenced.extend(recipients);
+                }
+            }
+            AgendaEffect::DiscardActionCards { players } => {
+                for player in self.recipients(phase, outcome, players) {
+                    events.extend(self.discard_action_cards(player));
+                }
+            }
+            AgendaEffect::DrawActionCards { players, count } => {
+                for player in self.recipients(phase, outcome, players) {
+                    events.extend(self.draw_action_cards(player, count));
+                }
+            }
+            AgendaEffect::DrawSecretObjective { players } => {
+                for player in self.recipients(phase, outcome, players) {
+                    events.extend(self.draw_secret_objective(player));
+                }
+            }
+            AgendaEffect::RevealObjective { stage } => {
+                let deck = match stage {
+                    Stage::StageOne => &mut self.objectives.stage_one,
+                    Stage::StageTwo => &mut self.objectives.stage_two,
+                    Stage::Secret => return events,
+                };
+                if let Some(objective) = deck.pop() {
+                    self.objectives.revealed.push(objective.clone());
+                    events.push(GameEvent::ObjectiveRevealed { objective });
+                }
+            }
+            AgendaEffect::DestroyGroundForces => {
+                events.extend(self.destroy_ground_forces(outcome));
+            }
+            AgendaEffect::DiscardLaws => {
+                for card in std::mem::take(&mut self.laws).into_keys() {
+                    self.agenda_deck.discard.push(card.clone());
+                    events.push(GameEvent::LawRepealed { card });
+                }
+            }
+        }
+        events
+    }
+
+    /// Gets the players an agenda effect applies to
+    fn recipients(
+        &self,
+        phase: &AgendaPhase,
+        outcome: &str,
+        recipients: Recipients,
+    ) -> Vec<PlayerId> {
+        let points = self.players.iter().map(|player| player.victory_points);
+        let (most, fewest) = (
+            points.clone().max().unwrap_or_default(),
+            points.min().unwrap_or_default(),
+        );
+        self.players
+            .iter()
+            .filter(|player| match recipients {
+                Recipients::Everyone => true,
+                Recipients::VotedFor => phase
+                    .votes
+                    .iter()
+                    .any(|vote| vote.player == player.id && vote.outcome == FOR),
+                Recipients::VotedAgainst => phase
+                    .votes
+                    .iter()
+                    .any(|vote| vote.player == player.id && vote.outcome == AGAINST),
+                Recipients::Elected => {
+                    player.id.to_string() == outcome || player.planets.contains_key(outcome)
+                }
+                Recipients::MostPoints => player.victory_points == most,
+                Recipients::FewestPoints => player.victory_points == fewest,
+            })
+            .map(|player| player.id)
+            .collect()
+    }
+
+    fn reward_rider(&mut self, player: PlayerId, rider: Rider) -> Vec<GameEvent> {
+        match rider {
+            Rider::Imperial => self.gain_victory_points(player, 1),
+            Rider::Trade => {
+                let Some(state) = self.player_mut(player) else {
+                    return vec![];
+                };
+                state.trade_goods += TRADE_RIDER_GOODS;
+                vec![GameEvent::TradeGoodsGained {
+                    player,
+                    amount: TRADE_RIDER_GOODS,
+                }]
+            }
+        }
+    }
+
+    /// Gets the systems and planets containing each planet a player controls on the board
+    fn controlled_on_board(&self, player: PlayerId) -> Vec<(Hex, String)> {
+        let Some(state) = self.player(player) else {
+            return vec![];
+        };
+        state
+            .planets
+            .keys()
+            .filter_map(|planet| Some((self.board.find_planet(planet)?, planet.clone())))
+            .collect()
+    }
+
+    fn disarm_infantry(&mut self, player: PlayerId) -> Vec<GameEvent> {
+        let mut events = vec![];
+        let mut destroyed = 0;
+        for (system, planet) in self.controlled_on_board(player) {
+            let units = self
+                .board
+                .system_mut(system)
+                .planets
+                .entry(planet)
+                .or_default();
+            let infantry = units
+                .iter()
+                .filter(|unit| unit.owner == player && unit.kind == UnitKind::Infantry)
+                .count();
+            let count = infantry.div_ceil(2);
+            if count == 0 {
+                continue;
+            }
+            destroyed += count as u8;
+            let mut lost = count;
+            units.retain(|unit| {
+                let destroy = lost > 0 && unit.owner == player && unit.kind == UnitKind::Infantry;
+                lost -= usize::from(destroy);
+                !destroy
+            });
+            events.push(GameEvent::UnitsDestroyed {
+                player,
+                system,
+                units: vec![UnitKind::Infantry; count],
+            });
+        }
+        if destroyed > 0 {
+            if let Some(state) = self.player_mut(player) {
+                state.trade_goods += destroyed;
+            }
+            events.push(GameEvent::TradeGoodsGained {
+                player,
+                amount: destroyed,
+            });
+        }
+        events
+    }
+
+    /// Destroys every ground force on a planet, and its controller gains a trade good for each
+    fn destroy_ground_forces(&mut self, planet: &str) -> Vec<GameEvent> {
+        let Some(system) = self.board.find_planet(planet) else {
+            return vec![];
+        };
+        let units = self
+            .board
+            .system_mut(system)
+            .planets
+            .entry(planet.to_string())
+            .or_default();
+        let mut destroyed = BTreeMap::<PlayerId, Vec<UnitKind>>::new();
+        units.retain(|unit| {
+            if unit.kind.is_ground_force() {
+                destroyed.entry(unit.owner).or_default().push(unit.kind);
+            }
+            !unit.kind.is_ground_force()
+        });
+        let mut events = vec![];
+        let count = destroyed.values().map(Vec::len).sum::<usize>() as u8;
+        for (player, units) in destroyed {
+            events.push(GameEvent::UnitsDestroyed {
+                player,
+                system,
+                units,
+            });
+        }
+        if count == 0 {
+            return events;
+        }
+        let controller = self
+            .players
+            .iter_mut()
+            .find(|player| player.planets.contains_key(planet));
+        if let Some(state) = controller {
+            state.trade_goods += count;
+            events.push(GameEvent::TradeGoodsGained {
+                player: state.id,
+                amount: count,
+            });
+        }
+        events
+    }
+
+    pub(super) fn place_infantry(&mut self, player: PlayerId) -> Vec<GameEvent> {
+        let mut events = vec![];
+        for (system, planet) in self.controlled_on_board(player) {
+            self.board
+                .system_mut(system)
+                .planets
+                .entry(planet.clone())
+                .or_default()
+                .push(Unit::new(player, UnitKind::Infantry));
+            events.push(GameEvent::UnitsPlaced {
+                player,
+                planet,
+                units: vec![UnitKind::Infantry],
+            });
+        }
+        events
+    }
+
+    /// Readies every exhausted planet at the end of the agenda phase
+    fn ready_planets(&mut self) -> Vec<GameEvent> {
+        let mut events = vec![];
+        for player in &mut self.players {
+            let planets = player
+                .planets
+                .iter_mut()
+                .filter(|(_, card)| card.exhausted)
+                .map(|(planet, card)| {
+                    card.exhausted = false;
+                    planet.clone()
+                })
+                .collect::<Vec<_>>();
+            if !planets.is_empty() {
+                events.push(GameEvent::PlanetsReadied {
+                    player: player.id,
+                    planets,
+                });
+            }
+        }
+        events
+    }
+
+    pub(super) fn lose_victory_points(&mut self, player: PlayerId, points: u8) -> Vec<GameEvent> {
+        let Some(state) = self.player_mut(player) else {
+            return vec![];
+        };
+        let points = points.min(state.victory_points);
+        if points == 0 {
+            return vec![];
+        }
+        state.victory_points -= points;
+        vec![GameEvent::VictoryPointsLost {
+            player,
+            points,
+            total: state.victory_points,
+        }]
+    }
+
+    /// Gets the rules changed by the laws in play, along with the outcome each law elected
+    fn law_rules(&self) -> impl Iterator<Item = (LawRule, Option<&str>)> {
+        self.laws.iter().flat_map(|(card, elected)| {
+            let rules = catalog()
+                .get(&card.0)
+                .map(|agenda| agenda.rules.as_slice())
+                .unwrap_or_default();
+            rules.iter().map(move |rule| (*rule, elected.as_deref()))
+        })
+    }
+
+    /// Gets how many units of a kind are produced for their cost
+    pub fn produced_per_cost(&self, kind: UnitKind) -> u8 {
+        if self
+            .law_rules()
+            .any(|(rule, _)| rule == LawRule::SingleUnitProduction)
+        {
+            return 1;
+        }
+        kind.produced_per_cost()
+    }
+
+    /// Checks whether units on a planet can use production
+    pub fn can_produce_on(&self, planet: &str) -> bool {
+        !self.law_rules().any(|(rule, elected)| {
+            rule == LawRule::NoProductionOnElectedPlanet && elected == Some(planet)
+        })
+    }
+
+    /// Gets the most command tokens a fleet pool can hold, if limited
+    pub fn fleet_pool_limit(&self) -> Option<u8> {
+        self.law_rules()
+            .filter_map(|(rule, _)| match rule {
+                LawRule::FleetPoolLimit { max } => Some(max),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Checks that a command sheet respects the limits on its pools
+    pub(super) fn check_command_tokens(&self, tokens: &CommandTokens) -> Result<(), CommandError> {
+        match self.fleet_pool_limit() {
+            Some(max) if tokens.fleet > max => Err(CommandError::FleetPoolLimit { max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies the laws triggered by a planet changing hands
+    pub(super) fn planet_control_changed(
+        &mut self,
+        planet: &str,
+        from: Option<PlayerId>,
+        to: PlayerId,
+    ) -> Vec<GameEvent> {
+        let points = self
+            .law_rules()
+            .filter_map(|(rule, elected)| match rule {
+                LawRule::ElectedPlanetPoints { points } if elected == Some(planet) => Some(points),
+                _ => None,
+            })
+            .sum::<u8>();
+        if points == 0 {
+            return vec![];
+        }
+        let mut events = vec![];
+        if let Some(from) = from {
+            events.extend(self.lose_victory_points(from, points));
+        }
+        events.extend(self.gain_victory_points(to, points));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::agenda::{catalog, Prediction, Rider, AGAINST, AGENDAS_PER_PHASE, FOR};
+    use crate::game::board::Unit;
+    use crate::game::objectives::{self, Stage};
+    use crate::game::round::{DecisionKind, PhaseKind};
+    use crate::game::units::UnitKind;
+    use crate::game::{test_game, CommandError, GameCommand, GameEvent, GameState, PlanetCard};
+    use crate::lobby::PlayerId;
+
+    /// Creates a three player game whose agenda deck reveals the given agendas in order
+    fn stacked_deck(cards: &[&str]) -> (GameState, Vec<PlayerId>) {
+        let (mut state, players) = test_game(3);
+        state.custodians = Some(players[0]);
+        state.agenda_deck.draw = cards.iter().rev().map(|&card| card.into()).collect();
+        (state, players)
+    }
+
+    fn rider(rider: Rider, outcome: &str) -> GameCommand {
+        GameCommand::PlaceRider {
+            prediction: Some(Prediction {
+                rider,
+                outcome: outcome.to_string(),
+            }),
+        }
+    }
+
+    fn vote(outcome: Option<&str>, planets: &[&str]) -> GameCommand {
+        GameCommand::Vote {
+            outcome: outcome.map(str::to_string),
+            planets: planets.iter().map(|planet| planet.to_string()).collect(),
+        }
+    }
+
+    const NO_RIDER: GameCommand = GameCommand::PlaceRider { prediction: None };
+
+    #[test]
+    fn votes_exhaust_planets_and_correct_predictions_are_rewarded() {
+        let (mut state, players) = stacked_deck(&["fleet_regulations", "mutiny"]);
+        state.start_agenda_phase();
+        // players[0] is the speaker, so they decide last
+        state
+            .apply(players[1], rider(Rider::Imperial, FOR))
+            .unwrap();
+        assert_eq!(
+            state.apply(players[2], rider(Rider::Imperial, AGAINST)),
+            Err(CommandError::RiderTaken(Rider::Imperial))
+        );
+        state
+            .apply(players[2], rider(Rider::Trade, AGAINST))
+            .unwrap();
+        state.apply(players[0], NO_RIDER).unwrap();
+
+        assert_eq!(
+            state.apply(players[1], vote(Some(FOR), &[])),
+            Err(CommandError::NoVotes)
+        );
+        state
+            .apply(players[1], vote(Some(FOR), &["Vefut II"]))
+            .unwrap();
+        assert!(state.player(players[1]).unwrap().planets["Vefut II"].exhausted);
+        state
+            .apply(players[2], vote(Some(AGAINST), &["Thibah"]))
+            .unwrap();
+        let events = state.apply(players[0], vote(None, &[])).unwrap();
+
+        assert!(events.contains(&GameEvent::LawEnacted {
+            card: "fleet_regulations".into(),
+            elected: None,
+        }));
+        assert_eq!(state.player(players[1]).unwrap().victory_points, 1);
+        assert_eq!(state.player(players[2]).unwrap().trade_goods, 0);
+        assert_eq!(state.fleet_pool_limit(), Some(4));
+        assert!(matches!(
+            state.pending()[0].kind,
+            DecisionKind::PlaceRider { agenda: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn speaker_breaks_ties_and_planets_are_readied_after_the_phase() {
+        let (mut state, players) = stacked_deck(&["regulated_conscription", "mutiny"]);
+        state.start_agenda_phase();
+        for player in [players[1], players[2], players[0]] {
+            state.apply(player, NO_RIDER).unwrap();
+        }
+        state
+            .apply(players[1], vote(Some(FOR), &["Vefut II"]))
+            .unwrap();
+        state.apply(players[2], vote(None, &[])).unwrap();
+        state
+            .apply(players[0], vote(Some(AGAINST), &["Wellon"]))
+            .unwrap();
+
+        assert_eq!(state.pending()[0].player, players[0]);
+        let tie_break = |outcome: &str| GameCommand::BreakTie {
+            outcome: outcome.to_string(),
+        };
+        assert_eq!(
+            state.apply(players[1], tie_break(FOR)),
+            Err(CommandError::NotYourTurn)
+        );
+        state.apply(players[0], tie_break(AGAINST)).unwrap();
+        assert!(state.laws.is_empty());
+
+        // mutiny: the player who voted for it gains a point
+        for player in [players[1], players[2], players[0]] {
+            state.apply(player, NO_RIDER).unwrap();
+        }
+        state.apply(players[1], vote(Some(FOR), &[])).unwrap_err();
+        state.apply(players[1], vote(None, &[])).unwrap();
+        state
+            .apply(players[2], vote(Some(FOR), &["Thibah"]))
+            .unwrap();
+        state.apply(players[0], vote(None, &[])).unwrap();
+
+        assert_eq!(state.player(players[2]).unwrap().victory_points, 1);
+        assert!(state
+            .players
+            .iter()
+            .all(|player| player.planets.values().all(|card| !card.exhausted)));
+        assert_eq!(state.round.number, 2);
+        assert_eq!(state.round.phase.kind(), PhaseKind::Strategy);
+    }
+
+    #[test]
+    fn laws_in_play_change_production_and_planet_control() {
+        let (mut state, players) = stacked_deck(&["holy_planet_of_ixth", "mutiny"]);
+        // the law elects a cultural planet, so it is discarded unless somebody controls one
+        state
+            .player_mut(players[1])
+            .unwrap()
+            .planets
+            .insert("Quann".to_string(), PlanetCard::default());
+        state.start_agenda_phase();
+        for player in [players[1], players[2], players[0]] {
+            state.apply(player, NO_RIDER).unwrap();
+        }
+        assert_eq!(
+            state.apply(players[1], vote(Some("Vefut II"), &["Vefut II"])),
+            Err(CommandError::InvalidOutcome("Vefut II".to_string()))
+        );
+        state
+            .apply(players[1], vote(Some("Quann"), &["Vefut II"]))
+            .unwrap();
+        state.apply(players[2], vote(None, &[])).unwrap();
+        state.apply(players[0], vote(None, &[])).unwrap();
+
+        assert_eq!(state.laws["holy_planet_of_ixth"], Some("Quann".to_string()));
+        assert_eq!(state.player(players[1]).unwrap().victory_points, 1);
+        assert!(!state.can_produce_on("Quann"));
+        assert!(state.can_produce_on("Vefut II"));
+
+        state.planet_control_changed("Quann", Some(players[1]), players[2]);
+        assert_eq!(state.player(players[1]).unwrap().victory_points, 0);
+        assert_eq!(state.player(players[2]).unwrap().victory_points, 1);
+
+        let infantry = [UnitKind::Infantry, UnitKind::Infantry];
+        assert_eq!(state.production_cost(players[0], &infantry), Ok(1));
+        state.laws.insert("regulated_conscription".into(), None);
+        assert_eq!(state.production_cost(players[0], &infantry), Ok(2));
+    }
+
+    #[test]
+    fn directives_reveal_objectives_and_disarm_the_elected_planet() {
+        let (mut state, players) = stacked_deck(&[
+            "classified_document_leaks",
+            "incentive_program",
+            "compensated_disarmament",
+        ]);
+        let vefut = state.board.find_planet("Vefut II").unwrap();
+        state.board.system_mut(vefut).planets.insert(
+            "Vefut II".to_string(),
+            vec![
+                Unit::new(players[1], UnitKind::Infantry),
+                Unit::new(players[1], UnitKind::Infantry),
+            ],
+        );
+        let revealed = state.objectives.revealed.len();
+        let events = state.start_agenda_phase();
+        // nobody scored a secret objective, so there is nothing to elect
+        assert!(events.contains(&GameEvent::AgendaRevealed {
+            agenda: 1,
+            card: Some("incentive_program".into()),
+        }));
+
+        for player in [players[1], players[2], players[0]] {
+            state.apply(player, NO_RIDER).unwrap();
+        }
+        state
+            .apply(players[1], vote(Some(AGAINST), &["Vefut II"]))
+            .unwrap();
+        state.apply(players[2], vote(None, &[])).unwrap();
+        state.apply(players[0], vote(None, &[])).unwrap();
+        let stage_two = &state.objectives.revealed[revealed];
+        assert_eq!(
+            objectives::catalog().get(&stage_two.0).unwrap().stage,
+            Stage::StageTwo
+        );
+
+        for player in [players[1], players[2], players[0]] {
+            state.apply(player, NO_RIDER).unwrap();
+        }
+        // Vefut II is still exhausted from voting on the previous agenda
+        state
+            .apply(players[1], vote(Some("Vefut II"), &["Vefut II"]))
+            .unwrap_err();
+        state.apply(players[1], vote(None, &[])).unwrap();
+        state
+            .apply(players[2], vote(Some("Vefut II"), &["Thibah"]))
+            .unwrap();
+        state.apply(players[0], vote(None, &[])).unwrap();
+
+        assert!(state.board.system(vefut).planets["Vefut II"].is_empty());
+        assert_eq!(state.player(players[1]).unwrap().trade_goods, 2);
+    }
+
+    #[test]
+    fn agendas_are_skipped_once_the_deck_runs_out() {
+        let (mut state, players) = stacked_deck(&[]);
+        // as if every agenda were in play, leaving none to shuffle into the deck again
+        state.laws = catalog()
+            .agendas()
+            .map(|agenda| (agenda.id.clone(), None))
+            .collect();
+        let events = state.start_agenda_phase();
+
+        for agenda in 1..=AGENDAS_PER_PHASE {
+            assert!(events.contains(&GameEvent::AgendaRevealed { agenda, card: None }));
+        }
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::AgendaResolved { .. })));
+        assert_eq!(state.round.phase.kind(), PhaseKind::Strategy);
+        assert_eq!(state.pending()[0].player, players[0]);
+    }
+}
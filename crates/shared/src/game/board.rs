@@ -0,0 +1,274 @@
+//! The pieces on the game board: units, and the command tokens players placed in systems.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::game::galaxy::Galaxy;
+use crate::game::hex::Hex;
+use crate::game::tiles::{self, Anomaly, Planet};
+use crate::game::units::UnitKind;
+use crate::lobby::PlayerId;
+
+/// The name of the planet at the center of the galaxy
+pub const MECATOL_REX: &str = "Mecatol Rex";
+
+/// A unit on the board
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Unit {
+    pub owner: PlayerId,
+    pub kind: UnitKind,
+    /// Whether the unit used its sustain damage ability, and needs repairing
+    #[serde(default)]
+    pub damaged: bool,
+}
+
+impl Unit {
+    pub fn new(owner: PlayerId, kind: UnitKind) -> Self {
+        Self {
+            owner,
+            kind,
+            damaged: false,
+        }
+    }
+}
+
+/// Everything in a system
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SystemState {
+    /// The units in the space area of the system
+    #[serde(default)]
+    pub space: Vec<Unit>,
+    /// The units on each planet of the system, by planet name
+    #[serde(default)]
+    pub planets: BTreeMap<String, Vec<Unit>>,
+    /// The players who placed a command token in the system
+    #[serde(default)]
+    pub command_tokens: BTreeSet<PlayerId>,
+}
+
+impl SystemState {
+    /// Checks whether nothing is in the system
+    pub fn is_empty(&self) -> bool {
+        self.space.is_empty()
+            && self.planets.values().all(Vec::is_empty)
+            && self.command_tokens.is_empty()
+    }
+
+    /// Gets every unit in the system, in space and on planets
+    pub fn units(&self) -> impl Iterator<Item = &Unit> {
+        self.space.iter().chain(self.planets.values().flatten())
+    }
+
+    /// Gets the ships a player has in the space area of the system
+    pub fn ships_of(&self, player: PlayerId) -> impl Iterator<Item = &Unit> {
+        self.space
+            .iter()
+            .filter(move |unit| unit.owner == player && unit.kind.is_ship())
+    }
+
+    /// Checks whether another player has ships in the system
+    pub fn has_other_ships(&self, player: PlayerId) -> bool {
+        self.space
+            .iter()
+            .any(|unit| unit.owner != player && unit.kind.is_ship())
+    }
+
+    /// Removes one of a player's units from the space area, or from a planet
+    pub fn take(&mut self, owner: PlayerId, kind: UnitKind, planet: Option<&str>) -> Option<Unit> {
+        let units = match planet {
+            Some(planet) => self.planets.get_mut(planet)?,
+            None => &mut self.space,
+        };
+        let index = units
+            .iter()
+            .position(|unit| unit.owner == owner && unit.kind == kind)?;
+        Some(units.remove(index))
+    }
+}
+
+/// The galaxy, along with every piece placed on it
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Board {
+    pub galaxy: Galaxy,
+    #[serde(with = "by_hex", default)]
+    systems: BTreeMap<Hex, SystemState>,
+}
+
+impl Board {
+    /// Creates a board with nothing placed on the galaxy yet
+    pub fn new(galaxy: Galaxy) -> Self {
+        Self {
+            galaxy,
+            systems: BTreeMap::new(),
+        }
+    }
+
+    /// Gets what's in the system at a hex, which is empty if nothing was placed there
+    pub fn system(&self, hex: Hex) -> &SystemState {
+        static EMPTY: SystemState = SystemState {
+            space: Vec::new(),
+            planets: BTreeMap::new(),
+            command_tokens: BTreeSet::new(),
+        };
+        self.systems.get(&hex).unwrap_or(&EMPTY)
+    }
+
+    /// Gets what's in the system at a hex, to change it
+    pub fn system_mut(&mut self, hex: Hex) -> &mut SystemState {
+        self.systems.entry(hex).or_default()
+    }
+
+    /// Gets every system with something in it
+    pub fn systems(&self) -> impl Iterator<Item = (Hex, &SystemState)> {
+        self.systems
+            .iter()
+            .filter(|(_, system)| !system.is_empty())
+            .map(|(&hex, system)| (hex, system))
+    }
+
+    /// Finds the system a planet is in
+    pub fn find_planet(&self, name: &str) -> Option<Hex> {
+        self.galaxy
+            .systems()
+            .find(|(_, tile)| {
+                tiles::catalog()
+                    .get(&tile.tile().0)
+                    .is_some_and(|system| system.planet(name).is_some())
+            })
+            .map(|(hex, _)| hex)
+    }
+
+    /// Gets the planets of the system at a hex, as printed
+    pub fn planets(&self, hex: Hex) -> &'static [Planet] {
+        self.galaxy
+            .get(hex)
+            .and_then(|tile| tiles::catalog().get(&tile.tile().0))
+            .map(|system| system.planets.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Checks whether there is a system at a hex, which ships can move into
+    pub fn is_system(&self, hex: Hex) -> bool {
+        self.galaxy.get(hex).is_some_and(|tile| tile.is_system())
+    }
+
+    /// Gets the anomaly of the system at a hex, if it is one
+    pub fn anomaly(&self, hex: Hex) -> Option<Anomaly> {
+        self.galaxy
+            .get(hex)
+            .and_then(|tile| tiles::catalog().get(&tile.tile().0))
+            .and_then(|system| system.anomaly)
+    }
+
+    /// Places a unit in the space area of a system
+    pub fn place_in_space(&mut self, hex: Hex, unit: Unit) {
+        self.system_mut(hex).space.push(unit);
+    }
+
+    /// Places a unit on a planet
+    pub fn place_on_planet(&mut self, hex: Hex, planet: &str, unit: Unit) {
+        self.system_mut(hex)
+            .planets
+            .entry(planet.to_string())
+            .or_default()
+            .push(unit);
+    }
+
+    /// Places a player's command token in a system, unless they already have one there.
+    /// Returns whether a token was placed.
+    pub fn place_command_token(&mut self, hex: Hex, player: PlayerId) -> bool {
+        self.system_mut(hex).command_tokens.insert(player)
+    }
+
+    /// Removes a player's command token from a system. Returns whether there was one.
+    pub fn remove_command_token(&mut self, hex: Hex, player: PlayerId) -> bool {
+        self.system_mut(hex).command_tokens.remove(&player)
+    }
+
+    /// Repairs every damaged unit on the board, returning the units repaired for each player
+    pub fn repair_units(&mut self) -> BTreeMap<PlayerId, Vec<UnitKind>> {
+        let mut repaired = BTreeMap::<_, Vec<_>>::new();
+        for system in self.systems.values_mut() {
+            let units = system
+                .space
+                .iter_mut()
+                .chain(system.planets.values_mut().flatten());
+            for unit in units.filter(|unit| unit.damaged) {
+                unit.damaged = false;
+                repaired.entry(unit.owner).or_default().push(unit.kind);
+            }
+        }
+        repaired
+    }
+}
+
+/// Serializes systems as a list, as JSON objects can't have hexes as keys
+mod by_hex {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Located<T> {
+        hex: Hex,
+        #[serde(flatten)]
+        value: T,
+    }
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        map: &BTreeMap<Hex, T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        map.iter()
+            .map(|(&hex, value)| Located { hex, value })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Hex, T>, D::Error> {
+        Ok(Vec::<Located<T>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|located| (located.hex, located.value))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::game::board::{Board, Unit, MECATOL_REX};
+    use crate::game::galaxy::{Galaxy, MapTile};
+    use crate::game::hex::Hex;
+    use crate::game::units::UnitKind;
+    use crate::lobby::PlayerId;
+
+    #[test]
+    fn planets_are_found_on_the_galaxy() {
+        let mut galaxy = Galaxy::new();
+        galaxy.place(Hex::ORIGIN, MapTile::system("18"));
+        galaxy.place(Hex::new(0, -1), MapTile::system("26"));
+        let board = Board::new(galaxy);
+        assert_eq!(board.find_planet(MECATOL_REX), Some(Hex::ORIGIN));
+        assert_eq!(board.find_planet("Lodor"), Some(Hex::new(0, -1)));
+        assert_eq!(board.find_planet("Jord"), None);
+        assert_eq!(board.planets(Hex::new(0, -1))[0].name, "Lodor");
+    }
+
+    #[test]
+    fn board_round_trips_through_json() {
+        let player = PlayerId(Uuid::new_v4());
+        let mut board = Board::new(Galaxy::new());
+        board.place_on_planet(
+            Hex::ORIGIN,
+            MECATOL_REX,
+            Unit::new(player, UnitKind::Infantry),
+        );
+        assert!(board.place_command_token(Hex::new(1, 0), player));
+        assert!(!board.place_command_token(Hex::new(1, 0), player));
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+        assert_eq!(board.systems().count(), 2);
+    }
+}
@@ -0,0 +1,1004 @@
+//! Space combat in the active system, after ships moved into it.
+//!
+//! Before combat, other players' units with space cannon fire at the active player's ships in
+//! the active system, including upgraded PDS in adjacent systems. If the active player and
+//! another player both have ships in the system, combat is then fought in rounds:
+//!
+//! 1. In the first round only, units with anti-fighter barrage fire at the opponent's fighters.
+//! 2. Players may play action cards, then each may announce a retreat, starting with the
+//!    defender.
+//! 3. Both players roll dice for their ships, then assign the hits they took to their own
+//!    ships. Ships with sustain damage may cancel a hit by becoming damaged.
+//! 4. Players who announced a retreat move their ships to the system they announced.
+//!
+//! Combat ends once either player has no ships left in the system, or retreated.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::board::Unit;
+use crate::game::dice::Dice;
+use crate::game::hex::Hex;
+use crate::game::round::Phase;
+use crate::game::units::{Roll, UnitKind, UnitStats};
+use crate::game::{CommandError, GameEvent, GameState};
+use crate::lobby::PlayerId;
+
+/// Combat in the active system, along with its progress
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Combat {
+    pub system: Hex,
+    /// The active player
+    pub attacker: PlayerId,
+    /// The other player with ships in the system, once space combat started
+    #[serde(default)]
+    pub defender: Option<PlayerId>,
+    /// The round of space combat, starting from 1, or 0 before space combat starts
+    pub round: u32,
+    pub step: CombatStep,
+    /// The hits each player still has to assign to their own units
+    #[serde(default)]
+    pub hits: BTreeMap<PlayerId, u8>,
+    /// The systems players announced retreating to this round
+    #[serde(default)]
+    pub retreats: BTreeMap<PlayerId, Hex>,
+    /// How much lower each player's ships hit on this round, from action cards
+    #[serde(default)]
+    pub bonuses: BTreeMap<PlayerId, u8>,
+}
+
+/// A step of combat waiting on players
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum CombatStep {
+    /// The active player assigns the hits of space cannon offense
+    SpaceCannonHits,
+    /// Players decide whether to retreat, in order
+    AnnounceRetreats { deciding: VecDeque<PlayerId> },
+    /// Players assign the hits of the combat round, at the same time
+    AssignHits,
+}
+
+/// A hit assigned to a unit
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Hit {
+    pub unit: UnitKind,
+    /// Whether the unit uses sustain damage to cancel the hit, rather than being destroyed
+    #[serde(default)]
+    pub sustain: bool,
+}
+
+/// What dice were rolled for
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollKind {
+    SpaceCannon,
+    AntiFighterBarrage,
+    SpaceCombat,
+    Bombardment,
+    GroundCombat,
+}
+
+/// Checks whether a unit takes hits in space, or on a planet
+fn is_target(unit: &Unit, planet: Option<&str>) -> bool {
+    match planet {
+        Some(_) => unit.kind.is_ground_force(),
+        None => unit.kind.is_ship(),
+    }
+}
+
+/// Rolls dice for units, returning every result and the number of hits
+pub(super) fn roll_for(
+    rolls: impl IntoIterator<Item = Roll>,
+    dice: &mut dyn Dice,
+) -> (Vec<u8>, u8) {
+    let mut results = vec![];
+    let mut hits = 0;
+    for roll in rolls {
+        for _ in 0..roll.dice {
+            let result = dice.roll();
+            results.push(result);
+            if result >= roll.value {
+                hits += 1;
+            }
+        }
+    }
+    (results, hits)
+}
+
+impl GameState {
+    /// Gets the combat being fought in the active system, if any
+    pub fn combat(&self) -> Option<&Combat> {
+        match &self.round.phase {
+            Phase::Action(phase) => phase.combat.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub(super) fn combat_mut(&mut self) -> Option<&mut Combat> {
+        match &mut self.round.phase {
+            Phase::Action(phase) => phase.combat.as_deref_mut(),
+            _ => None,
+        }
+    }
+
+    fn set_combat(&mut self, combat: Option<Combat>) {
+        if let Phase::Action(phase) = &mut self.round.phase {
+            phase.combat = combat.map(Box::new);
+        }
+    }
+
+    /// Gets the stats of a unit, as its owner's version of it
+    pub(super) fn stats(&self, unit: &Unit) -> Option<UnitStats> {
+        Some(self.player(unit.owner)?.unit(unit.kind)?.stats)
+    }
+
+    /// Gets the systems a player could retreat to from a system: adjacent systems without
+    /// other players' ships, containing their own units or a planet they control
+    pub fn retreat_destinations(&self, player: PlayerId, system: Hex) -> Vec<Hex> {
+        let Some(state) = self.player(player) else {
+            return vec![];
+        };
+        self.board
+            .galaxy
+            .adjacent(system)
+            .into_iter()
+            .filter(|hex| {
+                let other = self.board.system(*hex);
+                let controlled = self
+                    .board
+                    .planets(*hex)
+                    .iter()
+                    .any(|planet| state.planets.contains_key(&planet.name));
+                self.board.can_enter(state, *hex)
+                    && !other.has_other_ships(player)
+                    && (controlled || other.units().any(|unit| unit.owner == player))
+            })
+            .collect()
+    }
+
+    /// Starts combat in the active system once ships moved into it, beginning with space
+    /// cannon offense. Returns whether combat waits on players.
+    pub(super) fn start_combat(
+        &mut self,
+        attacker: PlayerId,
+        system: Hex,
+        dice: &mut dyn Dice,
+    ) -> (bool, Vec<GameEvent>) {
+        let mut events = vec![];
+        let mut hits = 0;
+        if self
+            .board
+            .system(system)
+            .ships_of(attacker)
+            .next()
+            .is_some()
+        {
+            for player in self.seats().into_iter().filter(|p| *p != attacker) {
+                let rolls = self.space_cannon(player, system);
+                if rolls.is_empty() {
+                    continue;
+                }
+                let (results, scored) = roll_for(rolls, dice);
+                hits += scored;
+                events.push(GameEvent::DiceRolled {
+                    player,
+                    kind: RollKind::SpaceCannon,
+                    results,
+                    hits: scored,
+                });
+            }
+        }
+        let mut combat = Combat {
+            system,
+            attacker,
+            defender: None,
+            round: 0,
+            step: CombatStep::SpaceCannonHits,
+            hits: BTreeMap::new(),
+            retreats: BTreeMap::new(),
+            bonuses: BTreeMap::new(),
+        };
+        if hits > 0 {
+            combat.hits.insert(attacker, hits);
+            self.set_combat(Some(combat));
+            return (true, events);
+        }
+        let (waiting, more) = self.start_space_combat(combat, dice);
+        events.extend(more);
+        (waiting, events)
+    }
+
+    /// Gets the space cannon rolls a player makes against ships in a system
+    fn space_cannon(&self, player: PlayerId, system: Hex) -> Vec<Roll> {
+        let Some(state) = self.player(player) else {
+            return vec![];
+        };
+        // upgraded PDS have deep space cannon, firing from adjacent systems too
+        let deep = state
+            .unit(UnitKind::Pds)
+            .is_some_and(|pds| pds.upgrade.is_some());
+        let mut rolls = vec![];
+        for hex in [system]
+            .into_iter()
+            .chain(self.board.galaxy.adjacent(system))
+        {
+            for unit in self.board.system(hex).units() {
+                if unit.owner != player || (hex != system && !(deep && unit.kind == UnitKind::Pds))
+                {
+                    continue;
+                }
+                if let Some(roll) = self.stats(unit).and_then(|stats| stats.space_cannon) {
+                    rolls.push(roll);
+                }
+            }
+        }
+        rolls
+    }
+
+    /// Starts space combat if another player has ships in the system, or ends combat
+    fn start_space_combat(
+        &mut self,
+        mut combat: Combat,
+        dice: &mut dyn Dice,
+    ) -> (bool, Vec<GameEvent>) {
+        let state = self.board.system(combat.system);
+        let defender = state
+            .space
+            .iter()
+            .find(|unit| unit.owner != combat.attacker && unit.kind.is_ship())
+            .map(|unit| unit.owner);
+        let Some(defender) = defender else {
+            self.set_combat(None);
+            return (false, vec![]);
+        };
+        if state.ships_of(combat.attacker).next().is_none() {
+            self.set_combat(None);
+            return (false, vec![]);
+        }
+        combat.defender = Some(defender);
+        combat.round = 1;
+        let mut events = vec![GameEvent::SpaceCombatStarted {
+            system: combat.system,
+            attacker: combat.attacker,
+            defender,
+        }];
+        for (player, opponent) in [(combat.attacker, defender), (defender, combat.attacker)] {
+            events.extend(self.anti_fighter_barrage(player, opponent, combat.system, dice));
+        }
+        if let Some(end) = self.combat_over(&combat) {
+            events.extend(end);
+            return (false, events);
+        }
+        self.set_combat(Some(combat));
+        let (waiting, more) = self.start_combat_round(dice);
+        events.extend(more);
+        (waiting, events)
+    }
+
+    /// Starts a round of space combat, letting players play action cards before anyone
+    /// announces a retreat
+    fn start_combat_round(&mut self, dice: &mut dyn Dice) -> (bool, Vec<GameEvent>) {
+        let Some(combat) = self.combat() else {
+            return (false, vec![]);
+        };
+        if self.start_of_combat_round(combat.attacker, combat.system) {
+            return (true, vec![]);
+        }
+        self.announce_retreats(dice)
+    }
+
+    /// Fires anti-fighter barrage at an opponent's fighters, destroying as many as it hits
+    fn anti_fighter_barrage(
+        &mut self,
+        player: PlayerId,
+        opponent: PlayerId,
+        system: Hex,
+        dice: &mut dyn Dice,
+    ) -> Vec<GameEvent> {
+        let rolls = self
+            .board
+            .system(system)
+            .ships_of(player)
+            .filter_map(|unit| self.stats(unit)?.anti_fighter_barrage)
+            .collect::<Vec<_>>();
+        if rolls.is_empty() {
+            return vec![];
+        }
+        let (results, hits) = roll_for(rolls, dice);
+        let mut events = vec![GameEvent::DiceRolled {
+            player,
+            kind: RollKind::AntiFighterBarrage,
+            results,
+            hits,
+        }];
+        let space = self.board.system_mut(system);
+        let mut destroyed = vec![];
+        for _ in 0..hits {
+            if space.take(opponent, UnitKind::Fighter, None).is_some() {
+                destroyed.push(UnitKind::Fighter);
+            }
+        }
+        if !destroyed.is_empty() {
+            events.push(GameEvent::UnitsDestroyed {
+                player: opponent,
+                system,
+                units: destroyed,
+            });
+        }
+        events
+    }
+
+    /// Asks players whether to retreat, skipping players with nowhere to go, then rolls
+    /// dice once everyone decided
+    pub(super) fn announce_retreats(&mut self, dice: &mut dyn Dice) -> (bool, Vec<GameEvent>) {
+        let Some(combat) = self.combat() else {
+            return (false, vec![]);
+        };
+        let system = combat.system;
+        let deciding = [combat.defender, Some(combat.attacker)]
+            .into_iter()
+            .flatten()
+            .filter(|player| !self.retreat_destinations(*player, system).is_empty())
+            .collect::<VecDeque<_>>();
+        if let Some(combat) = self.combat_mut() {
+            combat.step = CombatStep::AnnounceRetreats {
+                deciding: deciding.clone(),
+            };
+        }
+        if deciding.is_empty() {
+            self.roll_combat(dice)
+        } else {
+            (true, vec![])
+        }
+    }
+
+    /// Rolls dice for every ship in the combat
+    fn roll_combat(&mut self, dice: &mut dyn Dice) -> (bool, Vec<GameEvent>) {
+        let Some(combat) = self.combat().cloned() else {
+            return (false, vec![]);
+        };
+        let Some(defender) = combat.defender else {
+            return (false, vec![]);
+        };
+        let mut events = vec![];
+        let mut hits = BTreeMap::new();
+        for (player, opponent) in [(combat.attacker, defender), (defender, combat.attacker)] {
+            let bonus = combat.bonuses.get(&player).copied().unwrap_or_default();
+            let rolls = self
+                .board
+                .system(combat.system)
+                .ships_of(player)
+                .filter_map(|unit| self.stats(unit)?.combat)
+                .map(|roll| Roll {
+                    value: roll.value.saturating_sub(bonus),
+                    ..roll
+                })
+                .collect::<Vec<_>>();
+            let (results, scored) = roll_for(rolls, dice);
+            events.push(GameEvent::DiceRolled {
+                player,
+                kind: RollKind::SpaceCombat,
+                results,
+                hits: scored,
+            });
+            if scored > 0 {
+                hits.insert(opponent, scored);
+            }
+        }
+        let waiting = !hits.is_empty();
+        if let Some(combat) = self.combat_mut() {
+            combat.step = CombatStep::AssignHits;
+            combat.hits = hits;
+        }
+        if waiting {
+            return (true, events);
+        }
+        let (waiting, more) = self.end_combat_round(dice);
+        events.extend(more);
+        (waiting, events)
+    }
+
+    /// Gets the units of a player that take hits: their ships in the space area of a system, or
+    /// their ground forces on a planet
+    pub(super) fn targets(&self, player: PlayerId, system: Hex, planet: Option<&str>) -> Vec<Unit> {
+        let state = self.board.system(system);
+        let units = match planet {
+            Some(planet) => state.planets.get(planet).map(Vec::as_slice),
+            None => Some(state.space.as_slice()),
+        };
+        units
+            .unwrap_or_default()
+            .iter()
+            .filter(|unit| unit.owner == player && is_target(unit, planet))
+            .copied()
+            .collect()
+    }
+
+    /// Gets the most hits a player's units can take, counting sustain damage
+    pub(super) fn hits_absorbed(
+        &self,
+        player: PlayerId,
+        system: Hex,
+        planet: Option<&str>,
+    ) -> usize {
+        self.targets(player, system, planet)
+            .iter()
+            .map(|unit| {
+                let sustains =
+                    !unit.damaged && self.stats(unit).is_some_and(|stats| stats.sustain_damage);
+                1 + sustains as usize
+            })
+            .sum()
+    }
+
+    /// Assigns hits a player took to their ships in a system, or their ground forces on a
+    /// planet, which must cover as many of the hits as their units can take
+    pub(super) fn take_hits(
+        &mut self,
+        player: PlayerId,
+        system: Hex,
+        planet: Option<&str>,
+        taken: u8,
+        hits: &[Hit],
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let expected = (taken as usize).min(self.hits_absorbed(player, system, planet));
+        if hits.len() != expected {
+            return Err(CommandError::WrongHits {
+                expected: expected as u8,
+            });
+        }
+
+        let mut units = self.targets(player, system, planet);
+        let mut damaged = vec![];
+        for hit in hits.iter().filter(|hit| hit.sustain) {
+            let Some(unit) = units.iter_mut().find(|unit| {
+                unit.kind == hit.unit
+                    && !unit.damaged
+                    && self.stats(unit).is_some_and(|stats| stats.sustain_damage)
+            }) else {
+                return Err(CommandError::CannotSustain(hit.unit));
+            };
+            unit.damaged = true;
+            damaged.push(hit.unit);
+        }
+        let mut destroyed = vec![];
+        for hit in hits.iter().filter(|hit| !hit.sustain) {
+            // destroy damaged units first, keeping the ones that can still sustain damage
+            let index = units
+                .iter()
+                .position(|unit| unit.kind == hit.unit && unit.damaged)
+                .or_else(|| units.iter().position(|unit| unit.kind == hit.unit));
+            let Some(index) = index else {
+                return Err(CommandError::NoUnitToHit(hit.unit));
+            };
+            units.remove(index);
+            destroyed.push(hit.unit);
+        }
+        let state = self.board.system_mut(system);
+        let area = match planet {
+            Some(planet) => state.planets.entry(planet.to_string()).or_default(),
+            None => &mut state.space,
+        };
+        area.retain(|unit| unit.owner != player || !is_target(unit, planet));
+        area.extend(units);
+
+        let mut events = vec![];
+        if !damaged.is_empty() {
+            events.push(GameEvent::UnitsDamaged {
+                player,
+                system,
+                units: damaged,
+            });
+        }
+        if !destroyed.is_empty() {
+            events.push(GameEvent::UnitsDestroyed {
+                player,
+                system,
+                units: destroyed,
+            });
+        }
+        Ok(events)
+    }
+
+    pub(super) fn assign_hits(
+        &mut self,
+        player: PlayerId,
+        hits: Vec<Hit>,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Some(combat) = self.combat() else {
+            return Err(CommandError::NotNow);
+        };
+        if matches!(combat.step, CombatStep::AnnounceRetreats { .. }) {
+            return Err(CommandError::NotNow);
+        }
+        let Some(&taken) = combat.hits.get(&player) else {
+            return Err(CommandError::NotYourTurn);
+        };
+        let (attacker, system) = (combat.attacker, combat.system);
+        let mut events = self.take_hits(player, system, None, taken, &hits)?;
+        let Some(combat) = self.combat_mut() else {
+            return Ok(events);
+        };
+        combat.hits.remove(&player);
+        if !combat.hits.is_empty() {
+            return Ok(events);
+        }
+        let (_, more) = match combat.step {
+            CombatStep::SpaceCannonHits => {
+                let combat = combat.clone();
+                self.start_space_combat(combat, dice)
+            }
+            _ => self.end_combat_round(dice),
+        };
+        events.extend(more);
+        events.extend(self.after_combat(attacker, system, dice));
+        Ok(events)
+    }
+
+    pub(super) fn announce_retreat(
+        &mut self,
+        player: PlayerId,
+        to: Option<Hex>,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Some(combat) = self.combat() else {
+            return Err(CommandError::NotNow);
+        };
+        let CombatStep::AnnounceRetreats { deciding } = &combat.step else {
+            return Err(CommandError::NotNow);
+        };
+        if deciding.front() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        let (attacker, system) = (combat.attacker, combat.system);
+        if let Some(to) = to {
+            if !self
+                .retreat_destinations(player, combat.system)
+                .contains(&to)
+            {
+                return Err(CommandError::InvalidRetreat(to));
+            }
+        }
+        let mut events = vec![];
+        if let Some(combat) = self.combat_mut() {
+            if let CombatStep::AnnounceRetreats { deciding } = &mut combat.step {
+                deciding.pop_front();
+                // only one player retreats, and the defender announces first
+                if to.is_some() {
+                    deciding.clear();
+                }
+            }
+            if let Some(to) = to {
+                combat.retreats.insert(player, to);
+                events.push(GameEvent::RetreatAnnounced { player, to });
+            }
+        }
+        if matches!(
+            self.combat().map(|combat| &combat.step),
+            Some(CombatStep::AnnounceRetreats { deciding }) if deciding.is_empty()
+        ) {
+            let (_, more) = self.roll_combat(dice);
+            events.extend(more);
+            events.extend(self.after_combat(attacker, system, dice));
+        }
+        Ok(events)
+    }
+
+    /// Ends a round of space combat, moving retreating ships, then starts the next round
+    /// unless combat is over
+    fn end_combat_round(&mut self, dice: &mut dyn Dice) -> (bool, Vec<GameEvent>) {
+        let Some(mut combat) = self.combat().cloned() else {
+            return (false, vec![]);
+        };
+        let mut events = vec![];
+        for (player, to) in std::mem::take(&mut combat.retreats) {
+            let space = &mut self.board.system_mut(combat.system).space;
+            let (moving, staying) = space
+                .drain(..)
+                .partition::<Vec<_>, _>(|unit| unit.owner == player);
+            *space = staying;
+            if moving.is_empty() {
+                continue;
+            }
+            let units = moving.iter().map(|unit| unit.kind).collect();
+            self.board.system_mut(to).space.extend(moving);
+            if self.board.place_command_token(to, player) {
+                events.push(GameEvent::CommandTokenPlaced { player, system: to });
+            }
+            events.push(GameEvent::UnitsMoved {
+                player,
+                system: to,
+                units,
+            });
+        }
+        if let Some(end) = self.combat_over(&combat) {
+            events.extend(end);
+            return (false, events);
+        }
+        combat.round += 1;
+        combat.bonuses.clear();
+        self.set_combat(Some(combat));
+        let (waiting, more) = self.start_combat_round(dice);
+        events.extend(more);
+        (waiting, events)
+    }
+
+    /// Ends combat once either player has no ships left in the system
+    fn combat_over(&mut self, combat: &Combat) -> Option<Vec<GameEvent>> {
+        let defender = combat.defender?;
+        let state = self.board.system(combat.system);
+        let attacking = state.ships_of(combat.attacker).next().is_some();
+        let defending = state.ships_of(defender).next().is_some();
+        if attacking && defending {
+            return None;
+        }
+        let winner = match (attacking, defending) {
+            (true, false) => Some(combat.attacker),
+            (false, true) => Some(defender),
+            _ => None,
+        };
+        self.set_combat(None);
+        let mut events = vec![];
+        // ground forces and structures carried in space are lost along with their ships
+        for player in [combat.attacker, defender] {
+            if Some(player) == winner {
+                continue;
+            }
+            let space = &mut self.board.system_mut(combat.system).space;
+            let units = space
+                .iter()
+                .filter(|unit| unit.owner == player)
+                .map(|unit| unit.kind)
+                .collect::<Vec<_>>();
+            space.retain(|unit| unit.owner != player);
+            if !units.is_empty() {
+                events.push(GameEvent::UnitsDestroyed {
+                    player,
+                    system: combat.system,
+                    units,
+                });
+            }
+        }
+        events.push(GameEvent::SpaceCombatEnded {
+            system: combat.system,
+            winner,
+        });
+        Some(events)
+    }
+
+    /// Repairs every damaged unit during the status phase
+    pub(super) fn repair_units(&mut self) -> Vec<GameEvent> {
+        self.board
+            .repair_units()
+            .into_iter()
+            .map(|(player, units)| GameEvent::UnitsRepaired { player, units })
+            .collect()
+    }
+
+    /// Carries on with the tactical action once combat is over, invading planets in the
+    /// system then producing units
+    pub(super) fn after_combat(
+        &mut self,
+        player: PlayerId,
+        system: Hex,
+        dice: &mut dyn Dice,
+    ) -> Vec<GameEvent> {
+        if self.combat().is_some() {
+            return vec![];
+        }
+        let (waiting, mut events) = self.start_invasion(player, system, dice);
+        if !waiting {
+            events.extend(self.start_production(player, system));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::{Unit, MECATOL_REX};
+    use crate::game::combat::{Hit, RollKind};
+    use crate::game::dice::LoadedDice;
+    use crate::game::hex::Hex;
+    use crate::game::movement::{Movement, ShipMove};
+    use crate::game::round::DecisionKind;
+    use crate::game::units::UnitKind;
+    use crate::game::{test_action_phase, CommandError, GameCommand, GameEvent, GameState};
+    use crate::lobby::PlayerId;
+
+    /// Moves the first player's ships from their home system into Mecatol Rex
+    fn attack(
+        state: &mut GameState,
+        players: &[PlayerId],
+        ships: &[UnitKind],
+        dice: &mut LoadedDice,
+    ) -> Vec<GameEvent> {
+        let home = state.board.find_planet("Wellon").unwrap();
+        let mecatol = state.board.find_planet(MECATOL_REX).unwrap();
+        for ship in ships {
+            state
+                .board
+                .place_in_space(home, Unit::new(players[0], *ship));
+        }
+        let movement = Movement {
+            ships: ships
+                .iter()
+                .map(|&unit| ShipMove {
+                    unit,
+                    from: home,
+                    through: vec![],
+                })
+                .collect(),
+            transported: vec![],
+        };
+        state
+            .apply_with(
+                players[0],
+                GameCommand::TacticalAction {
+                    system: mecatol,
+                    movement,
+                },
+                dice,
+            )
+            .unwrap()
+    }
+
+    fn hit(unit: UnitKind) -> Hit {
+        Hit {
+            unit,
+            sustain: false,
+        }
+    }
+
+    #[test]
+    fn space_cannon_fires_at_ships_moving_in() {
+        let (mut state, players) = test_action_phase(5);
+        let mecatol = state.board.find_planet(MECATOL_REX).unwrap();
+        state
+            .board
+            .place_on_planet(mecatol, MECATOL_REX, Unit::new(players[1], UnitKind::Pds));
+
+        let mut dice = LoadedDice::new([6]);
+        let events = attack(
+            &mut state,
+            &players,
+            &[UnitKind::Cruiser, UnitKind::Cruiser],
+            &mut dice,
+        );
+        assert!(events.contains(&GameEvent::DiceRolled {
+            player: players[1],
+            kind: RollKind::SpaceCannon,
+            results: vec![6],
+            hits: 1,
+        }));
+        let pending = state.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].player, players[0]);
+        assert_eq!(
+            pending[0].kind,
+            DecisionKind::AssignHits {
+                system: mecatol,
+                planet: None,
+                hits: 1
+            }
+        );
+
+        assert_eq!(
+            state.apply(players[0], GameCommand::AssignHits { hits: vec![] }),
+            Err(CommandError::WrongHits { expected: 1 })
+        );
+        assert_eq!(
+            state.apply(
+                players[0],
+                GameCommand::AssignHits {
+                    hits: vec![Hit {
+                        unit: UnitKind::Cruiser,
+                        sustain: true
+                    }]
+                }
+            ),
+            Err(CommandError::CannotSustain(UnitKind::Cruiser))
+        );
+        let events = state
+            .apply(
+                players[0],
+                GameCommand::AssignHits {
+                    hits: vec![hit(UnitKind::Cruiser)],
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            events,
+            [
+                GameEvent::UnitsDestroyed {
+                    player: players[0],
+                    system: mecatol,
+                    units: vec![UnitKind::Cruiser]
+                },
+                GameEvent::TurnStarted { player: players[1] }
+            ]
+        );
+        assert_eq!(state.board.system(mecatol).ships_of(players[0]).count(), 1);
+    }
+
+    #[test]
+    fn combat_is_fought_until_a_player_retreats() {
+        let (mut state, players) = test_action_phase(5);
+        let mecatol = state.board.find_planet(MECATOL_REX).unwrap();
+        let retreat = state.board.find_planet("Vefut II").unwrap();
+        for ship in [UnitKind::Fighter, UnitKind::Fighter, UnitKind::Dreadnought] {
+            state
+                .board
+                .place_in_space(mecatol, Unit::new(players[1], ship));
+        }
+
+        // the destroyer's barrage rolls first, then both players stay
+        let mut dice = LoadedDice::new([9, 1]);
+        let events = attack(
+            &mut state,
+            &players,
+            &[UnitKind::Destroyer, UnitKind::Dreadnought],
+            &mut dice,
+        );
+        assert!(events.contains(&GameEvent::SpaceCombatStarted {
+            system: mecatol,
+            attacker: players[0],
+            defender: players[1],
+        }));
+        assert!(events.contains(&GameEvent::UnitsDestroyed {
+            player: players[1],
+            system: mecatol,
+            units: vec![UnitKind::Fighter],
+        }));
+        assert_eq!(state.pending()[0].player, players[1]);
+        assert!(matches!(
+            &state.pending()[0].kind,
+            DecisionKind::AnnounceRetreat { destinations, .. } if destinations.contains(&retreat)
+        ));
+        assert_eq!(
+            state.apply(
+                players[1],
+                GameCommand::AnnounceRetreat {
+                    to: Some(Hex::ORIGIN)
+                }
+            ),
+            Err(CommandError::InvalidRetreat(Hex::ORIGIN))
+        );
+
+        // the attacker rolls for the destroyer and dreadnought, then the defender
+        let mut dice = LoadedDice::new([1, 5, 1, 10]);
+        state
+            .apply_with(
+                players[1],
+                GameCommand::AnnounceRetreat { to: None },
+                &mut dice,
+            )
+            .unwrap();
+        state
+            .apply_with(
+                players[0],
+                GameCommand::AnnounceRetreat { to: None },
+                &mut dice,
+            )
+            .unwrap();
+        assert_eq!(dice.remaining(), 0);
+        assert_eq!(state.pending().len(), 2);
+
+        let events = state
+            .apply(
+                players[0],
+                GameCommand::AssignHits {
+                    hits: vec![Hit {
+                        unit: UnitKind::Dreadnought,
+                        sustain: true,
+                    }],
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            events,
+            [GameEvent::UnitsDamaged {
+                player: players[0],
+                system: mecatol,
+                units: vec![UnitKind::Dreadnought]
+            }]
+        );
+        state
+            .apply(
+                players[1],
+                GameCommand::AssignHits {
+                    hits: vec![hit(UnitKind::Fighter)],
+                },
+            )
+            .unwrap();
+
+        // the defender retreats, so the attacker can't, and nobody hits in the last round
+        assert_eq!(state.combat().unwrap().round, 2);
+        let mut dice = LoadedDice::new([1, 1, 1]);
+        let events = state
+            .apply_with(
+                players[1],
+                GameCommand::AnnounceRetreat { to: Some(retreat) },
+                &mut dice,
+            )
+            .unwrap();
+        assert!(events.contains(&GameEvent::SpaceCombatEnded {
+            system: mecatol,
+            winner: Some(players[0]),
+        }));
+        assert_eq!(state.combat(), None);
+        assert_eq!(state.pending()[0].player, players[1]);
+        assert_eq!(state.board.system(retreat).ships_of(players[1]).count(), 1);
+        assert!(state
+            .board
+            .system(retreat)
+            .command_tokens
+            .contains(&players[1]));
+
+        let repaired = state.board.repair_units();
+        assert_eq!(repaired[&players[0]], [UnitKind::Dreadnought]);
+    }
+
+    #[test]
+    fn combat_ends_when_a_side_is_destroyed() {
+        let (mut state, players) = test_action_phase(5);
+        let mecatol = state.board.find_planet(MECATOL_REX).unwrap();
+        state
+            .board
+            .place_in_space(mecatol, Unit::new(players[1], UnitKind::Destroyer));
+        state
+            .board
+            .place_in_space(mecatol, Unit::new(players[1], UnitKind::Infantry));
+
+        // barrages find no fighters, then the attacker hits and the defender misses
+        let mut dice = LoadedDice::new([1, 1, 1, 1, 9, 1]);
+        attack(&mut state, &players, &[UnitKind::Destroyer], &mut dice);
+        for player in [players[1], players[0]] {
+            state
+                .apply_with(player, GameCommand::AnnounceRetreat { to: None }, &mut dice)
+                .unwrap();
+        }
+        assert_eq!(dice.remaining(), 0);
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::AssignHits {
+                system: mecatol,
+                planet: None,
+                hits: 1
+            }
+        );
+
+        let events = state
+            .apply(
+                players[1],
+                GameCommand::AssignHits {
+                    hits: vec![hit(UnitKind::Destroyer)],
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            events,
+            [
+                GameEvent::UnitsDestroyed {
+                    player: players[1],
+                    system: mecatol,
+                    units: vec![UnitKind::Destroyer]
+                },
+                GameEvent::UnitsDestroyed {
+                    player: players[1],
+                    system: mecatol,
+                    units: vec![UnitKind::Infantry]
+                },
+                GameEvent::SpaceCombatEnded {
+                    system: mecatol,
+                    winner: Some(players[0])
+                },
+                GameEvent::TurnStarted { player: players[1] }
+            ]
+        );
+        assert_eq!(state.board.system(mecatol).space.len(), 1);
+    }
+}
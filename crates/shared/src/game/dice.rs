@@ -0,0 +1,98 @@
+//! The dice rolled during the game.
+//!
+//! Commands that roll dice take them as a [Dice], so that tests can load them. A game rolls its
+//! own [SeededDice], stored along with the rest of its state, so that replaying the same
+//! commands rolls the same results. The server seeds them at random and never sends them to
+//! players, who could otherwise predict every roll.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// The number of sides of every die, which roll from 1 to 10
+pub const SIDES: u8 = 10;
+
+/// Something that rolls dice
+pub trait Dice {
+    /// Rolls a single die
+    fn roll(&mut self) -> u8;
+}
+
+/// Dice rolled from a seed, using the splitmix64 generator
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SeededDice {
+    state: u64,
+}
+
+impl SeededDice {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Shuffles a deck of cards in place
+    pub fn shuffle<T>(&mut self, cards: &mut [T]) {
+        for index in (1..cards.len()).rev() {
+            let other = (self.next() % (index as u64 + 1)) as usize;
+            cards.swap(index, other);
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+}
+
+impl Dice for SeededDice {
+    fn roll(&mut self) -> u8 {
+        (self.next() % SIDES as u64) as u8 + 1
+    }
+}
+
+/// Dice that roll the given results in order
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LoadedDice {
+    results: VecDeque<u8>,
+}
+
+impl LoadedDice {
+    pub fn new(results: impl IntoIterator<Item = u8>) -> Self {
+        Self {
+            results: results.into_iter().collect(),
+        }
+    }
+
+    /// Gets the results not rolled yet
+    pub fn remaining(&self) -> usize {
+        self.results.len()
+    }
+}
+
+impl Dice for LoadedDice {
+    /// Rolls the next result, panicking if every result was already rolled
+    fn roll(&mut self) -> u8 {
+        self.results
+            .pop_front()
+            .expect("loaded dice ran out of results")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::dice::{Dice, SeededDice, SIDES};
+
+    #[test]
+    fn seeded_dice_roll_every_side_repeatably() {
+        let mut dice = SeededDice::new(7);
+        let rolls = (0..1000).map(|_| dice.roll()).collect::<Vec<_>>();
+        assert!((1..=SIDES).all(|side| rolls.contains(&side)));
+        assert!(rolls.iter().all(|roll| (1..=SIDES).contains(roll)));
+
+        let mut again = SeededDice::new(7);
+        assert!(rolls.iter().all(|roll| *roll == again.roll()));
+    }
+}
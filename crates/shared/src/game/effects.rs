@@ -0,0 +1,552 @@
+//! Effects described as data, which faction abilities, technologies and cards refer to by id.
+//!
+//! Every effect is read from `data/effects.json`, which is embedded into the crate. An effect
+//! names the [Trigger] it resolves on, the [Condition]s the player has to meet for it to apply,
+//! and the [Effect]s themselves. Passive effects change a rule for as long as the player has
+//! them, such as the stats of their units or how many command tokens they gain during the
+//! status phase. Other effects are resolved by the engine when their trigger happens, or when
+//! the card referring to them is played.
+//!
+//! Abilities used in a timing window, such as when an agenda is revealed, are passive effects
+//! offering the player an option in that [Window]. They are offered alongside action cards, as
+//! described in [crate::game::action_cards], and using the option resolves the effect it names.
+//!
+//! Only abilities that are too unusual to describe this way are still hand coded, such as
+//! "Sabotage" canceling another card.
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::action_cards::Window;
+use crate::game::factions;
+use crate::game::tech::{self as technologies, TechId};
+use crate::game::tiles::{self, PlanetTrait};
+use crate::game::units::{UnitDefinition, UnitKind};
+use crate::game::{CommandTokens, GameEvent, GameState, Player};
+use crate::lobby::PlayerId;
+
+const EFFECTS: &str = include_str!("../../data/effects.json");
+
+/// Gets the catalog of every effect
+pub fn catalog() -> &'static EffectCatalog {
+    static CATALOG: OnceLock<EffectCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let effects: Vec<EffectDefinition> =
+            serde_json::from_str(EFFECTS).expect("embedded effect catalog is valid");
+        EffectCatalog {
+            effects: effects
+                .into_iter()
+                .map(|effect| (effect.id.clone(), effect))
+                .collect(),
+        }
+    })
+}
+
+/// Identifies an effect, such as `unrelenting`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EffectId(pub String);
+
+impl Borrow<str> for EffectId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for EffectId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Display for EffectId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// When an effect applies
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// Always, changing a rule for as long as the player has the effect
+    Passive,
+    /// After the player gains control of a planet
+    PlanetGained,
+    /// When the card referring to the effect is played, or the player uses the window option
+    /// naming it
+    Played,
+}
+
+/// Something the player has to meet for an effect to apply
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum Condition {
+    /// The player controls at least this many planets, with the trait if any
+    ControlsPlanets {
+        count: u8,
+        #[serde(default, rename = "trait")]
+        planet_trait: Option<PlanetTrait>,
+    },
+    /// The player owns the technology
+    OwnsTechnology { tech: TechId },
+    /// The player has at least this many command tokens in their strategy pool
+    StrategyTokens { count: u8 },
+}
+
+impl Condition {
+    /// Checks whether a player meets this condition
+    pub fn holds(&self, player: &Player) -> bool {
+        match self {
+            Condition::ControlsPlanets {
+                count,
+                planet_trait,
+            } => {
+                let controlled = player
+                    .planets
+                    .keys()
+                    .filter(|planet| planet_trait.is_none_or(|wanted| has_trait(planet, wanted)))
+                    .count();
+                controlled >= usize::from(*count)
+            }
+            Condition::OwnsTechnology { tech } => player.technologies.contains(tech),
+            Condition::StrategyTokens { count } => player.command_tokens.strategy >= *count,
+        }
+    }
+}
+
+/// A stat of a unit an effect can change
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitStat {
+    /// Applied to the result of each combat roll, so a bonus lowers the value hit on
+    Combat,
+    Move,
+    Capacity,
+}
+
+/// Something an effect does
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum Effect {
+    /// Changes a stat of the player's units, or only of the listed kinds. Passive.
+    ModifyUnitStat {
+        stat: UnitStat,
+        amount: i8,
+        #[serde(default)]
+        units: Vec<UnitKind>,
+    },
+    /// Gains more command tokens during the status phase. Passive.
+    ExtraCommandTokens { count: u8 },
+    /// Draws more action cards during the status phase. Passive.
+    ExtraActionCards { count: u8 },
+    /// Gains trade goods, or that many for each planet with the trait the player controls
+    GainTradeGoods {
+        amount: u8,
+        #[serde(default)]
+        per_planet: Option<PlanetTrait>,
+    },
+    /// Readies every planet with the trait the player controls
+    ReadyPlanets {
+        #[serde(rename = "trait")]
+        planet_trait: PlanetTrait,
+    },
+    /// Places an infantry on each planet the player controls
+    PlaceInfantryOnEachPlanet,
+    /// Applies a bonus to the player's combat rolls for the rest of the combat round
+    CombatBonus { bonus: u8 },
+    /// Offers the player to resolve another effect in a timing window, like playing an action
+    /// card there. Passive.
+    WindowOption { window: Window, option: EffectId },
+    /// Spends command tokens from the player's strategy pool
+    SpendStrategyTokens { count: u8 },
+    /// Discards the agenda just revealed and reveals another in its place
+    ReplaceAgenda,
+}
+
+/// An effect, as referred to by abilities, technologies and cards
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct EffectDefinition {
+    pub id: EffectId,
+    pub trigger: Trigger,
+    /// Every condition has to hold for the effects to apply
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    pub effects: Vec<Effect>,
+}
+
+/// Every effect, by id
+#[derive(Debug)]
+pub struct EffectCatalog {
+    effects: BTreeMap<EffectId, EffectDefinition>,
+}
+
+impl EffectCatalog {
+    /// Gets an effect by its id
+    pub fn get(&self, id: &str) -> Option<&EffectDefinition> {
+        self.effects.get(id)
+    }
+}
+
+/// Checks whether a planet has a trait
+pub(super) fn has_trait(planet: &str, planet_trait: PlanetTrait) -> bool {
+    tiles::catalog()
+        .planet(planet)
+        .is_some_and(|(_, planet)| planet.planet_trait == Some(planet_trait))
+}
+
+impl Player {
+    /// Gets the effects of this player's faction abilities and technologies that apply right
+    /// now
+    pub fn effects(&self) -> Vec<&'static EffectDefinition> {
+        let abilities = self
+            .faction
+            .as_ref()
+            .and_then(|faction| factions::catalog().get(&faction.0))
+            .into_iter()
+            .flat_map(|faction| &faction.abilities)
+            .filter_map(|ability| ability.effect.as_ref());
+        let techs = self
+            .technologies
+            .iter()
+            .filter_map(|tech| technologies::catalog().get(&tech.0))
+            .filter_map(|tech| tech.effect.as_ref());
+        abilities
+            .chain(techs)
+            .filter_map(|id| catalog().get(&id.0))
+            .filter(|effect| {
+                effect
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.holds(self))
+            })
+            .collect()
+    }
+
+    /// Gets the passive effects that apply to this player right now
+    fn passive_effects(&self) -> impl Iterator<Item = &'static Effect> {
+        self.effects()
+            .into_iter()
+            .filter(|effect| effect.trigger == Trigger::Passive)
+            .flat_map(|effect| &effect.effects)
+    }
+
+    /// Applies this player's passive effects to the stats of one of their units
+    pub(super) fn modify_unit(&self, unit: &mut UnitDefinition) {
+        for effect in self.passive_effects() {
+            let Effect::ModifyUnitStat {
+                stat,
+                amount,
+                units,
+            } = effect
+            else {
+                continue;
+            };
+            if !units.is_empty() && !units.contains(&unit.unit) {
+                continue;
+            }
+            let stats = &mut unit.stats;
+            match stat {
+                UnitStat::Combat => {
+                    if let Some(combat) = &mut stats.combat {
+                        combat.value = combat.value.saturating_add_signed(-amount).max(1);
+                    }
+                }
+                UnitStat::Move => {
+                    if let Some(movement) = &mut stats.movement {
+                        *movement = movement.saturating_add_signed(*amount);
+                    }
+                }
+                UnitStat::Capacity => {
+                    stats.capacity = stats.capacity.saturating_add_signed(*amount);
+                }
+            }
+        }
+    }
+
+    /// Gets the additional command tokens this player gains during the status phase
+    pub fn extra_command_tokens(&self) -> u8 {
+        self.passive_effects()
+            .map(|effect| match effect {
+                Effect::ExtraCommandTokens { count } => *count,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Gets the options this player's effects offer them in a window, whose conditions hold
+    pub fn window_options(&self, window: Window) -> Vec<EffectId> {
+        self.passive_effects()
+            .filter_map(|effect| match effect {
+                Effect::WindowOption {
+                    window: offered,
+                    option,
+                } if *offered == window => Some(option),
+                _ => None,
+            })
+            .filter(|option| {
+                catalog().get(&option.0).is_some_and(|option| {
+                    option
+                        .conditions
+                        .iter()
+                        .all(|condition| condition.holds(self))
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Gets the additional action cards this player draws during the status phase
+    pub fn extra_action_cards(&self) -> u8 {
+        self.passive_effects()
+            .map(|effect| match effect {
+                Effect::ExtraActionCards { count } => *count,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+impl GameState {
+    /// Resolves the player's effects with a trigger that just happened
+    pub(super) fn trigger_effects(&mut self, player: PlayerId, trigger: Trigger) -> Vec<GameEvent> {
+        let Some(state) = self.player(player) else {
+            return vec![];
+        };
+        let mut events = vec![];
+        for definition in state.effects() {
+            if definition.trigger == trigger {
+                for effect in &definition.effects {
+                    events.extend(self.resolve_effect(player, effect));
+                }
+            }
+        }
+        events
+    }
+
+    /// Resolves an effect a card played by the player refers to
+    pub(super) fn play_effect(&mut self, player: PlayerId, id: &EffectId) -> Vec<GameEvent> {
+        let Some(definition) = catalog().get(&id.0) else {
+            return vec![];
+        };
+        let holds = self.player(player).is_some_and(|state| {
+            definition
+                .conditions
+                .iter()
+                .all(|condition| condition.holds(state))
+        });
+        if !holds {
+            return vec![];
+        }
+        let mut events = vec![];
+        for effect in &definition.effects {
+            events.extend(self.resolve_effect(player, effect));
+        }
+        events
+    }
+
+    /// Resolves a single effect for a player. Passive effects are rules rather than things to
+    /// do, so they do nothing here.
+    fn resolve_effect(&mut self, player: PlayerId, effect: &Effect) -> Vec<GameEvent> {
+        match *effect {
+            Effect::ModifyUnitStat { .. }
+            | Effect::ExtraCommandTokens { .. }
+            | Effect::ExtraActionCards { .. }
+            | Effect::WindowOption { .. } => vec![],
+            Effect::GainTradeGoods { amount, per_planet } => {
+                let times = match per_planet {
+                    Some(planet_trait) => self.player(player).map_or(0, |state| {
+                        state
+                            .planets
+                            .keys()
+                            .filter(|planet| has_trait(planet, planet_trait))
+                            .count() as u8
+                    }),
+                    None => 1,
+                };
+                self.gain_trade_goods(player, amount * times)
+            }
+            Effect::ReadyPlanets { planet_trait } => {
+                let Some(state) = self.player_mut(player) else {
+                    return vec![];
+                };
+                let planets = state
+                    .planets
+                    .iter_mut()
+                    .filter(|(planet, card)| card.exhausted && has_trait(planet, planet_trait))
+                    .map(|(planet, card)| {
+                        card.exhausted = false;
+                        planet.clone()
+                    })
+                    .collect::<Vec<_>>();
+                if planets.is_empty() {
+                    return vec![];
+                }
+                vec![GameEvent::PlanetsReadied { player, planets }]
+            }
+            Effect::PlaceInfantryOnEachPlanet => self.place_infantry(player),
+            Effect::CombatBonus { bonus } => {
+                if let Some(combat) = self.combat_mut() {
+                    *combat.bonuses.entry(player).or_default() += bonus;
+                }
+                vec![]
+            }
+            Effect::SpendStrategyTokens { count } => {
+                let Some(state) = self.player_mut(player) else {
+                    return vec![];
+                };
+                let strategy = count.min(state.command_tokens.strategy);
+                state.command_tokens.strategy -= strategy;
+                let tokens = CommandTokens {
+                    tactic: 0,
+                    fleet: 0,
+                    strategy,
+                };
+                vec![GameEvent::CommandTokensSpent { player, tokens }]
+            }
+            Effect::ReplaceAgenda => self.replace_agenda(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::action_cards::{self, ActionCardError, ActionEffect, Window};
+    use crate::game::effects::{catalog, Condition, Effect, Trigger};
+    use crate::game::round::DecisionKind;
+    use crate::game::units::{Roll, UnitKind};
+    use crate::game::{factions, tech, test_game, GameCommand, GameEvent};
+
+    #[test]
+    fn every_reference_names_an_effect_with_a_fitting_trigger() {
+        let abilities = factions::catalog()
+            .factions()
+            .flat_map(|faction| &faction.abilities)
+            .filter_map(|ability| ability.effect.as_ref());
+        let techs = tech::catalog()
+            .techs()
+            .filter_map(|tech| tech.effect.as_ref());
+        for id in abilities.chain(techs) {
+            let effect = catalog().get(&id.0).expect("referenced effects exist");
+            assert_ne!(effect.trigger, Trigger::Played, "{id} is only for cards");
+        }
+        for card in action_cards::catalog().cards() {
+            if let ActionEffect::Scripted { script } = &card.effect {
+                let effect = catalog().get(&script.0).expect("referenced effects exist");
+                assert_eq!(effect.trigger, Trigger::Played, "{script} is for cards");
+            }
+        }
+        for effect in catalog()
+            .effects
+            .values()
+            .flat_map(|effect| &effect.effects)
+        {
+            if let Effect::WindowOption { option, .. } = effect {
+                let option = catalog().get(&option.0).expect("offered options exist");
+                assert_eq!(option.trigger, Trigger::Played, "{} is used", option.id);
+            }
+        }
+    }
+
+    #[test]
+    fn passive_effects_change_unit_stats_and_status_phase_gains() {
+        let (mut state, players) = test_game(2);
+        let player = state.player_mut(players[0]).unwrap();
+        player.faction = Some("sardakk".into());
+        assert_eq!(
+            player.unit(UnitKind::Cruiser).unwrap().stats.combat,
+            Some(Roll::new(6, 1))
+        );
+        player.faction = Some("jol_nar".into());
+        assert_eq!(
+            player.unit(UnitKind::Cruiser).unwrap().stats.combat,
+            Some(Roll::new(8, 1))
+        );
+
+        player.faction = Some("sol".into());
+        player.technologies.insert("hyper_metabolism".into());
+        player.technologies.insert("neural_motivator".into());
+        assert_eq!(player.extra_command_tokens(), 2);
+        assert_eq!(player.extra_action_cards(), 1);
+    }
+
+    #[test]
+    fn triggered_effects_resolve_when_their_trigger_happens() {
+        let (mut state, players) = test_game(2);
+        state.player_mut(players[0]).unwrap().faction = Some("saar".into());
+        assert_eq!(
+            state.trigger_effects(players[0], Trigger::PlanetGained),
+            vec![GameEvent::TradeGoodsGained {
+                player: players[0],
+                amount: 1
+            }]
+        );
+        assert!(state
+            .trigger_effects(players[1], Trigger::PlanetGained)
+            .is_empty());
+
+        let player = state.player(players[0]).unwrap();
+        assert!(Condition::ControlsPlanets {
+            count: 1,
+            planet_trait: None
+        }
+        .holds(player));
+        assert!(!Condition::OwnsTechnology {
+            tech: "gravity_drive".into()
+        }
+        .holds(player));
+    }
+
+    #[test]
+    fn window_options_are_offered_like_action_cards() {
+        let (mut state, players) = test_game(3);
+        state.custodians = Some(players[0]);
+        state.agenda_deck.draw = vec!["mutiny".into(), "fleet_regulations".into()];
+        for player in &mut state.players {
+            player.action_cards.clear();
+        }
+        let xxcha = state.player_mut(players[1]).unwrap();
+        xxcha.faction = Some("xxcha".into());
+        xxcha.command_tokens.strategy = 1;
+        state.start_agenda_phase();
+
+        let decision = &state.pending()[0];
+        assert_eq!(decision.player, players[1]);
+        assert_eq!(
+            decision.kind,
+            DecisionKind::PlayActionCard {
+                window: Window::AgendaRevealed,
+                cards: vec![],
+                options: vec!["quash_agenda".into()],
+            }
+        );
+        let quash = |option: &str| GameCommand::UseWindowOption {
+            option: option.into(),
+        };
+        assert_eq!(
+            state.apply(players[1], quash("morale_boost")),
+            Err(ActionCardError::OptionNotOffered {
+                option: "morale_boost".into()
+            }
+            .into())
+        );
+        let events = state.apply(players[1], quash("quash_agenda")).unwrap();
+
+        assert!(events.contains(&GameEvent::AgendaRevealed {
+            agenda: 1,
+            card: Some("mutiny".into()),
+        }));
+        assert_eq!(state.agenda_deck.discard, vec!["fleet_regulations".into()]);
+        // without strategy tokens left, Quash can't be used on the new agenda
+        assert_eq!(state.player(players[1]).unwrap().command_tokens.strategy, 0);
+        assert!(state.windows.is_empty());
+        assert!(matches!(
+            state.pending()[0].kind,
+            DecisionKind::PlaceRider { agenda: 1, .. }
+        ));
+    }
+}
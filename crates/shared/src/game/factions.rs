@@ -0,0 +1,213 @@
+//! The catalog of every faction in the base game and Prophecy of Kings.
+//!
+//! The catalog is read from `data/factions.json`, which is embedded into the crate, so the
+//! server setting up a game and the faction picker in the browser agree on what each faction
+//! starts with.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::effects::EffectId;
+use crate::game::galaxy::TileId;
+use crate::game::tech::TechId;
+use crate::game::tiles::{self, Expansion, SystemTile};
+use crate::game::units::{UnitKind, UnitStats};
+
+const FACTIONS: &str = include_str!("../../data/factions.json");
+
+/// Gets the catalog of every faction
+pub fn catalog() -> &'static FactionCatalog {
+    static CATALOG: OnceLock<FactionCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| FactionCatalog {
+        factions: serde_json::from_str(FACTIONS).expect("embedded faction catalog is valid"),
+    })
+}
+
+/// Identifies a faction, such as `sol`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FactionId(pub String);
+
+impl From<&str> for FactionId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Display for FactionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A rule only one faction follows, as printed on its faction sheet
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Ability {
+    pub name: String,
+    pub text: String,
+    /// What the ability does, unless it is hand coded or not implemented yet
+    #[serde(default)]
+    pub effect: Option<EffectId>,
+}
+
+/// Technologies a faction picks during setup instead of starting with fixed ones
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TechChoice {
+    /// How many technologies are picked
+    pub count: u8,
+    /// The technologies to pick from. When empty, any technology without prerequisites may be
+    /// picked.
+    #[serde(default)]
+    pub options: Vec<TechId>,
+}
+
+/// A unit only one faction has, such as its flagship
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FactionUnit {
+    pub name: String,
+    pub stats: UnitStats,
+}
+
+/// A faction, as printed on its faction sheet
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Faction {
+    pub id: FactionId,
+    pub name: String,
+    pub expansion: Expansion,
+    /// The tile placed as the faction's home system. The Ghosts of Creuss place the Creuss
+    /// Gate on the map instead, and keep their home system off to the side.
+    pub home_system: TileId,
+    pub commodities: u8,
+    /// The units placed in the home system during setup
+    pub starting_units: BTreeMap<UnitKind, u8>,
+    pub starting_techs: Vec<TechId>,
+    #[serde(default)]
+    pub tech_choice: Option<TechChoice>,
+    pub abilities: Vec<Ability>,
+    /// The technologies only this faction can research
+    pub faction_techs: Vec<TechId>,
+    pub flagship: FactionUnit,
+    pub mech: FactionUnit,
+    /// The faction's promissory notes, in addition to the ones every player has
+    pub promissory_notes: Vec<String>,
+}
+
+impl Faction {
+    /// Gets the home system tile of this faction
+    pub fn home_tile(&self) -> &'static SystemTile {
+        tiles::catalog()
+            .get(&self.home_system.0)
+            .expect("every home system is cataloged")
+    }
+
+    /// Gets an ability of this faction by its name
+    pub fn ability(&self, name: &str) -> Option<&Ability> {
+        self.abilities.iter().find(|ability| ability.name == name)
+    }
+
+    /// Gets the stats of one of this faction's unique units, if the unit kind is unique
+    pub fn unit(&self, kind: UnitKind) -> Option<&FactionUnit> {
+        match kind {
+            UnitKind::Flagship => Some(&self.flagship),
+            UnitKind::Mech => Some(&self.mech),
+            _ => None,
+        }
+    }
+}
+
+/// Every faction
+#[derive(Debug)]
+pub struct FactionCatalog {
+    factions: Vec<Faction>,
+}
+
+impl FactionCatalog {
+    /// Gets a faction by its id
+    pub fn get(&self, id: &str) -> Option<&Faction> {
+        self.factions.iter().find(|faction| faction.id.0 == id)
+    }
+
+    /// Gets every faction, in the order they are listed in the rules
+    pub fn factions(&self) -> impl Iterator<Item = &Faction> {
+        self.factions.iter()
+    }
+
+    /// Finds the faction whose home system is the given tile
+    pub fn by_home_system(&self, tile: &str) -> Option<&Faction> {
+        self.factions
+            .iter()
+            .find(|faction| faction.home_system.0 == tile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::game::factions::catalog;
+    use crate::game::tiles::{Expansion, TileKind};
+    use crate::game::units::{Roll, UnitKind};
+
+    #[test]
+    fn every_faction_is_cataloged() {
+        assert_eq!(catalog().factions().count(), 24);
+        assert_eq!(
+            catalog()
+                .factions()
+                .filter(|faction| faction.expansion == Expansion::Base)
+                .count(),
+            17
+        );
+        let ids = catalog()
+            .factions()
+            .map(|faction| &faction.id)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(ids.len(), 24);
+    }
+
+    #[test]
+    fn home_systems_are_home_tiles() {
+        let mut homes = BTreeSet::new();
+        for faction in catalog().factions() {
+            assert_eq!(faction.home_tile().kind, TileKind::Home, "{}", faction.id);
+            assert!(homes.insert(&faction.home_system), "{}", faction.id);
+        }
+        let sol = catalog().by_home_system("1").unwrap();
+        assert_eq!(sol.id.0, "sol");
+        assert_eq!(sol.home_tile().planet("Jord").unwrap().resources, 4);
+    }
+
+    #[test]
+    fn factions_start_with_a_space_dock() {
+        for faction in catalog().factions() {
+            assert_eq!(
+                faction.starting_units.get(&UnitKind::SpaceDock),
+                Some(&1),
+                "{}",
+                faction.id
+            );
+            assert_eq!(faction.faction_techs.len(), 2, "{}", faction.id);
+            assert!(faction.flagship.stats.sustain_damage, "{}", faction.id);
+        }
+    }
+
+    #[test]
+    fn factions_are_looked_up_by_id() {
+        let letnev = catalog().get("letnev").unwrap();
+        assert_eq!(letnev.commodities, 2);
+        assert_eq!(
+            letnev.flagship.stats.bombardment,
+            Some(Roll { value: 5, dice: 3 })
+        );
+        assert!(letnev.ability("Armada").is_some());
+
+        let argent = catalog().get("argent").unwrap();
+        let choice = argent.tech_choice.as_ref().unwrap();
+        assert_eq!((choice.count, choice.options.len()), (2, 3));
+        assert!(argent.starting_techs.is_empty());
+        assert!(catalog().get("lazax").is_none());
+    }
+}
@@ -0,0 +1,510 @@
+//! The galaxy: the map of system tiles the game is played on.
+//!
+//! Two systems are adjacent when they are next to each other on the map, when a hyperlane
+//! connects them, or when they contain wormholes of the same type.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::hex::{Direction, Hex};
+use crate::lobby::PLAYER_LIMITS;
+
+/// The number printed on a tile, such as `18` for Mecatol Rex or `83A` for a hyperlane
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TileId(pub String);
+
+impl From<&str> for TileId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Borrow<str> for TileId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for TileId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A type of wormhole. Systems containing wormholes of the same type are adjacent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wormhole {
+    Alpha,
+    Beta,
+    Gamma,
+    Delta,
+}
+
+/// A tile placed on the map
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MapTile {
+    /// A system, which ships can move into
+    System {
+        tile: TileId,
+        #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+        wormholes: BTreeSet<Wormhole>,
+    },
+    /// A hyperlane, which is not a system but connects the systems at the ends of its lanes
+    Hyperlane {
+        tile: TileId,
+        /// The sides of the tile connected by each lane
+        lanes: Vec<[Direction; 2]>,
+    },
+}
+
+impl MapTile {
+    /// Creates a system without wormholes
+    pub fn system(tile: impl Into<TileId>) -> Self {
+        MapTile::System {
+            tile: tile.into(),
+            wormholes: BTreeSet::new(),
+        }
+    }
+
+    /// Gets the tile
+    pub fn tile(&self) -> &TileId {
+        match self {
+            MapTile::System { tile, .. } | MapTile::Hyperlane { tile, .. } => tile,
+        }
+    }
+
+    /// Checks whether this tile is a system
+    pub fn is_system(&self) -> bool {
+        matches!(self, MapTile::System { .. })
+    }
+
+    /// Gets the wormholes in this tile
+    pub fn wormholes(&self) -> impl Iterator<Item = Wormhole> + '_ {
+        let wormholes = match self {
+            MapTile::System { wormholes, .. } => Some(wormholes),
+            MapTile::Hyperlane { .. } => None,
+        };
+        wormholes.into_iter().flatten().copied()
+    }
+
+    /// Rotates this tile clockwise by the given number of sixth turns
+    pub fn rotated(mut self, steps: i32) -> Self {
+        if let MapTile::Hyperlane { lanes, .. } = &mut self {
+            for lane in lanes {
+                *lane = lane.map(|side| side.rotate(steps));
+            }
+        }
+        self
+    }
+
+    /// Gets the sides a lane entering this tile from the given side leads to
+    fn exits(&self, entry: Direction) -> impl Iterator<Item = Direction> + '_ {
+        let lanes = match self {
+            MapTile::Hyperlane { lanes, .. } => lanes.as_slice(),
+            MapTile::System { .. } => &[],
+        };
+        lanes.iter().filter_map(move |&[a, b]| {
+            if a == entry {
+                Some(b)
+            } else if b == entry {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// An error occurred while building a galaxy
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum GalaxyError {
+    #[error("more than one tile was placed at {0}")]
+    Occupied(Hex),
+}
+
+/// A tile and where it was placed, which is how a galaxy is serialized
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlacedTile {
+    pub hex: Hex,
+    #[serde(flatten)]
+    pub tile: MapTile,
+}
+
+/// The map of the galaxy
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<PlacedTile>", into = "Vec<PlacedTile>")]
+pub struct Galaxy {
+    tiles: BTreeMap<Hex, MapTile>,
+}
+
+impl Galaxy {
+    /// Creates an empty galaxy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places a tile on the map, returning the tile it replaced
+    pub fn place(&mut self, hex: Hex, tile: MapTile) -> Option<MapTile> {
+        self.tiles.insert(hex, tile)
+    }
+
+    /// Gets the tile at a hex
+    pub fn get(&self, hex: Hex) -> Option<&MapTile> {
+        self.tiles.get(&hex)
+    }
+
+    /// Gets every tile on the map
+    pub fn tiles(&self) -> impl Iterator<Item = (Hex, &MapTile)> {
+        self.tiles.iter().map(|(&hex, tile)| (hex, tile))
+    }
+
+    /// Gets every system on the map
+    pub fn systems(&self) -> impl Iterator<Item = (Hex, &MapTile)> {
+        self.tiles().filter(|(_, tile)| tile.is_system())
+    }
+
+    /// Finds the hex a tile was placed at
+    pub fn find(&self, tile: &TileId) -> Option<Hex> {
+        self.tiles()
+            .find(|(_, placed)| placed.tile() == tile)
+            .map(|(hex, _)| hex)
+    }
+
+    /// Gets every system containing a wormhole of the given type
+    pub fn with_wormhole(&self, wormhole: Wormhole) -> impl Iterator<Item = Hex> + '_ {
+        self.systems()
+            .filter(move |(_, tile)| tile.wormholes().any(|other| other == wormhole))
+            .map(|(hex, _)| hex)
+    }
+
+    /// Gets every system adjacent to the system at a hex.
+    ///
+    /// A hex without a system has no adjacent systems.
+    pub fn adjacent(&self, hex: Hex) -> BTreeSet<Hex> {
+        let mut adjacent = BTreeSet::new();
+        let Some(tile) = self.get(hex).filter(|tile| tile.is_system()) else {
+            return adjacent;
+        };
+        let mut crossed = BTreeSet::new();
+        for direction in Direction::ALL {
+            self.follow(hex, direction, &mut crossed, &mut adjacent);
+        }
+        for wormhole in tile.wormholes() {
+            adjacent.extend(self.with_wormhole(wormhole));
+        }
+        adjacent.remove(&hex);
+        adjacent
+    }
+
+    /// Checks whether the systems at two hexes are adjacent
+    pub fn is_adjacent(&self, a: Hex, b: Hex) -> bool {
+        self.adjacent(a).contains(&b)
+    }
+
+    /// Finds the systems reached by leaving a hex through one of its sides, following any
+    /// hyperlanes on the way
+    fn follow(
+        &self,
+        from: Hex,
+        direction: Direction,
+        crossed: &mut BTreeSet<(Hex, Direction)>,
+        found: &mut BTreeSet<Hex>,
+    ) {
+        let next = from.neighbor(direction);
+        match self.get(next) {
+            Some(MapTile::System { .. }) => {
+                found.insert(next);
+            }
+            Some(hyperlane @ MapTile::Hyperlane { .. }) => {
+                let entry = direction.opposite();
+                if !crossed.insert((next, entry)) {
+                    return;
+                }
+                for exit in hyperlane.exits(entry) {
+                    self.follow(next, exit, crossed, found);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+impl TryFrom<Vec<PlacedTile>> for Galaxy {
+    type Error = GalaxyError;
+
+    fn try_from(value: Vec<PlacedTile>) -> Result<Self, Self::Error> {
+        let mut galaxy = Galaxy::new();
+        for PlacedTile { hex, tile } in value {
+            if galaxy.place(hex, tile).is_some() {
+                return Err(GalaxyError::Occupied(hex));
+            }
+        }
+        Ok(galaxy)
+    }
+}
+
+impl From<Galaxy> for Vec<PlacedTile> {
+    fn from(value: Galaxy) -> Self {
+        value
+            .tiles
+            .into_iter()
+            .map(|(hex, tile)| PlacedTile { hex, tile })
+            .collect()
+    }
+}
+
+/// Where tiles go on the standard map for a number of players
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub players: u8,
+    /// The number of rings around Mecatol Rex
+    pub rings: u32,
+    /// The home system of each seat, in seat order
+    pub home_systems: Vec<Hex>,
+    /// The hexes filled with hyperlanes, where a seat is missing from the map
+    pub hyperlanes: Vec<Hex>,
+}
+
+impl Layout {
+    /// Gets the standard layout for a number of players.
+    ///
+    /// Up to six players play on a map with three rings, and seven or eight players on a map
+    /// with four rings. With five or seven players, the slice of the map belonging to the
+    /// missing seat is filled with hyperlanes. With eight players, hyperlanes fill the four
+    /// corners of the outer ring without a home system, so that every player places six systems.
+    pub fn standard(players: u8) -> Option<Layout> {
+        if !PLAYER_LIMITS.contains(&players) {
+            return None;
+        }
+        let (rings, seats) = match players {
+            3..=6 => (
+                3,
+                Direction::ALL
+                    .map(|direction| direction.offset() * 3)
+                    .to_vec(),
+            ),
+            _ => (4, Hex::ORIGIN.ring(4).into_iter().step_by(3).collect()),
+        };
+        // seats are numbered clockwise from the north, and the southern one is left out first
+        let missing: &[usize] = match players {
+            3 => &[1, 3, 5],
+            4 => &[0, 3],
+            5 => &[3],
+            7 => &[4],
+            _ => &[],
+        };
+        let home_systems = (0..seats.len())
+            .filter(|seat| !missing.contains(seat))
+            .map(|seat| seats[seat])
+            .collect::<Vec<_>>();
+
+        let hyperlanes = match players {
+            5 | 7 => Hex::ORIGIN
+                .spiral(rings)
+                .into_iter()
+                .filter(|&hex| hex != Hex::ORIGIN)
+                .filter(|&hex| {
+                    let missing = seats[missing[0]];
+                    home_systems
+                        .iter()
+                        .all(|home| hex.distance(missing) < hex.distance(*home))
+                })
+                .collect(),
+            8 => Hex::ORIGIN
+                .ring(rings)
+                .into_iter()
+                .step_by(rings as usize)
+                .filter(|hex| !home_systems.contains(hex))
+                .collect(),
+            _ => vec![],
+        };
+
+        Some(Layout {
+            players,
+            rings,
+            home_systems,
+            hyperlanes,
+        })
+    }
+
+    /// Gets every hex of the map
+    pub fn hexes(&self) -> Vec<Hex> {
+        Hex::ORIGIN.spiral(self.rings)
+    }
+
+    /// Gets the hexes left to fill with system tiles once Mecatol Rex, the home systems and the
+    /// hyperlanes are placed
+    pub fn system_slots(&self) -> Vec<Hex> {
+        self.hexes()
+            .into_iter()
+            .filter(|hex| {
+                *hex != Hex::ORIGIN
+                    && !self.home_systems.contains(hex)
+                    && !self.hyperlanes.contains(hex)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::galaxy::{Galaxy, Layout, MapTile, Wormhole};
+    use crate::game::hex::{Direction, Hex};
+    use std::collections::BTreeSet;
+
+    fn wormhole(tile: &str, wormhole: Wormhole) -> MapTile {
+        MapTile::System {
+            tile: tile.into(),
+            wormholes: BTreeSet::from([wormhole]),
+        }
+    }
+
+    #[test]
+    fn neighbors_are_adjacent() {
+        let mut galaxy = Galaxy::new();
+        for hex in Hex::ORIGIN.spiral(1) {
+            galaxy.place(hex, MapTile::system(hex.to_string().as_str()));
+        }
+        let adjacent = galaxy.adjacent(Hex::ORIGIN);
+        assert_eq!(adjacent, Hex::ORIGIN.neighbors().into_iter().collect());
+
+        let corner = Direction::North.offset();
+        assert_eq!(galaxy.adjacent(corner).len(), 3);
+        assert!(galaxy.adjacent(Hex::new(5, 5)).is_empty());
+    }
+
+    #[test]
+    fn wormholes_of_the_same_type_are_adjacent() {
+        let mut galaxy = Galaxy::new();
+        let (alpha, beta, other_alpha) = (Hex::new(-3, 0), Hex::new(3, 0), Hex::new(0, 3));
+        galaxy.place(alpha, wormhole("39", Wormhole::Alpha));
+        galaxy.place(beta, wormhole("40", Wormhole::Beta));
+        galaxy.place(other_alpha, wormhole("26", Wormhole::Alpha));
+
+        assert!(galaxy.is_adjacent(alpha, other_alpha));
+        assert!(galaxy.is_adjacent(other_alpha, alpha));
+        assert!(!galaxy.is_adjacent(alpha, beta));
+        assert!(galaxy.adjacent(beta).is_empty());
+    }
+
+    #[test]
+    fn hyperlanes_connect_their_ends() {
+        let mut galaxy = Galaxy::new();
+        // a lane bending from the north west side to the south side
+        let lane = MapTile::Hyperlane {
+            tile: "83A".into(),
+            lanes: vec![[Direction::NorthWest, Direction::South]],
+        };
+        galaxy.place(Hex::ORIGIN, lane.clone());
+        let west = Hex::ORIGIN.neighbor(Direction::NorthWest);
+        let south = Hex::ORIGIN.neighbor(Direction::South);
+        let north = Hex::ORIGIN.neighbor(Direction::North);
+        for hex in [west, south, north] {
+            galaxy.place(hex, MapTile::system(hex.to_string().as_str()));
+        }
+
+        assert!(galaxy.is_adjacent(west, south));
+        assert!(galaxy.is_adjacent(south, west));
+        assert!(
+            !galaxy.is_adjacent(west, Hex::ORIGIN),
+            "hyperlanes aren't systems"
+        );
+        assert!(!galaxy.is_adjacent(north, south));
+
+        // rotating the lane a sixth turn now connects the north and south west sides
+        galaxy.place(Hex::ORIGIN, lane.rotated(1));
+        let south_west = Hex::ORIGIN.neighbor(Direction::SouthWest);
+        galaxy.place(south_west, MapTile::system("south west"));
+        assert!(galaxy.is_adjacent(north, south_west));
+        assert!(!galaxy.is_adjacent(west, south));
+    }
+
+    #[test]
+    fn standard_layouts_seat_every_player() {
+        for players in 3..=8 {
+            let layout = Layout::standard(players).unwrap();
+            let hexes = layout.hexes();
+            assert_eq!(layout.home_systems.len(), players as usize);
+            let homes = layout.home_systems.iter().collect::<BTreeSet<_>>();
+            assert_eq!(homes.len(), players as usize, "homes must be distinct");
+            for home in &layout.home_systems {
+                assert_eq!(home.ring_index(), layout.rings);
+                assert!(!layout.hyperlanes.contains(home));
+            }
+            assert!(layout.hyperlanes.iter().all(|hex| hexes.contains(hex)));
+            assert_eq!(
+                layout.hyperlanes.is_empty(),
+                matches!(players, 3 | 4 | 6),
+                "{players} players"
+            );
+            assert_eq!(
+                layout.system_slots().len(),
+                hexes.len() - 1 - players as usize - layout.hyperlanes.len()
+            );
+        }
+        let eight = Layout::standard(8).unwrap();
+        assert_eq!(eight.hyperlanes.len(), 4);
+        assert_eq!(eight.system_slots().len(), 8 * 6);
+        assert_eq!(Layout::standard(2), None);
+        assert_eq!(Layout::standard(9), None);
+    }
+
+    #[test]
+    fn eight_player_hyperlanes_join_the_outer_ring() {
+        let eight = Layout::standard(8).unwrap();
+        let mut galaxy = Galaxy::new();
+        for hex in eight.hexes() {
+            galaxy.place(hex, MapTile::system(hex.to_string().as_str()));
+        }
+        for &hyperlane in &eight.hyperlanes {
+            assert_eq!(hyperlane.ring_index(), eight.rings);
+            // a corner of the outer ring only borders two other hexes of that ring
+            let ends = Direction::ALL
+                .into_iter()
+                .filter(|&direction| hyperlane.neighbor(direction).ring_index() == eight.rings)
+                .collect::<Vec<_>>();
+            assert_eq!(ends.len(), 2);
+            let [from, to] = [ends[0], ends[1]].map(|direction| hyperlane.neighbor(direction));
+            assert!(!galaxy.is_adjacent(from, to));
+
+            galaxy.place(
+                hyperlane,
+                MapTile::Hyperlane {
+                    tile: "hyperlane".into(),
+                    lanes: vec![[ends[0], ends[1]]],
+                },
+            );
+            assert!(galaxy.is_adjacent(from, to));
+            assert!(!galaxy.is_adjacent(from, hyperlane));
+        }
+    }
+
+    #[test]
+    fn galaxy_round_trips() {
+        let mut galaxy = Galaxy::new();
+        galaxy.place(Hex::ORIGIN, MapTile::system("18"));
+        galaxy.place(Hex::new(1, -3), wormhole("26", Wormhole::Alpha));
+        galaxy.place(
+            Hex::new(0, 3),
+            MapTile::Hyperlane {
+                tile: "84A".into(),
+                lanes: vec![[Direction::North, Direction::South]],
+            },
+        );
+        let json = serde_json::to_string(&galaxy).unwrap();
+        assert_eq!(serde_json::from_str::<Galaxy>(&json).unwrap(), galaxy);
+
+        let duplicate = r#"[
+            {"hex": {"q": 0, "r": 0}, "kind": "system", "tile": "18"},
+            {"hex": {"q": 0, "r": 0}, "kind": "system", "tile": "19"}
+        ]"#;
+        assert!(serde_json::from_str::<Galaxy>(duplicate).is_err());
+    }
+}
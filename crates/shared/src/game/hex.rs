@@ -0,0 +1,267 @@
+//! Hexagonal coordinates for the galaxy map.
+//!
+//! Tiles are flat topped, so every hex has a northern and a southern neighbor. Hexes are
+//! addressed with axial coordinates, with [Cube] coordinates available for the math that is
+//! simpler with three axes. See <https://www.redblobgames.com/grids/hexagons/> for background.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// A hex on the map, in axial coordinates.
+///
+/// `q` grows towards the south east and `r` grows towards the south. The center of the map,
+/// where Mecatol Rex sits, is [Hex::ORIGIN].
+#[derive(
+    Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize,
+)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+/// A hex in cube coordinates, where `q + r + s == 0`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Cube {
+    pub q: i32,
+    pub r: i32,
+    pub s: i32,
+}
+
+/// One of the six sides of a flat topped hex, in clockwise order starting from the north
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+impl Direction {
+    /// Every direction, in clockwise order starting from the north
+    pub const ALL: [Direction; 6] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::NorthWest,
+    ];
+
+    /// Gets the position of this direction in [Direction::ALL]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Gets the direction pointing the other way
+    pub fn opposite(self) -> Self {
+        self.rotate(3)
+    }
+
+    /// Rotates this direction clockwise by the given number of sixth turns
+    pub fn rotate(self, steps: i32) -> Self {
+        Self::ALL[(self.index() as i32 + steps).rem_euclid(6) as usize]
+    }
+
+    /// Gets the offset to the neighboring hex on this side
+    pub fn offset(self) -> Hex {
+        match self {
+            Direction::North => Hex::new(0, -1),
+            Direction::NorthEast => Hex::new(1, -1),
+            Direction::SouthEast => Hex::new(1, 0),
+            Direction::South => Hex::new(0, 1),
+            Direction::SouthWest => Hex::new(-1, 1),
+            Direction::NorthWest => Hex::new(-1, 0),
+        }
+    }
+}
+
+impl Hex {
+    /// The center of the map
+    pub const ORIGIN: Hex = Hex { q: 0, r: 0 };
+
+    pub const fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    /// Gets the third cube coordinate
+    pub fn s(self) -> i32 {
+        -self.q - self.r
+    }
+
+    /// Converts this hex to cube coordinates
+    pub fn cube(self) -> Cube {
+        Cube {
+            q: self.q,
+            r: self.r,
+            s: self.s(),
+        }
+    }
+
+    /// Gets the neighboring hex on the given side
+    pub fn neighbor(self, direction: Direction) -> Hex {
+        self + direction.offset()
+    }
+
+    /// Gets every neighboring hex, in the order of [Direction::ALL]
+    pub fn neighbors(self) -> [Hex; 6] {
+        Direction::ALL.map(|direction| self.neighbor(direction))
+    }
+
+    /// Gets the side of this hex facing a neighboring hex, if they are neighbors
+    pub fn direction_to(self, other: Hex) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| self.neighbor(*direction) == other)
+    }
+
+    /// Counts the steps needed to move from this hex to another, ignoring anything on the map
+    pub fn distance(self, other: Hex) -> u32 {
+        let difference = (self - other).cube();
+        difference
+            .q
+            .unsigned_abs()
+            .max(difference.r.unsigned_abs())
+            .max(difference.s.unsigned_abs())
+    }
+
+    /// Gets the ring this hex is in around the center of the map
+    pub fn ring_index(self) -> u32 {
+        self.distance(Hex::ORIGIN)
+    }
+
+    /// Rotates this hex clockwise around the center of the map by the given number of sixth turns
+    pub fn rotate(self, steps: i32) -> Hex {
+        let mut cube = self.cube();
+        for _ in 0..steps.rem_euclid(6) {
+            cube = Cube {
+                q: -cube.r,
+                r: -cube.s,
+                s: -cube.q,
+            };
+        }
+        cube.into()
+    }
+
+    /// Gets every hex at exactly `radius` steps from this one, clockwise starting from the north.
+    ///
+    /// A radius of zero gives just this hex.
+    pub fn ring(self, radius: u32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![self];
+        }
+        let mut hex = self + Direction::North.offset() * radius as i32;
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        for direction in Direction::ALL {
+            // walking clockwise from the northern corner, every side leads two steps further
+            let step = direction.rotate(2);
+            for _ in 0..radius {
+                ring.push(hex);
+                hex = hex.neighbor(step);
+            }
+        }
+        ring
+    }
+
+    /// Gets every hex within `radius` steps from this one, ring by ring starting from this hex
+    pub fn spiral(self, radius: u32) -> Vec<Hex> {
+        (0..=radius).flat_map(|ring| self.ring(ring)).collect()
+    }
+}
+
+impl From<Cube> for Hex {
+    fn from(value: Cube) -> Self {
+        Hex::new(value.q, value.r)
+    }
+}
+
+impl From<Hex> for Cube {
+    fn from(value: Hex) -> Self {
+        value.cube()
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Hex::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Hex::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl Neg for Hex {
+    type Output = Hex;
+
+    fn neg(self) -> Self::Output {
+        Hex::new(-self.q, -self.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        Hex::new(self.q * rhs, self.r * rhs)
+    }
+}
+
+impl Display for Hex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.q, self.r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::hex::{Direction, Hex};
+
+    #[test]
+    fn rings_have_six_hexes_per_step() {
+        for radius in 1..=4 {
+            let ring = Hex::ORIGIN.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring.iter().all(|hex| hex.ring_index() == radius));
+            // every hex is followed by one of its neighbors, all the way around
+            for (index, hex) in ring.iter().enumerate() {
+                let next = ring[(index + 1) % ring.len()];
+                assert_eq!(hex.distance(next), 1, "{hex} -> {next}");
+            }
+        }
+        assert_eq!(Hex::ORIGIN.spiral(3).len(), 37);
+        assert_eq!(Hex::ORIGIN.spiral(4).len(), 61);
+    }
+
+    #[test]
+    fn neighbors_face_each_other() {
+        let hex = Hex::new(2, -1);
+        for direction in Direction::ALL {
+            let neighbor = hex.neighbor(direction);
+            assert_eq!(neighbor.direction_to(hex), Some(direction.opposite()));
+            assert_eq!(hex.distance(neighbor), 1);
+        }
+    }
+
+    #[test]
+    fn rotation_follows_directions() {
+        let north = Direction::North.offset() * 3;
+        for steps in 0..6 {
+            assert_eq!(
+                north.rotate(steps),
+                Direction::North.rotate(steps).offset() * 3
+            );
+        }
+        assert_eq!(Hex::new(1, 2).rotate(6), Hex::new(1, 2));
+        assert_eq!(Hex::new(1, 2).rotate(-1), Hex::new(1, 2).rotate(5));
+    }
+}
@@ -0,0 +1,894 @@
+//! Invasion of the planets in the active system, once space combat is over.
+//!
+//! The active player first bombards planets with their ships, unless a planetary shield
+//! protects them, then commits ground forces from the space area to planets. Each planet
+//! ground forces were committed to is then resolved in turn:
+//!
+//! 1. Units with space cannon on the planet fire at the committed ground forces.
+//! 2. Ground combat is fought in rounds until only one player has ground forces left.
+//! 3. If the active player has ground forces left, they gain control of the planet. Other
+//!    players' structures on it are destroyed, and the planet card is gained exhausted.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::board::{Unit, MECATOL_REX};
+use crate::game::combat::{roll_for, Hit, RollKind};
+use crate::game::dice::Dice;
+use crate::game::effects::Trigger;
+use crate::game::hex::Hex;
+use crate::game::round::Phase;
+use crate::game::units::UnitKind;
+use crate::game::{CommandError, GameEvent, GameState, PlanetCard};
+use crate::lobby::PlayerId;
+
+/// The invasion of planets in the active system, along with its progress
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Invasion {
+    pub system: Hex,
+    /// The active player
+    pub invader: PlayerId,
+    pub step: InvasionStep,
+    /// The planets ground forces were committed to, still to resolve after the current one
+    #[serde(default)]
+    pub planets: VecDeque<String>,
+}
+
+/// A step of an invasion waiting on players
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum InvasionStep {
+    /// The active player chooses which planets their ships bombard
+    Bombard,
+    /// Players assign the hits of bombardment, one planet at a time
+    BombardmentHits { hits: VecDeque<PlanetHits> },
+    /// The active player commits ground forces to planets
+    CommitGroundForces,
+    /// Players assign hits on a planet being invaded, of space cannon defense in round 0, then
+    /// of each round of ground combat
+    GroundCombat {
+        planet: String,
+        round: u32,
+        #[serde(default)]
+        hits: BTreeMap<PlayerId, u8>,
+    },
+}
+
+/// Hits a player has to assign to their ground forces on a planet
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlanetHits {
+    pub planet: String,
+    pub player: PlayerId,
+    pub hits: u8,
+}
+
+/// A ship bombarding a planet
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Bombardment {
+    pub unit: UnitKind,
+    pub planet: String,
+}
+
+/// A ground force committed from the space area to a planet
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Landing {
+    pub unit: UnitKind,
+    pub planet: String,
+}
+
+impl GameState {
+    /// Gets the invasion of planets in the active system, if any
+    pub fn invasion(&self) -> Option<&Invasion> {
+        match &self.round.phase {
+            Phase::Action(phase) => phase.invasion.as_deref(),
+            _ => None,
+        }
+    }
+
+    fn invasion_mut(&mut self) -> Option<&mut Invasion> {
+        match &mut self.round.phase {
+            Phase::Action(phase) => phase.invasion.as_deref_mut(),
+            _ => None,
+        }
+    }
+
+    fn set_invasion(&mut self, invasion: Option<Invasion>) {
+        if let Phase::Action(phase) = &mut self.round.phase {
+            phase.invasion = invasion.map(Box::new);
+        }
+    }
+
+    /// Gets the player other than the invader with ground forces on a planet
+    fn ground_defender(&self, invader: PlayerId, system: Hex, planet: &str) -> Option<PlayerId> {
+        self.board
+            .system(system)
+            .planets
+            .get(planet)?
+            .iter()
+            .find(|unit| unit.owner != invader && unit.kind.is_ground_force())
+            .map(|unit| unit.owner)
+    }
+
+    fn has_ground_forces(&self, player: PlayerId, system: Hex, planet: &str) -> bool {
+        self.board
+            .system(system)
+            .planets
+            .get(planet)
+            .is_some_and(|units| {
+                units
+                    .iter()
+                    .any(|unit| unit.owner == player && unit.kind.is_ground_force())
+            })
+    }
+
+    /// Checks whether a planet is protected from a player's bombardment by a planetary shield,
+    /// which their war suns disable
+    fn is_shielded(&self, invader: PlayerId, system: Hex, planet: &str) -> bool {
+        let state = self.board.system(system);
+        let war_sun = state
+            .ships_of(invader)
+            .any(|unit| unit.kind == UnitKind::WarSun);
+        !war_sun
+            && state.planets.get(planet).is_some_and(|units| {
+                units.iter().any(|unit| {
+                    unit.owner != invader
+                        && self.stats(unit).is_some_and(|stats| stats.planetary_shield)
+                })
+            })
+    }
+
+    /// Gets the planets of a system a player can bombard: ones with other players' ground
+    /// forces, without a planetary shield
+    pub fn bombardment_targets(&self, player: PlayerId, system: Hex) -> Vec<String> {
+        self.board
+            .planets(system)
+            .iter()
+            .filter(|planet| {
+                self.ground_defender(player, system, &planet.name).is_some()
+                    && !self.is_shielded(player, system, &planet.name)
+            })
+            .map(|planet| planet.name.clone())
+            .collect()
+    }
+
+    /// Gets the player's ships in the space area of a system that have bombardment
+    fn bombarding_ships(&self, player: PlayerId, system: Hex) -> Vec<Unit> {
+        self.board
+            .system(system)
+            .ships_of(player)
+            .filter(|unit| {
+                self.stats(unit)
+                    .is_some_and(|stats| stats.bombardment.is_some())
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Gets the planets of a system a player can commit ground forces to
+    pub fn landing_planets(&self, system: Hex) -> Vec<String> {
+        self.board
+            .planets(system)
+            .iter()
+            // nobody lands on Mecatol Rex while the custodians token is on it
+            .filter(|planet| planet.name != MECATOL_REX || self.custodians.is_some())
+            .map(|planet| planet.name.clone())
+            .collect()
+    }
+
+    /// Starts invading the planets of the active system once space combat is over. Returns
+    /// whether the invasion waits on players.
+    pub(super) fn start_invasion(
+        &mut self,
+        invader: PlayerId,
+        system: Hex,
+        dice: &mut dyn Dice,
+    ) -> (bool, Vec<GameEvent>) {
+        if self.board.planets(system).is_empty() {
+            return (false, vec![]);
+        }
+        self.set_invasion(Some(Invasion {
+            system,
+            invader,
+            step: InvasionStep::Bombard,
+            planets: VecDeque::new(),
+        }));
+        self.advance_invasion(dice)
+    }
+
+    /// Resolves invasion steps until one waits on players, or the invasion is over
+    fn advance_invasion(&mut self, dice: &mut dyn Dice) -> (bool, Vec<GameEvent>) {
+        let mut events = vec![];
+        loop {
+            let Some(invasion) = self.invasion().cloned() else {
+                return (false, events);
+            };
+            let (system, invader) = (invasion.system, invasion.invader);
+            let step = match invasion.step {
+                InvasionStep::Bombard => {
+                    if !self.bombardment_targets(invader, system).is_empty()
+                        && !self.bombarding_ships(invader, system).is_empty()
+                    {
+                        return (true, events);
+                    }
+                    InvasionStep::CommitGroundForces
+                }
+                InvasionStep::BombardmentHits { mut hits } => {
+                    // skip planets whose ground forces were already destroyed
+                    while hits.front().is_some_and(|front| {
+                        self.hits_absorbed(front.player, system, Some(&front.planet)) == 0
+                    }) {
+                        hits.pop_front();
+                    }
+                    if !hits.is_empty() {
+                        if let Some(invasion) = self.invasion_mut() {
+                            invasion.step = InvasionStep::BombardmentHits { hits };
+                        }
+                        return (true, events);
+                    }
+                    InvasionStep::CommitGroundForces
+                }
+                InvasionStep::CommitGroundForces => {
+                    let ground_forces = self
+                        .board
+                        .system(system)
+                        .space
+                        .iter()
+                        .any(|unit| unit.owner == invader && unit.kind.is_ground_force());
+                    if ground_forces && !self.landing_planets(system).is_empty() {
+                        return (true, events);
+                    }
+                    self.set_invasion(None);
+                    return (false, events);
+                }
+                InvasionStep::GroundCombat {
+                    planet,
+                    round,
+                    hits,
+                } => {
+                    if !hits.is_empty() {
+                        return (true, events);
+                    }
+                    match self.ground_defender(invader, system, &planet) {
+                        Some(defender) if self.has_ground_forces(invader, system, &planet) => {
+                            let (hits, more) =
+                                self.roll_ground_combat(invader, defender, system, dice);
+                            events.extend(more);
+                            InvasionStep::GroundCombat {
+                                planet,
+                                round: round + 1,
+                                hits,
+                            }
+                        }
+                        _ => {
+                            events.extend(self.establish_control(invader, system, &planet));
+                            let Some(next) = self
+                                .invasion_mut()
+                                .and_then(|invasion| invasion.planets.pop_front())
+                            else {
+                                self.set_invasion(None);
+                                return (false, events);
+                            };
+                            let (hits, more) =
+                                self.space_cannon_defense(invader, system, &next, dice);
+                            events.extend(more);
+                            InvasionStep::GroundCombat {
+                                planet: next,
+                                round: 0,
+                                hits,
+                            }
+                        }
+                    }
+                }
+            };
+            if let Some(invasion) = self.invasion_mut() {
+                invasion.step = step;
+            }
+        }
+    }
+
+    /// Fires units with space cannon on a planet at the ground forces committed to it
+    fn space_cannon_defense(
+        &mut self,
+        invader: PlayerId,
+        system: Hex,
+        planet: &str,
+        dice: &mut dyn Dice,
+    ) -> (BTreeMap<PlayerId, u8>, Vec<GameEvent>) {
+        let mut rolls = BTreeMap::<PlayerId, Vec<_>>::new();
+        for unit in self
+            .board
+            .system(system)
+            .planets
+            .get(planet)
+            .into_iter()
+            .flatten()
+            .filter(|unit| unit.owner != invader)
+        {
+            if let Some(roll) = self.stats(unit).and_then(|stats| stats.space_cannon) {
+                rolls.entry(unit.owner).or_default().push(roll);
+            }
+        }
+        let mut hits = 0;
+        let mut events = vec![];
+        for (player, rolls) in rolls {
+            let (results, scored) = roll_for(rolls, dice);
+            hits += scored;
+            events.push(GameEvent::DiceRolled {
+                player,
+                kind: RollKind::SpaceCannon,
+                results,
+                hits: scored,
+            });
+        }
+        let hits = (hits > 0).then_some((invader, hits)).into_iter().collect();
+        (hits, events)
+    }
+
+    /// Rolls dice for every ground force on the planet being invaded
+    fn roll_ground_combat(
+        &self,
+        invader: PlayerId,
+        defender: PlayerId,
+        system: Hex,
+        dice: &mut dyn Dice,
+    ) -> (BTreeMap<PlayerId, u8>, Vec<GameEvent>) {
+        let Some(InvasionStep::GroundCombat { planet, .. }) =
+            self.invasion().map(|invasion| &invasion.step)
+        else {
+            return (BTreeMap::new(), vec![]);
+        };
+        let mut events = vec![];
+        let mut hits = BTreeMap::new();
+        for (player, opponent) in [(invader, defender), (defender, invader)] {
+            let rolls = self
+                .targets(player, system, Some(planet))
+                .iter()
+                .filter_map(|unit| self.stats(unit)?.combat)
+                .collect::<Vec<_>>();
+            let (results, scored) = roll_for(rolls, dice);
+            events.push(GameEvent::DiceRolled {
+                player,
+                kind: RollKind::GroundCombat,
+                results,
+                hits: scored,
+            });
+            if scored > 0 {
+                hits.insert(opponent, scored);
+            }
+        }
+        (hits, events)
+    }
+
+    /// Gives control of a planet to the invader if only their ground forces are left on it,
+    /// destroying other players' structures there
+    fn establish_control(
+        &mut self,
+        invader: PlayerId,
+        system: Hex,
+        planet: &str,
+    ) -> Vec<GameEvent> {
+        if !self.has_ground_forces(invader, system, planet)
+            || self.ground_defender(invader, system, planet).is_some()
+        {
+            return vec![];
+        }
+        let mut events = vec![];
+        let units = self
+            .board
+            .system_mut(system)
+            .planets
+            .entry(planet.to_string())
+            .or_default();
+        let (kept, lost) = units
+            .drain(..)
+            .partition::<Vec<_>, _>(|unit| unit.owner == invader);
+        *units = kept;
+        let mut destroyed = BTreeMap::<_, Vec<_>>::new();
+        for unit in lost {
+            destroyed.entry(unit.owner).or_default().push(unit.kind);
+        }
+        for (player, units) in destroyed {
+            events.push(GameEvent::UnitsDestroyed {
+                player,
+                system,
+                units,
+            });
+        }
+
+        let from = self
+            .players
+            .iter()
+            .find(|player| player.planets.contains_key(planet))
+            .map(|player| player.id);
+        if from == Some(invader) {
+            return events;
+        }
+        if let Some(from) = from.and_then(|from| self.player_mut(from)) {
+            from.planets.remove(planet);
+        }
+        if let Some(state) = self.player_mut(invader) {
+            state
+                .planets
+                .insert(planet.to_string(), PlanetCard { exhausted: true });
+        }
+        events.push(GameEvent::PlanetControlGained {
+            player: invader,
+            planet: planet.to_string(),
+            from,
+        });
+        events.extend(self.planet_control_changed(planet, from, invader));
+        events.extend(self.trigger_effects(invader, Trigger::PlanetGained));
+        events
+    }
+
+    pub(super) fn bombard(
+        &mut self,
+        player: PlayerId,
+        targets: Vec<Bombardment>,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let invasion = self.check_invasion(player, &InvasionStep::Bombard)?;
+        let system = invasion.system;
+        let allowed = self.bombardment_targets(player, system);
+        let mut ships = self.bombarding_ships(player, system);
+        let mut rolls = BTreeMap::<&str, Vec<_>>::new();
+        for target in &targets {
+            if !allowed.contains(&target.planet) {
+                return Err(CommandError::CannotBombard(target.planet.clone()));
+            }
+            let Some(index) = ships.iter().position(|unit| unit.kind == target.unit) else {
+                return Err(CommandError::NoSuchUnit(target.unit));
+            };
+            let ship = ships.remove(index);
+            if let Some(roll) = self.stats(&ship).and_then(|stats| stats.bombardment) {
+                rolls.entry(&target.planet).or_default().push(roll);
+            }
+        }
+
+        let mut events = vec![];
+        let mut hits = VecDeque::new();
+        for (planet, rolls) in rolls {
+            let (results, scored) = roll_for(rolls, dice);
+            events.push(GameEvent::DiceRolled {
+                player,
+                kind: RollKind::Bombardment,
+                results,
+                hits: scored,
+            });
+            if let Some(defender) = self.ground_defender(player, system, planet) {
+                if scored > 0 {
+                    hits.push_back(PlanetHits {
+                        planet: planet.to_string(),
+                        player: defender,
+                        hits: scored,
+                    });
+                }
+            }
+        }
+        if let Some(invasion) = self.invasion_mut() {
+            invasion.step = InvasionStep::BombardmentHits { hits };
+        }
+        events.extend(self.continue_invasion(dice));
+        Ok(events)
+    }
+
+    pub(super) fn commit_ground_forces(
+        &mut self,
+        player: PlayerId,
+        landings: Vec<Landing>,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let invasion = self.check_invasion(player, &InvasionStep::CommitGroundForces)?;
+        let system = invasion.system;
+        let allowed = self.landing_planets(system);
+        let mut state = self.board.system(system).clone();
+        let mut landed = BTreeMap::<&str, Vec<_>>::new();
+        let mut planets = VecDeque::new();
+        for landing in &landings {
+            if !allowed.contains(&landing.planet) {
+                return Err(CommandError::CannotLand(landing.planet.clone()));
+            }
+            if !landing.unit.is_ground_force() {
+                return Err(CommandError::NoSuchUnit(landing.unit));
+            }
+            let Some(unit) = state.take(player, landing.unit, None) else {
+                return Err(CommandError::NoSuchUnit(landing.unit));
+            };
+            state
+                .planets
+                .entry(landing.planet.clone())
+                .or_default()
+                .push(unit);
+            landed.entry(&landing.planet).or_default().push(unit.kind);
+            if !planets.contains(&landing.planet) {
+                planets.push_back(landing.planet.clone());
+            }
+        }
+        *self.board.system_mut(system) = state;
+
+        let mut events = landed
+            .into_iter()
+            .map(|(planet, units)| GameEvent::GroundForcesCommitted {
+                player,
+                planet: planet.to_string(),
+                units,
+            })
+            .collect::<Vec<_>>();
+        let Some(first) = planets.pop_front() else {
+            self.set_invasion(None);
+            events.extend(self.start_production(player, system));
+            return Ok(events);
+        };
+        let (hits, more) = self.space_cannon_defense(player, system, &first, dice);
+        events.extend(more);
+        if let Some(invasion) = self.invasion_mut() {
+            invasion.planets = planets;
+            invasion.step = InvasionStep::GroundCombat {
+                planet: first,
+                round: 0,
+                hits,
+            };
+        }
+        events.extend(self.continue_invasion(dice));
+        Ok(events)
+    }
+
+    pub(super) fn assign_ground_hits(
+        &mut self,
+        player: PlayerId,
+        hits: Vec<Hit>,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Some(invasion) = self.invasion() else {
+            return Err(CommandError::NotNow);
+        };
+        let system = invasion.system;
+        let (planet, taken) = match &invasion.step {
+            InvasionStep::BombardmentHits { hits } => match hits.front() {
+                Some(front) if front.player == player => (front.planet.clone(), front.hits),
+                _ => return Err(CommandError::NotYourTurn),
+            },
+            InvasionStep::GroundCombat { planet, hits, .. } => match hits.get(&player) {
+                Some(&taken) => (planet.clone(), taken),
+                None => return Err(CommandError::NotYourTurn),
+            },
+            _ => return Err(CommandError::NotNow),
+        };
+        let mut events = self.take_hits(player, system, Some(&planet), taken, &hits)?;
+        if let Some(invasion) = self.invasion_mut() {
+            match &mut invasion.step {
+                InvasionStep::BombardmentHits { hits } => {
+                    hits.pop_front();
+                }
+                InvasionStep::GroundCombat { hits, .. } => {
+                    hits.remove(&player);
+                }
+                _ => {}
+            }
+        }
+        events.extend(self.continue_invasion(dice));
+        Ok(events)
+    }
+
+    /// Checks that the invasion waits on the active player for a step
+    fn check_invasion(
+        &self,
+        player: PlayerId,
+        step: &InvasionStep,
+    ) -> Result<&Invasion, CommandError> {
+        let Some(invasion) = self.invasion() else {
+            return Err(CommandError::NotNow);
+        };
+        if invasion.step != *step {
+            return Err(CommandError::NotNow);
+        }
+        if invasion.invader != player {
+            return Err(CommandError::NotYourTurn);
+        }
+        Ok(invasion)
+    }
+
+    /// Carries on with the invasion, moving on to production once it is over
+    fn continue_invasion(&mut self, dice: &mut dyn Dice) -> Vec<GameEvent> {
+        let Some(invasion) = self.invasion() else {
+            return vec![];
+        };
+        let (invader, system) = (invasion.invader, invasion.system);
+        let (waiting, mut events) = self.advance_invasion(dice);
+        if !waiting {
+            events.extend(self.start_production(invader, system));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::{Unit, MECATOL_REX};
+    use crate::game::combat::{Hit, RollKind};
+    use crate::game::dice::LoadedDice;
+    use crate::game::hex::Hex;
+    use crate::game::invasion::{Bombardment, Landing};
+    use crate::game::movement::{Movement, ShipMove, Transport};
+    use crate::game::round::DecisionKind;
+    use crate::game::units::UnitKind;
+    use crate::game::{test_action_phase, CommandError, GameCommand, GameEvent, GameState};
+    use crate::lobby::PlayerId;
+
+    const TARGET: &str = "Vefut II";
+
+    /// Starts the action phase, where the first player invades the second player's home system
+    /// with ships and infantry waiting on Mecatol Rex, then defended by the given units
+    fn invade(
+        ships: &[UnitKind],
+        infantry: usize,
+        defense: &[UnitKind],
+        dice: &mut LoadedDice,
+    ) -> (GameState, Vec<PlayerId>, Hex) {
+        let (mut state, players) = test_action_phase(5);
+        let mecatol = state.board.find_planet(MECATOL_REX).unwrap();
+        let system = state.board.find_planet(TARGET).unwrap();
+        for unit in defense {
+            state
+                .board
+                .place_on_planet(system, TARGET, Unit::new(players[1], *unit));
+        }
+        let units = ships
+            .iter()
+            .chain(&vec![UnitKind::Infantry; infantry])
+            .copied()
+            .collect::<Vec<_>>();
+        for unit in &units {
+            state
+                .board
+                .place_in_space(mecatol, Unit::new(players[0], *unit));
+        }
+        let movement = Movement {
+            ships: ships
+                .iter()
+                .map(|&unit| ShipMove {
+                    unit,
+                    from: mecatol,
+                    through: vec![],
+                })
+                .collect(),
+            transported: (0..infantry)
+                .map(|_| Transport {
+                    unit: UnitKind::Infantry,
+                    from: mecatol,
+                    planet: None,
+                })
+                .collect(),
+        };
+        state
+            .apply_with(
+                players[0],
+                GameCommand::TacticalAction { system, movement },
+                dice,
+            )
+            .unwrap();
+        (state, players, system)
+    }
+
+    fn hit(unit: UnitKind) -> Hit {
+        Hit {
+            unit,
+            sustain: false,
+        }
+    }
+
+    fn land(infantry: usize) -> GameCommand {
+        GameCommand::CommitGroundForces {
+            landings: (0..infantry)
+                .map(|_| Landing {
+                    unit: UnitKind::Infantry,
+                    planet: TARGET.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn invasion_bombards_then_fights_for_control_of_a_planet() {
+        let (mut state, players, system) = invade(
+            &[UnitKind::Dreadnought, UnitKind::Carrier],
+            3,
+            &[UnitKind::Infantry, UnitKind::Infantry, UnitKind::SpaceDock],
+            &mut LoadedDice::default(),
+        );
+        assert_eq!(state.pending()[0].player, players[0]);
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::Bombard {
+                system,
+                planets: vec![TARGET.to_string()]
+            }
+        );
+
+        let events = state
+            .apply_with(
+                players[0],
+                GameCommand::Bombard {
+                    targets: vec![Bombardment {
+                        unit: UnitKind::Dreadnought,
+                        planet: TARGET.to_string(),
+                    }],
+                },
+                &mut LoadedDice::new([5]),
+            )
+            .unwrap();
+        assert_eq!(
+            events,
+            [GameEvent::DiceRolled {
+                player: players[0],
+                kind: RollKind::Bombardment,
+                results: vec![5],
+                hits: 1
+            }]
+        );
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::AssignHits {
+                system,
+                planet: Some(TARGET.to_string()),
+                hits: 1
+            }
+        );
+        state
+            .apply(
+                players[1],
+                GameCommand::AssignHits {
+                    hits: vec![hit(UnitKind::Infantry)],
+                },
+            )
+            .unwrap();
+
+        assert!(matches!(
+            state.pending()[0].kind,
+            DecisionKind::CommitGroundForces { .. }
+        ));
+        assert_eq!(
+            state.apply(
+                players[0],
+                GameCommand::CommitGroundForces {
+                    landings: vec![Landing {
+                        unit: UnitKind::Infantry,
+                        planet: MECATOL_REX.to_string(),
+                    }],
+                }
+            ),
+            Err(CommandError::CannotLand(MECATOL_REX.to_string()))
+        );
+        assert_eq!(
+            state.apply(players[0], land(4)),
+            Err(CommandError::NoSuchUnit(UnitKind::Infantry))
+        );
+
+        // the invader's three infantry hit once, and the last defender misses
+        let mut dice = LoadedDice::new([8, 1, 1, 1]);
+        state.apply_with(players[0], land(3), &mut dice).unwrap();
+        assert_eq!(dice.remaining(), 0);
+        let events = state
+            .apply(
+                players[1],
+                GameCommand::AssignHits {
+                    hits: vec![hit(UnitKind::Infantry)],
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            events[1..],
+            [
+                GameEvent::UnitsDestroyed {
+                    player: players[1],
+                    system,
+                    units: vec![UnitKind::SpaceDock]
+                },
+                GameEvent::PlanetControlGained {
+                    player: players[0],
+                    planet: TARGET.to_string(),
+                    from: Some(players[1])
+                },
+                GameEvent::TurnStarted { player: players[1] }
+            ]
+        );
+        assert!(state.player(players[0]).unwrap().planets[TARGET].exhausted);
+        assert!(!state
+            .player(players[1])
+            .unwrap()
+            .planets
+            .contains_key(TARGET));
+        let units = &state.board.system(system).planets[TARGET];
+        assert_eq!(units.len(), 3);
+        assert!(units.iter().all(|unit| unit.owner == players[0]));
+        assert_eq!(state.invasion(), None);
+    }
+
+    #[test]
+    fn planetary_shield_stops_bombardment_and_space_cannon_defends() {
+        // the defending PDS fires at the ships moving in, and misses
+        let mut dice = LoadedDice::new([1]);
+        let (mut state, players, system) = invade(
+            &[UnitKind::Dreadnought, UnitKind::Carrier],
+            2,
+            &[UnitKind::Pds, UnitKind::Infantry],
+            &mut dice,
+        );
+        assert_eq!(dice.remaining(), 0);
+        assert!(state.bombardment_targets(players[0], system).is_empty());
+        assert!(matches!(
+            state.pending()[0].kind,
+            DecisionKind::CommitGroundForces { .. }
+        ));
+
+        // space cannon defense hits one infantry before ground combat
+        let events = state
+            .apply_with(players[0], land(2), &mut LoadedDice::new([6]))
+            .unwrap();
+        assert_eq!(
+            events[1],
+            GameEvent::DiceRolled {
+                player: players[1],
+                kind: RollKind::SpaceCannon,
+                results: vec![6],
+                hits: 1
+            }
+        );
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::AssignHits {
+                system,
+                planet: Some(TARGET.to_string()),
+                hits: 1
+            }
+        );
+
+        // the last infantry of each side fight, and the defender wins
+        let mut dice = LoadedDice::new([1, 8]);
+        state
+            .apply_with(
+                players[0],
+                GameCommand::AssignHits {
+                    hits: vec![hit(UnitKind::Infantry)],
+                },
+                &mut dice,
+            )
+            .unwrap();
+        let events = state
+            .apply(
+                players[0],
+                GameCommand::AssignHits {
+                    hits: vec![hit(UnitKind::Infantry)],
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&GameEvent::TurnStarted { player: players[1] })
+        );
+        assert!(state
+            .player(players[1])
+            .unwrap()
+            .planets
+            .contains_key(TARGET));
+        assert_eq!(state.board.system(system).planets[TARGET].len(), 2);
+    }
+
+    #[test]
+    fn undefended_planets_are_taken_without_combat() {
+        let (mut state, players, system) =
+            invade(&[UnitKind::Carrier], 1, &[], &mut LoadedDice::default());
+        let events = state.apply(players[0], land(1)).unwrap();
+        assert_eq!(
+            events[1],
+            GameEvent::PlanetControlGained {
+                player: players[0],
+                planet: TARGET.to_string(),
+                from: Some(players[1])
+            }
+        );
+        assert!(state.player(players[0]).unwrap().planets[TARGET].exhausted);
+        assert_eq!(state.board.system(system).planets[TARGET].len(), 1);
+    }
+}
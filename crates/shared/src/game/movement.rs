@@ -0,0 +1,821 @@
+//! Moving ships into the active system during a tactical action.
+//!
+//! Ships move up to their move value, one adjacent system at a time, and can't move through
+//! systems containing other players' ships. Anomalies restrict where they go: nothing moves into
+//! a supernova, asteroid fields need Antimass Deflectors, and a nebula can only be entered as
+//! the active system, with ships leaving it moving a single system. Ships leaving a gravity rift
+//! move one system further, but roll a die to survive.
+//!
+//! Fighters and ground forces are transported by ships with capacity, picked up from the
+//! systems those ships start in or move through. Once everything has moved, the ships in the
+//! active system must fit the player's fleet pool.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::board::Board;
+use crate::game::dice::Dice;
+use crate::game::hex::Hex;
+use crate::game::tiles::Anomaly;
+use crate::game::units::UnitKind;
+use crate::game::Player;
+
+/// Lets ships move through and into asteroid fields
+pub const ANTIMASS_DEFLECTORS: &str = "antimass_deflectors";
+/// Lets ships move through systems containing other players' ships
+pub const LIGHT_WAVE_DEFLECTOR: &str = "light_wave_deflector";
+
+/// Ships leaving a gravity rift are destroyed on this roll or lower
+pub const GRAVITY_RIFT_DESTROYS: u8 = 3;
+
+/// The move value of ships starting their movement in a nebula
+const NEBULA_MOVE: u8 = 1;
+
+/// A ship moving to the active system
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ShipMove {
+    pub unit: UnitKind,
+    /// The system the ship starts in
+    pub from: Hex,
+    /// The systems the ship moves through, in order, not counting the active system
+    #[serde(default)]
+    pub through: Vec<Hex>,
+}
+
+/// A fighter or ground force transported to the active system
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Transport {
+    pub unit: UnitKind,
+    /// The system the unit is picked up from
+    pub from: Hex,
+    /// The planet a ground force is picked up from, or none for units in space
+    #[serde(default)]
+    pub planet: Option<String>,
+}
+
+/// Everything a player moves into the active system
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Movement {
+    #[serde(default)]
+    pub ships: Vec<ShipMove>,
+    #[serde(default)]
+    pub transported: Vec<Transport>,
+}
+
+/// A way for a ship to reach a system
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    /// The systems moved through, in order
+    pub through: Vec<Hex>,
+    /// The number of gravity rifts the ship leaves on the way, rolling to survive each one
+    pub rifts: u8,
+}
+
+/// A movement breaks the rules
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum MoveError {
+    #[error("{system} is not a system")]
+    NotASystem { system: Hex },
+    #[error("you already have a command token in {system}")]
+    AlreadyActivated { system: Hex },
+    #[error("ships can't move out of {system}, which contains your command token")]
+    CommandToken { system: Hex },
+    #[error("you don't have enough units of type {unit} in {system}")]
+    NoSuchUnit { unit: UnitKind, system: Hex },
+    #[error("a {unit} can't move on its own")]
+    CannotMove { unit: UnitKind },
+    #[error("a {unit} can't be transported")]
+    CannotTransport { unit: UnitKind },
+    #[error("{from} is not adjacent to {to}")]
+    NotAdjacent { from: Hex, to: Hex },
+    #[error("a {unit} in {from} can't move {distance} systems")]
+    OutOfRange {
+        unit: UnitKind,
+        from: Hex,
+        distance: usize,
+    },
+    #[error("other players' ships block movement through {system}")]
+    Blocked { system: Hex },
+    #[error("ships can't move into the {anomaly} in {system}")]
+    CannotEnter { system: Hex, anomaly: Anomaly },
+    #[error("ships can't move through the {anomaly} in {system}")]
+    CannotMoveThrough { system: Hex, anomaly: Anomaly },
+    #[error("no moving ship picks up units in {system}")]
+    NotPickedUp { system: Hex },
+    #[error("{carried} units need capacity, but the ships only have {capacity}")]
+    OverCapacity { carried: usize, capacity: usize },
+    #[error("{ships} ships don't fit a fleet pool of {fleet}")]
+    FleetPool { ships: usize, fleet: u8 },
+}
+
+/// What happened while moving
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Moved {
+    /// The units that reached the active system
+    pub arrived: Vec<UnitKind>,
+    /// The rolls made leaving gravity rifts, for each ship in the order they moved
+    pub rift_rolls: Vec<RiftRoll>,
+    /// The transported units destroyed along with the ships carrying them
+    pub lost: Vec<UnitKind>,
+}
+
+/// A die rolled by a ship leaving a gravity rift
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RiftRoll {
+    pub unit: UnitKind,
+    pub rift: Hex,
+    pub roll: u8,
+    pub destroyed: bool,
+}
+
+/// The movement rules, as they apply to one player's ships
+struct Rules<'a> {
+    board: &'a Board,
+    player: &'a Player,
+}
+
+impl Rules<'_> {
+    fn has(&self, tech: &str) -> bool {
+        self.player.technologies.contains(tech)
+    }
+
+    fn is_rift(&self, system: Hex) -> bool {
+        self.board.anomaly(system) == Some(Anomaly::GravityRift)
+    }
+
+    /// Checks that the player's ships can end their movement in a system
+    fn check_destination(&self, system: Hex) -> Result<(), MoveError> {
+        if !self.board.is_system(system) {
+            return Err(MoveError::NotASystem { system });
+        }
+        match self.board.anomaly(system) {
+            Some(anomaly @ Anomaly::Supernova) => Err(MoveError::CannotEnter { system, anomaly }),
+            Some(anomaly @ Anomaly::AsteroidField) if !self.has(ANTIMASS_DEFLECTORS) => {
+                Err(MoveError::CannotEnter { system, anomaly })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the player's ships can move through a system on the way to another
+    fn check_through(&self, system: Hex) -> Result<(), MoveError> {
+        self.check_destination(system)?;
+        if let Some(anomaly @ Anomaly::Nebula) = self.board.anomaly(system) {
+            return Err(MoveError::CannotMoveThrough { system, anomaly });
+        }
+        if self.board.system(system).has_other_ships(self.player.id)
+            && !self.has(LIGHT_WAVE_DEFLECTOR)
+        {
+            return Err(MoveError::Blocked { system });
+        }
+        Ok(())
+    }
+
+    /// Gets how far a ship moves from a system, before gravity rifts
+    fn move_value(&self, unit: UnitKind, from: Hex) -> Option<u8> {
+        let movement = self.player.unit(unit)?.stats.movement?;
+        if self.board.anomaly(from) == Some(Anomaly::Nebula) {
+            Some(NEBULA_MOVE)
+        } else {
+            Some(movement)
+        }
+    }
+
+    /// Checks the route of a ship to the active system
+    fn check_route(&self, ship: &ShipMove, active: Hex) -> Result<(), MoveError> {
+        let Some(move_value) = self.move_value(ship.unit, ship.from) else {
+            return Err(MoveError::CannotMove { unit: ship.unit });
+        };
+        if self
+            .board
+            .system(ship.from)
+            .command_tokens
+            .contains(&self.player.id)
+        {
+            return Err(MoveError::CommandToken { system: ship.from });
+        }
+        let mut at = ship.from;
+        for &next in ship.through.iter().chain([&active]) {
+            if !self.board.galaxy.is_adjacent(at, next) {
+                return Err(MoveError::NotAdjacent { from: at, to: next });
+            }
+            at = next;
+        }
+        for &system in &ship.through {
+            self.check_through(system)?;
+        }
+        let rifts = self.rifts(ship);
+        let distance = ship.through.len() + 1;
+        if distance > (move_value + rifts) as usize {
+            return Err(MoveError::OutOfRange {
+                unit: ship.unit,
+                from: ship.from,
+                distance,
+            });
+        }
+        Ok(())
+    }
+
+    /// Gets the gravity rifts a ship leaves on its way
+    fn rifts(&self, ship: &ShipMove) -> u8 {
+        [ship.from]
+            .iter()
+            .chain(&ship.through)
+            .filter(|system| self.is_rift(**system))
+            .count() as u8
+    }
+
+    /// Gets the systems the moving ships can pick units up from
+    fn pickups(&self, ships: &[ShipMove]) -> BTreeSet<Hex> {
+        ships
+            .iter()
+            .filter(|ship| {
+                self.player
+                    .unit(ship.unit)
+                    .is_some_and(|unit| unit.stats.capacity > 0)
+            })
+            .flat_map(|ship| [ship.from].into_iter().chain(ship.through.iter().copied()))
+            .filter(|system| {
+                !self
+                    .board
+                    .system(*system)
+                    .command_tokens
+                    .contains(&self.player.id)
+            })
+            .collect()
+    }
+
+    /// Checks that the units in the active system fit the capacity of the ships there and the
+    /// fleet pool, counting the moving units as if they had arrived
+    fn check_capacity(&self, active: Hex, arriving: &[UnitKind]) -> Result<(), MoveError> {
+        let units = self
+            .board
+            .system(active)
+            .space
+            .iter()
+            .filter(|unit| unit.owner == self.player.id)
+            .map(|unit| unit.kind)
+            .chain(arriving.iter().copied())
+            .collect::<Vec<_>>();
+        let capacity = units
+            .iter()
+            .filter_map(|kind| self.player.unit(*kind))
+            .map(|unit| unit.stats.capacity as usize)
+            .sum::<usize>();
+        let ground_forces = units.iter().filter(|kind| kind.is_ground_force()).count();
+        let fighters = units
+            .iter()
+            .filter(|kind| **kind == UnitKind::Fighter)
+            .count();
+        let excess_fighters =
+            (ground_forces + fighters).saturating_sub(capacity.max(ground_forces));
+        // fighters that move on their own don't need capacity, but count against the fleet pool
+        let independent = self
+            .player
+            .unit(UnitKind::Fighter)
+            .is_some_and(|fighter| fighter.stats.movement.is_some());
+        if ground_forces > capacity || (excess_fighters > 0 && !independent) {
+            return Err(MoveError::OverCapacity {
+                carried: ground_forces + fighters,
+                capacity,
+            });
+        }
+        let ships = units
+            .iter()
+            .filter(|kind| kind.is_ship() && **kind != UnitKind::Fighter)
+            .count()
+            + excess_fighters;
+        let fleet = self.player.command_tokens.fleet;
+        if ships > fleet as usize {
+            return Err(MoveError::FleetPool { ships, fleet });
+        }
+        Ok(())
+    }
+
+    /// Follows every route a ship can take with the moves it has left, recording the best one
+    /// to each system. The systems moved through so far start with the one the ship moves from.
+    fn explore(
+        &self,
+        at: Hex,
+        remaining: u8,
+        through: &mut Vec<Hex>,
+        rifts: u8,
+        found: &mut BTreeMap<Hex, Route>,
+    ) {
+        if remaining == 0 {
+            return;
+        }
+        for next in self.board.galaxy.adjacent(at) {
+            // the route starts with the system the ship moves from
+            if through.contains(&next) {
+                continue;
+            }
+            if self.check_destination(next).is_ok()
+                && !self
+                    .board
+                    .system(next)
+                    .command_tokens
+                    .contains(&self.player.id)
+            {
+                let route = Route {
+                    through: through[1..].to_vec(),
+                    rifts,
+                };
+                let better = found.get(&next).is_none_or(|best| {
+                    (route.rifts, route.through.len()) < (best.rifts, best.through.len())
+                });
+                if better {
+                    found.insert(next, route);
+                }
+            }
+            if self.check_through(next).is_ok() {
+                let rift = self.is_rift(next) as u8;
+                through.push(next);
+                self.explore(next, remaining - 1 + rift, through, rifts + rift, found);
+                through.pop();
+            }
+        }
+    }
+}
+
+impl Board {
+    /// Finds every system a player's ship can move to from a system, along with the route
+    /// leaving the fewest gravity rifts, then moving through the fewest systems
+    pub fn reachable(&self, player: &Player, unit: UnitKind, from: Hex) -> BTreeMap<Hex, Route> {
+        let rules = Rules {
+            board: self,
+            player,
+        };
+        let mut found = BTreeMap::new();
+        if self.system(from).command_tokens.contains(&player.id) {
+            return found;
+        }
+        let Some(move_value) = rules.move_value(unit, from) else {
+            return found;
+        };
+        let rift = rules.is_rift(from) as u8;
+        rules.explore(from, move_value + rift, &mut vec![from], rift, &mut found);
+        found.remove(&from);
+        found
+    }
+
+    /// Checks whether a player's ships can end their move in a system, ignoring other ships
+    pub fn can_enter(&self, player: &Player, system: Hex) -> bool {
+        let rules = Rules {
+            board: self,
+            player,
+        };
+        rules.check_destination(system).is_ok()
+    }
+
+    /// Checks that a player can move units into the active system, which must not contain
+    /// their command token yet
+    pub fn check_movement(
+        &self,
+        player: &Player,
+        active: Hex,
+        movement: &Movement,
+    ) -> Result<(), MoveError> {
+        let rules = Rules {
+            board: self,
+            player,
+        };
+        rules.check_destination(active)?;
+        if self.system(active).command_tokens.contains(&player.id) {
+            return Err(MoveError::AlreadyActivated { system: active });
+        }
+
+        let mut ships = BTreeMap::<(Hex, UnitKind), usize>::new();
+        for ship in &movement.ships {
+            rules.check_route(ship, active)?;
+            *ships.entry((ship.from, ship.unit)).or_default() += 1;
+        }
+        for ((system, unit), count) in ships {
+            let available = self
+                .system(system)
+                .ships_of(player.id)
+                .filter(|ship| ship.kind == unit)
+                .count();
+            if count > available {
+                return Err(MoveError::NoSuchUnit { unit, system });
+            }
+        }
+
+        let pickups = rules.pickups(&movement.ships);
+        let mut transported = BTreeMap::<(Hex, Option<&str>, UnitKind), usize>::new();
+        for transport in &movement.transported {
+            if transport.unit != UnitKind::Fighter && !transport.unit.is_ground_force() {
+                return Err(MoveError::CannotTransport {
+                    unit: transport.unit,
+                });
+            }
+            if !pickups.contains(&transport.from) {
+                return Err(MoveError::NotPickedUp {
+                    system: transport.from,
+                });
+            }
+            *transported
+                .entry((transport.from, transport.planet.as_deref(), transport.unit))
+                .or_default() += 1;
+        }
+        for ((system, planet, unit), count) in transported {
+            let state = self.system(system);
+            let units = match planet {
+                Some(planet) => state.planets.get(planet).map(Vec::as_slice),
+                None => Some(state.space.as_slice()),
+            };
+            let available = units
+                .unwrap_or_default()
+                .iter()
+                .filter(|other| other.owner == player.id && other.kind == unit)
+                .count();
+            if count > available {
+                return Err(MoveError::NoSuchUnit { unit, system });
+            }
+        }
+
+        let arriving = movement
+            .ships
+            .iter()
+            .map(|ship| ship.unit)
+            .chain(movement.transported.iter().map(|transport| transport.unit))
+            .collect::<Vec<_>>();
+        rules.check_capacity(active, &arriving)
+    }
+
+    /// Moves units into the active system, once [Board::check_movement] accepted them.
+    ///
+    /// Every ship leaving a gravity rift rolls a die, and is destroyed on a
+    /// [GRAVITY_RIFT_DESTROYS] or lower. If the ships lost that way were carrying units that
+    /// no longer fit, the last transported units are destroyed too.
+    pub fn move_units(
+        &mut self,
+        player: &Player,
+        active: Hex,
+        movement: &Movement,
+        dice: &mut dyn Dice,
+    ) -> Moved {
+        let mut moved = Moved::default();
+        for ship in &movement.ships {
+            let Some(unit) = self.system_mut(ship.from).take(player.id, ship.unit, None) else {
+                continue;
+            };
+            let rifts = [ship.from]
+                .into_iter()
+                .chain(ship.through.iter().copied())
+                .filter(|system| self.anomaly(*system) == Some(Anomaly::GravityRift))
+                .collect::<Vec<_>>();
+            let mut destroyed = false;
+            for rift in rifts {
+                let roll = dice.roll();
+                destroyed = roll <= GRAVITY_RIFT_DESTROYS;
+                moved.rift_rolls.push(RiftRoll {
+                    unit: ship.unit,
+                    rift,
+                    roll,
+                    destroyed,
+                });
+                if destroyed {
+                    break;
+                }
+            }
+            if !destroyed {
+                self.place_in_space(active, unit);
+                moved.arrived.push(ship.unit);
+            }
+        }
+
+        let mut carried = vec![];
+        for transport in &movement.transported {
+            if let Some(unit) = self.system_mut(transport.from).take(
+                player.id,
+                transport.unit,
+                transport.planet.as_deref(),
+            ) {
+                carried.push(unit);
+            }
+        }
+        while !carried.is_empty() {
+            let kinds = carried.iter().map(|unit| unit.kind).collect::<Vec<_>>();
+            let rules = Rules {
+                board: self,
+                player,
+            };
+            if rules.check_capacity(active, &kinds).is_ok() {
+                break;
+            }
+            moved.lost.extend(carried.pop().map(|unit| unit.kind));
+        }
+        for unit in carried {
+            moved.arrived.push(unit.kind);
+            self.place_in_space(active, unit);
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::{Board, Unit};
+    use crate::game::dice::LoadedDice;
+    use crate::game::galaxy::{Galaxy, MapTile};
+    use crate::game::hex::Hex;
+    use crate::game::movement::{MoveError, Movement, Route, ShipMove, Transport};
+    use crate::game::tech::TechId;
+    use crate::game::tiles::Anomaly;
+    use crate::game::units::UnitKind;
+    use crate::game::{test_game, Player};
+
+    /// Places a row of systems, from the ship's starting system eastwards
+    fn board(row: &[&str]) -> Board {
+        let mut galaxy = Galaxy::new();
+        for (q, tile) in row.iter().enumerate() {
+            galaxy.place(hex(q), MapTile::system(*tile));
+        }
+        Board::new(galaxy)
+    }
+
+    fn hex(q: usize) -> Hex {
+        Hex::new(q as i32, 0)
+    }
+
+    fn players() -> (Player, Player) {
+        let (state, _) = test_game(2);
+        (state.players[0].clone(), state.players[1].clone())
+    }
+
+    fn ship(unit: UnitKind, through: &[usize]) -> ShipMove {
+        ShipMove {
+            unit,
+            from: hex(0),
+            through: through.iter().map(|q| hex(*q)).collect(),
+        }
+    }
+
+    fn moving(ships: Vec<ShipMove>) -> Movement {
+        Movement {
+            ships,
+            transported: vec![],
+        }
+    }
+
+    #[test]
+    fn ships_move_up_to_their_move_value() {
+        let (player, _) = players();
+        let mut board = board(&["19", "46", "47", "48"]);
+        board.place_in_space(hex(0), Unit::new(player.id, UnitKind::Cruiser));
+        board.place_in_space(hex(0), Unit::new(player.id, UnitKind::Carrier));
+
+        let reachable = board.reachable(&player, UnitKind::Cruiser, hex(0));
+        assert_eq!(
+            reachable.keys().copied().collect::<Vec<_>>(),
+            [hex(1), hex(2)]
+        );
+        assert_eq!(
+            reachable[&hex(2)],
+            Route {
+                through: vec![hex(1)],
+                rifts: 0
+            }
+        );
+        assert!(board
+            .check_movement(
+                &player,
+                hex(2),
+                &moving(vec![ship(UnitKind::Cruiser, &[1])])
+            )
+            .is_ok());
+        assert_eq!(
+            board.check_movement(
+                &player,
+                hex(2),
+                &moving(vec![ship(UnitKind::Carrier, &[1])])
+            ),
+            Err(MoveError::OutOfRange {
+                unit: UnitKind::Carrier,
+                from: hex(0),
+                distance: 2
+            })
+        );
+        assert_eq!(
+            board.check_movement(
+                &player,
+                hex(3),
+                &moving(vec![ship(UnitKind::Cruiser, &[2])])
+            ),
+            Err(MoveError::NotAdjacent {
+                from: hex(0),
+                to: hex(2)
+            })
+        );
+        assert_eq!(
+            board.check_movement(
+                &player,
+                hex(1),
+                &moving(vec![ship(UnitKind::Dreadnought, &[])])
+            ),
+            Err(MoveError::NoSuchUnit {
+                unit: UnitKind::Dreadnought,
+                system: hex(0)
+            })
+        );
+
+        // ships can't leave a system containing their command token
+        board.place_command_token(hex(0), player.id);
+        assert!(board
+            .reachable(&player, UnitKind::Cruiser, hex(0))
+            .is_empty());
+    }
+
+    #[test]
+    fn other_players_ships_block_movement() {
+        let (mut player, enemy) = players();
+        let mut board = board(&["19", "46", "47"]);
+        board.place_in_space(hex(0), Unit::new(player.id, UnitKind::Cruiser));
+        board.place_in_space(hex(1), Unit::new(enemy.id, UnitKind::Destroyer));
+
+        // ships may end their movement in the blocked system, to fight
+        let reachable = board.reachable(&player, UnitKind::Cruiser, hex(0));
+        assert_eq!(reachable.keys().copied().collect::<Vec<_>>(), [hex(1)]);
+        assert_eq!(
+            board.check_movement(
+                &player,
+                hex(2),
+                &moving(vec![ship(UnitKind::Cruiser, &[1])])
+            ),
+            Err(MoveError::Blocked { system: hex(1) })
+        );
+
+        player
+            .technologies
+            .insert(TechId("light_wave_deflector".to_string()));
+        assert!(board
+            .check_movement(
+                &player,
+                hex(2),
+                &moving(vec![ship(UnitKind::Cruiser, &[1])])
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn anomalies_restrict_movement() {
+        let (mut player, _) = players();
+
+        // ships can't move through a nebula, and only move one system out of it
+        let mut nebula = board(&["19", "42", "47"]);
+        nebula.place_in_space(hex(0), Unit::new(player.id, UnitKind::Cruiser));
+        nebula.place_in_space(hex(1), Unit::new(player.id, UnitKind::Cruiser));
+        assert_eq!(
+            nebula
+                .reachable(&player, UnitKind::Cruiser, hex(0))
+                .keys()
+                .copied()
+                .collect::<Vec<_>>(),
+            [hex(1)]
+        );
+        assert_eq!(
+            nebula.check_movement(
+                &player,
+                hex(2),
+                &moving(vec![ship(UnitKind::Cruiser, &[1])])
+            ),
+            Err(MoveError::CannotMoveThrough {
+                system: hex(1),
+                anomaly: Anomaly::Nebula
+            })
+        );
+        assert_eq!(
+            nebula
+                .reachable(&player, UnitKind::Cruiser, hex(1))
+                .keys()
+                .copied()
+                .collect::<Vec<_>>(),
+            [hex(0), hex(2)]
+        );
+
+        // nothing moves into a supernova
+        let supernova = board(&["19", "43"]);
+        assert_eq!(
+            supernova.check_movement(&player, hex(1), &Movement::default()),
+            Err(MoveError::CannotEnter {
+                system: hex(1),
+                anomaly: Anomaly::Supernova
+            })
+        );
+
+        // asteroid fields need Antimass Deflectors
+        let mut asteroids = board(&["19", "44", "47"]);
+        asteroids.place_in_space(hex(0), Unit::new(player.id, UnitKind::Cruiser));
+        assert!(asteroids
+            .reachable(&player, UnitKind::Cruiser, hex(0))
+            .is_empty());
+        player
+            .technologies
+            .insert(TechId("antimass_deflectors".to_string()));
+        assert_eq!(
+            asteroids
+                .reachable(&player, UnitKind::Cruiser, hex(0))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn gravity_rifts_extend_movement_but_may_destroy_ships() {
+        let (player, _) = players();
+        let mut board = board(&["19", "41", "47", "48"]);
+        for _ in 0..2 {
+            board.place_in_space(hex(0), Unit::new(player.id, UnitKind::Cruiser));
+        }
+        let reachable = board.reachable(&player, UnitKind::Cruiser, hex(0));
+        assert_eq!(
+            reachable[&hex(3)],
+            Route {
+                through: vec![hex(1), hex(2)],
+                rifts: 1
+            }
+        );
+
+        let movement = moving(vec![
+            ship(UnitKind::Cruiser, &[1, 2]),
+            ship(UnitKind::Cruiser, &[1, 2]),
+        ]);
+        board.check_movement(&player, hex(3), &movement).unwrap();
+        let mut dice = LoadedDice::new([3, 4]);
+        let moved = board.move_units(&player, hex(3), &movement, &mut dice);
+        assert_eq!(moved.arrived, [UnitKind::Cruiser]);
+        assert!(moved.rift_rolls[0].destroyed);
+        assert!(!moved.rift_rolls[1].destroyed);
+        assert_eq!(board.system(hex(3)).ships_of(player.id).count(), 1);
+        assert_eq!(board.system(hex(0)).ships_of(player.id).count(), 0);
+    }
+
+    #[test]
+    fn transported_units_need_capacity_and_ships_need_fleet_supply() {
+        let (mut player, _) = players();
+        let mut board = board(&["19", "46", "47"]);
+        board.place_in_space(hex(0), Unit::new(player.id, UnitKind::Carrier));
+        for _ in 0..3 {
+            board.place_on_planet(hex(0), "Wellon", Unit::new(player.id, UnitKind::Infantry));
+            board.place_in_space(hex(0), Unit::new(player.id, UnitKind::Fighter));
+        }
+        board.place_in_space(hex(2), Unit::new(player.id, UnitKind::Fighter));
+        let infantry = Transport {
+            unit: UnitKind::Infantry,
+            from: hex(0),
+            planet: Some("Wellon".to_string()),
+        };
+        let fighter = Transport {
+            unit: UnitKind::Fighter,
+            from: hex(0),
+            planet: None,
+        };
+        let carrier = vec![ship(UnitKind::Carrier, &[])];
+
+        let movement = Movement {
+            ships: carrier.clone(),
+            transported: vec![
+                infantry.clone(),
+                infantry.clone(),
+                fighter.clone(),
+                fighter.clone(),
+            ],
+        };
+        board.check_movement(&player, hex(1), &movement).unwrap();
+        let movement = Movement {
+            transported: [movement.transported, vec![fighter.clone()]].concat(),
+            ..movement
+        };
+        assert_eq!(
+            board.check_movement(&player, hex(1), &movement),
+            Err(MoveError::OverCapacity {
+                carried: 5,
+                capacity: 4
+            })
+        );
+        let far = Movement {
+            ships: carrier.clone(),
+            transported: vec![Transport {
+                from: hex(2),
+                ..fighter.clone()
+            }],
+        };
+        assert_eq!(
+            board.check_movement(&player, hex(1), &far),
+            Err(MoveError::NotPickedUp { system: hex(2) })
+        );
+
+        // fighters that move on their own count against the fleet pool instead
+        player.technologies.insert(TechId("fighter_ii".to_string()));
+        board.check_movement(&player, hex(1), &movement).unwrap();
+        player.command_tokens.fleet = 1;
+        assert_eq!(
+            board.check_movement(&player, hex(1), &movement),
+            Err(MoveError::FleetPool { ships: 2, fleet: 1 })
+        );
+    }
+}
@@ -0,0 +1,933 @@
+//! Public and secret objectives, and the rules for scoring them.
+//!
+//! Every objective is read from `data/objectives.json`, which is embedded into the crate, along
+//! with the condition a player must fulfil to score it. Five stage I and five stage II public
+//! objectives are set aside at the start of the game; two stage I objectives start revealed and
+//! one more is revealed every status phase, stage I first. Each player also starts with a secret
+//! objective, and holds at most three secret objectives, scored or not.
+//!
+//! The secret deck only holds the secret objectives scored during the status phase, so it can
+//! run out; a player who would draw from it then draws nothing.
+//!
+//! During the status phase, each player scores at most one public and one secret objective.
+//! Objectives that spend something are paid for when scored.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::board::MECATOL_REX;
+use crate::game::payment::{planet_value, Currency, Payment};
+use crate::game::round::{Decision, DecisionKind, Phase, StatusStep};
+use crate::game::tech::{self as technologies, TechColor};
+use crate::game::tiles::{self, PlanetTrait};
+use crate::game::units::UnitKind;
+use crate::game::{CommandError, CommandTokens, GameEvent, GameState, Player};
+use crate::lobby::PlayerId;
+
+const OBJECTIVES: &str = include_str!("../../data/objectives.json");
+
+/// The number of public objectives of each stage set aside for a game
+pub const PUBLIC_OBJECTIVES_PER_STAGE: usize = 5;
+
+/// The number of stage I objectives revealed at the start of the game
+pub const STARTING_PUBLIC_OBJECTIVES: usize = 2;
+
+/// The most secret objectives a player can have, scored or not
+pub const SECRET_OBJECTIVE_LIMIT: usize = 3;
+
+/// Gets the catalog of every objective
+pub fn catalog() -> &'static ObjectiveCatalog {
+    static CATALOG: OnceLock<ObjectiveCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let objectives: Vec<Objective> =
+            serde_json::from_str(OBJECTIVES).expect("embedded objective catalog is valid");
+        ObjectiveCatalog {
+            order: objectives
+                .iter()
+                .map(|objective| objective.id.clone())
+                .collect(),
+            objectives: objectives
+                .into_iter()
+                .map(|objective| (objective.id.clone(), objective))
+                .collect(),
+        }
+    })
+}
+
+/// Identifies an objective, such as `expand_borders`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ObjectiveId(pub String);
+
+impl Borrow<str> for ObjectiveId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for ObjectiveId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Display for ObjectiveId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The deck an objective belongs to
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    StageOne,
+    StageTwo,
+    Secret,
+}
+
+impl Stage {
+    /// Gets the victory points gained for scoring an objective of this stage
+    pub fn points(self) -> u8 {
+        match self {
+            Stage::StageOne | Stage::Secret => 1,
+            Stage::StageTwo => 2,
+        }
+    }
+
+    pub fn is_public(self) -> bool {
+        self != Stage::Secret
+    }
+}
+
+/// What a player must do to score an objective
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum Condition {
+    /// Control planets, with the given trait if any, outside every home system if
+    /// `outside_home` is set
+    ControlPlanets {
+        count: u8,
+        #[serde(rename = "trait", default)]
+        planet_trait: Option<PlanetTrait>,
+        #[serde(default)]
+        outside_home: bool,
+    },
+    /// Control planets that all have the same trait
+    ControlPlanetsSharingTrait {
+        count: u8,
+    },
+    /// Control planets that have a technology specialty
+    ControlSpecialtyPlanets {
+        count: u8,
+    },
+    /// Control planets in other players' home systems
+    ControlPlanetsInOtherHomes {
+        count: u8,
+    },
+    /// Own `count` technologies of each of `colors` colors
+    OwnTechnologies {
+        count: u8,
+        colors: u8,
+    },
+    OwnUnitUpgrades {
+        count: u8,
+    },
+    /// Have `ships` ships or more in each of `systems` systems
+    ShipsInSystems {
+        systems: u8,
+        ships: u8,
+    },
+    /// Have ships in systems adjacent to Mecatol Rex's system
+    ShipsAdjacentToMecatol {
+        systems: u8,
+    },
+    /// Have ships in systems adjacent to other players' home systems
+    ShipsAdjacentToOtherHomes {
+        systems: u8,
+    },
+    /// Control Mecatol Rex and have ships in its system
+    OccupyMecatol {
+        ships: u8,
+    },
+    /// Have units of a kind on the board
+    UnitsOnBoard {
+        unit: UnitKind,
+        count: u8,
+    },
+    LawsInPlay {
+        count: u8,
+    },
+    /// Spend resources, influence and trade goods when scoring
+    Spend {
+        #[serde(default)]
+        resources: u8,
+        #[serde(default)]
+        influence: u8,
+        #[serde(default)]
+        trade_goods: u8,
+    },
+    /// Spend tokens from the tactic and strategy pools when scoring
+    SpendCommandTokens {
+        count: u8,
+    },
+}
+
+/// An objective, as printed on its card
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Objective {
+    pub id: ObjectiveId,
+    pub name: String,
+    pub stage: Stage,
+    pub text: String,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+/// Every objective, by id
+#[derive(Debug)]
+pub struct ObjectiveCatalog {
+    objectives: BTreeMap<ObjectiveId, Objective>,
+    /// Ids in the order they are listed in, which groups them by stage
+    order: Vec<ObjectiveId>,
+}
+
+impl ObjectiveCatalog {
+    /// Gets an objective by its id
+    pub fn get(&self, id: &str) -> Option<&Objective> {
+        self.objectives.get(id)
+    }
+
+    /// Gets every objective, grouped by stage
+    pub fn objectives(&self) -> impl Iterator<Item = &Objective> {
+        self.order.iter().map(|id| &self.objectives[id])
+    }
+
+    /// Gets every objective of a stage
+    pub fn of_stage(&self, stage: Stage) -> impl Iterator<Item = &Objective> {
+        self.objectives()
+            .filter(move |objective| objective.stage == stage)
+    }
+}
+
+/// The objective decks of a game, and the public objectives revealed from them
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ObjectiveDecks {
+    /// The stage I objectives left to reveal, drawn from the end
+    #[serde(default)]
+    pub stage_one: Vec<ObjectiveId>,
+    /// The stage II objectives left to reveal, drawn from the end
+    #[serde(default)]
+    pub stage_two: Vec<ObjectiveId>,
+    /// The secret objectives left to draw, drawn from the end
+    #[serde(default)]
+    pub secret: Vec<ObjectiveId>,
+    /// The public objectives revealed so far, in order
+    #[serde(default)]
+    pub revealed: Vec<ObjectiveId>,
+}
+
+/// What a player spends to score an objective that asks for it
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ObjectivePayment {
+    #[serde(default)]
+    pub resources: Payment,
+    #[serde(default)]
+    pub influence: Payment,
+    /// The trade goods spent as trade goods, rather than as resources or influence
+    #[serde(default)]
+    pub trade_goods: u8,
+    /// The tokens spent from the tactic and strategy pools
+    #[serde(default)]
+    pub command_tokens: CommandTokens,
+}
+
+/// Scoring an objective breaks the rules
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum ObjectiveError {
+    #[error("{objective} is not an objective")]
+    Unknown { objective: ObjectiveId },
+    #[error("{objective} has not been revealed")]
+    NotRevealed { objective: ObjectiveId },
+    #[error("{objective} is not one of your secret objectives")]
+    NotInHand { objective: ObjectiveId },
+    #[error("{objective} is not a public objective")]
+    NotPublic { objective: ObjectiveId },
+    #[error("you have already scored {objective}")]
+    AlreadyScored { objective: ObjectiveId },
+    #[error("you have already scored a public objective this status phase")]
+    PublicLimit,
+    #[error("you have already scored a secret objective this status phase")]
+    SecretLimit,
+    #[error("you don't fulfil the requirements of {objective}")]
+    NotFulfilled { objective: ObjectiveId },
+    #[error("command tokens for objectives are spent from the tactic and strategy pools")]
+    FleetTokens,
+    #[error("you only have {tactic} tactic and {strategy} strategy tokens to spend")]
+    NotEnoughCommandTokens { tactic: u8, strategy: u8 },
+    #[error("you have more than {SECRET_OBJECTIVE_LIMIT} secret objectives, discard one first")]
+    OverHandLimit,
+}
+
+impl Player {
+    /// Counts the secret objectives this player has, scored or not
+    pub fn secret_objective_count(&self) -> usize {
+        let scored = self
+            .scored_objectives
+            .iter()
+            .filter(|id| {
+                catalog()
+                    .get(&id.0)
+                    .is_some_and(|o| o.stage == Stage::Secret)
+            })
+            .count();
+        self.secret_objectives.len() + scored
+    }
+
+    /// Checks whether this player has more secret objectives than they can keep
+    pub fn is_over_secret_limit(&self) -> bool {
+        self.secret_objective_count() > SECRET_OBJECTIVE_LIMIT
+    }
+
+    /// Checks whether this player can afford to spend resources, influence and trade goods,
+    /// with trade goods making up for what their ready planets lack
+    fn can_spend(&self, resources: u8, influence: u8, trade_goods: u8) -> bool {
+        // every pair of resources and influence the ready planets can add up to, capped at
+        // what is needed
+        let mut reachable = BTreeSet::from([(0, 0)]);
+        for (name, card) in &self.planets {
+            if card.exhausted {
+                continue;
+            }
+            let planet_resources = planet_value(name, Currency::Resources);
+            let planet_influence = planet_value(name, Currency::Influence);
+            for (r, i) in reachable.clone() {
+                reachable.insert(((r + planet_resources).min(resources), i));
+                reachable.insert((r, (i + planet_influence).min(influence)));
+            }
+        }
+        reachable.into_iter().any(|(r, i)| {
+            let missing = u16::from(resources - r) + u16::from(influence - i);
+            u16::from(trade_goods) + missing <= u16::from(self.trade_goods)
+        })
+    }
+}
+
+impl GameState {
+    /// Shuffles the objective decks, reveals the first public objectives and deals each player
+    /// a secret objective
+    pub(super) fn set_up_objectives(&mut self) {
+        let mut deck = |stage| {
+            let mut deck = catalog()
+                .of_stage(stage)
+                .map(|objective| objective.id.clone())
+                .collect::<Vec<_>>();
+            self.dice.shuffle(&mut deck);
+            deck
+        };
+        let mut stage_one = deck(Stage::StageOne);
+        let mut stage_two = deck(Stage::StageTwo);
+        let secret = deck(Stage::Secret);
+        stage_one.truncate(PUBLIC_OBJECTIVES_PER_STAGE);
+        stage_two.truncate(PUBLIC_OBJECTIVES_PER_STAGE);
+        let revealed = stage_one.split_off(stage_one.len() - STARTING_PUBLIC_OBJECTIVES);
+        self.objectives = ObjectiveDecks {
+            stage_one,
+            stage_two,
+            secret,
+            revealed,
+        };
+        for player in self.seats() {
+            self.draw_secret_objective(player);
+        }
+    }
+
+    /// Reveals the next public objective during the status phase, stage I objectives first
+    pub(super) fn reveal_objective(&mut self) -> Vec<GameEvent> {
+        let decks = &mut self.objectives;
+        let Some(objective) = decks.stage_one.pop().or_else(|| decks.stage_two.pop()) else {
+            return vec![];
+        };
+        decks.revealed.push(objective.clone());
+        vec![GameEvent::ObjectiveRevealed { objective }]
+    }
+
+    /// Draws a secret objective, which the player must discard one for if it takes them over
+    /// the limit
+    pub(super) fn draw_secret_objective(&mut self, player: PlayerId) -> Vec<GameEvent> {
+        if self.player(player).is_none() {
+            return vec![];
+        }
+        let Some(objective) = self.objectives.secret.pop() else {
+            return vec![GameEvent::SecretObjectiveDeckEmpty { player }];
+        };
+        let Some(state) = self.player_mut(player) else {
+            return vec![];
+        };
+        state.secret_objectives.push(objective);
+        vec![GameEvent::SecretObjectiveDrawn { player }]
+    }
+
+    /// Discards an unscored secret objective back into the deck, while over the limit
+    pub(super) fn discard_secret_objective(
+        &mut self,
+        player: PlayerId,
+        objective: ObjectiveId,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let state = self.player_state_mut(player)?;
+        if !state.is_over_secret_limit() {
+            return Err(CommandError::NotNow);
+        }
+        let Some(index) = state
+            .secret_objectives
+            .iter()
+            .position(|id| *id == objective)
+        else {
+            return Err(ObjectiveError::NotInHand { objective }.into());
+        };
+        state.secret_objectives.remove(index);
+        self.objectives.secret.insert(0, objective);
+        Ok(vec![GameEvent::SecretObjectiveDiscarded { player }])
+    }
+
+    /// Gets the decisions of players who have to discard a secret objective
+    pub(super) fn secret_objective_discards(&self) -> Vec<Decision> {
+        self.players
+            .iter()
+            .filter(|player| player.is_over_secret_limit())
+            .map(|player| Decision {
+                player: player.id,
+                kind: DecisionKind::DiscardSecretObjective {
+                    objectives: player.secret_objectives.clone(),
+                },
+            })
+            .collect()
+    }
+
+    /// Gets the objectives a player could score right now, ignoring the limit on how many can
+    /// be scored each status phase
+    pub fn qualifying_objectives(&self, player: PlayerId) -> Vec<ObjectiveId> {
+        let Some(state) = self.player(player) else {
+            return vec![];
+        };
+        self.objectives
+            .revealed
+            .iter()
+            .chain(&state.secret_objectives)
+            .filter(|id| !state.scored_objectives.contains(id))
+            .filter(|id| {
+                catalog()
+                    .get(&id.0)
+                    .is_some_and(|objective| self.fulfils(state, objective.condition))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Checks whether a player fulfils the condition of an objective, affording what it spends
+    pub fn fulfils(&self, player: &Player, condition: Condition) -> bool {
+        let homes = self
+            .seats()
+            .into_iter()
+            .filter_map(|seat| Some((seat, self.home_system(seat)?)))
+            .collect::<BTreeMap<_, _>>();
+        let planets = || {
+            player
+                .planets
+                .keys()
+                .filter_map(|name| Some((name, tiles::catalog().planet(name)?.1)))
+        };
+        let ships_in = |hex| self.board.system(hex).ships_of(player.id).count();
+        let systems_with_ships = || {
+            self.board
+                .systems()
+                .filter(|(_, system)| system.ships_of(player.id).next().is_some())
+                .map(|(hex, _)| hex)
+        };
+        match condition {
+            Condition::ControlPlanets {
+                count,
+                planet_trait,
+                outside_home,
+            } => {
+                planets()
+                    .filter(|(_, planet)| {
+                        planet_trait.is_none_or(|t| planet.planet_trait == Some(t))
+                    })
+                    .filter(|(name, _)| {
+                        !outside_home
+                            || self
+                                .board
+                                .find_planet(name)
+                                .is_some_and(|hex| !homes.values().any(|home| *home == hex))
+                    })
+                    .count()
+                    >= count as usize
+            }
+            Condition::ControlPlanetsSharingTrait { count } => {
+                let mut traits = BTreeMap::<PlanetTrait, usize>::new();
+                for planet_trait in planets().filter_map(|(_, planet)| planet.planet_trait) {
+                    *traits.entry(planet_trait).or_default() += 1;
+                }
+                traits.values().any(|planets| *planets >= count as usize)
+            }
+            Condition::ControlSpecialtyPlanets { count } => {
+                planets()
+                    .filter(|(_, planet)| planet.specialty.is_some())
+                    .count()
+                    >= count as usize
+            }
+            Condition::ControlPlanetsInOtherHomes { count } => {
+                planets()
+                    .filter(|(name, _)| {
+                        homes.iter().any(|(seat, home)| {
+                            *seat != player.id && self.board.find_planet(name) == Some(*home)
+                        })
+                    })
+                    .count()
+                    >= count as usize
+            }
+            Condition::OwnTechnologies { count, colors } => {
+                let mut owned = BTreeMap::<TechColor, u8>::new();
+                for tech in &player.technologies {
+                    if let Some(color) = technologies::catalog()
+                        .get(&tech.0)
+                        .and_then(|tech| tech.color)
+                    {
+                        *owned.entry(color).or_default() += 1;
+                    }
+                }
+                owned.values().filter(|owned| **owned >= count).count() >= colors as usize
+            }
+            Condition::OwnUnitUpgrades { count } => {
+                player
+                    .technologies
+                    .iter()
+                    .filter(|tech| {
+                        technologies::catalog()
+                            .get(&tech.0)
+                            .is_some_and(|tech| tech.is_unit_upgrade())
+                    })
+                    .count()
+                    >= count as usize
+            }
+            Condition::ShipsInSystems { systems, ships } => {
+                systems_with_ships()
+                    .filter(|hex| ships_in(*hex) >= ships as usize)
+                    .count()
+                    >= systems as usize
+            }
+            Condition::ShipsAdjacentToMecatol { systems } => {
+                let mecatol = self.board.find_planet(MECATOL_REX);
+                systems_with_ships()
+                    .filter(|hex| {
+                        mecatol.is_some_and(|mecatol| self.board.galaxy.is_adjacent(*hex, mecatol))
+                    })
+                    .count()
+                    >= systems as usize
+            }
+            Condition::ShipsAdjacentToOtherHomes { systems } => {
+                systems_with_ships()
+                    .filter(|hex| {
+                        homes.iter().any(|(seat, home)| {
+                            *seat != player.id && self.board.galaxy.is_adjacent(*hex, *home)
+                        })
+                    })
+                    .count()
+                    >= systems as usize
+            }
+            Condition::OccupyMecatol { ships } => {
+                player.planets.contains_key(MECATOL_REX)
+                    && self
+                        .board
+                        .find_planet(MECATOL_REX)
+                        .is_some_and(|hex| ships_in(hex) >= ships as usize)
+            }
+            Condition::UnitsOnBoard { unit, count } => {
+                self.board
+                    .systems()
+                    .flat_map(|(_, system)| system.units())
+                    .filter(|u| u.owner == player.id && u.kind == unit)
+                    .count()
+                    >= count as usize
+            }
+            Condition::LawsInPlay { count } => self.laws.len() >= count as usize,
+            Condition::Spend {
+                resources,
+                influence,
+                trade_goods,
+            } => player.can_spend(resources, influence, trade_goods),
+            Condition::SpendCommandTokens { count } => {
+                player.command_tokens.tactic + player.command_tokens.strategy >= count
+            }
+        }
+    }
+
+    /// Scores an objective during the status phase, at most one public and one secret objective
+    /// each phase
+    pub(super) fn score_objective(
+        &mut self,
+        player: PlayerId,
+        objective: ObjectiveId,
+        payment: ObjectivePayment,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Phase::Status(phase) = &self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        if phase.step != StatusStep::ScoreObjectives {
+            return Err(CommandError::NotNow);
+        }
+        if phase.waiting.first() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        let stage = catalog()
+            .get(&objective.0)
+            .ok_or_else(|| ObjectiveError::Unknown {
+                objective: objective.clone(),
+            })?
+            .stage;
+        let scored = phase.scored.get(&player).into_iter().flatten();
+        if scored
+            .filter_map(|id| catalog().get(&id.0))
+            .any(|scored| scored.stage.is_public() == stage.is_public())
+        {
+            return Err(if stage.is_public() {
+                ObjectiveError::PublicLimit
+            } else {
+                ObjectiveError::SecretLimit
+            }
+            .into());
+        }
+        let events = self.score(player, &objective, &payment)?;
+        if let Phase::Status(phase) = &mut self.round.phase {
+            phase.scored.entry(player).or_default().push(objective);
+        }
+        Ok(events)
+    }
+
+    /// Scores a public objective with the primary ability of Imperial
+    pub(super) fn score_public_objective(
+        &mut self,
+        player: PlayerId,
+        objective: ObjectiveId,
+        payment: ObjectivePayment,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        if catalog()
+            .get(&objective.0)
+            .is_some_and(|objective| !objective.stage.is_public())
+        {
+            return Err(ObjectiveError::NotPublic { objective }.into());
+        }
+        self.score(player, &objective, &payment)
+    }
+
+    /// Scores an objective the player fulfils, spending what it asks for
+    fn score(
+        &mut self,
+        player: PlayerId,
+        objective: &ObjectiveId,
+        payment: &ObjectivePayment,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let definition = catalog()
+            .get(&objective.0)
+            .ok_or_else(|| ObjectiveError::Unknown {
+                objective: objective.clone(),
+            })?;
+        let state = self.player_state_mut(player)?;
+        if state.scored_objectives.contains(objective) {
+            return Err(ObjectiveError::AlreadyScored {
+                objective: objective.clone(),
+            }
+            .into());
+        }
+        let held = if definition.stage.is_public() {
+            self.objectives.revealed.contains(objective)
+        } else {
+            self.player_state_mut(player)?
+                .secret_objectives
+                .contains(objective)
+        };
+        if !held {
+            return Err(if definition.stage.is_public() {
+                ObjectiveError::NotRevealed {
+                    objective: objective.clone(),
+                }
+            } else {
+                ObjectiveError::NotInHand {
+                    objective: objective.clone(),
+                }
+            }
+            .into());
+        }
+
+        let mut events = vec![];
+        match definition.condition {
+            Condition::Spend {
+                resources,
+                influence,
+                trade_goods,
+            } => {
+                events.extend(self.spend(
+                    player,
+                    resources,
+                    &payment.resources,
+                    Currency::Resources,
+                )?);
+                events.extend(self.spend(
+                    player,
+                    influence,
+                    &payment.influence,
+                    Currency::Influence,
+                )?);
+                let state = self.player_state_mut(player)?;
+                if payment.trade_goods < trade_goods {
+                    return Err(CommandError::CannotAfford {
+                        cost: trade_goods,
+                        paid: payment.trade_goods,
+                    });
+                }
+                if payment.trade_goods > state.trade_goods {
+                    return Err(CommandError::NotEnoughTradeGoods {
+                        have: state.trade_goods,
+                    });
+                }
+                state.trade_goods -= payment.trade_goods;
+                if payment.trade_goods > 0 {
+                    events.push(GameEvent::TradeGoodsSpent {
+                        player,
+                        amount: payment.trade_goods,
+                    });
+                }
+            }
+            Condition::SpendCommandTokens { count } => {
+                let spent = payment.command_tokens;
+                if spent.fleet > 0 {
+                    return Err(ObjectiveError::FleetTokens.into());
+                }
+                if spent.total() < count {
+                    return Err(CommandError::CannotAfford {
+                        cost: count,
+                        paid: spent.total(),
+                    });
+                }
+                let tokens = &mut self.player_state_mut(player)?.command_tokens;
+                if spent.tactic > tokens.tactic || spent.strategy > tokens.strategy {
+                    return Err(ObjectiveError::NotEnoughCommandTokens {
+                        tactic: tokens.tactic,
+                        strategy: tokens.strategy,
+                    }
+                    .into());
+                }
+                tokens.tactic -= spent.tactic;
+                tokens.strategy -= spent.strategy;
+                events.push(GameEvent::CommandTokensSpent {
+                    player,
+                    tokens: spent,
+                });
+            }
+            condition => {
+                let state = self.player_state(player)?;
+                if !self.fulfils(state, condition) {
+                    return Err(ObjectiveError::NotFulfilled {
+                        objective: objective.clone(),
+                    }
+                    .into());
+                }
+            }
+        }
+
+        let state = self.player_state_mut(player)?;
+        state.secret_objectives.retain(|id| id != objective);
+        state.scored_objectives.push(objective.clone());
+        events.push(GameEvent::ObjectiveScored {
+            player,
+            objective: objective.clone(),
+        });
+        events.extend(self.gain_victory_points(player, definition.stage.points()));
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::Unit;
+    use crate::game::hex::Hex;
+    use crate::game::objectives::{
+        catalog, ObjectiveError, ObjectivePayment, Stage, SECRET_OBJECTIVE_LIMIT,
+    };
+    use crate::game::payment::Payment;
+    use crate::game::round::{DecisionKind, Phase, StatusPhase, StatusStep};
+    use crate::game::units::UnitKind;
+    use crate::game::{test_game, CommandError, GameCommand, GameEvent, GameState, PlanetCard};
+    use crate::lobby::PlayerId;
+
+    /// Starts the status phase of a two player game, with the given objectives revealed
+    fn scoring(revealed: &[&str]) -> (GameState, Vec<PlayerId>) {
+        let (mut state, players) = test_game(2);
+        state.objectives.revealed = revealed.iter().map(|&id| id.into()).collect();
+        state.round.phase = Phase::Status(StatusPhase {
+            step: StatusStep::ScoreObjectives,
+            waiting: players.clone(),
+            scored: Default::default(),
+        });
+        (state, players)
+    }
+
+    fn score(objective: &str, payment: ObjectivePayment) -> GameCommand {
+        GameCommand::ScoreObjective {
+            objective: objective.into(),
+            payment,
+        }
+    }
+
+    #[test]
+    fn catalog_has_ten_objectives_of_each_public_stage() {
+        assert_eq!(catalog().of_stage(Stage::StageOne).count(), 10);
+        assert_eq!(catalog().of_stage(Stage::StageTwo).count(), 10);
+        assert!(catalog().of_stage(Stage::Secret).count() > 0);
+    }
+
+    #[test]
+    fn qualifying_objectives_follow_the_board() {
+        let (mut state, players) = scoring(&["sway_the_council", "intimidate_council"]);
+        let player = players[0];
+        state.player_mut(player).unwrap().secret_objectives = vec!["forge_an_alliance".into()];
+        assert!(state.qualifying_objectives(player).is_empty());
+
+        // Wellon has 2 influence, so 6 trade goods make up the rest of sway the council
+        state.player_mut(player).unwrap().trade_goods = 6;
+        assert_eq!(
+            state.qualifying_objectives(player),
+            vec!["sway_the_council".into()]
+        );
+        for planet in ["Quann", "Lodor", "Torkan", "Rarron"] {
+            state
+                .player_mut(player)
+                .unwrap()
+                .planets
+                .insert(planet.to_string(), PlanetCard::default());
+        }
+        for hex in Hex::ORIGIN.spiral(1).into_iter().skip(1).take(2) {
+            state
+                .board
+                .place_in_space(hex, Unit::new(player, UnitKind::Cruiser));
+        }
+        let mut qualifying = state.qualifying_objectives(player);
+        qualifying.sort();
+        assert_eq!(
+            qualifying,
+            vec![
+                "forge_an_alliance".into(),
+                "intimidate_council".into(),
+                "sway_the_council".into()
+            ]
+        );
+    }
+
+    #[test]
+    fn one_public_and_one_secret_objective_score_per_status_phase() {
+        let (mut state, players) = scoring(&["sway_the_council", "erect_a_monument"]);
+        let player = players[0];
+        {
+            let state = state.player_mut(player).unwrap();
+            state.trade_goods = 16;
+            state.secret_objectives = vec!["dictate_policy".into()];
+        }
+        assert_eq!(
+            state.apply(
+                player,
+                score("sway_the_council", ObjectivePayment::default())
+            ),
+            Err(CommandError::CannotAfford { cost: 8, paid: 0 })
+        );
+        let influence = ObjectivePayment {
+            influence: Payment {
+                planets: vec!["Wellon".to_string()],
+                trade_goods: 6,
+            },
+            ..Default::default()
+        };
+        let events = state
+            .apply(player, score("sway_the_council", influence))
+            .unwrap();
+        assert!(events.contains(&GameEvent::ObjectiveScored {
+            player,
+            objective: "sway_the_council".into(),
+        }));
+        assert_eq!(state.player(player).unwrap().victory_points, 1);
+
+        let resources = ObjectivePayment {
+            resources: Payment {
+                planets: vec![],
+                trade_goods: 8,
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            state.apply(player, score("erect_a_monument", resources)),
+            Err(ObjectiveError::PublicLimit.into())
+        );
+        assert_eq!(
+            state.apply(player, score("dictate_policy", ObjectivePayment::default())),
+            Err(ObjectiveError::NotFulfilled {
+                objective: "dictate_policy".into()
+            }
+            .into())
+        );
+        assert_eq!(
+            state.apply(players[1], score("erect_a_monument", Default::default())),
+            Err(CommandError::NotYourTurn)
+        );
+    }
+
+    #[test]
+    fn players_over_the_secret_objective_limit_must_discard() {
+        let (mut state, players) = test_game(2);
+        let player = players[0];
+        state.objectives.secret = vec!["dictate_policy".into(); SECRET_OBJECTIVE_LIMIT + 1];
+        state.player_mut(player).unwrap().secret_objectives.clear();
+        for _ in 0..=SECRET_OBJECTIVE_LIMIT {
+            state.draw_secret_objective(player);
+        }
+        let pending = state.pending();
+        assert_eq!(pending[0].player, player);
+        assert!(matches!(
+            pending[0].kind,
+            DecisionKind::DiscardSecretObjective { .. }
+        ));
+        assert_eq!(
+            state.apply(player, GameCommand::ScoreObjectives),
+            Err(ObjectiveError::OverHandLimit.into())
+        );
+        state
+            .apply(
+                player,
+                GameCommand::DiscardSecretObjective {
+                    objective: "dictate_policy".into(),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            state.player(player).unwrap().secret_objective_count(),
+            SECRET_OBJECTIVE_LIMIT
+        );
+    }
+
+    #[test]
+    fn drawing_from_an_empty_secret_deck_draws_nothing() {
+        let (mut state, players) = test_game(2);
+        state.objectives.secret.clear();
+        let held = state.player(players[0]).unwrap().secret_objectives.clone();
+        assert_eq!(
+            state.draw_secret_objective(players[0]),
+            vec![GameEvent::SecretObjectiveDeckEmpty { player: players[0] }]
+        );
+        assert_eq!(state.player(players[0]).unwrap().secret_objectives, held);
+    }
+}
@@ -0,0 +1,218 @@
+//! Paying for things with the resources or influence of planets, and with trade goods
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::tiles;
+use crate::game::{CommandError, Player};
+
+/// What a cost is paid with
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Currency {
+    Resources,
+    Influence,
+}
+
+/// The planets a player exhausts and the trade goods they spend to pay a cost
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Payment {
+    #[serde(default)]
+    pub planets: Vec<String>,
+    #[serde(default)]
+    pub trade_goods: u8,
+}
+
+impl Payment {
+    /// Checks whether nothing is paid
+    pub fn is_empty(&self) -> bool {
+        self.planets.is_empty() && self.trade_goods == 0
+    }
+}
+
+impl Currency {
+    /// Gets the other value printed on planets
+    pub fn other(self) -> Currency {
+        match self {
+            Currency::Resources => Currency::Influence,
+            Currency::Influence => Currency::Resources,
+        }
+    }
+}
+
+/// Gets the resources or influence of a planet
+pub fn planet_value(planet: &str, currency: Currency) -> u8 {
+    tiles::catalog()
+        .planet(planet)
+        .map(|(_, planet)| match currency {
+            Currency::Resources => planet.resources,
+            Currency::Influence => planet.influence,
+        })
+        .unwrap_or_default()
+}
+
+impl Player {
+    /// Exhausts planets this player controls, which must all be ready
+    pub fn exhaust_planets(&mut self, planets: &[String]) -> Result<(), CommandError> {
+        for name in planets {
+            let planet = self
+                .planets
+                .get_mut(name)
+                .ok_or_else(|| CommandError::NotYourPlanet(name.clone()))?;
+            if planet.exhausted {
+                return Err(CommandError::PlanetExhausted(name.clone()));
+            }
+            planet.exhausted = true;
+        }
+        Ok(())
+    }
+
+    /// Readies exhausted planets this player controls
+    pub fn ready_planets(&mut self, planets: &[String]) -> Result<(), CommandError> {
+        for name in planets {
+            let planet = self
+                .planets
+                .get_mut(name)
+                .ok_or_else(|| CommandError::NotYourPlanet(name.clone()))?;
+            if !planet.exhausted {
+                return Err(CommandError::PlanetReady(name.clone()));
+            }
+            planet.exhausted = false;
+        }
+        Ok(())
+    }
+
+    /// Pays with the given planets and trade goods, returning the total paid.
+    ///
+    /// Each trade good is worth one resource or one influence. The player may be left with
+    /// exhausted planets even if the payment fails, so callers should only keep the player's
+    /// state when it succeeds.
+    pub fn pay(&mut self, payment: &Payment, currency: Currency) -> Result<u8, CommandError> {
+        self.exhaust_planets(&payment.planets)?;
+        if payment.trade_goods > self.trade_goods {
+            return Err(CommandError::NotEnoughTradeGoods {
+                have: self.trade_goods,
+            });
+        }
+        self.trade_goods -= payment.trade_goods;
+        Ok(payment
+            .planets
+            .iter()
+            .map(|planet| planet_value(planet, currency))
+            .sum::<u8>()
+            + payment.trade_goods)
+    }
+
+    /// Pays at least a cost, returning the total paid
+    pub fn pay_at_least(
+        &mut self,
+        cost: u8,
+        payment: &Payment,
+        currency: Currency,
+    ) -> Result<u8, CommandError> {
+        let paid = self.pay(payment, currency)?;
+        if paid < cost {
+            return Err(CommandError::CannotAfford { cost, paid });
+        }
+        Ok(paid)
+    }
+
+    /// Suggests the payment of a cost that wastes the least: paying the least beyond the cost,
+    /// then spending the fewest trade goods, then exhausting planets with the least of the
+    /// other value. Returns none if the player can't afford the cost.
+    pub fn suggest_payment(&self, cost: u8, currency: Currency) -> Option<Payment> {
+        // the ready planets adding up to each total, losing the least of the other value
+        let mut best = BTreeMap::from([(0u16, (0u16, vec![]))]);
+        for (name, card) in &self.planets {
+            let value = planet_value(name, currency) as u16;
+            if card.exhausted || value == 0 {
+                continue;
+            }
+            let lost = planet_value(name, currency.other()) as u16;
+            let known = best.clone();
+            for (total, (other, planets)) in known {
+                let candidate = (other + lost, planets.len() + 1);
+                let better = best
+                    .get(&(total + value))
+                    .is_none_or(|(other, planets)| candidate < (*other, planets.len()));
+                if better {
+                    let mut planets = planets.clone();
+                    planets.push(name.clone());
+                    best.insert(total + value, (other + lost, planets));
+                }
+            }
+        }
+
+        let cost = cost as u16;
+        (0..=(self.trade_goods as u16).min(cost))
+            .flat_map(|trade_goods| {
+                best.range(cost - trade_goods..)
+                    .map(move |(total, (other, planets))| {
+                        let waste = total + trade_goods - cost;
+                        (
+                            (waste, trade_goods, *other, planets.len()),
+                            trade_goods,
+                            planets,
+                        )
+                    })
+            })
+            .min_by_key(|(key, ..)| *key)
+            .map(|(_, trade_goods, planets)| Payment {
+                planets: planets.clone(),
+                trade_goods: trade_goods as u8,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::payment::{Currency, Payment};
+    use crate::game::{test_game, PlanetCard};
+
+    #[test]
+    fn suggested_payment_wastes_the_least() {
+        let (state, players) = test_game(1);
+        let mut player = state.player(players[0]).unwrap().clone();
+        player.planets = ["Wellon", "Thibah", "Vefut II"]
+            .into_iter()
+            .map(|name| (name.to_string(), PlanetCard::default()))
+            .collect();
+        player.trade_goods = 1;
+
+        // Vefut II and Thibah pay exactly, giving up less influence than with Wellon
+        assert_eq!(
+            player.suggest_payment(3, Currency::Resources),
+            Some(Payment {
+                planets: vec!["Thibah".to_string(), "Vefut II".to_string()],
+                trade_goods: 0
+            })
+        );
+        assert_eq!(
+            player.suggest_payment(5, Currency::Resources),
+            Some(Payment {
+                planets: vec![
+                    "Thibah".to_string(),
+                    "Vefut II".to_string(),
+                    "Wellon".to_string()
+                ],
+                trade_goods: 1
+            })
+        );
+        assert_eq!(player.suggest_payment(6, Currency::Resources), None);
+        assert_eq!(
+            player.suggest_payment(0, Currency::Influence),
+            Some(Payment::default())
+        );
+
+        // exhausted planets can't pay
+        player.exhaust_planets(&["Vefut II".to_string()]).unwrap();
+        assert_eq!(
+            player.suggest_payment(3, Currency::Influence),
+            Some(Payment {
+                planets: vec!["Thibah".to_string(), "Wellon".to_string()],
+                trade_goods: 0
+            })
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//! Producing units with space docks and other units with production.
+//!
+//! A player produces units in a system with the production of their units there: at the end of
+//! a tactical action in the active system, or with the secondary ability of Warfare in their
+//! home system. Fighters and infantry are produced two for their cost, but each unit counts
+//! against the production limit. Units with production are blockaded while other players have
+//! ships in their system and their owner doesn't, and can then only produce ground forces.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::board::Unit;
+use crate::game::factions;
+use crate::game::hex::Hex;
+use crate::game::payment::{planet_value, Currency, Payment};
+use crate::game::round::Phase;
+use crate::game::units::UnitKind;
+use crate::game::{CommandError, GameEvent, GameState};
+use crate::lobby::PlayerId;
+
+/// Producing units breaks the rules
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum ProductionError {
+    #[error("you have no units with production in {system}")]
+    NoProduction { system: Hex },
+    #[error("a {unit} can't be produced")]
+    CannotProduce { unit: UnitKind },
+    #[error("your production in {system} is blockaded, so it can't produce ships")]
+    Blockaded { system: Hex },
+    #[error("{produced} units is more than your production of {capacity}")]
+    OverLimit { produced: usize, capacity: u8 },
+}
+
+impl GameState {
+    /// Gets the production value of each of a player's units with production in a system
+    fn production_values(&self, player: PlayerId, system: Hex) -> Vec<u8> {
+        let state = self.board.system(system);
+        let in_space = state.space.iter().map(|unit| (unit, 0));
+        let on_planets = state
+            .planets
+            .iter()
+            .filter(|(planet, _)| self.can_produce_on(planet))
+            .flat_map(|(planet, units)| {
+                let resources = planet_value(planet, Currency::Resources);
+                units.iter().map(move |unit| (unit, resources))
+            });
+        in_space
+            .chain(on_planets)
+            .filter(|(unit, _)| unit.owner == player)
+            .filter_map(|(unit, resources)| Some(self.stats(unit)?.production?.value(resources)))
+            .collect()
+    }
+
+    /// Gets how many units a player can produce in a system, adding up the production of all
+    /// their units there
+    pub fn production_capacity(&self, player: PlayerId, system: Hex) -> u8 {
+        self.production_values(player, system).into_iter().sum()
+    }
+
+    /// Checks whether a player's units with production in a system are blockaded
+    pub fn is_blockaded(&self, player: PlayerId, system: Hex) -> bool {
+        let state = self.board.system(system);
+        state.has_other_ships(player) && state.ships_of(player).next().is_none()
+    }
+
+    /// Gets the system containing a player's home planets
+    pub fn home_system(&self, player: PlayerId) -> Option<Hex> {
+        let faction = self.player(player)?.faction.as_ref()?;
+        let home = factions::catalog().get(&faction.0)?.home_tile();
+        self.board.find_planet(&home.planets.first()?.name)
+    }
+
+    /// Gets the resources a player spends to produce units, each fighter and infantry pair
+    /// costing as much as one unless a law in play says otherwise
+    pub fn production_cost(
+        &self,
+        player: PlayerId,
+        units: &[UnitKind],
+    ) -> Result<u8, ProductionError> {
+        let mut cost = 0;
+        for kind in UnitKind::ALL {
+            let count = units.iter().filter(|unit| **unit == kind).count() as u8;
+            if count == 0 {
+                continue;
+            }
+            let unit_cost = self
+                .player(player)
+                .and_then(|state| state.unit(kind))
+                .and_then(|unit| unit.stats.cost)
+                .filter(|_| !kind.is_structure())
+                .ok_or(ProductionError::CannotProduce { unit: kind })?;
+            cost += count.div_ceil(self.produced_per_cost(kind)) * unit_cost;
+        }
+        Ok(cost)
+    }
+
+    /// Checks that a player can produce units in a system with the given production, returning
+    /// their cost
+    pub fn check_production(
+        &self,
+        player: PlayerId,
+        system: Hex,
+        units: &[UnitKind],
+        capacity: u8,
+    ) -> Result<u8, ProductionError> {
+        if capacity == 0 {
+            return Err(ProductionError::NoProduction { system });
+        }
+        if units.len() > capacity as usize {
+            return Err(ProductionError::OverLimit {
+                produced: units.len(),
+                capacity,
+            });
+        }
+        if self.is_blockaded(player, system) && units.iter().any(|unit| unit.is_ship()) {
+            return Err(ProductionError::Blockaded { system });
+        }
+        self.production_cost(player, units)
+    }
+
+    /// Produces units in a system, paying for them with resources. Ships are placed in the
+    /// space area, and ground forces on a planet with one of the player's units with
+    /// production, if any.
+    pub(super) fn produce(
+        &mut self,
+        player: PlayerId,
+        system: Hex,
+        units: Vec<UnitKind>,
+        capacity: u8,
+        payment: &Payment,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let cost = self.check_production(player, system, &units, capacity)?;
+        let mut events = self.spend(player, cost, payment, Currency::Resources)?;
+        let planet = self
+            .board
+            .system(system)
+            .planets
+            .iter()
+            .find(|(_, units)| {
+                units.iter().any(|unit| {
+                    unit.owner == player
+                        && self
+                            .stats(unit)
+                            .is_some_and(|stats| stats.production.is_some())
+                })
+            })
+            .map(|(planet, _)| planet.clone());
+        for kind in &units {
+            let unit = Unit::new(player, *kind);
+            match &planet {
+                Some(planet) if kind.is_ground_force() => {
+                    self.board.place_on_planet(system, planet, unit)
+                }
+                _ => self.board.place_in_space(system, unit),
+            }
+        }
+        if !units.is_empty() {
+            events.push(GameEvent::UnitsProduced {
+                player,
+                system,
+                units,
+            });
+        }
+        Ok(events)
+    }
+
+    /// Produces units with the production of one of the player's units in their home system,
+    /// such as a space dock, for the secondary ability of Warfare
+    pub(super) fn produce_at_home(
+        &mut self,
+        player: PlayerId,
+        units: Vec<UnitKind>,
+        payment: &Payment,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Some(home) = self.home_system(player) else {
+            return Err(CommandError::NoHomeSystem);
+        };
+        let capacity = self
+            .production_values(player, home)
+            .into_iter()
+            .max()
+            .unwrap_or_default();
+        self.produce(player, home, units, capacity, payment)
+    }
+
+    /// Ends the tactical action once invasion is over, letting the active player produce units
+    /// in the active system first if they can
+    pub(super) fn start_production(&mut self, player: PlayerId, system: Hex) -> Vec<GameEvent> {
+        if self.production_capacity(player, system) == 0 {
+            return self.end_turn();
+        }
+        if let Phase::Action(phase) = &mut self.round.phase {
+            phase.production = Some(system);
+        }
+        vec![]
+    }
+
+    /// Produces units in the active system at the end of a tactical action, then ends the turn
+    pub(super) fn production_step(
+        &mut self,
+        player: PlayerId,
+        units: Vec<UnitKind>,
+        payment: Payment,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Phase::Action(phase) = &mut self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        let Some(system) = phase.production else {
+            return Err(CommandError::NotNow);
+        };
+        if phase.active != player {
+            return Err(CommandError::NotYourTurn);
+        }
+        phase.production = None;
+        let mut events = vec![];
+        if !units.is_empty() || !payment.is_empty() {
+            let capacity = self.production_capacity(player, system);
+            events = self.produce(player, system, units, capacity, &payment)?;
+        }
+        events.extend(self.end_turn());
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::Unit;
+    use crate::game::hex::Hex;
+    use crate::game::movement::Movement;
+    use crate::game::payment::{Currency, Payment};
+    use crate::game::production::ProductionError;
+    use crate::game::round::DecisionKind;
+    use crate::game::units::UnitKind;
+    use crate::game::{test_action_phase, CommandError, GameCommand, GameEvent, GameState};
+    use crate::lobby::PlayerId;
+
+    const HOME: &str = "Wellon";
+
+    /// Starts the action phase, with a space dock in the first player's home system
+    fn with_dock() -> (GameState, Vec<PlayerId>, Hex) {
+        let (mut state, players) = test_action_phase(5);
+        let system = state.board.find_planet(HOME).unwrap();
+        state
+            .board
+            .place_on_planet(system, HOME, Unit::new(players[0], UnitKind::SpaceDock));
+        (state, players, system)
+    }
+
+    fn activate(state: &mut GameState, player: PlayerId, system: Hex) {
+        state
+            .apply(
+                player,
+                GameCommand::TacticalAction {
+                    system,
+                    movement: Movement::default(),
+                },
+            )
+            .unwrap();
+    }
+
+    fn produce(units: &[UnitKind], payment: Payment) -> GameCommand {
+        GameCommand::Produce {
+            units: units.to_vec(),
+            payment,
+        }
+    }
+
+    #[test]
+    fn units_are_produced_at_the_end_of_a_tactical_action() {
+        let (mut state, players, system) = with_dock();
+        state.player_mut(players[0]).unwrap().trade_goods = 1;
+        activate(&mut state, players[0], system);
+
+        // the space dock on Wellon produces its resources plus two
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::Produce {
+                system,
+                capacity: 3
+            }
+        );
+        assert_eq!(
+            state.apply(
+                players[0],
+                produce(&[UnitKind::Fighter; 4], Payment::default())
+            ),
+            Err(CommandError::Production(ProductionError::OverLimit {
+                produced: 4,
+                capacity: 3
+            }))
+        );
+        assert_eq!(
+            state.apply(players[0], produce(&[UnitKind::WarSun], Payment::default())),
+            Err(CommandError::Production(ProductionError::CannotProduce {
+                unit: UnitKind::WarSun
+            }))
+        );
+
+        // two fighters cost as much as one, and the infantry costs one more
+        let units = [UnitKind::Fighter, UnitKind::Fighter, UnitKind::Infantry];
+        let wellon = Payment {
+            planets: vec![HOME.to_string()],
+            trade_goods: 0,
+        };
+        assert_eq!(
+            state.apply(players[0], produce(&units, wellon)),
+            Err(CommandError::CannotAfford { cost: 2, paid: 1 })
+        );
+        let payment = state
+            .player(players[0])
+            .unwrap()
+            .suggest_payment(2, Currency::Resources)
+            .unwrap();
+        let events = state.apply(players[0], produce(&units, payment)).unwrap();
+        assert!(events.contains(&GameEvent::UnitsProduced {
+            player: players[0],
+            system,
+            units: units.to_vec(),
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&GameEvent::TurnStarted { player: players[1] })
+        );
+
+        let player = state.player(players[0]).unwrap();
+        assert_eq!(player.trade_goods, 0);
+        assert!(player.planets[HOME].exhausted);
+        let area = state.board.system(system);
+        assert_eq!(area.ships_of(players[0]).count(), 2);
+        assert_eq!(area.planets[HOME].len(), 2);
+    }
+
+    #[test]
+    fn blockaded_production_only_produces_ground_forces() {
+        let (mut state, players, system) = with_dock();
+        state
+            .board
+            .place_in_space(system, Unit::new(players[1], UnitKind::Destroyer));
+        activate(&mut state, players[0], system);
+        assert!(state.is_blockaded(players[0], system));
+
+        let wellon = Payment {
+            planets: vec![HOME.to_string()],
+            trade_goods: 0,
+        };
+        assert_eq!(
+            state.apply(players[0], produce(&[UnitKind::Fighter], wellon.clone())),
+            Err(CommandError::Production(ProductionError::Blockaded {
+                system
+            }))
+        );
+        state
+            .apply(players[0], produce(&[UnitKind::Infantry], wellon))
+            .unwrap();
+        assert_eq!(state.pending()[0].player, players[1]);
+    }
+
+    #[test]
+    fn systems_without_production_skip_producing() {
+        let (mut state, players) = test_action_phase(5);
+        let system = state.board.find_planet(HOME).unwrap();
+        activate(&mut state, players[0], system);
+        assert_eq!(state.pending()[0].player, players[1]);
+        assert_eq!(
+            state.production_cost(players[0], &[UnitKind::Infantry; 3]),
+            Ok(2)
+        );
+    }
+}
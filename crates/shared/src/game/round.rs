@@ -0,0 +1,1212 @@
+//! The round, and the phases every round goes through.
+//!
+//! A round starts with the strategy phase, where players pick strategy cards clockwise starting
+//! with the speaker, going around the table twice with four players or fewer. In the action
+//! phase, players take turns in initiative order until every player has passed, and the status
+//! phase then readies everything for the next round. Once the custodians token has been removed
+//! from Mecatol Rex, every round ends with an agenda phase.
+//!
+//! Only validated commands move the round forward. At any point, [GameState::pending] tells
+//! which decisions the game is waiting on.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::action_cards::{ActionCardId, Window, STATUS_PHASE_ACTION_CARDS};
+use crate::game::agenda::{AgendaPhase, Rider};
+use crate::game::combat::{Combat, CombatStep};
+use crate::game::effects::EffectId;
+use crate::game::hex::Hex;
+use crate::game::invasion::{Invasion, InvasionStep};
+use crate::game::objectives::ObjectiveId;
+use crate::game::strategy::{PrimaryAbility, SecondaryAbility, StrategyCard};
+use crate::game::tech::TechId;
+use crate::game::{CommandError, CommandTokens, GameEvent, GameState};
+use crate::lobby::PlayerId;
+
+/// The number of command tokens every player gains during the status phase
+pub const STATUS_PHASE_TOKENS: u8 = 2;
+
+/// The round being played
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Round {
+    /// The number of the round, starting from 1
+    pub number: u32,
+    pub speaker: PlayerId,
+    /// The strategy cards picked this round
+    pub strategy_cards: BTreeMap<StrategyCard, HeldCard>,
+    pub phase: Phase,
+}
+
+/// A strategy card picked by a player
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HeldCard {
+    pub player: PlayerId,
+    /// Whether the card's strategic action has been performed
+    pub exhausted: bool,
+}
+
+/// A phase of the round, along with its progress
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum Phase {
+    Strategy(StrategyPhase),
+    Action(ActionPhase),
+    Status(StatusPhase),
+    Agenda(AgendaPhase),
+}
+
+impl Phase {
+    /// Gets which phase this is
+    pub fn kind(&self) -> PhaseKind {
+        match self {
+            Phase::Strategy(_) => PhaseKind::Strategy,
+            Phase::Action(_) => PhaseKind::Action,
+            Phase::Status(_) => PhaseKind::Status,
+            Phase::Agenda(_) => PhaseKind::Agenda,
+        }
+    }
+}
+
+/// A phase of the round, without its progress
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseKind {
+    Strategy,
+    Action,
+    Status,
+    Agenda,
+}
+
+/// The strategy phase, where players pick strategy cards
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StrategyPhase {
+    /// The players still to pick a card, in order
+    pub picking: VecDeque<PlayerId>,
+}
+
+/// The action phase, where players take turns until every player has passed
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ActionPhase {
+    /// Every player, in initiative order
+    pub order: Vec<PlayerId>,
+    /// The player whose turn it is
+    pub active: PlayerId,
+    pub passed: BTreeSet<PlayerId>,
+    /// The secondary ability other players are deciding whether to follow, which ends the
+    /// turn once everyone has decided
+    #[serde(default)]
+    pub secondaries: Option<Secondaries>,
+    /// The combat being fought in the system the active player activated, which ends the
+    /// turn once it is over
+    #[serde(default)]
+    pub combat: Option<Box<Combat>>,
+    /// The invasion of planets in that system once combat is over, which ends the turn once
+    /// it is over
+    #[serde(default)]
+    pub invasion: Option<Box<Invasion>>,
+    /// The active system, while the active player decides what to produce there at the end of
+    /// their tactical action
+    #[serde(default)]
+    pub production: Option<Hex>,
+}
+
+/// The secondary ability of a strategy card the active player just used
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Secondaries {
+    pub card: StrategyCard,
+    /// The players still to decide, clockwise from the active player
+    pub deciding: VecDeque<PlayerId>,
+    /// The players who follow without spending a strategy token
+    #[serde(default)]
+    pub free: BTreeSet<PlayerId>,
+}
+
+impl Secondaries {
+    /// Gets the strategy tokens a player spends to follow the secondary ability
+    pub fn cost(&self, player: PlayerId) -> u8 {
+        if self.free.contains(&player) {
+            0
+        } else {
+            self.card.secondary_cost()
+        }
+    }
+}
+
+/// The status phase, which scores objectives and readies everything for the next round
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StatusPhase {
+    pub step: StatusStep,
+    /// The players the current step is waiting on, in initiative order
+    pub waiting: Vec<PlayerId>,
+    /// The objectives each player scored this status phase
+    #[serde(default)]
+    pub scored: BTreeMap<PlayerId, Vec<ObjectiveId>>,
+}
+
+/// A step of the status phase, in the order they are resolved
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusStep {
+    ScoreObjectives,
+    RevealObjective,
+    DrawActionCards,
+    RemoveCommandTokens,
+    GainCommandTokens,
+    ReadyCards,
+    RepairUnits,
+    ReturnStrategyCards,
+}
+
+impl StatusStep {
+    /// Gets the step resolved after this one, if any
+    pub fn next(self) -> Option<StatusStep> {
+        match self {
+            StatusStep::ScoreObjectives => Some(StatusStep::RevealObjective),
+            StatusStep::RevealObjective => Some(StatusStep::DrawActionCards),
+            StatusStep::DrawActionCards => Some(StatusStep::RemoveCommandTokens),
+            StatusStep::RemoveCommandTokens => Some(StatusStep::GainCommandTokens),
+            StatusStep::GainCommandTokens => Some(StatusStep::ReadyCards),
+            StatusStep::ReadyCards => Some(StatusStep::RepairUnits),
+            StatusStep::RepairUnits => Some(StatusStep::ReturnStrategyCards),
+            StatusStep::ReturnStrategyCards => None,
+        }
+    }
+
+    /// Checks whether this step waits on players, rather than resolving on its own
+    pub fn needs_players(self) -> bool {
+        matches!(
+            self,
+            StatusStep::ScoreObjectives | StatusStep::GainCommandTokens
+        )
+    }
+}
+
+/// A decision the game is waiting on
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    /// The player who has to decide
+    pub player: PlayerId,
+    #[serde(flatten)]
+    pub kind: DecisionKind,
+}
+
+/// What a player has to decide
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum DecisionKind {
+    /// Pick this many of the given technologies as starting technologies
+    PickStartingTechs { count: u8, options: Vec<TechId> },
+    /// Pick one of the strategy cards nobody has picked yet
+    PickStrategyCard { available: Vec<StrategyCard> },
+    /// Take an action on their turn of the action phase
+    TakeAction {
+        /// Whether the player may pass, having performed the strategic action of each of
+        /// their strategy cards
+        can_pass: bool,
+    },
+    /// Follow the secondary ability of the strategy card another player used, or decline
+    FollowSecondary {
+        card: StrategyCard,
+        /// The strategy tokens spent to follow
+        cost: u8,
+    },
+    /// Assign the hits their units took during combat, to their ships or to their ground
+    /// forces on a planet
+    AssignHits {
+        system: Hex,
+        #[serde(default)]
+        planet: Option<String>,
+        hits: u8,
+    },
+    /// Choose which planets to bombard, if any
+    Bombard { system: Hex, planets: Vec<String> },
+    /// Commit ground forces to planets, if any
+    CommitGroundForces { system: Hex, planets: Vec<String> },
+    /// Produce units in the active system, if any
+    Produce { system: Hex, capacity: u8 },
+    /// Announce a retreat to one of the given systems, or stay in combat
+    AnnounceRetreat { system: Hex, destinations: Vec<Hex> },
+    /// Score objectives during the status phase, then finish scoring
+    ScoreObjectives,
+    /// Discard one of these unscored secret objectives, having more than the limit
+    DiscardSecretObjective { objectives: Vec<ObjectiveId> },
+    /// Place the command tokens gained during the status phase, redistributing them between
+    /// the pools of their command sheet
+    RedistributeCommandTokens { total: u8 },
+    /// Place a rider on an outcome of an agenda before voting starts, or none
+    PlaceRider {
+        agenda: u8,
+        /// The riders nobody placed yet
+        riders: Vec<Rider>,
+        outcomes: Vec<String>,
+    },
+    /// Vote for one of the outcomes of an agenda, or abstain
+    Vote { agenda: u8, outcomes: Vec<String> },
+    /// Choose between the outcomes of an agenda tied for the most votes, as the speaker
+    BreakTie { agenda: u8, outcomes: Vec<String> },
+    /// Play one of these action cards in the open timing window, use one of these options
+    /// offered by the player's abilities and technologies, or pass
+    PlayActionCard {
+        window: Window,
+        cards: Vec<ActionCardId>,
+        #[serde(default)]
+        options: Vec<EffectId>,
+    },
+}
+
+impl Round {
+    /// Starts a round with the strategy phase
+    pub fn new(number: u32, speaker: PlayerId, seats: &[PlayerId]) -> Self {
+        Self {
+            number,
+            speaker,
+            strategy_cards: BTreeMap::new(),
+            phase: Phase::Strategy(StrategyPhase {
+                picking: picking_order(seats, speaker),
+            }),
+        }
+    }
+
+    /// Gets the strategy cards a player holds, in initiative order
+    pub fn cards_of(&self, player: PlayerId) -> Vec<StrategyCard> {
+        self.strategy_cards
+            .iter()
+            .filter(|(_, held)| held.player == player)
+            .map(|(card, _)| *card)
+            .collect()
+    }
+
+    /// Gets the initiative a player acts on, which is the lowest of their strategy cards
+    pub fn initiative_of(&self, player: PlayerId) -> Option<u8> {
+        self.cards_of(player).first().map(|card| card.initiative())
+    }
+}
+
+/// Gets the order strategy cards are picked in, going around the table as many times as each
+/// player picks a card
+fn picking_order(seats: &[PlayerId], speaker: PlayerId) -> VecDeque<PlayerId> {
+    let order = clockwise_from(seats, speaker);
+    order
+        .iter()
+        .cycle()
+        .take(order.len() * StrategyCard::cards_per_player(seats.len()))
+        .copied()
+        .collect()
+}
+
+/// Orders players clockwise around the table, starting from the given player
+pub(super) fn clockwise_from(seats: &[PlayerId], first: PlayerId) -> Vec<PlayerId> {
+    let start = seats.iter().position(|seat| *seat == first).unwrap_or(0);
+    seats[start..]
+        .iter()
+        .chain(&seats[..start])
+        .copied()
+        .collect()
+}
+
+impl GameState {
+    /// Gets the decisions the game is waiting on. Decisions are made one at a time, except for
+    /// ones every player makes at the same time, such as redistributing command tokens. Players
+    /// with too many secret objectives have to discard one before anything else, and open
+    /// timing windows come before whatever they interrupted. Before any of that, factions
+    /// pick the starting technologies they choose.
+    pub fn pending(&self) -> Vec<Decision> {
+        let picks = self.starting_tech_picks();
+        if !picks.is_empty() {
+            return picks;
+        }
+        let discards = self.secret_objective_discards();
+        if !discards.is_empty() {
+            return discards;
+        }
+        if let Some(decision) = self.window_decision() {
+            return vec![decision];
+        }
+        match &self.round.phase {
+            Phase::Strategy(phase) => phase
+                .picking
+                .front()
+                .map(|&player| Decision {
+                    player,
+                    kind: DecisionKind::PickStrategyCard {
+                        available: self.available_strategy_cards(),
+                    },
+                })
+                .into_iter()
+                .collect(),
+            Phase::Action(ActionPhase {
+                combat: Some(combat),
+                ..
+            }) => match &combat.step {
+                CombatStep::AnnounceRetreats { deciding } => deciding
+                    .front()
+                    .map(|&player| Decision {
+                        player,
+                        kind: DecisionKind::AnnounceRetreat {
+                            system: combat.system,
+                            destinations: self.retreat_destinations(player, combat.system),
+                        },
+                    })
+                    .into_iter()
+                    .collect(),
+                _ => combat
+                    .hits
+                    .iter()
+                    .map(|(&player, &hits)| Decision {
+                        player,
+                        kind: DecisionKind::AssignHits {
+                            system: combat.system,
+                            planet: None,
+                            hits,
+                        },
+                    })
+                    .collect(),
+            },
+            Phase::Action(ActionPhase {
+                invasion: Some(invasion),
+                ..
+            }) => self.invasion_decisions(invasion),
+            Phase::Action(ActionPhase {
+                active,
+                production: Some(system),
+                ..
+            }) => vec![Decision {
+                player: *active,
+                kind: DecisionKind::Produce {
+                    system: *system,
+                    capacity: self.production_capacity(*active, *system),
+                },
+            }],
+            Phase::Action(ActionPhase {
+                secondaries: Some(secondaries),
+                ..
+            }) => secondaries
+                .deciding
+                .front()
+                .map(|&player| Decision {
+                    player,
+                    kind: DecisionKind::FollowSecondary {
+                        card: secondaries.card,
+                        cost: secondaries.cost(player),
+                    },
+                })
+                .into_iter()
+                .collect(),
+            Phase::Action(phase) => vec![Decision {
+                player: phase.active,
+                kind: DecisionKind::TakeAction {
+                    can_pass: self.can_pass(phase.active),
+                },
+            }],
+            Phase::Status(phase) => match phase.step {
+                StatusStep::ScoreObjectives => phase
+                    .waiting
+                    .first()
+                    .map(|&player| Decision {
+                        player,
+                        kind: DecisionKind::ScoreObjectives,
+                    })
+                    .into_iter()
+                    .collect(),
+                StatusStep::GainCommandTokens => phase
+                    .waiting
+                    .iter()
+                    .map(|&player| Decision {
+                        player,
+                        kind: DecisionKind::RedistributeCommandTokens {
+                            total: self.command_tokens_after_gain(player),
+                        },
+                    })
+                    .collect(),
+                _ => vec![],
+            },
+            Phase::Agenda(phase) => self.agenda_decisions(phase),
+        }
+    }
+
+    /// Gets the decisions an invasion is waiting on
+    fn invasion_decisions(&self, invasion: &Invasion) -> Vec<Decision> {
+        let system = invasion.system;
+        let player = invasion.invader;
+        match &invasion.step {
+            InvasionStep::Bombard => vec![Decision {
+                player,
+                kind: DecisionKind::Bombard {
+                    system,
+                    planets: self.bombardment_targets(player, system),
+                },
+            }],
+            InvasionStep::BombardmentHits { hits } => hits
+                .front()
+                .map(|front| Decision {
+                    player: front.player,
+                    kind: DecisionKind::AssignHits {
+                        system,
+                        planet: Some(front.planet.clone()),
+                        hits: front.hits,
+                    },
+                })
+                .into_iter()
+                .collect(),
+            InvasionStep::CommitGroundForces => vec![Decision {
+                player,
+                kind: DecisionKind::CommitGroundForces {
+                    system,
+                    planets: self.landing_planets(system),
+                },
+            }],
+            InvasionStep::GroundCombat { planet, hits, .. } => hits
+                .iter()
+                .map(|(&player, &hits)| Decision {
+                    player,
+                    kind: DecisionKind::AssignHits {
+                        system,
+                        planet: Some(planet.clone()),
+                        hits,
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    /// Checks whether the game is waiting on a player
+    pub fn is_pending(&self, player: PlayerId) -> bool {
+        self.pending()
+            .iter()
+            .any(|decision| decision.player == player)
+    }
+
+    /// Gets every player, in seat order
+    pub fn seats(&self) -> Vec<PlayerId> {
+        self.players.iter().map(|player| player.id).collect()
+    }
+
+    /// Gets every player in initiative order, for the action and status phases
+    pub fn initiative_order(&self) -> Vec<PlayerId> {
+        let mut order = self.seats();
+        order.sort_by_key(|player| self.round.initiative_of(*player).unwrap_or(u8::MAX));
+        order
+    }
+
+    /// Gets the strategy cards nobody has picked this round
+    pub fn available_strategy_cards(&self) -> Vec<StrategyCard> {
+        StrategyCard::ALL
+            .into_iter()
+            .filter(|card| !self.round.strategy_cards.contains_key(card))
+            .collect()
+    }
+
+    /// Checks whether a player has performed the strategic action of each of their strategy
+    /// cards, which they must do before passing
+    pub fn can_pass(&self, player: PlayerId) -> bool {
+        self.round
+            .strategy_cards
+            .values()
+            .all(|held| held.player != player || held.exhausted)
+    }
+
+    fn command_tokens_after_gain(&self, player: PlayerId) -> u8 {
+        self.player(player)
+            .map(|player| {
+                player.command_tokens.total() + STATUS_PHASE_TOKENS + player.extra_command_tokens()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(super) fn pick_strategy_card(
+        &mut self,
+        player: PlayerId,
+        card: StrategyCard,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Phase::Strategy(phase) = &mut self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        if phase.picking.front() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        if self.round.strategy_cards.contains_key(&card) {
+            return Err(CommandError::CardTaken(card));
+        }
+        phase.picking.pop_front();
+        let done = phase.picking.is_empty();
+        self.round.strategy_cards.insert(
+            card,
+            HeldCard {
+                player,
+                exhausted: false,
+            },
+        );
+        let mut events = vec![GameEvent::StrategyCardPicked { player, card }];
+        let trade_goods = self
+            .strategy_card_trade_goods
+            .remove(&card)
+            .unwrap_or_default();
+        events.extend(self.gain_trade_goods(player, trade_goods));
+        if done {
+            events.extend(self.end_strategy_phase());
+        }
+        Ok(events)
+    }
+
+    pub(super) fn strategic_action(
+        &mut self,
+        player: PlayerId,
+        ability: PrimaryAbility,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        self.check_turn(player)?;
+        let card = ability.card();
+        let Some(held) = self
+            .round
+            .strategy_cards
+            .get_mut(&card)
+            .filter(|held| held.player == player)
+        else {
+            return Err(CommandError::NotYourCard(card));
+        };
+        if held.exhausted {
+            return Err(CommandError::CardExhausted(card));
+        }
+        held.exhausted = true;
+        let mut deciding = clockwise_from(&self.seats(), player);
+        deciding.remove(0);
+        if let Phase::Action(phase) = &mut self.round.phase {
+            phase.secondaries = Some(Secondaries {
+                card,
+                deciding: deciding.into(),
+                free: BTreeSet::new(),
+            });
+        }
+        let mut events = vec![GameEvent::StrategicActionTaken { player, card }];
+        events.extend(self.resolve_primary(player, ability)?);
+        events.extend(self.next_secondary());
+        Ok(events)
+    }
+
+    pub(super) fn follow_secondary(
+        &mut self,
+        player: PlayerId,
+        ability: SecondaryAbility,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let secondaries = self.check_secondary(player)?;
+        let card = secondaries.card;
+        if ability.card() != card {
+            return Err(CommandError::WrongCard);
+        }
+        let cost = secondaries.cost(player);
+        let Some(state) = self.player_mut(player) else {
+            return Err(CommandError::NotAPlayer(player));
+        };
+        if state.command_tokens.strategy < cost {
+            return Err(CommandError::NoStrategyToken);
+        }
+        state.command_tokens.strategy -= cost;
+        let mut events = vec![GameEvent::SecondaryFollowed { player, card }];
+        events.extend(self.resolve_secondary(player, ability)?);
+        events.extend(self.secondary_decided());
+        Ok(events)
+    }
+
+    pub(super) fn decline_secondary(
+        &mut self,
+        player: PlayerId,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let card = self.check_secondary(player)?.card;
+        let mut events = vec![GameEvent::SecondaryDeclined { player, card }];
+        events.extend(self.secondary_decided());
+        Ok(events)
+    }
+
+    pub(super) fn pass(&mut self, player: PlayerId) -> Result<Vec<GameEvent>, CommandError> {
+        self.check_turn(player)?;
+        if !self.can_pass(player) {
+            return Err(CommandError::MustUseStrategyCard);
+        }
+        if let Phase::Action(phase) = &mut self.round.phase {
+            phase.passed.insert(player);
+        }
+        let mut events = vec![GameEvent::Passed { player }];
+        events.extend(self.end_turn());
+        Ok(events)
+    }
+
+    pub(super) fn score_objectives(
+        &mut self,
+        player: PlayerId,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let Phase::Status(phase) = &mut self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        if phase.step != StatusStep::ScoreObjectives {
+            return Err(CommandError::NotNow);
+        }
+        if phase.waiting.first() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        phase.waiting.remove(0);
+        Ok(self.advance_status())
+    }
+
+    pub(super) fn redistribute_command_tokens(
+        &mut self,
+        player: PlayerId,
+        tokens: CommandTokens,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let expected = self.command_tokens_after_gain(player);
+        let Phase::Status(phase) = &mut self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        if phase.step != StatusStep::GainCommandTokens {
+            return Err(CommandError::NotNow);
+        }
+        let Some(index) = phase.waiting.iter().position(|p| *p == player) else {
+            return Err(CommandError::NotYourTurn);
+        };
+        if tokens.total() != expected {
+            return Err(CommandError::InvalidCommandTokens { expected });
+        }
+        self.check_command_tokens(&tokens)?;
+        let Phase::Status(phase) = &mut self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        phase.waiting.remove(index);
+        if let Some(player) = self.player_mut(player) {
+            player.command_tokens = tokens;
+        }
+        let mut events = vec![GameEvent::CommandTokensRedistributed { player, tokens }];
+        events.extend(self.advance_status());
+        Ok(events)
+    }
+
+    /// Checks that it is the player's turn of the action phase
+    pub(super) fn check_turn(&self, player: PlayerId) -> Result<(), CommandError> {
+        match &self.round.phase {
+            Phase::Action(phase)
+                if phase.active == player
+                    && phase.secondaries.is_none()
+                    && phase.combat.is_none()
+                    && phase.invasion.is_none()
+                    && phase.production.is_none() =>
+            {
+                Ok(())
+            }
+            Phase::Action(_) => Err(CommandError::NotYourTurn),
+            _ => Err(CommandError::NotNow),
+        }
+    }
+
+    /// Checks that the player is the next to decide whether to follow a secondary ability
+    fn check_secondary(&self, player: PlayerId) -> Result<&Secondaries, CommandError> {
+        let Phase::Action(phase) = &self.round.phase else {
+            return Err(CommandError::NotNow);
+        };
+        let Some(secondaries) = &phase.secondaries else {
+            return Err(CommandError::NotNow);
+        };
+        if secondaries.deciding.front() != Some(&player) {
+            return Err(CommandError::NotYourTurn);
+        }
+        Ok(secondaries)
+    }
+
+    /// Moves on to the next player deciding whether to follow a secondary ability, ending the
+    /// turn once everyone has decided
+    fn next_secondary(&mut self) -> Vec<GameEvent> {
+        let Phase::Action(phase) = &mut self.round.phase else {
+            return vec![];
+        };
+        if phase
+            .secondaries
+            .as_ref()
+            .is_some_and(|secondaries| !secondaries.deciding.is_empty())
+        {
+            return vec![];
+        }
+        phase.secondaries = None;
+        self.end_turn()
+    }
+
+    /// Records that the next player decided whether to follow a secondary ability
+    fn secondary_decided(&mut self) -> Vec<GameEvent> {
+        if let Phase::Action(ActionPhase {
+            secondaries: Some(secondaries),
+            ..
+        }) = &mut self.round.phase
+        {
+            secondaries.deciding.pop_front();
+        }
+        self.next_secondary()
+    }
+
+    fn end_strategy_phase(&mut self) -> Vec<GameEvent> {
+        let unpicked = self.available_strategy_cards();
+        for card in &unpicked {
+            *self.strategy_card_trade_goods.entry(*card).or_default() += 1;
+        }
+        let mut events = vec![];
+        if !unpicked.is_empty() {
+            events.push(GameEvent::TradeGoodsPlaced { cards: unpicked });
+        }
+        events.extend(self.start_action_phase());
+        events
+    }
+
+    fn start_action_phase(&mut self) -> Vec<GameEvent> {
+        let order = self.initiative_order();
+        let active = order[0];
+        self.round.phase = Phase::Action(ActionPhase {
+            order,
+            active,
+            passed: BTreeSet::new(),
+            secondaries: None,
+            combat: None,
+            invasion: None,
+            production: None,
+        });
+        vec![
+            GameEvent::PhaseStarted {
+                phase: PhaseKind::Action,
+            },
+            GameEvent::TurnStarted { player: active },
+        ]
+    }
+
+    /// Ends the turn of the active player, handing it to the next player who hasn't passed
+    pub(super) fn end_turn(&mut self) -> Vec<GameEvent> {
+        let Phase::Action(phase) = &mut self.round.phase else {
+            return vec![];
+        };
+        let current = phase
+            .order
+            .iter()
+            .position(|player| *player == phase.active)
+            .unwrap_or_default();
+        let next = (1..=phase.order.len())
+            .map(|offset| phase.order[(current + offset) % phase.order.len()])
+            .find(|player| !phase.passed.contains(player));
+        match next {
+            Some(player) => {
+                phase.active = player;
+                vec![GameEvent::TurnStarted { player }]
+            }
+            None => self.start_status_phase(),
+        }
+    }
+
+    fn start_status_phase(&mut self) -> Vec<GameEvent> {
+        self.round.phase = Phase::Status(StatusPhase {
+            step: StatusStep::ScoreObjectives,
+            waiting: self.initiative_order(),
+            scored: BTreeMap::new(),
+        });
+        let mut events = vec![GameEvent::PhaseStarted {
+            phase: PhaseKind::Status,
+        }];
+        events.extend(self.advance_status());
+        events
+    }
+
+    /// Resolves status phase steps until one waits on players, or the phase ends
+    fn advance_status(&mut self) -> Vec<GameEvent> {
+        let mut events = vec![];
+        loop {
+            let order = self.initiative_order();
+            let Phase::Status(phase) = &mut self.round.phase else {
+                return events;
+            };
+            if !phase.waiting.is_empty() {
+                return events;
+            }
+            let Some(step) = phase.step.next() else {
+                events.extend(self.end_status_phase());
+                return events;
+            };
+            phase.step = step;
+            if step.needs_players() {
+                phase.waiting = order;
+            }
+            if step == StatusStep::RevealObjective {
+                events.extend(self.reveal_objective());
+            }
+            if step == StatusStep::DrawActionCards {
+                for player in self.initiative_order() {
+                    let extra = self
+                        .player(player)
+                        .map(|state| state.extra_action_cards())
+                        .unwrap_or_default();
+                    events
+                        .extend(self.draw_action_cards(player, STATUS_PHASE_ACTION_CARDS + extra));
+                }
+            }
+            if step == StatusStep::RepairUnits {
+                events.extend(self.repair_units());
+            }
+            if step == StatusStep::ReturnStrategyCards {
+                self.round.strategy_cards.clear();
+            }
+        }
+    }
+
+    fn end_status_phase(&mut self) -> Vec<GameEvent> {
+        if self.custodians.is_none() {
+            return self.start_round();
+        }
+        self.start_agenda_phase()
+    }
+
+    /// Gets the order players vote in, starting from the player left of the speaker so that
+    /// the speaker votes last
+    pub(super) fn voting_order(&self) -> Vec<PlayerId> {
+        let mut order = clockwise_from(&self.seats(), self.round.speaker);
+        order.rotate_left(1);
+        order
+    }
+
+    pub(super) fn start_round(&mut self) -> Vec<GameEvent> {
+        self.round = Round::new(self.round.number + 1, self.round.speaker, &self.seats());
+        vec![
+            GameEvent::RoundStarted {
+                round: self.round.number,
+                speaker: self.round.speaker,
+            },
+            GameEvent::PhaseStarted {
+                phase: PhaseKind::Strategy,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::round::{DecisionKind, Phase, PhaseKind, StatusStep};
+    use crate::game::strategy::{any_primary, StrategyCard};
+    use crate::game::{test_game, CommandError, CommandTokens, GameCommand, GameEvent, GameState};
+    use crate::lobby::PlayerId;
+
+    fn pick(state: &mut GameState, player: PlayerId, card: StrategyCard) -> Vec<GameEvent> {
+        state
+            .apply(player, GameCommand::PickStrategyCard { card })
+            .unwrap()
+    }
+
+    /// Plays through the strategy phase, with whoever picks next picking the given cards
+    fn pick_all(state: &mut GameState, cards: &[StrategyCard]) {
+        for card in cards {
+            let player = state.pending()[0].player;
+            pick(state, player, *card);
+        }
+    }
+
+    /// Performs the strategic action of a card, with every other player declining to follow
+    fn act(state: &mut GameState, player: PlayerId, card: StrategyCard) {
+        let ability = any_primary(state, player, card);
+        state
+            .apply(player, GameCommand::StrategicAction { ability })
+            .unwrap();
+        while let [decision] = &state.pending()[..] {
+            if !matches!(decision.kind, DecisionKind::FollowSecondary { .. }) {
+                break;
+            }
+            state
+                .apply(decision.player, GameCommand::DeclineSecondary)
+                .unwrap();
+        }
+    }
+
+    /// Plays through the action phase, with every player using their cards then passing
+    fn play_action_phase(state: &mut GameState) {
+        while state.round.phase.kind() == PhaseKind::Action {
+            let player = state.pending()[0].player;
+            match state
+                .round
+                .cards_of(player)
+                .into_iter()
+                .find(|card| !state.round.strategy_cards[card].exhausted)
+            {
+                Some(card) => act(state, player, card),
+                None => {
+                    state.apply(player, GameCommand::Pass).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Strategy cards that leave every player's command tokens and the speaker untouched, for
+    /// five players whose initiative order is 1, 4, 3, 0, 2
+    const QUIET_CARDS: [StrategyCard; 5] = [
+        StrategyCard::Technology,
+        StrategyCard::Diplomacy,
+        StrategyCard::Imperial,
+        StrategyCard::Trade,
+        StrategyCard::Construction,
+    ];
+
+    #[test]
+    fn strategy_cards_are_picked_from_the_speaker() {
+        let (mut state, players) = test_game(3);
+        assert_eq!(state.round.number, 1);
+        assert_eq!(state.round.speaker, players[0]);
+        let pending = state.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].player, players[0]);
+        assert!(matches!(
+            &pending[0].kind,
+            DecisionKind::PickStrategyCard { available } if available.len() == 8
+        ));
+
+        assert_eq!(
+            state.apply(
+                players[1],
+                GameCommand::PickStrategyCard {
+                    card: StrategyCard::Trade
+                }
+            ),
+            Err(CommandError::NotYourTurn)
+        );
+        pick(&mut state, players[0], StrategyCard::Trade);
+        assert_eq!(
+            state.apply(
+                players[1],
+                GameCommand::PickStrategyCard {
+                    card: StrategyCard::Trade
+                }
+            ),
+            Err(CommandError::CardTaken(StrategyCard::Trade))
+        );
+        pick(&mut state, players[1], StrategyCard::Imperial);
+        pick(&mut state, players[2], StrategyCard::Leadership);
+
+        // with three players, everyone picks a second card, again from the speaker
+        assert_eq!(state.pending()[0].player, players[0]);
+        pick(&mut state, players[0], StrategyCard::Construction);
+        pick(&mut state, players[1], StrategyCard::Warfare);
+        let events = pick(&mut state, players[2], StrategyCard::Diplomacy);
+        assert_eq!(
+            events[1..],
+            [
+                GameEvent::TradeGoodsPlaced {
+                    cards: vec![StrategyCard::Politics, StrategyCard::Technology]
+                },
+                GameEvent::PhaseStarted {
+                    phase: PhaseKind::Action
+                },
+                GameEvent::TurnStarted { player: players[2] }
+            ]
+        );
+        // players act on the lowest initiative of their cards
+        assert_eq!(
+            state.initiative_order(),
+            [players[2], players[0], players[1]]
+        );
+    }
+
+    #[test]
+    fn players_use_their_strategy_cards_before_passing() {
+        let (mut state, players) = test_game(3);
+        pick_all(
+            &mut state,
+            &[
+                StrategyCard::Warfare,
+                StrategyCard::Diplomacy,
+                StrategyCard::Technology,
+                StrategyCard::Imperial,
+                StrategyCard::Leadership,
+                StrategyCard::Trade,
+            ],
+        );
+        // Leadership acts first
+        assert_eq!(state.pending()[0].player, players[1]);
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::TakeAction { can_pass: false }
+        );
+        assert_eq!(
+            state.apply(players[1], GameCommand::Pass),
+            Err(CommandError::MustUseStrategyCard)
+        );
+        let ability = any_primary(&mut state, players[1], StrategyCard::Trade);
+        assert_eq!(
+            state.apply(players[1], GameCommand::StrategicAction { ability }),
+            Err(CommandError::NotYourCard(StrategyCard::Trade))
+        );
+        act(&mut state, players[1], StrategyCard::Leadership);
+        act(&mut state, players[2], StrategyCard::Trade);
+        act(&mut state, players[0], StrategyCard::Warfare);
+
+        // having used one of two cards isn't enough to pass
+        assert_eq!(state.pending()[0].player, players[1]);
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::TakeAction { can_pass: false }
+        );
+        act(&mut state, players[1], StrategyCard::Diplomacy);
+        act(&mut state, players[2], StrategyCard::Technology);
+        act(&mut state, players[0], StrategyCard::Imperial);
+
+        // passed players are skipped, and the last player keeps taking turns
+        state.apply(players[1], GameCommand::Pass).unwrap();
+        state.apply(players[2], GameCommand::Pass).unwrap();
+        assert_eq!(state.pending()[0].player, players[0]);
+        assert_eq!(
+            state.apply(players[1], GameCommand::Pass),
+            Err(CommandError::NotYourTurn)
+        );
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::TakeAction { can_pass: true }
+        );
+        let events = state.apply(players[0], GameCommand::Pass).unwrap();
+        assert!(events.contains(&GameEvent::PhaseStarted {
+            phase: PhaseKind::Status
+        }));
+    }
+
+    #[test]
+    fn status_phase_scores_then_gains_command_tokens() {
+        let (mut state, players) = test_game(5);
+        pick_all(&mut state, &QUIET_CARDS);
+        play_action_phase(&mut state);
+        let Phase::Status(phase) = &state.round.phase else {
+            panic!("status phase should have started");
+        };
+        assert_eq!(phase.step, StatusStep::ScoreObjectives);
+        // objectives are scored in initiative order
+        let order = [players[1], players[4], players[3], players[0], players[2]];
+        assert_eq!(state.initiative_order(), order);
+        for player in order {
+            assert_eq!(state.pending()[0].player, player);
+            state.apply(player, GameCommand::ScoreObjectives).unwrap();
+        }
+
+        // every player redistributes their tokens at the same time
+        let pending = state.pending();
+        assert_eq!(pending.len(), 5);
+        assert_eq!(
+            pending[0].kind,
+            DecisionKind::RedistributeCommandTokens { total: 10 }
+        );
+        let tokens = CommandTokens {
+            tactic: 5,
+            fleet: 3,
+            strategy: 1,
+        };
+        assert_eq!(
+            state.apply(
+                players[2],
+                GameCommand::RedistributeCommandTokens { tokens }
+            ),
+            Err(CommandError::InvalidCommandTokens { expected: 10 })
+        );
+        let tokens = CommandTokens {
+            tactic: 5,
+            fleet: 3,
+            strategy: 2,
+        };
+        for player in &players[..4] {
+            state
+                .apply(*player, GameCommand::RedistributeCommandTokens { tokens })
+                .unwrap();
+        }
+        let events = state
+            .apply(
+                players[4],
+                GameCommand::RedistributeCommandTokens { tokens },
+            )
+            .unwrap();
+        assert!(events.contains(&GameEvent::RoundStarted {
+            round: 2,
+            speaker: players[0]
+        }));
+        assert_eq!(state.player(players[1]).unwrap().command_tokens, tokens);
+        assert!(state.round.strategy_cards.is_empty());
+        assert_eq!(state.round.phase.kind(), PhaseKind::Strategy);
+    }
+
+    #[test]
+    fn agenda_phase_follows_once_custodians_are_claimed() {
+        let (mut state, players) = test_game(5);
+        state.custodians = Some(players[1]);
+        state.agenda_deck.draw = vec!["economic_equality".into(), "mutiny".into()];
+        pick_all(&mut state, &QUIET_CARDS);
+        play_action_phase(&mut state);
+        for player in state.initiative_order() {
+            state.apply(player, GameCommand::ScoreObjectives).unwrap();
+        }
+        // nobody holds a card, so no window interrupts the agendas
+        state.action_deck.draw.clear();
+        for player in &players {
+            state.player_mut(*player).unwrap().action_cards.clear();
+        }
+        for player in players.clone() {
+            let tokens = CommandTokens {
+                tactic: 5,
+                fleet: 3,
+                strategy: 2,
+            };
+            state
+                .apply(player, GameCommand::RedistributeCommandTokens { tokens })
+                .unwrap();
+        }
+        assert_eq!(state.round.phase.kind(), PhaseKind::Agenda);
+
+        // the speaker places riders and votes last, on both agendas
+        for agenda in 1..=2 {
+            for player in players[1..].iter().chain(&players[..1]) {
+                let pending = state.pending();
+                assert!(matches!(
+                    pending[0].kind,
+                    DecisionKind::PlaceRider { agenda: a, .. } if a == agenda
+                ));
+                state
+                    .apply(*player, GameCommand::PlaceRider { prediction: None })
+                    .unwrap();
+            }
+            for player in players[1..].iter().chain(&players[..1]) {
+                assert!(matches!(
+                    state.pending()[0].kind,
+                    DecisionKind::Vote { agenda: a, .. } if a == agenda
+                ));
+                state
+                    .apply(
+                        *player,
+                        GameCommand::Vote {
+                            outcome: None,
+                            planets: vec![],
+                        },
+                    )
+                    .unwrap();
+            }
+            // nobody voted, so every outcome is tied
+            assert_eq!(state.pending()[0].player, players[0]);
+            let DecisionKind::BreakTie { outcomes, .. } = &state.pending()[0].kind else {
+                panic!("the speaker should break the tie");
+            };
+            let outcome = outcomes[0].clone();
+            state
+                .apply(players[0], GameCommand::BreakTie { outcome })
+                .unwrap();
+        }
+        assert_eq!(state.round.number, 2);
+        assert_eq!(state.round.phase.kind(), PhaseKind::Strategy);
+    }
+
+    #[test]
+    fn commands_are_rejected_outside_their_phase() {
+        let (mut state, players) = test_game(3);
+        let before = state.clone();
+        assert_eq!(
+            state.apply(players[0], GameCommand::Pass),
+            Err(CommandError::NotNow)
+        );
+        assert_eq!(
+            state.apply(players[0], GameCommand::ScoreObjectives),
+            Err(CommandError::NotNow)
+        );
+        assert_eq!(
+            state.apply(players[0], GameCommand::DeclineSecondary),
+            Err(CommandError::NotNow)
+        );
+        assert_eq!(state, before);
+    }
+}
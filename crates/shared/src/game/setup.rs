@@ -0,0 +1,367 @@
+//! Setting up a game: dealing factions, building the map, and placing what each faction starts
+//! with.
+//!
+//! Every player is dealt a faction at random from the catalog in [crate::game::factions]. The
+//! map follows the standard [Layout] for the number of players: Mecatol Rex at the center, the
+//! home system of each player's faction at their seat, and every other slot filled with blue
+//! and red system tiles drawn at random, two in five of them red. The hexes the layout fills
+//! with hyperlanes are left empty, so nothing moves through them. The Ghosts of Creuss place
+//! the Creuss Gate at their seat instead, and their home system past the edge of the map, where
+//! only their delta wormholes reach it.
+//!
+//! Each player then controls the planets of their home system, where their faction's starting
+//! units are placed, and owns its starting technologies and promissory notes. Factions that
+//! pick some of their starting technologies do so before anything else happens in the game.
+
+use crate::game::board::{Board, Unit};
+use crate::game::factions::{self, Faction};
+use crate::game::galaxy::{Galaxy, Layout};
+use crate::game::hex::Hex;
+use crate::game::round::{Decision, DecisionKind};
+use crate::game::tech::{self as technologies, TechId};
+use crate::game::tiles::{self, TileKind};
+use crate::game::{transactions, CommandError, GameEvent, GameState, PlanetCard};
+use crate::lobby::PlayerId;
+
+/// The tile of Mecatol Rex, at the center of every map
+const MECATOL_REX_TILE: &str = "18";
+
+/// The faction whose home system is placed off the map
+const CREUSS: &str = "creuss";
+
+/// The tile the Ghosts of Creuss place at their seat instead of their home system
+const CREUSS_GATE_TILE: &str = "17";
+
+impl GameState {
+    /// Deals every player a faction, builds the map around their home systems and gives them
+    /// what their faction starts with. Games with fewer players than any map holds only get
+    /// their factions.
+    pub(super) fn set_up_factions(&mut self) {
+        let mut dealt = factions::catalog().factions().collect::<Vec<_>>();
+        self.dice.shuffle(&mut dealt);
+        dealt.truncate(self.players.len());
+
+        for (player, faction) in self.players.iter_mut().zip(&dealt) {
+            player.faction = Some(faction.id.clone());
+            player.technologies = faction.starting_techs.iter().cloned().collect();
+            player.tech_picks = faction
+                .tech_choice
+                .as_ref()
+                .map_or(0, |choice| choice.count);
+            player.promissory_notes = transactions::starting_notes(player.id, Some(&faction.id));
+            player.planets = faction
+                .home_tile()
+                .planets
+                .iter()
+                .map(|planet| (planet.name.clone(), PlanetCard::default()))
+                .collect();
+        }
+
+        let Some(layout) = Layout::standard(self.players.len() as u8) else {
+            return;
+        };
+        self.board = Board::new(self.build_galaxy(&layout, &dealt));
+        for (player, faction) in self.seats().into_iter().zip(dealt) {
+            self.place_starting_units(player, faction);
+        }
+    }
+
+    /// Builds the standard map for the factions dealt, in seat order
+    fn build_galaxy(&mut self, layout: &Layout, dealt: &[&Faction]) -> Galaxy {
+        let catalog = tiles::catalog();
+        let mut galaxy = Galaxy::new();
+        let tile = |id: &str| {
+            catalog
+                .get(id)
+                .expect("setup tiles are cataloged")
+                .map_tile()
+        };
+        galaxy.place(Hex::ORIGIN, tile(MECATOL_REX_TILE));
+
+        for (&seat, faction) in layout.home_systems.iter().zip(dealt) {
+            let home = faction.home_tile();
+            if faction.id.0 != CREUSS {
+                galaxy.place(seat, home.map_tile());
+                continue;
+            }
+            // the home system of the Ghosts of Creuss sits two hexes past the edge of the map,
+            // so it only borders the gate through their wormholes
+            let beyond = Hex::ORIGIN
+                .ring(layout.rings + 2)
+                .into_iter()
+                .min_by_key(|hex| hex.distance(seat))
+                .expect("rings around the map aren't empty");
+            galaxy.place(seat, tile(CREUSS_GATE_TILE));
+            galaxy.place(beyond, home.map_tile());
+        }
+
+        let slots = layout.system_slots();
+        let mut draw = |kind| {
+            let mut tiles = catalog.of_kind(kind).collect::<Vec<_>>();
+            self.dice.shuffle(&mut tiles);
+            tiles
+        };
+        let (mut blue, mut red) = (draw(TileKind::Blue), draw(TileKind::Red));
+        red.truncate(slots.len() * 2 / 5);
+        blue.truncate(slots.len() - red.len());
+        let mut systems = blue.into_iter().chain(red).collect::<Vec<_>>();
+        self.dice.shuffle(&mut systems);
+        for (hex, system) in slots.into_iter().zip(systems) {
+            galaxy.place(hex, system.map_tile());
+        }
+        galaxy
+    }
+
+    /// Places a faction's starting units in its home system: ships in space, and ground forces
+    /// and structures on its first planet
+    fn place_starting_units(&mut self, player: PlayerId, faction: &Faction) {
+        let home = faction.home_tile();
+        let (Some(system), Some(planet)) = (self.home_system(player), home.planets.first()) else {
+            return;
+        };
+        let system = self.board.system_mut(system);
+        for (&kind, &count) in &faction.starting_units {
+            let units = (0..count).map(|_| Unit::new(player, kind));
+            if kind.is_ship() {
+                system.space.extend(units);
+            } else {
+                system
+                    .planets
+                    .entry(planet.name.clone())
+                    .or_default()
+                    .extend(units);
+            }
+        }
+    }
+
+    /// Gets the technologies a player can pick from as their starting technologies
+    pub fn starting_tech_options(&self, player: PlayerId) -> Vec<TechId> {
+        let Some(state) = self.player(player) else {
+            return vec![];
+        };
+        let choice = state
+            .faction
+            .as_ref()
+            .and_then(|faction| factions::catalog().get(&faction.0))
+            .and_then(|faction| faction.tech_choice.as_ref());
+        let Some(choice) = choice else {
+            return vec![];
+        };
+        let options = if choice.options.is_empty() {
+            technologies::catalog()
+                .techs()
+                .filter(|tech| tech.prerequisites.is_empty() && tech.faction.is_none())
+                .map(|tech| tech.id.clone())
+                .collect()
+        } else {
+            choice.options.clone()
+        };
+        options
+            .into_iter()
+            .filter(|tech| !state.technologies.contains(tech))
+            .collect()
+    }
+
+    /// Gets the decisions of players who still have to pick their starting technologies
+    pub(super) fn starting_tech_picks(&self) -> Vec<Decision> {
+        self.players
+            .iter()
+            .filter(|player| player.tech_picks > 0)
+            .map(|player| Decision {
+                player: player.id,
+                kind: DecisionKind::PickStartingTechs {
+                    count: player.tech_picks,
+                    options: self.starting_tech_options(player.id),
+                },
+            })
+            .collect()
+    }
+
+    /// Picks the starting technologies a player's faction lets them choose
+    pub(super) fn pick_starting_techs(
+        &mut self,
+        player: PlayerId,
+        techs: Vec<TechId>,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let options = self.starting_tech_options(player);
+        let state = self.player_state_mut(player)?;
+        if state.tech_picks == 0 {
+            return Err(CommandError::NotNow);
+        }
+        let mut picked = techs.clone();
+        picked.sort();
+        picked.dedup();
+        if picked.len() != techs.len() || techs.len() != usize::from(state.tech_picks) {
+            return Err(CommandError::WrongStartingTechs {
+                expected: state.tech_picks,
+            });
+        }
+        if let Some(tech) = techs.iter().find(|tech| !options.contains(tech)) {
+            return Err(CommandError::NotAStartingTech(tech.clone()));
+        }
+        state.tech_picks = 0;
+        state.technologies.extend(techs.iter().cloned());
+        Ok(techs
+            .into_iter()
+            .map(|tech| GameEvent::TechnologyResearched { player, tech })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::game::board::Board;
+    use crate::game::factions;
+    use crate::game::galaxy::Layout;
+    use crate::game::round::DecisionKind;
+    use crate::game::units::UnitKind;
+    use crate::game::{test_game, transactions, CommandError, GameCommand, GameState};
+    use crate::lobby::{GameId, GameSettings, GameStatus, GameSummary, PlayerId, Seat};
+
+    fn new_game(players: usize, seed: u64) -> GameState {
+        let ids = (0..players)
+            .map(|_| PlayerId(uuid::Uuid::new_v4()))
+            .collect::<Vec<_>>();
+        let summary = GameSummary {
+            id: GameId(uuid::Uuid::new_v4()),
+            name: "setup".to_string(),
+            host: ids[0],
+            settings: GameSettings::default(),
+            status: GameStatus::InProgress,
+            seats: ids
+                .iter()
+                .map(|&player| {
+                    Some(Seat {
+                        player,
+                        name: player.to_string(),
+                        disconnected: false,
+                    })
+                })
+                .collect(),
+        };
+        GameState::new(&summary, seed)
+    }
+
+    #[test]
+    fn players_start_in_their_home_systems() {
+        for players in 3..=8 {
+            let state = new_game(players, players as u64);
+            let factions = state
+                .players
+                .iter()
+                .filter_map(|player| player.faction.clone())
+                .collect::<BTreeSet<_>>();
+            assert_eq!(factions.len(), players, "every faction is dealt once");
+            // Mecatol Rex, a system in every slot, every home system, and the Creuss Gate
+            let slots = Layout::standard(players as u8)
+                .unwrap()
+                .system_slots()
+                .len();
+            let creuss = factions.contains(&"creuss".into()) as usize;
+            assert_eq!(
+                state.board.galaxy.systems().count(),
+                1 + slots + players + creuss
+            );
+
+            for player in &state.players {
+                let faction = factions::catalog()
+                    .get(&player.faction.as_ref().unwrap().0)
+                    .unwrap();
+                let home = state.home_system(player.id).expect("home system is placed");
+                let ships = state.board.system(home).ships_of(player.id).count();
+                let expected = faction
+                    .starting_units
+                    .iter()
+                    .filter(|(kind, _)| kind.is_ship())
+                    .map(|(_, count)| usize::from(*count))
+                    .sum::<usize>();
+                assert_eq!(ships, expected, "{}", faction.id);
+                assert!(state
+                    .board
+                    .system(home)
+                    .units()
+                    .any(|unit| unit.owner == player.id && unit.kind == UnitKind::SpaceDock));
+                for planet in &faction.home_tile().planets {
+                    assert!(player.planets.contains_key(&planet.name));
+                }
+                for tech in &faction.starting_techs {
+                    assert!(player.technologies.contains(tech));
+                }
+                let notes = transactions::catalog()
+                    .notes()
+                    .filter(|note| note.faction.as_ref() == Some(&faction.id));
+                for note in notes {
+                    assert!(player
+                        .promissory_notes
+                        .iter()
+                        .any(|held| held.card == note.id));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_creuss_home_system_is_only_reached_through_the_gate() {
+        let (mut state, players) = test_game(3);
+        let creuss = factions::catalog().get("creuss").unwrap();
+        let sol = factions::catalog().get("sol").unwrap();
+        let layout = Layout::standard(3).unwrap();
+        let galaxy = state.build_galaxy(&layout, &[creuss, sol, sol]);
+        state.board = Board::new(galaxy);
+        state.player_mut(players[0]).unwrap().faction = Some(creuss.id.clone());
+
+        let home = state.home_system(players[0]).unwrap();
+        let gate = layout.home_systems[0];
+        assert!(home.distance(gate) > 1);
+        assert!(state.board.galaxy.is_adjacent(home, gate));
+        assert_eq!(state.board.galaxy.adjacent(home), BTreeSet::from([gate]));
+    }
+
+    #[test]
+    fn factions_with_a_choice_pick_their_starting_techs_first() {
+        let (mut state, players) = test_game(3);
+        let argent = state.player_mut(players[1]).unwrap();
+        argent.faction = Some("argent".into());
+        argent.tech_picks = 2;
+
+        let decisions = state.pending();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].player, players[1]);
+        assert!(matches!(
+            &decisions[0].kind,
+            DecisionKind::PickStartingTechs { count: 2, options } if options.len() == 3
+        ));
+        let pick = |techs: &[&str]| GameCommand::PickStartingTechs {
+            techs: techs.iter().map(|&tech| tech.into()).collect(),
+        };
+        assert_eq!(
+            state.apply(
+                players[0],
+                GameCommand::PickStrategyCard {
+                    card: crate::game::strategy::StrategyCard::Leadership
+                }
+            ),
+            Err(CommandError::NotNow)
+        );
+        assert_eq!(
+            state.apply(players[1], pick(&["neural_motivator", "neural_motivator"])),
+            Err(CommandError::WrongStartingTechs { expected: 2 })
+        );
+        assert_eq!(
+            state.apply(players[1], pick(&["neural_motivator", "gravity_drive"])),
+            Err(CommandError::NotAStartingTech("gravity_drive".into()))
+        );
+        state
+            .apply(players[1], pick(&["neural_motivator", "plasma_scoring"]))
+            .unwrap();
+
+        let technologies = &state.player(players[1]).unwrap().technologies;
+        assert!(technologies.contains("plasma_scoring"));
+        assert!(matches!(
+            state.pending()[0].kind,
+            DecisionKind::PickStrategyCard { .. }
+        ));
+    }
+}
@@ -0,0 +1,1065 @@
+//! Strategy cards and their abilities.
+//!
+//! The player performing a strategic action resolves the primary ability of their card, then
+//! every other player, clockwise from the active player, may follow its secondary ability.
+//! Parts of abilities that involve cards and decks, such as drawing action cards or secret
+//! objectives, resolve once those decks exist.
+
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::board::{Unit, MECATOL_REX};
+use crate::game::hex::Hex;
+use crate::game::objectives::{ObjectiveId, ObjectivePayment};
+use crate::game::payment::{Currency, Payment};
+use crate::game::round::Phase;
+use crate::game::tech::TechId;
+use crate::game::tiles;
+use crate::game::units::UnitKind;
+use crate::game::{CommandError, CommandTokens, GameEvent, GameState, Player};
+use crate::lobby::PlayerId;
+
+/// A strategy card, picked during the strategy phase
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyCard {
+    Leadership,
+    Diplomacy,
+    Politics,
+    Construction,
+    Trade,
+    Warfare,
+    Technology,
+    Imperial,
+}
+
+impl StrategyCard {
+    /// Every strategy card, in initiative order
+    pub const ALL: [StrategyCard; 8] = [
+        StrategyCard::Leadership,
+        StrategyCard::Diplomacy,
+        StrategyCard::Politics,
+        StrategyCard::Construction,
+        StrategyCard::Trade,
+        StrategyCard::Warfare,
+        StrategyCard::Technology,
+        StrategyCard::Imperial,
+    ];
+
+    /// Gets the initiative number printed on the card. Players act in ascending initiative.
+    pub fn initiative(self) -> u8 {
+        self as u8 + 1
+    }
+}
+
+impl Display for StrategyCard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            StrategyCard::Leadership => "Leadership",
+            StrategyCard::Diplomacy => "Diplomacy",
+            StrategyCard::Politics => "Politics",
+            StrategyCard::Construction => "Construction",
+            StrategyCard::Trade => "Trade",
+            StrategyCard::Warfare => "Warfare",
+            StrategyCard::Technology => "Technology",
+            StrategyCard::Imperial => "Imperial",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl StrategyCard {
+    /// Gets how many strategy cards each player picks. With four players or fewer, every
+    /// player picks two.
+    pub fn cards_per_player(players: usize) -> usize {
+        if players <= 4 {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Gets the number of strategy tokens spent to follow the secondary ability of this card
+    pub fn secondary_cost(self) -> u8 {
+        match self {
+            StrategyCard::Leadership => 0,
+            _ => 1,
+        }
+    }
+
+    /// Describes the primary ability, resolved by the player holding the card
+    pub fn primary(self) -> &'static str {
+        match self {
+            StrategyCard::Leadership => "Gain 3 command tokens. Then, spend any amount of influence to gain 1 command token for every 3 influence spent.",
+            StrategyCard::Diplomacy => "Choose 1 system other than the Mecatol Rex system that contains a planet you control; each other player places a command token from their reinforcements in that system. Then, ready up to 2 exhausted planets you control.",
+            StrategyCard::Politics => "Choose a player other than the speaker; that player gains the speaker token. Then, draw 2 action cards and look at the top 2 cards of the agenda deck.",
+            StrategyCard::Construction => "Place 1 PDS or 1 space dock on a planet you control. Then, you may place 1 PDS on a planet you control.",
+            StrategyCard::Trade => "Gain 3 trade goods and replenish your commodities. Then, choose any number of other players; those players use the secondary ability of this card without spending a command token.",
+            StrategyCard::Warfare => "Remove 1 of your command tokens from the game board; then, gain 1 command token. Then, redistribute any number of the command tokens on your command sheet.",
+            StrategyCard::Technology => "Research 1 technology. Then, you may spend 6 resources to research 1 technology.",
+            StrategyCard::Imperial => "Immediately score 1 public objective if you fulfill its requirements. Then, gain 1 victory point if you control Mecatol Rex; otherwise, draw 1 secret objective.",
+        }
+    }
+
+    /// Describes the secondary ability, which other players may follow
+    pub fn secondary(self) -> &'static str {
+        match self {
+            StrategyCard::Leadership => "Spend any amount of influence to gain 1 command token for every 3 influence spent.",
+            StrategyCard::Diplomacy => "Spend 1 token from your strategy pool to ready up to 2 exhausted planets you control.",
+            StrategyCard::Politics => "Spend 1 token from your strategy pool to draw 2 action cards.",
+            StrategyCard::Construction => "Spend 1 token from your strategy pool and place it in any system; you may place 1 space dock or 1 PDS on a planet you control in that system.",
+            StrategyCard::Trade => "Spend 1 token from your strategy pool to replenish your commodities.",
+            StrategyCard::Warfare => "Spend 1 token from your strategy pool to use the production ability of 1 of your space docks in your home system.",
+            StrategyCard::Technology => "Spend 1 token from your strategy pool and 4 resources to research 1 technology.",
+            StrategyCard::Imperial => "Spend 1 token from your strategy pool to draw 1 secret objective.",
+        }
+    }
+}
+
+/// A technology picked with a strategy card, along with the planets exhausted to skip its
+/// prerequisites with their specialties
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TechPick {
+    pub tech: TechId,
+    #[serde(default)]
+    pub specialties: Vec<String>,
+}
+
+/// The choices made when resolving the primary ability of a strategy card
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "card", rename_all = "snake_case")]
+pub enum PrimaryAbility {
+    Leadership {
+        /// Where the gained command tokens go
+        tokens: CommandTokens,
+        /// The influence spent on additional command tokens
+        #[serde(default)]
+        payment: Payment,
+    },
+    Diplomacy {
+        /// A planet in the chosen system
+        planet: String,
+        #[serde(default)]
+        ready: Vec<String>,
+    },
+    Politics {
+        speaker: PlayerId,
+    },
+    Construction {
+        structure: UnitKind,
+        planet: String,
+        /// The planet an additional PDS is placed on
+        #[serde(default)]
+        pds: Option<String>,
+    },
+    Trade {
+        /// The other players who follow the secondary ability for free
+        #[serde(default)]
+        replenish: BTreeSet<PlayerId>,
+    },
+    Warfare {
+        /// The system a command token is removed from
+        system: Hex,
+        /// How the command tokens are redistributed afterwards
+        tokens: CommandTokens,
+    },
+    Technology {
+        research: TechPick,
+        /// The technology researched for 6 resources
+        #[serde(default)]
+        second: Option<TechPick>,
+        #[serde(default)]
+        payment: Payment,
+    },
+    Imperial {
+        /// The public objective scored, if any
+        #[serde(default)]
+        objective: Option<ObjectiveId>,
+        #[serde(default)]
+        payment: ObjectivePayment,
+    },
+}
+
+impl PrimaryAbility {
+    /// Gets the card this ability is printed on
+    pub fn card(&self) -> StrategyCard {
+        match self {
+            PrimaryAbility::Leadership { .. } => StrategyCard::Leadership,
+            PrimaryAbility::Diplomacy { .. } => StrategyCard::Diplomacy,
+            PrimaryAbility::Politics { .. } => StrategyCard::Politics,
+            PrimaryAbility::Construction { .. } => StrategyCard::Construction,
+            PrimaryAbility::Trade { .. } => StrategyCard::Trade,
+            PrimaryAbility::Warfare { .. } => StrategyCard::Warfare,
+            PrimaryAbility::Technology { .. } => StrategyCard::Technology,
+            PrimaryAbility::Imperial { .. } => StrategyCard::Imperial,
+        }
+    }
+}
+
+/// The choices made when following the secondary ability of a strategy card
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "card", rename_all = "snake_case")]
+pub enum SecondaryAbility {
+    Leadership {
+        tokens: CommandTokens,
+        #[serde(default)]
+        payment: Payment,
+    },
+    Diplomacy {
+        #[serde(default)]
+        ready: Vec<String>,
+    },
+    Politics,
+    Construction {
+        structure: UnitKind,
+        planet: String,
+    },
+    Trade,
+    Warfare {
+        #[serde(default)]
+        units: Vec<UnitKind>,
+        #[serde(default)]
+        payment: Payment,
+    },
+    Technology {
+        research: TechPick,
+        #[serde(default)]
+        payment: Payment,
+    },
+    Imperial,
+}
+
+impl SecondaryAbility {
+    /// Gets the card this ability is printed on
+    pub fn card(&self) -> StrategyCard {
+        match self {
+            SecondaryAbility::Leadership { .. } => StrategyCard::Leadership,
+            SecondaryAbility::Diplomacy { .. } => StrategyCard::Diplomacy,
+            SecondaryAbility::Politics => StrategyCard::Politics,
+            SecondaryAbility::Construction { .. } => StrategyCard::Construction,
+            SecondaryAbility::Trade => StrategyCard::Trade,
+            SecondaryAbility::Warfare { .. } => StrategyCard::Warfare,
+            SecondaryAbility::Technology { .. } => StrategyCard::Technology,
+            SecondaryAbility::Imperial => StrategyCard::Imperial,
+        }
+    }
+}
+
+/// The number of planets Diplomacy readies
+const DIPLOMACY_READIES: usize = 2;
+/// The influence spent on each command token with Leadership
+const INFLUENCE_PER_TOKEN: u8 = 3;
+/// The resources spent on the second technology researched with Technology
+const SECOND_TECH_COST: u8 = 6;
+/// The resources spent to follow Technology
+const FOLLOW_TECH_COST: u8 = 4;
+/// The action cards drawn with either ability of Politics
+const POLITICS_ACTION_CARDS: u8 = 2;
+
+impl GameState {
+    /// Resolves the primary ability of a strategy card
+    pub(super) fn resolve_primary(
+        &mut self,
+        player: PlayerId,
+        ability: PrimaryAbility,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        match ability {
+            PrimaryAbility::Leadership { tokens, payment } => {
+                self.buy_command_tokens(player, 3, tokens, &payment)
+            }
+            PrimaryAbility::Diplomacy { planet, ready } => {
+                if planet == MECATOL_REX {
+                    return Err(CommandError::CannotTarget(planet));
+                }
+                self.controlled(player, &planet)?;
+                let system = self.locate(&planet)?;
+                let mut events = vec![];
+                for other in self.seats().into_iter().filter(|other| *other != player) {
+                    if self.board.place_command_token(system, other) {
+                        events.push(GameEvent::CommandTokenPlaced {
+                            player: other,
+                            system,
+                        });
+                    }
+                }
+                events.extend(self.ready_for_diplomacy(player, ready)?);
+                Ok(events)
+            }
+            PrimaryAbility::Politics { speaker } => {
+                if speaker == self.round.speaker || !self.is_player(speaker) {
+                    return Err(CommandError::InvalidSpeaker(speaker));
+                }
+                self.round.speaker = speaker;
+                let mut events = vec![GameEvent::SpeakerChanged { speaker }];
+                events.extend(self.draw_action_cards(player, POLITICS_ACTION_CARDS));
+                Ok(events)
+            }
+            PrimaryAbility::Construction {
+                structure,
+                planet,
+                pds,
+            } => {
+                let mut events = self.place_structure(player, structure, &planet)?;
+                if let Some(planet) = pds {
+                    events.extend(self.place_structure(player, UnitKind::Pds, &planet)?);
+                }
+                Ok(events)
+            }
+            PrimaryAbility::Trade { replenish } => {
+                if let Some(other) = replenish
+                    .iter()
+                    .find(|other| **other == player || !self.is_player(**other))
+                {
+                    return Err(CommandError::NotAPlayer(*other));
+                }
+                let mut events = self.gain_trade_goods(player, 3);
+                events.extend(self.replenish_commodities(player));
+                if let Phase::Action(phase) = &mut self.round.phase {
+                    if let Some(secondaries) = &mut phase.secondaries {
+                        secondaries.free = replenish;
+                    }
+                }
+                Ok(events)
+            }
+            PrimaryAbility::Warfare { system, tokens } => {
+                if !self.board.remove_command_token(system, player) {
+                    return Err(CommandError::NoCommandToken(system));
+                }
+                let expected = self.player_state(player)?.command_tokens.total() + 1;
+                if tokens.total() != expected {
+                    return Err(CommandError::InvalidCommandTokens { expected });
+                }
+                self.player_state_mut(player)?.command_tokens = tokens;
+                Ok(vec![
+                    GameEvent::CommandTokenRemoved { player, system },
+                    GameEvent::CommandTokensRedistributed { player, tokens },
+                ])
+            }
+            PrimaryAbility::Technology {
+                research,
+                second,
+                payment,
+            } => {
+                let mut events = self.research(player, research)?;
+                if let Some(second) = second {
+                    events.extend(self.spend(
+                        player,
+                        SECOND_TECH_COST,
+                        &payment,
+                        Currency::Resources,
+                    )?);
+                    events.extend(self.research(player, second)?);
+                }
+                Ok(events)
+            }
+            PrimaryAbility::Imperial { objective, payment } => {
+                let mut events = vec![];
+                if let Some(objective) = objective {
+                    events.extend(self.score_public_objective(player, objective, payment)?);
+                }
+                if self.player_state(player)?.planets.contains_key(MECATOL_REX) {
+                    events.extend(self.gain_victory_points(player, 1));
+                } else {
+                    events.extend(self.draw_secret_objective(player));
+                }
+                Ok(events)
+            }
+        }
+    }
+
+    /// Resolves the secondary ability of a strategy card
+    pub(super) fn resolve_secondary(
+        &mut self,
+        player: PlayerId,
+        ability: SecondaryAbility,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        match ability {
+            SecondaryAbility::Leadership { tokens, payment } => {
+                self.buy_command_tokens(player, 0, tokens, &payment)
+            }
+            SecondaryAbility::Diplomacy { ready } => self.ready_for_diplomacy(player, ready),
+            SecondaryAbility::Politics => Ok(self.draw_action_cards(player, POLITICS_ACTION_CARDS)),
+            SecondaryAbility::Construction { structure, planet } => {
+                let system = self.locate(&planet)?;
+                let mut events = vec![];
+                if self.board.place_command_token(system, player) {
+                    events.push(GameEvent::CommandTokenPlaced { player, system });
+                }
+                events.extend(self.place_structure(player, structure, &planet)?);
+                Ok(events)
+            }
+            SecondaryAbility::Trade => Ok(self.replenish_commodities(player)),
+            SecondaryAbility::Warfare { units, payment } => {
+                self.produce_at_home(player, units, &payment)
+            }
+            SecondaryAbility::Technology { research, payment } => {
+                let mut events =
+                    self.spend(player, FOLLOW_TECH_COST, &payment, Currency::Resources)?;
+                events.extend(self.research(player, research)?);
+                Ok(events)
+            }
+            SecondaryAbility::Imperial => Ok(self.draw_secret_objective(player)),
+        }
+    }
+
+    pub(super) fn player_state(&self, player: PlayerId) -> Result<&Player, CommandError> {
+        self.player(player).ok_or(CommandError::NotAPlayer(player))
+    }
+
+    pub(super) fn player_state_mut(
+        &mut self,
+        player: PlayerId,
+    ) -> Result<&mut Player, CommandError> {
+        self.player_mut(player)
+            .ok_or(CommandError::NotAPlayer(player))
+    }
+
+    /// Checks that a player controls a planet
+    fn controlled(&self, player: PlayerId, planet: &str) -> Result<(), CommandError> {
+        if self.player_state(player)?.planets.contains_key(planet) {
+            Ok(())
+        } else {
+            Err(CommandError::NotYourPlanet(planet.to_string()))
+        }
+    }
+
+    /// Finds the system a planet is in
+    fn locate(&self, planet: &str) -> Result<Hex, CommandError> {
+        self.board
+            .find_planet(planet)
+            .ok_or_else(|| CommandError::PlanetNotOnBoard(planet.to_string()))
+    }
+
+    pub(super) fn spend(
+        &mut self,
+        player: PlayerId,
+        cost: u8,
+        payment: &Payment,
+        currency: Currency,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        self.player_state_mut(player)?
+            .pay_at_least(cost, payment, currency)?;
+        Ok(payment_events(player, payment))
+    }
+
+    /// Gains command tokens, buying more with influence
+    fn buy_command_tokens(
+        &mut self,
+        player: PlayerId,
+        free: u8,
+        tokens: CommandTokens,
+        payment: &Payment,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let mut state = self.player_state_mut(player)?.clone();
+        let influence = state.pay(payment, Currency::Influence)?;
+        let expected = free + influence / INFLUENCE_PER_TOKEN;
+        if tokens.total() != expected {
+            return Err(CommandError::InvalidCommandTokens { expected });
+        }
+        state.command_tokens.tactic += tokens.tactic;
+        state.command_tokens.fleet += tokens.fleet;
+        state.command_tokens.strategy += tokens.strategy;
+        self.check_command_tokens(&state.command_tokens)?;
+        *self.player_state_mut(player)? = state;
+        let mut events = payment_events(player, payment);
+        events.push(GameEvent::CommandTokensGained { player, tokens });
+        Ok(events)
+    }
+
+    fn ready_for_diplomacy(
+        &mut self,
+        player: PlayerId,
+        planets: Vec<String>,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        if planets.len() > DIPLOMACY_READIES {
+            return Err(CommandError::TooManyPlanets {
+                max: DIPLOMACY_READIES,
+            });
+        }
+        if planets.is_empty() {
+            return Ok(vec![]);
+        }
+        self.player_state_mut(player)?.ready_planets(&planets)?;
+        Ok(vec![GameEvent::PlanetsReadied { player, planets }])
+    }
+
+    fn place_structure(
+        &mut self,
+        player: PlayerId,
+        structure: UnitKind,
+        planet: &str,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        if !structure.is_structure() {
+            return Err(CommandError::NotAStructure(structure));
+        }
+        self.controlled(player, planet)?;
+        let system = self.locate(planet)?;
+        self.board
+            .place_on_planet(system, planet, Unit::new(player, structure));
+        Ok(vec![GameEvent::StructurePlaced {
+            player,
+            unit: structure,
+            planet: planet.to_string(),
+        }])
+    }
+
+    fn research(
+        &mut self,
+        player: PlayerId,
+        pick: TechPick,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let state = self.player_state_mut(player)?;
+        let mut specialties = vec![];
+        for planet in &pick.specialties {
+            let specialty = tiles::catalog()
+                .planet(planet)
+                .and_then(|(_, planet)| planet.specialty)
+                .ok_or_else(|| CommandError::NoSpecialty(planet.clone()))?;
+            specialties.push(specialty);
+        }
+        state.exhaust_planets(&pick.specialties)?;
+        let research = state.research(&pick.tech.0, &specialties)?;
+        let mut events = vec![];
+        if !pick.specialties.is_empty() {
+            events.push(GameEvent::PlanetsExhausted {
+                player,
+                planets: pick.specialties,
+            });
+        }
+        events.push(GameEvent::TechnologyResearched {
+            player,
+            tech: research.tech,
+        });
+        Ok(events)
+    }
+
+    pub(super) fn gain_trade_goods(&mut self, player: PlayerId, amount: u8) -> Vec<GameEvent> {
+        match self.player_mut(player) {
+            Some(state) if amount > 0 => {
+                state.trade_goods += amount;
+                vec![GameEvent::TradeGoodsGained { player, amount }]
+            }
+            _ => vec![],
+        }
+    }
+
+    pub(super) fn replenish_commodities(&mut self, player: PlayerId) -> Vec<GameEvent> {
+        let Some(state) = self.player_mut(player) else {
+            return vec![];
+        };
+        state.commodities = state.commodity_limit();
+        let mut events = vec![GameEvent::CommoditiesReplenished {
+            player,
+            commodities: state.commodities,
+        }];
+        events.extend(self.commodities_replenished(player));
+        events
+    }
+
+    pub(super) fn gain_victory_points(&mut self, player: PlayerId, points: u8) -> Vec<GameEvent> {
+        let Some(state) = self.player_mut(player) else {
+            return vec![];
+        };
+        state.victory_points += points;
+        vec![GameEvent::VictoryPointsGained {
+            player,
+            points,
+            total: state.victory_points,
+        }]
+    }
+}
+
+fn payment_events(player: PlayerId, payment: &Payment) -> Vec<GameEvent> {
+    let mut events = vec![];
+    if !payment.planets.is_empty() {
+        events.push(GameEvent::PlanetsExhausted {
+            player,
+            planets: payment.planets.clone(),
+        });
+    }
+    if payment.trade_goods > 0 {
+        events.push(GameEvent::TradeGoodsSpent {
+            player,
+            amount: payment.trade_goods,
+        });
+    }
+    events
+}
+
+/// Picks choices for the primary ability of a card that the player can always resolve in a
+/// [test game](crate::game::test_game)
+#[cfg(test)]
+pub(crate) fn any_primary(
+    state: &mut GameState,
+    player: PlayerId,
+    card: StrategyCard,
+) -> PrimaryAbility {
+    let planet = state.player(player).unwrap().planets.keys().next().cloned();
+    let planet = planet.expect("player controls a planet");
+    match card {
+        StrategyCard::Leadership => PrimaryAbility::Leadership {
+            tokens: CommandTokens {
+                tactic: 3,
+                fleet: 0,
+                strategy: 0,
+            },
+            payment: Payment::default(),
+        },
+        StrategyCard::Diplomacy => PrimaryAbility::Diplomacy {
+            planet,
+            ready: vec![],
+        },
+        StrategyCard::Politics => PrimaryAbility::Politics {
+            speaker: state
+                .seats()
+                .into_iter()
+                .find(|seat| *seat != state.round.speaker)
+                .unwrap(),
+        },
+        StrategyCard::Construction => PrimaryAbility::Construction {
+            structure: UnitKind::Pds,
+            planet,
+            pds: None,
+        },
+        StrategyCard::Trade => PrimaryAbility::Trade {
+            replenish: BTreeSet::new(),
+        },
+        StrategyCard::Warfare => {
+            let system = state.board.find_planet(&planet).unwrap();
+            state.board.place_command_token(system, player);
+            let mut tokens = state.player(player).unwrap().command_tokens;
+            tokens.tactic += 1;
+            PrimaryAbility::Warfare { system, tokens }
+        }
+        StrategyCard::Technology => {
+            let owned = &state.player(player).unwrap().technologies;
+            let tech = crate::game::tech::catalog()
+                .techs()
+                .find(|tech| {
+                    tech.prerequisites.is_empty()
+                        && tech.faction.is_none()
+                        && !owned.contains(&tech.id)
+                })
+                .unwrap();
+            PrimaryAbility::Technology {
+                research: TechPick {
+                    tech: tech.id.clone(),
+                    specialties: vec![],
+                },
+                second: None,
+                payment: Payment::default(),
+            }
+        }
+        StrategyCard::Imperial => PrimaryAbility::Imperial {
+            objective: None,
+            payment: ObjectivePayment::default(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::MECATOL_REX;
+    use crate::game::payment::Payment;
+    use crate::game::round::DecisionKind;
+    use crate::game::strategy::{
+        any_primary, PrimaryAbility, SecondaryAbility, StrategyCard, TechPick,
+    };
+    use crate::game::tech::TechId;
+    use crate::game::PlanetCard;
+    use crate::game::{test_game, CommandError, CommandTokens, GameCommand, GameEvent, GameState};
+    use crate::lobby::PlayerId;
+
+    fn pick_all(state: &mut GameState, cards: &[StrategyCard]) -> Vec<GameEvent> {
+        let mut events = vec![];
+        for &card in cards {
+            let player = state.pending()[0].player;
+            events = state
+                .apply(player, GameCommand::PickStrategyCard { card })
+                .unwrap();
+        }
+        events
+    }
+
+    fn follow(
+        state: &mut GameState,
+        player: PlayerId,
+        ability: SecondaryAbility,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        state.apply(player, GameCommand::FollowSecondary { ability })
+    }
+
+    fn tech(id: &str, payment: Payment) -> SecondaryAbility {
+        SecondaryAbility::Technology {
+            research: TechPick {
+                tech: TechId(id.to_string()),
+                specialties: vec![],
+            },
+            payment,
+        }
+    }
+
+    #[test]
+    fn players_pick_two_cards_with_four_players_or_fewer() {
+        for (players, picks) in [(3, 6), (4, 8), (5, 5), (6, 6), (8, 8)] {
+            let (state, _) = test_game(players);
+            let crate::game::round::Phase::Strategy(phase) = &state.round.phase else {
+                panic!("game should start with the strategy phase");
+            };
+            assert_eq!(phase.picking.len(), picks);
+        }
+        let (mut state, players) = test_game(4);
+        let mut order = vec![];
+        for card in StrategyCard::ALL {
+            let player = state.pending()[0].player;
+            order.push(player);
+            state
+                .apply(player, GameCommand::PickStrategyCard { card })
+                .unwrap();
+        }
+        assert_eq!(order, [&players[..], &players[..]].concat());
+    }
+
+    #[test]
+    fn unpicked_cards_collect_trade_goods() {
+        let (mut state, players) = test_game(5);
+        state
+            .strategy_card_trade_goods
+            .insert(StrategyCard::Politics, 2);
+        let events = pick_all(&mut state, &[StrategyCard::Politics]);
+        assert_eq!(
+            events[1],
+            GameEvent::TradeGoodsGained {
+                player: players[0],
+                amount: 2
+            }
+        );
+        assert_eq!(state.player(players[0]).unwrap().trade_goods, 2);
+
+        let events = pick_all(
+            &mut state,
+            &[
+                StrategyCard::Leadership,
+                StrategyCard::Diplomacy,
+                StrategyCard::Construction,
+                StrategyCard::Trade,
+            ],
+        );
+        assert_eq!(
+            events[1],
+            GameEvent::TradeGoodsPlaced {
+                cards: vec![
+                    StrategyCard::Warfare,
+                    StrategyCard::Technology,
+                    StrategyCard::Imperial
+                ]
+            }
+        );
+        assert_eq!(
+            state.strategy_card_trade_goods,
+            [
+                (StrategyCard::Warfare, 1),
+                (StrategyCard::Technology, 1),
+                (StrategyCard::Imperial, 1)
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn secondaries_are_followed_clockwise_for_a_strategy_token() {
+        let (mut state, players) = test_game(3);
+        pick_all(
+            &mut state,
+            &[
+                StrategyCard::Leadership,
+                StrategyCard::Warfare,
+                StrategyCard::Technology,
+                StrategyCard::Trade,
+                StrategyCard::Imperial,
+                StrategyCard::Construction,
+            ],
+        );
+        state.player_mut(players[1]).unwrap().faction = Some("letnev".into());
+        state
+            .player_mut(players[2])
+            .unwrap()
+            .command_tokens
+            .strategy = 0;
+
+        // Trade lets the third player follow for free
+        let ability = PrimaryAbility::Trade {
+            replenish: [players[2]].into(),
+        };
+        state
+            .apply(players[0], GameCommand::StrategicAction { ability })
+            .unwrap();
+        assert_eq!(state.player(players[0]).unwrap().trade_goods, 3);
+        assert_eq!(
+            state.apply(players[0], GameCommand::Pass),
+            Err(CommandError::NotYourTurn)
+        );
+        assert_eq!(state.pending()[0].player, players[1]);
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::FollowSecondary {
+                card: StrategyCard::Trade,
+                cost: 1
+            }
+        );
+        follow(&mut state, players[1], SecondaryAbility::Trade).unwrap();
+        let follower = state.player(players[1]).unwrap();
+        assert_eq!(follower.command_tokens.strategy, 1);
+        assert_eq!(follower.commodities, 2);
+
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::FollowSecondary {
+                card: StrategyCard::Trade,
+                cost: 0
+            }
+        );
+        assert_eq!(
+            follow(&mut state, players[2], SecondaryAbility::Imperial),
+            Err(CommandError::WrongCard)
+        );
+        follow(&mut state, players[2], SecondaryAbility::Trade).unwrap();
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::TakeAction { can_pass: false }
+        );
+
+        // following Technology costs a strategy token and 4 resources
+        assert_eq!(state.pending()[0].player, players[2]);
+        let ability = any_primary(&mut state, players[2], StrategyCard::Technology);
+        state
+            .apply(players[2], GameCommand::StrategicAction { ability })
+            .unwrap();
+        state
+            .player_mut(players[0])
+            .unwrap()
+            .command_tokens
+            .strategy = 0;
+        assert_eq!(
+            follow(
+                &mut state,
+                players[0],
+                tech("sarween_tools", Payment::default())
+            ),
+            Err(CommandError::NoStrategyToken)
+        );
+        state
+            .apply(players[0], GameCommand::DeclineSecondary)
+            .unwrap();
+
+        let vefut = Payment {
+            planets: vec!["Vefut II".to_string()],
+            trade_goods: 0,
+        };
+        let before = state.clone();
+        assert_eq!(
+            follow(&mut state, players[1], tech("sarween_tools", vefut.clone())),
+            Err(CommandError::CannotAfford { cost: 4, paid: 2 })
+        );
+        assert_eq!(state, before);
+        state.player_mut(players[1]).unwrap().trade_goods = 2;
+        follow(
+            &mut state,
+            players[1],
+            tech(
+                "sarween_tools",
+                Payment {
+                    trade_goods: 2,
+                    ..vefut
+                },
+            ),
+        )
+        .unwrap();
+        let follower = state.player(players[1]).unwrap();
+        assert!(follower.technologies.contains("sarween_tools"));
+        assert!(follower.planets["Vefut II"].exhausted);
+        assert_eq!(follower.trade_goods, 0);
+        assert_eq!(follower.command_tokens.strategy, 0);
+        assert_eq!(state.pending()[0].player, players[1]);
+    }
+
+    #[test]
+    fn leadership_buys_command_tokens_with_influence() {
+        let (mut state, players) = test_game(5);
+        pick_all(
+            &mut state,
+            &[
+                StrategyCard::Leadership,
+                StrategyCard::Diplomacy,
+                StrategyCard::Politics,
+                StrategyCard::Construction,
+                StrategyCard::Trade,
+            ],
+        );
+        state.player_mut(players[0]).unwrap().trade_goods = 1;
+        let payment = Payment {
+            planets: vec!["Wellon".to_string()],
+            trade_goods: 1,
+        };
+        let tokens = CommandTokens {
+            tactic: 2,
+            fleet: 1,
+            strategy: 0,
+        };
+        let before = state.clone();
+        assert_eq!(
+            state.apply(
+                players[0],
+                GameCommand::StrategicAction {
+                    ability: PrimaryAbility::Leadership {
+                        tokens,
+                        payment: payment.clone()
+                    }
+                }
+            ),
+            Err(CommandError::InvalidCommandTokens { expected: 4 })
+        );
+        assert_eq!(state, before);
+
+        let tokens = CommandTokens {
+            strategy: 1,
+            ..tokens
+        };
+        state
+            .apply(
+                players[0],
+                GameCommand::StrategicAction {
+                    ability: PrimaryAbility::Leadership { tokens, payment },
+                },
+            )
+            .unwrap();
+        let leader = state.player(players[0]).unwrap();
+        assert_eq!(
+            leader.command_tokens,
+            CommandTokens {
+                tactic: 5,
+                fleet: 4,
+                strategy: 3
+            }
+        );
+        assert!(leader.planets["Wellon"].exhausted);
+
+        // following Leadership is free, but gains nothing without spending influence
+        assert_eq!(
+            state.pending()[0].kind,
+            DecisionKind::FollowSecondary {
+                card: StrategyCard::Leadership,
+                cost: 0
+            }
+        );
+        follow(
+            &mut state,
+            players[1],
+            SecondaryAbility::Leadership {
+                tokens: CommandTokens {
+                    tactic: 0,
+                    fleet: 0,
+                    strategy: 0,
+                },
+                payment: Payment::default(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            state.player(players[1]).unwrap().command_tokens,
+            CommandTokens::default()
+        );
+    }
+
+    #[test]
+    fn technology_and_imperial_primaries() {
+        let (mut state, players) = test_game(3);
+        pick_all(
+            &mut state,
+            &[
+                StrategyCard::Technology,
+                StrategyCard::Leadership,
+                StrategyCard::Diplomacy,
+                StrategyCard::Trade,
+                StrategyCard::Imperial,
+                StrategyCard::Warfare,
+            ],
+        );
+        // the second player acts first with Leadership, and nobody follows
+        let ability = any_primary(&mut state, players[1], StrategyCard::Leadership);
+        state
+            .apply(players[1], GameCommand::StrategicAction { ability })
+            .unwrap();
+        for player in [players[2], players[0]] {
+            state.apply(player, GameCommand::DeclineSecondary).unwrap();
+        }
+
+        // the third player uses Diplomacy on their own system
+        let ability = any_primary(&mut state, players[2], StrategyCard::Diplomacy);
+        state
+            .apply(players[2], GameCommand::StrategicAction { ability })
+            .unwrap();
+        let system = state.board.find_planet("Thibah").unwrap();
+        assert_eq!(
+            state.board.system(system).command_tokens,
+            [players[0], players[1]].into()
+        );
+        for player in [players[0], players[1]] {
+            state.apply(player, GameCommand::DeclineSecondary).unwrap();
+        }
+
+        // a second technology costs 6 resources
+        state.player_mut(players[0]).unwrap().trade_goods = 5;
+        let research = |tech: &str| TechPick {
+            tech: TechId(tech.to_string()),
+            specialties: vec![],
+        };
+        state
+            .apply(
+                players[0],
+                GameCommand::StrategicAction {
+                    ability: PrimaryAbility::Technology {
+                        research: research("neural_motivator"),
+                        second: Some(research("sarween_tools")),
+                        payment: Payment {
+                            planets: vec!["Wellon".to_string()],
+                            trade_goods: 5,
+                        },
+                    },
+                },
+            )
+            .unwrap();
+        let researcher = state.player(players[0]).unwrap();
+        assert!(researcher.technologies.contains("neural_motivator"));
+        assert!(researcher.technologies.contains("sarween_tools"));
+        for player in [players[1], players[2]] {
+            state.apply(player, GameCommand::DeclineSecondary).unwrap();
+        }
+
+        // Imperial scores a point for controlling Mecatol Rex
+        state
+            .player_mut(players[1])
+            .unwrap()
+            .planets
+            .insert(MECATOL_REX.to_string(), PlanetCard::default());
+        assert_eq!(state.pending()[0].player, players[1]);
+        let events = state
+            .apply(
+                players[1],
+                GameCommand::StrategicAction {
+                    ability: PrimaryAbility::Imperial {
+                        objective: None,
+                        payment: Default::default(),
+                    },
+                },
+            )
+            .unwrap();
+        assert!(events.contains(&GameEvent::VictoryPointsGained {
+            player: players[1],
+            points: 1,
+            total: 1
+        }));
+    }
+}
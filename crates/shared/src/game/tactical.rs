@@ -0,0 +1,171 @@
+//! The tactical action, where the active player activates a system with a tactic token and
+//! moves units into it, fighting any [combat](crate::game::combat) there and then
+//! [invading](crate::game::invasion) its planets. Other players may play
+//! [action cards](crate::game::action_cards) once the system is activated, before any unit
+//! moves.
+
+use crate::game::dice::Dice;
+use crate::game::hex::Hex;
+use crate::game::movement::Movement;
+use crate::game::{CommandError, GameEvent, GameState};
+use crate::lobby::PlayerId;
+
+impl GameState {
+    pub(super) fn tactical_action(
+        &mut self,
+        player: PlayerId,
+        system: Hex,
+        movement: Movement,
+        dice: &mut dyn Dice,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        self.check_turn(player)?;
+        let Some(state) = self.player(player) else {
+            return Err(CommandError::NotAPlayer(player));
+        };
+        if state.command_tokens.tactic == 0 {
+            return Err(CommandError::NoTacticToken);
+        }
+        self.board.check_movement(state, system, &movement)?;
+
+        if let Some(state) = self.player_mut(player) {
+            state.command_tokens.tactic -= 1;
+        }
+        self.board.place_command_token(system, player);
+        let mut events = vec![GameEvent::SystemActivated { player, system }];
+        events.extend(self.return_notes_on_activation(player, system));
+        if !self.after_system_activated(player, system, &movement) {
+            events.extend(self.move_into(player, system, &movement, dice));
+        }
+        Ok(events)
+    }
+
+    /// Moves the active player's units into the system they activated, then fights and
+    /// invades there
+    pub(super) fn move_into(
+        &mut self,
+        player: PlayerId,
+        system: Hex,
+        movement: &Movement,
+        dice: &mut dyn Dice,
+    ) -> Vec<GameEvent> {
+        let Some(state) = self.player(player).cloned() else {
+            return vec![];
+        };
+        let mut events = vec![];
+        let moved = self.board.move_units(&state, system, movement, dice);
+        events.extend(
+            moved
+                .rift_rolls
+                .into_iter()
+                .map(|roll| GameEvent::GravityRiftRolled { player, roll }),
+        );
+        if !moved.lost.is_empty() {
+            events.push(GameEvent::UnitsDestroyed {
+                player,
+                system,
+                units: moved.lost,
+            });
+        }
+        if !moved.arrived.is_empty() {
+            events.push(GameEvent::UnitsMoved {
+                player,
+                system,
+                units: moved.arrived,
+            });
+        }
+        let (waiting, more) = self.start_combat(player, system, dice);
+        events.extend(more);
+        if !waiting {
+            events.extend(self.after_combat(player, system, dice));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::{Unit, MECATOL_REX};
+    use crate::game::dice::LoadedDice;
+    use crate::game::movement::{MoveError, Movement, ShipMove};
+    use crate::game::units::UnitKind;
+    use crate::game::{test_action_phase, CommandError, GameCommand, GameEvent};
+
+    #[test]
+    fn tactical_action_activates_a_system_and_moves_ships() {
+        let (mut state, players) = test_action_phase(5);
+        let home = state.board.find_planet("Wellon").unwrap();
+        let mecatol = state.board.find_planet(MECATOL_REX).unwrap();
+        state
+            .board
+            .place_in_space(home, Unit::new(players[0], UnitKind::Cruiser));
+
+        let command = GameCommand::TacticalAction {
+            system: mecatol,
+            movement: Movement {
+                ships: vec![ShipMove {
+                    unit: UnitKind::Cruiser,
+                    from: home,
+                    through: vec![],
+                }],
+                transported: vec![],
+            },
+        };
+        let events = state
+            .apply_with(players[0], command.clone(), &mut LoadedDice::default())
+            .unwrap();
+        assert_eq!(
+            events[..2],
+            [
+                GameEvent::SystemActivated {
+                    player: players[0],
+                    system: mecatol
+                },
+                GameEvent::UnitsMoved {
+                    player: players[0],
+                    system: mecatol,
+                    units: vec![UnitKind::Cruiser]
+                }
+            ]
+        );
+        let player = state.player(players[0]).unwrap();
+        assert_eq!(player.command_tokens.tactic, 2);
+        assert!(state
+            .board
+            .system(mecatol)
+            .command_tokens
+            .contains(&players[0]));
+        assert_eq!(state.board.system(mecatol).ships_of(players[0]).count(), 1);
+        assert_eq!(state.pending()[0].player, players[1]);
+
+        // every other player activates their own system, without moving anything
+        for player in &players[1..] {
+            let planet = state
+                .player(*player)
+                .unwrap()
+                .planets
+                .keys()
+                .next()
+                .unwrap();
+            let system = state.board.find_planet(planet).unwrap();
+            state
+                .apply(
+                    *player,
+                    GameCommand::TacticalAction {
+                        system,
+                        movement: Movement::default(),
+                    },
+                )
+                .unwrap();
+        }
+
+        // the system can't be activated again
+        let before = state.clone();
+        assert_eq!(
+            state.apply(players[0], command),
+            Err(CommandError::Movement(MoveError::AlreadyActivated {
+                system: mecatol
+            }))
+        );
+        assert_eq!(state, before);
+    }
+}
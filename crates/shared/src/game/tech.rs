@@ -0,0 +1,453 @@
+//! Technologies and the rules for researching them.
+//!
+//! Every technology is read from `data/techs.json`, which is embedded into the crate. A
+//! technology's prerequisites are colors: researching it needs as many owned technologies of
+//! each color. Unit upgrades have no color, so they never count towards prerequisites, and a
+//! faction that has its own upgrade of a unit can't research the generic one it replaces.
+//! Exhausting a planet with a technology specialty skips one prerequisite of its color.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::effects::EffectId;
+use crate::game::factions::FactionId;
+use crate::game::tiles::Expansion;
+use crate::game::units::UnitKind;
+
+const TECHS: &str = include_str!("../../data/techs.json");
+
+/// Gets the catalog of every technology
+pub fn catalog() -> &'static TechCatalog {
+    static CATALOG: OnceLock<TechCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let techs: Vec<Technology> =
+            serde_json::from_str(TECHS).expect("embedded technology catalog is valid");
+        TechCatalog {
+            order: techs.iter().map(|tech| tech.id.clone()).collect(),
+            techs: techs
+                .into_iter()
+                .map(|tech| (tech.id.clone(), tech))
+                .collect(),
+        }
+    })
+}
+
+/// Identifies a technology, such as `gravity_drive`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TechId(pub String);
+
+impl Borrow<str> for TechId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for TechId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Display for TechId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The color of a technology, which planets with a matching specialty help research
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TechColor {
+    /// Green
+    Biotic,
+    /// Blue
+    Propulsion,
+    /// Yellow
+    Cybernetic,
+    /// Red
+    Warfare,
+}
+
+impl Display for TechColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TechColor::Biotic => "biotic",
+            TechColor::Propulsion => "propulsion",
+            TechColor::Cybernetic => "cybernetic",
+            TechColor::Warfare => "warfare",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A technology, as printed on its card
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Technology {
+    pub id: TechId,
+    pub name: String,
+    /// Unit upgrades and a few faction technologies have no color
+    #[serde(default)]
+    pub color: Option<TechColor>,
+    /// The unit this technology upgrades, if it is a unit upgrade
+    #[serde(default)]
+    pub unit: Option<UnitKind>,
+    /// The colors of the technologies needed to research this one, one entry per technology
+    pub prerequisites: Vec<TechColor>,
+    /// The faction this technology belongs to, if only one faction can research it
+    #[serde(default)]
+    pub faction: Option<FactionId>,
+    pub expansion: Expansion,
+    /// What the technology does, unless it is hand coded or not implemented yet
+    #[serde(default)]
+    pub effect: Option<EffectId>,
+}
+
+impl Technology {
+    /// Checks whether this technology is a unit upgrade
+    pub fn is_unit_upgrade(&self) -> bool {
+        self.unit.is_some()
+    }
+}
+
+/// A technology can't be researched
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum ResearchError {
+    #[error("{tech} is not a technology")]
+    Unknown { tech: TechId },
+    #[error("{tech} is already researched")]
+    AlreadyOwned { tech: TechId },
+    #[error("{tech} belongs to another faction")]
+    OtherFaction { tech: TechId },
+    #[error("{tech} is replaced by your faction's {by}")]
+    Replaced { tech: TechId, by: TechId },
+    #[error("{tech} is missing prerequisites: {}", list(missing))]
+    MissingPrerequisites {
+        tech: TechId,
+        missing: Vec<TechColor>,
+    },
+}
+
+fn list(colors: &[TechColor]) -> String {
+    colors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A technology that can be researched
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Research {
+    pub tech: TechId,
+    /// The specialties that must be exhausted to skip missing prerequisites
+    pub skips: Vec<TechColor>,
+}
+
+/// Where a technology stands for a player, for drawing the technology tree
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TechStatus {
+    Researched,
+    /// The technology can be researched, after exhausting planets with these specialties
+    Available {
+        skips: Vec<TechColor>,
+    },
+    /// The technology can't be researched until these prerequisites are met
+    Locked {
+        missing: Vec<TechColor>,
+    },
+}
+
+/// A technology in a player's technology tree
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TechNode {
+    pub tech: TechId,
+    #[serde(flatten)]
+    pub status: TechStatus,
+}
+
+/// Every technology, by id
+#[derive(Debug)]
+pub struct TechCatalog {
+    techs: BTreeMap<TechId, Technology>,
+    /// Ids in the order they are listed in, which groups them by color
+    order: Vec<TechId>,
+}
+
+impl TechCatalog {
+    /// Gets a technology by its id
+    pub fn get(&self, id: &str) -> Option<&Technology> {
+        self.techs.get(id)
+    }
+
+    /// Gets every technology, grouped by color with unit upgrades and faction technologies last
+    pub fn techs(&self) -> impl Iterator<Item = &Technology> {
+        self.order.iter().map(|id| &self.techs[id])
+    }
+
+    /// Gets every technology a faction may own, leaving out other factions' technologies and
+    /// the unit upgrades the faction's own replace
+    pub fn available_to<'a>(
+        &'a self,
+        faction: Option<&'a FactionId>,
+    ) -> impl Iterator<Item = &'a Technology> {
+        self.techs().filter(move |tech| {
+            (tech.faction.is_none() || tech.faction.as_ref() == faction)
+                && self.replacement(tech, faction).is_none()
+        })
+    }
+
+    /// Gets the faction unit upgrade that replaces a generic one for a faction, if it has one
+    pub fn replacement(
+        &self,
+        tech: &Technology,
+        faction: Option<&FactionId>,
+    ) -> Option<&Technology> {
+        if tech.faction.is_some() || !tech.is_unit_upgrade() {
+            return None;
+        }
+        let faction = faction?;
+        self.techs()
+            .find(|other| other.faction.as_ref() == Some(faction) && other.unit == tech.unit)
+    }
+
+    /// Gets the prerequisites of a technology that aren't met by the owned technologies, one
+    /// entry per missing technology
+    pub fn missing_prerequisites(
+        &self,
+        tech: &Technology,
+        owned: &BTreeSet<TechId>,
+    ) -> Vec<TechColor> {
+        let mut have = BTreeMap::<TechColor, usize>::new();
+        for color in owned
+            .iter()
+            .filter_map(|id| self.get(&id.0))
+            .filter_map(|tech| tech.color)
+        {
+            *have.entry(color).or_default() += 1;
+        }
+        let mut missing = vec![];
+        let mut needed = tech.prerequisites.clone();
+        needed.sort();
+        for color in needed {
+            let count = have.entry(color).or_default();
+            match count.checked_sub(1) {
+                Some(left) => *count = left,
+                None => missing.push(color),
+            }
+        }
+        missing
+    }
+
+    /// Checks whether a technology can be researched right now.
+    ///
+    /// `specialties` are the specialties of the ready planets the player controls. Each of
+    /// them skips one missing prerequisite of its color, and the ones needed are returned.
+    pub fn check(
+        &self,
+        tech: &str,
+        faction: Option<&FactionId>,
+        owned: &BTreeSet<TechId>,
+        specialties: &[TechColor],
+    ) -> Result<Research, ResearchError> {
+        let Some(technology) = self.get(tech) else {
+            return Err(ResearchError::Unknown { tech: tech.into() });
+        };
+        let id = technology.id.clone();
+        if owned.contains(&id) {
+            return Err(ResearchError::AlreadyOwned { tech: id });
+        }
+        if technology
+            .faction
+            .as_ref()
+            .is_some_and(|owner| Some(owner) != faction)
+        {
+            return Err(ResearchError::OtherFaction { tech: id });
+        }
+        if let Some(replacement) = self.replacement(technology, faction) {
+            return Err(ResearchError::Replaced {
+                tech: id,
+                by: replacement.id.clone(),
+            });
+        }
+        let mut specialties = specialties.to_vec();
+        let mut skips = vec![];
+        let mut missing = vec![];
+        for color in self.missing_prerequisites(technology, owned) {
+            match specialties.iter().position(|specialty| *specialty == color) {
+                Some(index) => skips.push(specialties.swap_remove(index)),
+                None => missing.push(color),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(ResearchError::MissingPrerequisites { tech: id, missing });
+        }
+        Ok(Research { tech: id, skips })
+    }
+
+    /// Gets the technology tree of a player, showing what they researched and what they can
+    /// research right now
+    pub fn tree(
+        &self,
+        faction: Option<&FactionId>,
+        owned: &BTreeSet<TechId>,
+        specialties: &[TechColor],
+    ) -> Vec<TechNode> {
+        self.available_to(faction)
+            .map(|tech| {
+                let status = match self.check(&tech.id.0, faction, owned, specialties) {
+                    Ok(research) => TechStatus::Available {
+                        skips: research.skips,
+                    },
+                    Err(ResearchError::AlreadyOwned { .. }) => TechStatus::Researched,
+                    Err(_) => TechStatus::Locked {
+                        missing: self.missing_prerequisites(tech, owned),
+                    },
+                };
+                TechNode {
+                    tech: tech.id.clone(),
+                    status,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::game::factions::{self, FactionId};
+    use crate::game::tech::{catalog, ResearchError, TechColor, TechId, TechStatus};
+    use crate::game::units;
+
+    fn techs(names: &[&str]) -> BTreeSet<TechId> {
+        names.iter().map(|name| TechId::from(*name)).collect()
+    }
+
+    #[test]
+    fn catalog_matches_factions_and_units() {
+        for faction in factions::catalog().factions() {
+            for tech in &faction.faction_techs {
+                let tech = catalog().get(&tech.0).unwrap();
+                assert_eq!(tech.faction.as_ref(), Some(&faction.id));
+            }
+            for tech in &faction.starting_techs {
+                assert!(catalog().get(&tech.0).is_some(), "{tech}");
+            }
+        }
+        for tech in catalog().techs() {
+            let upgrade = units::catalog().upgrade(&tech.id.0);
+            assert_eq!(upgrade.map(|unit| unit.unit), tech.unit, "{}", tech.id);
+            assert!(tech.color.is_none() || !tech.is_unit_upgrade());
+        }
+        assert_eq!(catalog().available_to(None).count(), 33);
+    }
+
+    #[test]
+    fn prerequisites_are_counted_by_color() {
+        let owned = techs(&["neural_motivator", "antimass_deflectors", "carrier_ii"]);
+        let hyper_metabolism = catalog().get("hyper_metabolism").unwrap();
+        assert_eq!(
+            catalog().missing_prerequisites(hyper_metabolism, &owned),
+            [TechColor::Biotic]
+        );
+        let cruiser = catalog().get("cruiser_ii").unwrap();
+        assert_eq!(
+            catalog().missing_prerequisites(cruiser, &owned),
+            [TechColor::Cybernetic, TechColor::Warfare]
+        );
+        assert_eq!(
+            catalog()
+                .check("gravity_drive", None, &owned, &[])
+                .unwrap()
+                .skips,
+            []
+        );
+        assert_eq!(
+            catalog().check("cruiser_ii", None, &owned, &[TechColor::Warfare]),
+            Err(ResearchError::MissingPrerequisites {
+                tech: "cruiser_ii".into(),
+                missing: vec![TechColor::Cybernetic],
+            })
+        );
+    }
+
+    #[test]
+    fn specialties_skip_prerequisites() {
+        let owned = techs(&["sarween_tools"]);
+        let research = catalog()
+            .check(
+                "cruiser_ii",
+                None,
+                &owned,
+                &[TechColor::Warfare, TechColor::Biotic, TechColor::Biotic],
+            )
+            .unwrap();
+        assert_eq!(research.skips, [TechColor::Biotic, TechColor::Warfare]);
+    }
+
+    #[test]
+    fn faction_technologies_are_restricted() {
+        let sol = FactionId::from("sol");
+        let owned = techs(&["neural_motivator", "dacxive_animators"]);
+        assert!(catalog()
+            .check("spec_ops_ii", Some(&sol), &owned, &[])
+            .is_ok());
+        assert_eq!(
+            catalog().check("letani_warrior_ii", Some(&sol), &owned, &[]),
+            Err(ResearchError::OtherFaction {
+                tech: "letani_warrior_ii".into()
+            })
+        );
+        assert_eq!(
+            catalog().check("carrier_ii", Some(&sol), &owned, &[]),
+            Err(ResearchError::Replaced {
+                tech: "carrier_ii".into(),
+                by: "advanced_carrier_ii".into()
+            })
+        );
+        let propulsion = techs(&["antimass_deflectors", "gravity_drive"]);
+        assert!(catalog()
+            .check("carrier_ii", None, &propulsion, &[])
+            .is_ok());
+        assert_eq!(
+            catalog().check("neural_motivator", Some(&sol), &owned, &[]),
+            Err(ResearchError::AlreadyOwned {
+                tech: "neural_motivator".into()
+            })
+        );
+        assert!(matches!(
+            catalog().check("warp_drive", None, &owned, &[]),
+            Err(ResearchError::Unknown { .. })
+        ));
+    }
+
+    #[test]
+    fn tree_shows_researched_and_available_technologies() {
+        let sol = FactionId::from("sol");
+        let tree = catalog().tree(Some(&sol), &techs(&["neural_motivator"]), &[]);
+        // Advanced Carrier II and Spec Ops II replace Carrier II and Infantry II
+        assert_eq!(tree.len(), 33);
+        assert!(!tree.iter().any(|node| node.tech.0 == "carrier_ii"));
+        let status = |id: &str| &tree.iter().find(|node| node.tech.0 == id).unwrap().status;
+        assert_eq!(status("neural_motivator"), &TechStatus::Researched);
+        assert_eq!(
+            status("dacxive_animators"),
+            &TechStatus::Available { skips: vec![] }
+        );
+        assert_eq!(
+            status("spec_ops_ii"),
+            &TechStatus::Locked {
+                missing: vec![TechColor::Biotic]
+            }
+        );
+    }
+}
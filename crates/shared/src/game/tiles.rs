@@ -0,0 +1,304 @@
+//! The catalog of every system tile in the base game and Prophecy of Kings.
+//!
+//! The catalog is read from `data/tiles.json`, which is embedded into the crate. Hyperlanes
+//! are listed along with the systems, once for each side of their tiles (83A to 91B), even
+//! though they aren't systems themselves. The Wormhole Nexus (tile 82) is listed with its
+//! inactive side up, showing only its gamma wormhole.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::galaxy::{MapTile, TileId, Wormhole};
+use crate::game::hex::Direction;
+use crate::game::tech::TechColor;
+
+const TILES: &str = include_str!("../../data/tiles.json");
+
+/// Gets the catalog of every system tile
+pub fn catalog() -> &'static TileCatalog {
+    static CATALOG: OnceLock<TileCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let tiles: Vec<SystemTile> =
+            serde_json::from_str(TILES).expect("embedded tile catalog is valid");
+        TileCatalog {
+            tiles: tiles
+                .into_iter()
+                .map(|tile| (tile.id.clone(), tile))
+                .collect(),
+        }
+    })
+}
+
+/// The product a tile comes from
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expansion {
+    Base,
+    ProphecyOfKings,
+}
+
+/// The role of a tile when building the map, as shown by the color of its back
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TileKind {
+    /// A home system, with a green back
+    Home,
+    /// A system with planets, placed by players while building the map
+    Blue,
+    /// An empty system or anomaly, placed by players while building the map
+    Red,
+    /// A tile that is never placed while building the map, such as Mecatol Rex
+    Special,
+    /// The Creuss Gate, placed at the edge of the map by the Creuss player instead of their
+    /// home system, which stays off the map
+    CreussGate,
+    /// A hyperlane, placed where a seat is missing from the map
+    Hyperlane,
+}
+
+/// A trait of a planet, which some cards and abilities refer to
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanetTrait {
+    Cultural,
+    Hazardous,
+    Industrial,
+}
+
+/// A hazard making a system harder to move through or fight in
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anomaly {
+    AsteroidField,
+    GravityRift,
+    Nebula,
+    Supernova,
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Anomaly::AsteroidField => "asteroid field",
+            Anomaly::GravityRift => "gravity rift",
+            Anomaly::Nebula => "nebula",
+            Anomaly::Supernova => "supernova",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A planet printed on a system tile
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Planet {
+    pub name: String,
+    pub resources: u8,
+    pub influence: u8,
+    /// Home planets and Mecatol Rex have no trait
+    #[serde(rename = "trait", default)]
+    pub planet_trait: Option<PlanetTrait>,
+    #[serde(default)]
+    pub specialty: Option<TechColor>,
+    #[serde(default)]
+    pub legendary: bool,
+}
+
+/// A system tile, as printed
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SystemTile {
+    pub id: TileId,
+    pub kind: TileKind,
+    pub expansion: Expansion,
+    #[serde(default)]
+    pub planets: Vec<Planet>,
+    #[serde(default)]
+    pub wormholes: BTreeSet<Wormhole>,
+    #[serde(default)]
+    pub anomaly: Option<Anomaly>,
+    /// The sides joined by each lane of a hyperlane, as printed with the tile unrotated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lanes: Vec<[Direction; 2]>,
+}
+
+impl SystemTile {
+    /// Gets the total resources of every planet in the system
+    pub fn resources(&self) -> u32 {
+        self.planets
+            .iter()
+            .map(|planet| planet.resources as u32)
+            .sum()
+    }
+
+    /// Gets the total influence of every planet in the system
+    pub fn influence(&self) -> u32 {
+        self.planets
+            .iter()
+            .map(|planet| planet.influence as u32)
+            .sum()
+    }
+
+    /// Gets a planet of the system by its name
+    pub fn planet(&self, name: &str) -> Option<&Planet> {
+        self.planets.iter().find(|planet| planet.name == name)
+    }
+
+    /// Creates the tile to place on the map for this system or hyperlane
+    pub fn map_tile(&self) -> MapTile {
+        if self.kind == TileKind::Hyperlane {
+            return MapTile::Hyperlane {
+                tile: self.id.clone(),
+                lanes: self.lanes.clone(),
+            };
+        }
+        MapTile::System {
+            tile: self.id.clone(),
+            wormholes: self.wormholes.clone(),
+        }
+    }
+}
+
+/// Every system tile, by number
+#[derive(Debug)]
+pub struct TileCatalog {
+    tiles: BTreeMap<TileId, SystemTile>,
+}
+
+impl TileCatalog {
+    /// Gets a tile by its number
+    pub fn get(&self, id: &str) -> Option<&SystemTile> {
+        self.tiles.get(id)
+    }
+
+    /// Gets every tile, ordered by number, and the sides of a hyperlane tile by letter
+    pub fn tiles(&self) -> impl Iterator<Item = &SystemTile> {
+        let mut tiles = self.tiles.values().collect::<Vec<_>>();
+        tiles.sort_by_key(|tile| {
+            let number = tile.id.0.trim_end_matches(char::is_alphabetic);
+            (number.parse::<u32>().unwrap_or(u32::MAX), &tile.id)
+        });
+        tiles.into_iter()
+    }
+
+    /// Gets every tile of a kind
+    pub fn of_kind(&self, kind: TileKind) -> impl Iterator<Item = &SystemTile> {
+        self.tiles().filter(move |tile| tile.kind == kind)
+    }
+
+    /// Finds a planet by its name, along with the tile it's on
+    pub fn planet(&self, name: &str) -> Option<(&SystemTile, &Planet)> {
+        self.tiles
+            .values()
+            .find_map(|tile| Some((tile, tile.planet(name)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::game::galaxy::{MapTile, Wormhole};
+    use crate::game::hex::Direction;
+    use crate::game::tech::TechColor;
+    use crate::game::tiles::{catalog, Anomaly, Expansion, PlanetTrait, TileKind};
+
+    #[test]
+    fn every_tile_is_cataloged() {
+        let numbers = catalog()
+            .tiles()
+            .filter(|tile| tile.kind != TileKind::Hyperlane)
+            .map(|tile| tile.id.0.parse::<u32>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, (1..=82).collect::<Vec<_>>());
+        let hyperlanes = catalog()
+            .of_kind(TileKind::Hyperlane)
+            .map(|tile| tile.id.0.as_str())
+            .collect::<Vec<_>>();
+        let sides = (83..=91)
+            .flat_map(|number| [format!("{number}A"), format!("{number}B")])
+            .collect::<Vec<_>>();
+        assert_eq!(hyperlanes, sides);
+        assert_eq!(catalog().of_kind(TileKind::Home).count(), 24);
+        assert_eq!(catalog().get("17").unwrap().kind, TileKind::CreussGate);
+        assert_eq!(
+            catalog()
+                .tiles()
+                .filter(|tile| tile.expansion == Expansion::Base)
+                .count(),
+            51
+        );
+    }
+
+    #[test]
+    fn planet_names_are_unique() {
+        let mut names = BTreeSet::new();
+        for tile in catalog().tiles() {
+            for planet in &tile.planets {
+                assert!(
+                    names.insert(&planet.name),
+                    "{} is listed twice",
+                    planet.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_are_looked_up_by_number() {
+        let mecatol = catalog().get("18").unwrap();
+        assert_eq!((mecatol.resources(), mecatol.influence()), (1, 6));
+        assert_eq!(mecatol.planets[0].planet_trait, None);
+
+        let (tile, planet) = catalog().planet("Meer").unwrap();
+        assert_eq!(tile.id.0, "37");
+        assert_eq!(planet.planet_trait, Some(PlanetTrait::Hazardous));
+        assert_eq!(planet.specialty, Some(TechColor::Warfare));
+
+        let lodor = catalog().get("26").unwrap();
+        assert_eq!(
+            lodor.map_tile(),
+            MapTile::System {
+                tile: "26".into(),
+                wormholes: BTreeSet::from([Wormhole::Alpha]),
+            }
+        );
+        assert_eq!(
+            catalog().get("41").unwrap().anomaly,
+            Some(Anomaly::GravityRift)
+        );
+    }
+
+    #[test]
+    fn hyperlanes_join_distinct_sides() {
+        for tile in catalog().of_kind(TileKind::Hyperlane) {
+            assert!(tile.planets.is_empty() && tile.wormholes.is_empty());
+            assert!(!tile.lanes.is_empty(), "{} has no lanes", tile.id);
+            for [from, to] in &tile.lanes {
+                assert_ne!(from, to, "{} has a lane going nowhere", tile.id);
+            }
+        }
+        let tile = catalog().get("90A").unwrap();
+        assert_eq!(
+            tile.map_tile(),
+            MapTile::Hyperlane {
+                tile: "90A".into(),
+                lanes: vec![
+                    [Direction::NorthEast, Direction::NorthWest],
+                    [Direction::SouthEast, Direction::SouthWest],
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn legendary_planets_are_flagged() {
+        let legendary = catalog()
+            .tiles()
+            .flat_map(|tile| &tile.planets)
+            .filter(|planet| planet.legendary)
+            .map(|planet| planet.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(legendary, ["Primor", "Hope's End"]);
+    }
+}
@@ -0,0 +1,769 @@
+//! Transactions between neighbors, and the promissory notes exchanged in them.
+//!
+//! A transaction is proposed by one player to another, who can accept it, counter it with
+//! other terms, or cancel it, and so can the proposer once countered. Nothing changes hands
+//! until the player the latest terms wait on accepts them, at which point the whole exchange
+//! is checked again and applied at once.
+//!
+//! Players can only transact with their neighbors, the players with units or planets in the
+//! same or adjacent systems, except during the agenda phase where anyone can. During the action
+//! phase, only the active player transacts. Each pair of players resolves at most one
+//! transaction per turn, or per phase outside the action phase.
+//!
+//! Commodities become trade goods when they are received. Promissory notes are read from
+//! `data/promissory_notes.json`, which is embedded into the crate. Every player starts with
+//! one of each generic note, along with the notes of their faction. Notes given to other players
+//! either take effect on their own, going back to the player who owns them once they are used,
+//! or are resolved by the players from their text.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::factions::FactionId;
+use crate::game::hex::Hex;
+use crate::game::round::{Phase, PhaseKind};
+use crate::game::{CommandError, GameEvent, GameState};
+use crate::lobby::PlayerId;
+
+const PROMISSORY_NOTES: &str = include_str!("../../data/promissory_notes.json");
+
+/// Gets the catalog of every promissory note
+pub fn catalog() -> &'static NoteCatalog {
+    static CATALOG: OnceLock<NoteCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let notes: Vec<NoteDefinition> = serde_json::from_str(PROMISSORY_NOTES)
+            .expect("embedded promissory note catalog is valid");
+        NoteCatalog {
+            order: notes.iter().map(|note| note.id.clone()).collect(),
+            notes: notes
+                .into_iter()
+                .map(|note| (note.id.clone(), note))
+                .collect(),
+        }
+    })
+}
+
+/// Identifies a promissory note, such as `trade_agreement`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NoteId(pub String);
+
+impl Borrow<str> for NoteId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for NoteId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Display for NoteId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A promissory note, as printed on its card
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NoteDefinition {
+    pub id: NoteId,
+    pub name: String,
+    /// The faction whose players start with the note, or none if every player does
+    #[serde(default)]
+    pub faction: Option<FactionId>,
+    pub text: String,
+    #[serde(flatten)]
+    pub effect: NoteEffect,
+}
+
+/// What a promissory note does for a player holding another player's copy
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum NoteEffect {
+    /// The holder has victory points while they hold it, until they activate a system with
+    /// the owner's units
+    VictoryPoints { points: u8 },
+    /// The owner gives the holder their commodities once they replenish them
+    Commodities,
+    /// Nothing the engine resolves, the players follow the text of the card
+    Printed,
+}
+
+/// Every promissory note, by id
+#[derive(Debug)]
+pub struct NoteCatalog {
+    notes: BTreeMap<NoteId, NoteDefinition>,
+    /// Ids in the order they are listed in
+    order: Vec<NoteId>,
+}
+
+impl NoteCatalog {
+    /// Gets a promissory note by its id
+    pub fn get(&self, id: &str) -> Option<&NoteDefinition> {
+        self.notes.get(id)
+    }
+
+    /// Gets every promissory note
+    pub fn notes(&self) -> impl Iterator<Item = &NoteDefinition> {
+        self.order.iter().map(|id| &self.notes[id])
+    }
+}
+
+/// A player's copy of a promissory note
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct PromissoryNote {
+    pub card: NoteId,
+    pub owner: PlayerId,
+}
+
+/// Gets the promissory notes a player of the given faction starts the game with
+pub fn starting_notes(player: PlayerId, faction: Option<&FactionId>) -> Vec<PromissoryNote> {
+    catalog()
+        .notes()
+        .filter(|note| note.faction.is_none() || note.faction.as_ref() == faction)
+        .map(|note| PromissoryNote {
+            card: note.id.clone(),
+            owner: player,
+        })
+        .collect()
+}
+
+/// Identifies a transaction within a game
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TransactionId(pub u32);
+
+impl Display for TransactionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// What a player gives in a transaction
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    #[serde(default)]
+    pub trade_goods: u8,
+    /// Received as trade goods
+    #[serde(default)]
+    pub commodities: u8,
+    /// At most one promissory note changes hands each way
+    #[serde(default)]
+    pub promissory_note: Option<PromissoryNote>,
+}
+
+impl Bundle {
+    /// Checks whether nothing is given
+    pub fn is_empty(&self) -> bool {
+        self.trade_goods == 0 && self.commodities == 0 && self.promissory_note.is_none()
+    }
+}
+
+/// A transaction being negotiated between two players
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub id: TransactionId,
+    /// What each of the two players gives the other
+    pub terms: BTreeMap<PlayerId, Bundle>,
+    /// The player who has to accept or counter the latest terms
+    pub awaiting: PlayerId,
+}
+
+impl Transaction {
+    /// Gets the other player taking part in this transaction
+    pub fn other(&self, player: PlayerId) -> Option<PlayerId> {
+        if !self.terms.contains_key(&player) {
+            return None;
+        }
+        self.terms.keys().copied().find(|other| *other != player)
+    }
+}
+
+/// The transactions of a game
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Transactions {
+    /// The id given to the next transaction proposed
+    #[serde(default)]
+    pub next_id: u32,
+    /// The transactions still being negotiated
+    #[serde(default)]
+    pub open: Vec<Transaction>,
+    /// The turn the resolved transactions were resolved in
+    #[serde(default)]
+    pub turn: Option<TransactionTurn>,
+    /// The pairs of players who resolved a transaction during that turn, in order
+    #[serde(default)]
+    pub resolved: BTreeSet<(PlayerId, PlayerId)>,
+}
+
+/// A turn of the action phase, or another phase as a whole, during which each pair of
+/// players resolves at most one transaction
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TransactionTurn {
+    pub round: u32,
+    pub phase: PhaseKind,
+    /// The active player, during the action phase
+    #[serde(default)]
+    pub active: Option<PlayerId>,
+}
+
+/// A transaction breaks the rules
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum TransactionError {
+    #[error("transaction {transaction} doesn't exist")]
+    Unknown { transaction: TransactionId },
+    #[error("you can't transact with yourself")]
+    WithYourself,
+    #[error("{player} is not your neighbor")]
+    NotNeighbors { player: PlayerId },
+    #[error("only the active player can transact during the action phase")]
+    NotActivePlayer,
+    #[error("you already resolved a transaction with {player} this turn")]
+    AlreadyTransacted { player: PlayerId },
+    #[error("a transaction has to exchange something")]
+    Empty,
+    #[error("{player} only has {have} commodities")]
+    NotEnoughCommodities { player: PlayerId, have: u8 },
+    #[error("{player} doesn't hold that promissory note")]
+    NoteNotHeld { player: PlayerId },
+    #[error("transaction {transaction} is waiting on the other player")]
+    NotAwaiting { transaction: TransactionId },
+}
+
+/// Sorts a pair of players, so that it is the same whoever proposed
+fn pair(a: PlayerId, b: PlayerId) -> (PlayerId, PlayerId) {
+    (a.min(b), a.max(b))
+}
+
+impl GameState {
+    /// Gets the systems a player has units in or controls planets in
+    fn presence(&self, player: PlayerId) -> BTreeSet<Hex> {
+        let mut systems = self
+            .board
+            .systems()
+            .filter(|(_, system)| system.units().any(|unit| unit.owner == player))
+            .map(|(hex, _)| hex)
+            .collect::<BTreeSet<_>>();
+        if let Some(state) = self.player(player) {
+            systems.extend(
+                state
+                    .planets
+                    .keys()
+                    .filter_map(|planet| self.board.find_planet(planet)),
+            );
+        }
+        systems
+    }
+
+    /// Checks whether two players are neighbors, having units or planets in the same or
+    /// adjacent systems
+    pub fn are_neighbors(&self, player: PlayerId, other: PlayerId) -> bool {
+        let theirs = self.presence(other);
+        self.presence(player).into_iter().any(|system| {
+            theirs.contains(&system)
+                || self
+                    .board
+                    .galaxy
+                    .adjacent(system)
+                    .iter()
+                    .any(|hex| theirs.contains(hex))
+        })
+    }
+
+    /// Gets the turn transactions are limited to right now, if players can transact at all
+    fn transaction_turn(&self) -> Option<TransactionTurn> {
+        let active = match &self.round.phase {
+            Phase::Action(phase) => Some(phase.active),
+            Phase::Status(_) => return None,
+            _ => None,
+        };
+        Some(TransactionTurn {
+            round: self.round.number,
+            phase: self.round.phase.kind(),
+            active,
+        })
+    }
+
+    /// Checks that two players can resolve a transaction right now
+    fn check_transaction(&self, player: PlayerId, other: PlayerId) -> Result<(), CommandError> {
+        if player == other {
+            return Err(TransactionError::WithYourself.into());
+        }
+        if !self.is_player(other) {
+            return Err(CommandError::NotAPlayer(other));
+        }
+        let Some(turn) = self.transaction_turn() else {
+            return Err(CommandError::NotNow);
+        };
+        if turn
+            .active
+            .is_some_and(|active| active != player && active != other)
+        {
+            return Err(TransactionError::NotActivePlayer.into());
+        }
+        if turn.phase != PhaseKind::Agenda && !self.are_neighbors(player, other) {
+            return Err(TransactionError::NotNeighbors { player: other }.into());
+        }
+        if self.transactions.turn == Some(turn)
+            && self.transactions.resolved.contains(&pair(player, other))
+        {
+            return Err(TransactionError::AlreadyTransacted { player: other }.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that a player has everything they give
+    fn check_bundle(&self, player: PlayerId, bundle: &Bundle) -> Result<(), CommandError> {
+        let state = self.player_state(player)?;
+        if bundle.trade_goods > state.trade_goods {
+            return Err(CommandError::NotEnoughTradeGoods {
+                have: state.trade_goods,
+            });
+        }
+        if bundle.commodities > state.commodities {
+            return Err(TransactionError::NotEnoughCommodities {
+                player,
+                have: state.commodities,
+            }
+            .into());
+        }
+        if let Some(note) = &bundle.promissory_note {
+            if !state.promissory_notes.contains(note) {
+                return Err(TransactionError::NoteNotHeld { player }.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the terms of a transaction, where the player gives one bundle and receives the
+    /// other
+    fn check_terms(
+        &self,
+        player: PlayerId,
+        other: PlayerId,
+        give: &Bundle,
+        receive: &Bundle,
+    ) -> Result<(), CommandError> {
+        self.check_transaction(player, other)?;
+        if give.is_empty() && receive.is_empty() {
+            return Err(TransactionError::Empty.into());
+        }
+        self.check_bundle(player, give)?;
+        self.check_bundle(other, receive)
+    }
+
+    fn open_transaction(&self, transaction: TransactionId) -> Result<&Transaction, CommandError> {
+        self.transactions
+            .open
+            .iter()
+            .find(|open| open.id == transaction)
+            .ok_or(TransactionError::Unknown { transaction }.into())
+    }
+
+    /// Proposes a transaction to another player, which they can accept or counter
+    pub(super) fn propose_transaction(
+        &mut self,
+        player: PlayerId,
+        to: PlayerId,
+        give: Bundle,
+        receive: Bundle,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        self.check_terms(player, to, &give, &receive)?;
+        let id = TransactionId(self.transactions.next_id);
+        self.transactions.next_id += 1;
+        let transaction = Transaction {
+            id,
+            terms: BTreeMap::from([(player, give), (to, receive)]),
+            awaiting: to,
+        };
+        self.transactions.open.push(transaction.clone());
+        Ok(vec![GameEvent::TransactionProposed { transaction }])
+    }
+
+    /// Replaces the terms of a transaction waiting on the player, sending it back to the other
+    /// player
+    pub(super) fn counter_transaction(
+        &mut self,
+        player: PlayerId,
+        transaction: TransactionId,
+        give: Bundle,
+        receive: Bundle,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let open = self.open_transaction(transaction)?;
+        let Some(other) = open.other(player) else {
+            return Err(TransactionError::Unknown { transaction }.into());
+        };
+        if open.awaiting != player {
+            return Err(TransactionError::NotAwaiting { transaction }.into());
+        }
+        self.check_terms(player, other, &give, &receive)?;
+        let Some(open) = self
+            .transactions
+            .open
+            .iter_mut()
+            .find(|open| open.id == transaction)
+        else {
+            return Err(TransactionError::Unknown { transaction }.into());
+        };
+        open.terms = BTreeMap::from([(player, give), (other, receive)]);
+        open.awaiting = other;
+        Ok(vec![GameEvent::TransactionCountered {
+            transaction: open.clone(),
+        }])
+    }
+
+    /// Accepts the terms of a transaction waiting on the player, exchanging everything in them
+    pub(super) fn accept_transaction(
+        &mut self,
+        player: PlayerId,
+        transaction: TransactionId,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let open = self.open_transaction(transaction)?.clone();
+        let Some(other) = open.other(player) else {
+            return Err(TransactionError::Unknown { transaction }.into());
+        };
+        if open.awaiting != player {
+            return Err(TransactionError::NotAwaiting { transaction }.into());
+        }
+        let give = open.terms.get(&player).cloned().unwrap_or_default();
+        let receive = open.terms.get(&other).cloned().unwrap_or_default();
+        self.check_terms(player, other, &give, &receive)?;
+
+        self.transactions.open.retain(|open| open.id != transaction);
+        let turn = self.transaction_turn();
+        if self.transactions.turn != turn {
+            self.transactions.turn = turn;
+            self.transactions.resolved.clear();
+        }
+        self.transactions.resolved.insert(pair(player, other));
+        let mut events = vec![GameEvent::TransactionResolved {
+            transaction: open.clone(),
+        }];
+        for (from, to, bundle) in [(player, other, give), (other, player, receive)] {
+            events.extend(self.hand_over(from, to, bundle)?);
+        }
+        Ok(events)
+    }
+
+    /// Cancels a transaction the player takes part in
+    pub(super) fn cancel_transaction(
+        &mut self,
+        player: PlayerId,
+        transaction: TransactionId,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        if self.open_transaction(transaction)?.other(player).is_none() {
+            return Err(TransactionError::Unknown { transaction }.into());
+        }
+        self.transactions.open.retain(|open| open.id != transaction);
+        Ok(vec![GameEvent::TransactionCanceled {
+            transaction,
+            player,
+        }])
+    }
+
+    /// Moves what one player gives in a transaction to the other
+    fn hand_over(
+        &mut self,
+        from: PlayerId,
+        to: PlayerId,
+        bundle: Bundle,
+    ) -> Result<Vec<GameEvent>, CommandError> {
+        let giver = self.player_state_mut(from)?;
+        giver.trade_goods -= bundle.trade_goods;
+        giver.commodities -= bundle.commodities;
+        if let Some(note) = &bundle.promissory_note {
+            giver.promissory_notes.retain(|held| held != note);
+        }
+        let receiver = self.player_state_mut(to)?;
+        receiver.trade_goods += bundle.trade_goods + bundle.commodities;
+        let Some(note) = bundle.promissory_note else {
+            return Ok(vec![]);
+        };
+        receiver.promissory_notes.push(note.clone());
+        let mut events = vec![];
+        if let Some(NoteEffect::VictoryPoints { points }) = catalog()
+            .get(&note.card.0)
+            .map(|definition| definition.effect)
+        {
+            if from != note.owner {
+                events.extend(self.lose_victory_points(from, points));
+            }
+            if to != note.owner {
+                events.extend(self.gain_victory_points(to, points));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Returns a promissory note a player holds to its owner
+    fn return_note(&mut self, holder: PlayerId, note: &PromissoryNote) -> Vec<GameEvent> {
+        let Some(state) = self.player_mut(holder) else {
+            return vec![];
+        };
+        state.promissory_notes.retain(|held| held != note);
+        if let Some(owner) = self.player_mut(note.owner) {
+            owner.promissory_notes.push(note.clone());
+        }
+        vec![GameEvent::PromissoryNoteReturned {
+            note: note.clone(),
+            from: holder,
+        }]
+    }
+
+    /// Gets the other players' notes with an effect that a player holds
+    fn held_notes(
+        &self,
+        holder: PlayerId,
+        effect: impl Fn(NoteEffect) -> bool,
+    ) -> Vec<PromissoryNote> {
+        let Some(state) = self.player(holder) else {
+            return vec![];
+        };
+        state
+            .promissory_notes
+            .iter()
+            .filter(|note| note.owner != holder)
+            .filter(|note| {
+                catalog()
+                    .get(&note.card.0)
+                    .is_some_and(|definition| effect(definition.effect))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the notes giving victory points to a player who activated a system with their
+    /// owner's units
+    pub(super) fn return_notes_on_activation(
+        &mut self,
+        player: PlayerId,
+        system: Hex,
+    ) -> Vec<GameEvent> {
+        let mut events = vec![];
+        let notes = self.held_notes(player, |effect| {
+            matches!(effect, NoteEffect::VictoryPoints { .. })
+        });
+        for note in notes {
+            let owner_present = self
+                .board
+                .system(system)
+                .units()
+                .any(|unit| unit.owner == note.owner);
+            if !owner_present {
+                continue;
+            }
+            if let Some(NoteEffect::VictoryPoints { points }) = catalog()
+                .get(&note.card.0)
+                .map(|definition| definition.effect)
+            {
+                events.extend(self.lose_victory_points(player, points));
+            }
+            events.extend(self.return_note(player, &note));
+        }
+        events
+    }
+
+    /// Gives the commodities a player just replenished to the players holding their notes
+    /// asking for them
+    pub(super) fn commodities_replenished(&mut self, owner: PlayerId) -> Vec<GameEvent> {
+        let mut events = vec![];
+        for holder in self.seats() {
+            let notes = self.held_notes(holder, |effect| effect == NoteEffect::Commodities);
+            for note in notes.into_iter().filter(|note| note.owner == owner) {
+                let amount = self
+                    .player_mut(owner)
+                    .map(|state| std::mem::take(&mut state.commodities))
+                    .unwrap_or_default();
+                events.extend(self.gain_trade_goods(holder, amount));
+                events.extend(self.return_note(holder, &note));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board::Unit;
+    use crate::game::factions;
+    use crate::game::transactions::{
+        catalog, starting_notes, Bundle, PromissoryNote, TransactionError, TransactionId,
+    };
+    use crate::game::units::UnitKind;
+    use crate::game::{test_game, CommandError, GameCommand, GameEvent};
+
+    fn goods(trade_goods: u8, commodities: u8) -> Bundle {
+        Bundle {
+            trade_goods,
+            commodities,
+            promissory_note: None,
+        }
+    }
+
+    #[test]
+    fn neighbors_propose_counter_and_accept_once_per_turn() {
+        let (mut state, players) = test_game(3);
+        state.player_mut(players[0]).unwrap().trade_goods = 3;
+        state.player_mut(players[1]).unwrap().commodities = 2;
+        // players[0] and players[2] sit two systems apart
+        assert!(!state.are_neighbors(players[0], players[2]));
+        assert_eq!(
+            state.apply(
+                players[0],
+                GameCommand::ProposeTransaction {
+                    to: players[2],
+                    give: goods(1, 0),
+                    receive: Bundle::default(),
+                }
+            ),
+            Err(CommandError::Transaction(TransactionError::NotNeighbors {
+                player: players[2]
+            }))
+        );
+
+        state
+            .apply(
+                players[0],
+                GameCommand::ProposeTransaction {
+                    to: players[1],
+                    give: goods(1, 0),
+                    receive: goods(0, 2),
+                },
+            )
+            .unwrap();
+        let transaction = TransactionId(0);
+        assert_eq!(
+            state.apply(players[0], GameCommand::AcceptTransaction { transaction }),
+            Err(CommandError::Transaction(TransactionError::NotAwaiting {
+                transaction
+            }))
+        );
+        state
+            .apply(
+                players[1],
+                GameCommand::CounterTransaction {
+                    transaction,
+                    give: goods(0, 1),
+                    receive: goods(2, 0),
+                },
+            )
+            .unwrap();
+        let events = state
+            .apply(players[0], GameCommand::AcceptTransaction { transaction })
+            .unwrap();
+        assert!(matches!(events[0], GameEvent::TransactionResolved { .. }));
+
+        // the commodity became a trade good
+        let (first, second) = (
+            state.player(players[0]).unwrap(),
+            state.player(players[1]).unwrap(),
+        );
+        assert_eq!((first.trade_goods, first.commodities), (2, 0));
+        assert_eq!((second.trade_goods, second.commodities), (2, 1));
+        assert!(state.transactions.open.is_empty());
+
+        assert_eq!(
+            state.apply(
+                players[1],
+                GameCommand::ProposeTransaction {
+                    to: players[0],
+                    give: goods(1, 0),
+                    receive: Bundle::default(),
+                }
+            ),
+            Err(CommandError::Transaction(
+                TransactionError::AlreadyTransacted { player: players[0] }
+            ))
+        );
+    }
+
+    #[test]
+    fn promissory_notes_take_effect_and_return_to_their_owner() {
+        let (mut state, players) = test_game(3);
+        state.player_mut(players[0]).unwrap().faction = Some("arborec".into());
+        let note = |card: &str| PromissoryNote {
+            card: card.into(),
+            owner: players[0],
+        };
+        for card in ["support_for_the_throne", "trade_agreement"] {
+            state
+                .apply(
+                    players[0],
+                    GameCommand::ProposeTransaction {
+                        to: players[1],
+                        give: Bundle {
+                            promissory_note: Some(note(card)),
+                            ..Bundle::default()
+                        },
+                        receive: Bundle::default(),
+                    },
+                )
+                .unwrap();
+            let transaction = state.transactions.open[0].id;
+            state
+                .apply(players[1], GameCommand::AcceptTransaction { transaction })
+                .unwrap();
+            // a new phase starts a new turn for transactions
+            state.transactions.resolved.clear();
+        }
+        assert_eq!(state.player(players[1]).unwrap().victory_points, 1);
+
+        let events = state.replenish_commodities(players[0]);
+        assert!(events.contains(&GameEvent::TradeGoodsGained {
+            player: players[1],
+            amount: 3,
+        }));
+        assert_eq!(state.player(players[0]).unwrap().commodities, 0);
+
+        let home = state.board.find_planet("Wellon").unwrap();
+        state
+            .board
+            .place_in_space(home, Unit::new(players[0], UnitKind::Carrier));
+        state.return_notes_on_activation(players[1], home);
+        let (first, second) = (
+            state.player(players[0]).unwrap(),
+            state.player(players[1]).unwrap(),
+        );
+        assert_eq!(second.victory_points, 0);
+        assert!(second
+            .promissory_notes
+            .iter()
+            .all(|note| note.owner == players[1]));
+        assert_eq!(
+            first.promissory_notes.len(),
+            starting_notes(players[0], None).len()
+        );
+    }
+
+    #[test]
+    fn every_faction_starts_with_its_own_notes() {
+        let (_, players) = test_game(2);
+        let generic = catalog()
+            .notes()
+            .filter(|note| note.faction.is_none())
+            .count();
+        assert_eq!(generic, 5);
+        for faction in factions::catalog().factions() {
+            let notes = starting_notes(players[0], Some(&faction.id));
+            assert!(notes.iter().all(|note| note.owner == players[0]));
+            let own = notes
+                .iter()
+                .map(|note| catalog().get(&note.card.0).unwrap())
+                .filter(|note| note.faction.is_some())
+                .map(|note| note.name.clone())
+                .collect::<Vec<_>>();
+            assert_eq!(own, faction.promissory_notes, "{}", faction.id);
+            assert_eq!(notes.len(), generic + own.len());
+        }
+        assert_eq!(starting_notes(players[1], None).len(), generic);
+    }
+}
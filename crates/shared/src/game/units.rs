@@ -0,0 +1,355 @@
+//! Units and their stats.
+//!
+//! Every version of every unit is read from `data/units.json`, which is embedded into the
+//! crate. A player's version of a unit depends on their faction, which may replace a unit with
+//! its own, and on the unit upgrades they researched. Flagships and mechs only exist as
+//! faction units, and are taken from the faction catalog.
+
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::factions::{self, FactionId};
+use crate::game::tech::TechId;
+
+const UNITS: &str = include_str!("../../data/units.json");
+
+/// Gets the catalog of every version of every unit
+pub fn catalog() -> &'static UnitCatalog {
+    static CATALOG: OnceLock<UnitCatalog> = OnceLock::new();
+    CATALOG.get_or_init(|| UnitCatalog {
+        units: serde_json::from_str(UNITS).expect("embedded unit catalog is valid"),
+    })
+}
+
+/// A type of unit
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitKind {
+    Carrier,
+    Cruiser,
+    Destroyer,
+    Dreadnought,
+    Fighter,
+    Flagship,
+    Infantry,
+    Mech,
+    Pds,
+    SpaceDock,
+    WarSun,
+}
+
+impl UnitKind {
+    /// Every type of unit
+    pub const ALL: [UnitKind; 11] = [
+        UnitKind::Carrier,
+        UnitKind::Cruiser,
+        UnitKind::Destroyer,
+        UnitKind::Dreadnought,
+        UnitKind::Fighter,
+        UnitKind::Flagship,
+        UnitKind::Infantry,
+        UnitKind::Mech,
+        UnitKind::Pds,
+        UnitKind::SpaceDock,
+        UnitKind::WarSun,
+    ];
+
+    /// Checks whether this unit is a ship, which is placed in space
+    pub fn is_ship(self) -> bool {
+        !self.is_ground_force() && !self.is_structure()
+    }
+
+    /// Checks whether this unit is a ground force, which is placed on planets
+    pub fn is_ground_force(self) -> bool {
+        matches!(self, UnitKind::Infantry | UnitKind::Mech)
+    }
+
+    /// Checks whether this unit is a structure
+    pub fn is_structure(self) -> bool {
+        matches!(self, UnitKind::Pds | UnitKind::SpaceDock)
+    }
+
+    /// Gets how many of this unit are produced for its cost
+    pub fn produced_per_cost(self) -> u8 {
+        match self {
+            UnitKind::Fighter | UnitKind::Infantry => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for UnitKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            UnitKind::Carrier => "carrier",
+            UnitKind::Cruiser => "cruiser",
+            UnitKind::Destroyer => "destroyer",
+            UnitKind::Dreadnought => "dreadnought",
+            UnitKind::Fighter => "fighter",
+            UnitKind::Flagship => "flagship",
+            UnitKind::Infantry => "infantry",
+            UnitKind::Mech => "mech",
+            UnitKind::Pds => "PDS",
+            UnitKind::SpaceDock => "space dock",
+            UnitKind::WarSun => "war sun",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A number of dice rolled together, each hitting on the value or higher
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Roll {
+    pub value: u8,
+    #[serde(default = "one")]
+    pub dice: u8,
+}
+
+impl Roll {
+    pub const fn new(value: u8, dice: u8) -> Self {
+        Self { value, dice }
+    }
+}
+
+fn one() -> u8 {
+    1
+}
+
+/// How many units a unit with production can produce at once
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Production {
+    /// A fixed number of units
+    Fixed(u8),
+    /// The resources of the planet the unit is on, plus a number of units
+    PlanetResources(u8),
+}
+
+impl Production {
+    /// Gets the number of units that can be produced, given the resources of the planet the
+    /// unit is on
+    pub fn value(self, planet_resources: u8) -> u8 {
+        match self {
+            Production::Fixed(value) => value,
+            Production::PlanetResources(bonus) => planet_resources + bonus,
+        }
+    }
+}
+
+/// The stats printed for a unit
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitStats {
+    /// The resources needed to produce the unit, if it can be produced
+    pub cost: Option<u8>,
+    pub combat: Option<Roll>,
+    #[serde(rename = "move")]
+    pub movement: Option<u8>,
+    pub capacity: u8,
+    pub sustain_damage: bool,
+    pub planetary_shield: bool,
+    pub anti_fighter_barrage: Option<Roll>,
+    pub bombardment: Option<Roll>,
+    pub space_cannon: Option<Roll>,
+    pub production: Option<Production>,
+}
+
+/// One version of a unit, as printed on a faction sheet or unit upgrade card
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UnitDefinition {
+    pub unit: UnitKind,
+    pub name: String,
+    /// The faction this version belongs to, replacing the generic one
+    #[serde(default)]
+    pub faction: Option<FactionId>,
+    /// The technology needed to use this version, if it isn't available from the start
+    #[serde(default)]
+    pub upgrade: Option<TechId>,
+    pub stats: UnitStats,
+}
+
+/// Every version of every unit, except for the unupgraded flagships and mechs
+#[derive(Debug)]
+pub struct UnitCatalog {
+    units: Vec<UnitDefinition>,
+}
+
+impl UnitCatalog {
+    /// Gets every version of every unit
+    pub fn units(&self) -> impl Iterator<Item = &UnitDefinition> {
+        self.units.iter()
+    }
+
+    /// Gets the unit upgrade granted by a technology, if any
+    pub fn upgrade(&self, tech: &str) -> Option<&UnitDefinition> {
+        self.units.iter().find(|unit| {
+            unit.upgrade
+                .as_ref()
+                .is_some_and(|upgrade| upgrade.0 == tech)
+        })
+    }
+
+    /// Resolves the version of a unit a player uses, given their faction and the technologies
+    /// they own.
+    ///
+    /// Gives nothing if the player can't have the unit at all, such as a war sun before
+    /// researching it, or a flagship without a faction.
+    pub fn resolve(
+        &self,
+        kind: UnitKind,
+        faction: Option<&FactionId>,
+        technologies: &BTreeSet<TechId>,
+    ) -> Option<UnitDefinition> {
+        let faction = faction.and_then(|id| factions::catalog().get(&id.0));
+        let mut versions = self
+            .units
+            .iter()
+            .filter(|unit| unit.unit == kind)
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(faction) = faction {
+            if let Some(unique) = faction.unit(kind) {
+                versions.insert(
+                    0,
+                    UnitDefinition {
+                        unit: kind,
+                        name: unique.name.clone(),
+                        faction: Some(faction.id.clone()),
+                        upgrade: None,
+                        stats: unique.stats.clone(),
+                    },
+                );
+            }
+        }
+        // a faction unit replaces the generic unit, upgraded or not
+        let faction_id = faction.map(|faction| &faction.id);
+        if versions
+            .iter()
+            .any(|unit| unit.faction.is_some() && unit.faction.as_ref() == faction_id)
+        {
+            versions.retain(|unit| unit.faction.as_ref() == faction_id);
+        } else {
+            versions.retain(|unit| unit.faction.is_none());
+        }
+        versions
+            .into_iter()
+            .filter(|unit| {
+                unit.upgrade
+                    .as_ref()
+                    .is_none_or(|upgrade| technologies.contains(upgrade))
+            })
+            .max_by_key(|unit| unit.upgrade.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::game::factions::{self, FactionId};
+    use crate::game::tech::TechId;
+    use crate::game::units::{catalog, Production, Roll, UnitKind};
+
+    fn techs(names: &[&str]) -> BTreeSet<TechId> {
+        names.iter().map(|name| TechId::from(*name)).collect()
+    }
+
+    #[test]
+    fn upgrades_replace_generic_units() {
+        let carrier = catalog()
+            .resolve(UnitKind::Carrier, None, &BTreeSet::new())
+            .unwrap();
+        assert_eq!(carrier.name, "Carrier I");
+        assert_eq!(carrier.stats.capacity, 4);
+
+        let carrier = catalog()
+            .resolve(UnitKind::Carrier, None, &techs(&["carrier_ii"]))
+            .unwrap();
+        assert_eq!(carrier.name, "Carrier II");
+        assert_eq!(
+            (carrier.stats.movement, carrier.stats.capacity),
+            (Some(2), 6)
+        );
+        assert_eq!(
+            catalog()
+                .upgrade("destroyer_ii")
+                .unwrap()
+                .stats
+                .anti_fighter_barrage,
+            Some(Roll::new(6, 3))
+        );
+    }
+
+    #[test]
+    fn faction_units_replace_generic_units() {
+        let sol = FactionId::from("sol");
+        let carrier = catalog()
+            .resolve(UnitKind::Carrier, Some(&sol), &techs(&["carrier_ii"]))
+            .unwrap();
+        // the generic upgrade does nothing for a faction with its own carrier
+        assert_eq!(carrier.name, "Advanced Carrier I");
+
+        let arborec = FactionId::from("arborec");
+        let infantry = catalog()
+            .resolve(
+                UnitKind::Infantry,
+                Some(&arborec),
+                &techs(&["letani_warrior_ii"]),
+            )
+            .unwrap();
+        assert_eq!(infantry.stats.production, Some(Production::Fixed(2)));
+        assert_eq!(infantry.stats.production.unwrap().value(3), 2);
+
+        let dock = catalog()
+            .resolve(UnitKind::SpaceDock, Some(&arborec), &BTreeSet::new())
+            .unwrap();
+        assert_eq!(dock.stats.production.unwrap().value(3), 5);
+    }
+
+    #[test]
+    fn war_suns_need_research() {
+        assert!(catalog()
+            .resolve(UnitKind::WarSun, None, &BTreeSet::new())
+            .is_none());
+        assert!(catalog()
+            .resolve(UnitKind::WarSun, None, &techs(&["war_sun"]))
+            .is_some());
+        let muaat = catalog()
+            .resolve(UnitKind::WarSun, Some(&"muaat".into()), &BTreeSet::new())
+            .unwrap();
+        assert_eq!(muaat.name, "Prototype War Sun I");
+    }
+
+    #[test]
+    fn flagships_and_mechs_come_from_factions() {
+        assert!(catalog()
+            .resolve(UnitKind::Flagship, None, &BTreeSet::new())
+            .is_none());
+        let nomad = FactionId::from("nomad");
+        let memoria = catalog()
+            .resolve(UnitKind::Flagship, Some(&nomad), &BTreeSet::new())
+            .unwrap();
+        assert_eq!(memoria.name, "Memoria");
+        let memoria = catalog()
+            .resolve(UnitKind::Flagship, Some(&nomad), &techs(&["memoria_ii"]))
+            .unwrap();
+        assert_eq!(memoria.name, "Memoria II");
+        let mech = catalog()
+            .resolve(UnitKind::Mech, Some(&"sol".into()), &BTreeSet::new())
+            .unwrap();
+        assert_eq!(mech.name, "ZS Thunderbolt M2");
+    }
+
+    #[test]
+    fn faction_upgrades_are_faction_techs() {
+        for unit in catalog().units() {
+            if let (Some(faction), Some(upgrade)) = (&unit.faction, &unit.upgrade) {
+                let faction = factions::catalog().get(&faction.0).unwrap();
+                assert!(faction.faction_techs.contains(upgrade), "{}", unit.name);
+            }
+        }
+    }
+}
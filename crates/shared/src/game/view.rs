@@ -0,0 +1,177 @@
+//! What one player is allowed to see of a game.
+//!
+//! The [GameState] holds everything, including the cards in every player's hand, their secret
+//! objectives, the order of every deck and the state of the dice. Clients only ever get a
+//! [GameView], where the hands and secret objectives of other players and the contents of decks
+//! are replaced by how many cards they hold, and the dice are left out.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::action_cards::{ActionCardId, OpenWindow};
+use crate::game::agenda::AgendaId;
+use crate::game::board::Board;
+use crate::game::objectives::ObjectiveId;
+use crate::game::round::Round;
+use crate::game::strategy::StrategyCard;
+use crate::game::transactions::Transactions;
+use crate::game::{GameState, Player};
+use crate::lobby::{GameId, PlayerId};
+
+/// A game as seen by one of its players
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameView {
+    /// The player this view is for
+    pub viewer: PlayerId,
+    pub id: GameId,
+    /// Every player, in seat order
+    pub players: Vec<PlayerView>,
+    /// The number of commands applied to this game so far
+    pub sequence: u64,
+    pub round: Round,
+    pub custodians: Option<PlayerId>,
+    pub board: Board,
+    pub strategy_card_trade_goods: BTreeMap<StrategyCard, u8>,
+    pub agenda_deck: DeckView<AgendaId>,
+    pub laws: BTreeMap<AgendaId, Option<String>>,
+    pub objectives: ObjectivesView,
+    pub action_deck: DeckView<ActionCardId>,
+    pub windows: Vec<OpenWindow>,
+    pub transactions: Transactions,
+}
+
+/// A player as seen by another player, or by themselves
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlayerView {
+    /// The player, with an empty hand and no secret objectives unless they are the viewer
+    #[serde(flatten)]
+    pub player: Player,
+    /// The number of action cards in the player's hand
+    pub action_card_count: usize,
+    /// The number of unscored secret objectives the player holds
+    pub secret_objective_count: usize,
+}
+
+/// A deck whose cards are drawn face down, and discarded face up
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeckView<T> {
+    /// The number of cards left to draw
+    pub draw: usize,
+    pub discard: Vec<T>,
+}
+
+/// The objective decks, of which only the revealed public objectives are face up
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ObjectivesView {
+    /// The number of stage I objectives left to reveal
+    pub stage_one: usize,
+    /// The number of stage II objectives left to reveal
+    pub stage_two: usize,
+    /// The number of secret objectives left to draw
+    pub secret: usize,
+    pub revealed: Vec<ObjectiveId>,
+}
+
+impl GameState {
+    /// Gets what a player is allowed to see of this game
+    pub fn view_for(&self, viewer: PlayerId) -> GameView {
+        let players = self
+            .players
+            .iter()
+            .map(|player| {
+                let mut visible = player.clone();
+                if player.id != viewer {
+                    visible.action_cards.clear();
+                    visible.secret_objectives.clear();
+                }
+                PlayerView {
+                    player: visible,
+                    action_card_count: player.action_cards.len(),
+                    secret_objective_count: player.secret_objectives.len(),
+                }
+            })
+            .collect();
+        GameView {
+            viewer,
+            id: self.id,
+            players,
+            sequence: self.sequence,
+            round: self.round.clone(),
+            custodians: self.custodians,
+            board: self.board.clone(),
+            strategy_card_trade_goods: self.strategy_card_trade_goods.clone(),
+            agenda_deck: DeckView {
+                draw: self.agenda_deck.draw.len(),
+                discard: self.agenda_deck.discard.clone(),
+            },
+            laws: self.laws.clone(),
+            objectives: ObjectivesView {
+                stage_one: self.objectives.stage_one.len(),
+                stage_two: self.objectives.stage_two.len(),
+                secret: self.objectives.secret.len(),
+                revealed: self.objectives.revealed.clone(),
+            },
+            action_deck: DeckView {
+                draw: self.action_deck.draw.len(),
+                discard: self.action_deck.discard.clone(),
+            },
+            windows: self.windows.clone(),
+            transactions: self.transactions.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::test_game;
+
+    #[test]
+    fn other_players_hands_and_the_decks_are_hidden() {
+        let (mut state, players) = test_game(3);
+        state.draw_action_cards(players[0], 2);
+        let hand = state.player(players[0]).unwrap().action_cards.clone();
+
+        let view = state.view_for(players[1]);
+        let theirs = &view.players[0];
+        assert!(theirs.player.action_cards.is_empty());
+        assert_eq!(theirs.action_card_count, 2);
+        assert_eq!(view.action_deck.draw, state.action_deck.draw.len());
+        let json = serde_json::to_value(&view).unwrap();
+        assert!(json.get("dice").is_none());
+        for card in &hand {
+            assert!(!json.to_string().contains(&format!("\"{card}\"")));
+        }
+
+        let own = state.view_for(players[0]);
+        assert_eq!(own.players[0].player.action_cards, hand);
+    }
+
+    #[test]
+    fn other_players_secret_objectives_are_hidden() {
+        let (state, players) = test_game(3);
+        let secret = state.player(players[0]).unwrap().secret_objectives.clone();
+        assert_eq!(secret.len(), 1);
+
+        let view = state.view_for(players[1]);
+        let theirs = &view.players[0];
+        assert!(theirs.player.secret_objectives.is_empty());
+        assert_eq!(theirs.secret_objective_count, 1);
+        assert_eq!(view.objectives.secret, state.objectives.secret.len());
+        assert_eq!(view.objectives.revealed, state.objectives.revealed);
+        let json = serde_json::to_string(&view).unwrap();
+        let hidden = state
+            .objectives
+            .stage_one
+            .iter()
+            .chain(&state.objectives.stage_two)
+            .chain(&state.objectives.secret)
+            .chain(&secret);
+        for objective in hidden {
+            assert!(!json.contains(&format!("\"{objective}\"")));
+        }
+
+        let own = state.view_for(players[0]);
+        assert_eq!(own.players[0].player.secret_objectives, secret);
+    }
+}
@@ -0,0 +1,286 @@
+//! The rules a rejected command broke, with where to read about them.
+//!
+//! Most commands are rejected with a [CommandError] that only makes sense to the engine, such
+//! as a command sent at the wrong time. The ones that break a rule of the game are also
+//! described as a [RuleViolation], which cites the section of the Living Rules Reference that
+//! explains the rule, so that clients can show players why their command was rejected.
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::hex::Hex;
+use crate::game::movement::MoveError;
+use crate::game::objectives::ObjectiveError;
+use crate::game::production::ProductionError;
+use crate::game::tiles::Anomaly;
+use crate::game::transactions::TransactionError;
+use crate::game::units::UnitKind;
+use crate::game::CommandError;
+use crate::lobby::PlayerId;
+
+/// A section of the Living Rules Reference
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    Adjacency,
+    Anomalies,
+    Blockaded,
+    Capacity,
+    CommandTokens,
+    Exhausted,
+    FleetPool,
+    Movement,
+    TacticalAction,
+    Transactions,
+}
+
+impl Rule {
+    /// Gets the number of this rule's section
+    pub fn section(self) -> u8 {
+        match self {
+            Rule::Adjacency => 6,
+            Rule::Anomalies => 9,
+            Rule::Blockaded => 14,
+            Rule::Capacity => 16,
+            Rule::CommandTokens => 20,
+            Rule::Exhausted => 34,
+            Rule::FleetPool => 37,
+            Rule::Movement => 58,
+            Rule::TacticalAction => 89,
+            Rule::Transactions => 94,
+        }
+    }
+
+    /// Gets the title of this rule's section
+    pub fn title(self) -> &'static str {
+        match self {
+            Rule::Adjacency => "Adjacency",
+            Rule::Anomalies => "Anomalies",
+            Rule::Blockaded => "Blockaded",
+            Rule::Capacity => "Capacity",
+            Rule::CommandTokens => "Command Tokens",
+            Rule::Exhausted => "Exhausted",
+            Rule::FleetPool => "Fleet Pool",
+            Rule::Movement => "Movement",
+            Rule::TacticalAction => "Tactical Action",
+            Rule::Transactions => "Transactions",
+        }
+    }
+
+    /// Cites this rule's section
+    pub fn citation(self) -> Citation {
+        Citation {
+            section: self.section(),
+            title: self.title().to_string(),
+        }
+    }
+}
+
+/// Where a rule is explained in the Living Rules Reference
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Citation {
+    pub section: u8,
+    pub title: String,
+}
+
+impl Display for Citation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LRR {} {}", self.section, self.title)
+    }
+}
+
+/// A rule of the game a command broke
+#[derive(Debug, Clone, Eq, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "violation", rename_all = "snake_case")]
+pub enum RuleViolation {
+    #[error("the moving ships can carry {capacity} units, but {carried} were moved with them")]
+    InsufficientCapacity { carried: usize, capacity: usize },
+    #[error("{from} is not adjacent to {to}")]
+    NotAdjacent { from: Hex, to: Hex },
+    #[error("ships can't move through {system}, which contains other players' ships")]
+    BlockedByEnemyShips { system: Hex },
+    #[error("ships can't move out of {system}, which contains your command token")]
+    MovedFromActivatedSystem { system: Hex },
+    #[error("ships can't move into the {anomaly} in {system}")]
+    CannotEnterAnomaly { system: Hex, anomaly: Anomaly },
+    #[error("ships can't move through the {anomaly} in {system}")]
+    CannotMoveThroughAnomaly { system: Hex, anomaly: Anomaly },
+    #[error("a {unit} in {from} can only move {distance} systems or fewer")]
+    OutOfRange {
+        unit: UnitKind,
+        from: Hex,
+        distance: usize,
+    },
+    #[error("{ships} non-fighter ships in one system need more than the {fleet} tokens in your fleet pool")]
+    FleetPoolExceeded { ships: usize, fleet: u8 },
+    #[error("{planet} is exhausted, so it can't be spent until it is readied")]
+    PlanetExhausted { planet: String },
+    #[error("other players' ships in {system} blockade it, so it can't produce ships")]
+    ProductionBlockaded { system: Hex },
+    #[error("activating a system takes a command token from your tactic pool")]
+    NoTacticToken,
+    #[error("you only have {tactic} tactic and {strategy} strategy tokens to spend")]
+    NotEnoughCommandTokens { tactic: u8, strategy: u8 },
+    #[error("you can only transact with your neighbors, and {player} isn't one")]
+    NotNeighbors { player: PlayerId },
+    #[error("you can only resolve one transaction with {player} each turn")]
+    AlreadyTransacted { player: PlayerId },
+}
+
+impl RuleViolation {
+    /// Gets the rule broken
+    pub fn rule(&self) -> Rule {
+        match self {
+            RuleViolation::InsufficientCapacity { .. } => Rule::Capacity,
+            RuleViolation::NotAdjacent { .. } => Rule::Adjacency,
+            RuleViolation::BlockedByEnemyShips { .. }
+            | RuleViolation::MovedFromActivatedSystem { .. }
+            | RuleViolation::OutOfRange { .. } => Rule::Movement,
+            RuleViolation::CannotEnterAnomaly { .. }
+            | RuleViolation::CannotMoveThroughAnomaly { .. } => Rule::Anomalies,
+            RuleViolation::FleetPoolExceeded { .. } => Rule::FleetPool,
+            RuleViolation::PlanetExhausted { .. } => Rule::Exhausted,
+            RuleViolation::ProductionBlockaded { .. } => Rule::Blockaded,
+            RuleViolation::NoTacticToken => Rule::TacticalAction,
+            RuleViolation::NotEnoughCommandTokens { .. } => Rule::CommandTokens,
+            RuleViolation::NotNeighbors { .. } | RuleViolation::AlreadyTransacted { .. } => {
+                Rule::Transactions
+            }
+        }
+    }
+
+    /// Explains the violation to a player, citing the rule broken
+    pub fn explanation(&self) -> String {
+        format!("{self} ({})", self.rule().citation())
+    }
+}
+
+impl CommandError {
+    /// Gets the rule of the game this error is about, if it is about one
+    pub fn violation(&self) -> Option<RuleViolation> {
+        let violation = match self {
+            CommandError::Movement(MoveError::OverCapacity { carried, capacity }) => {
+                RuleViolation::InsufficientCapacity {
+                    carried: *carried,
+                    capacity: *capacity,
+                }
+            }
+            CommandError::Movement(MoveError::NotAdjacent { from, to }) => {
+                RuleViolation::NotAdjacent {
+                    from: *from,
+                    to: *to,
+                }
+            }
+            CommandError::Movement(MoveError::Blocked { system }) => {
+                RuleViolation::BlockedByEnemyShips { system: *system }
+            }
+            CommandError::Movement(MoveError::CommandToken { system }) => {
+                RuleViolation::MovedFromActivatedSystem { system: *system }
+            }
+            CommandError::Movement(MoveError::CannotEnter { system, anomaly }) => {
+                RuleViolation::CannotEnterAnomaly {
+                    system: *system,
+                    anomaly: *anomaly,
+                }
+            }
+            CommandError::Movement(MoveError::CannotMoveThrough { system, anomaly }) => {
+                RuleViolation::CannotMoveThroughAnomaly {
+                    system: *system,
+                    anomaly: *anomaly,
+                }
+            }
+            CommandError::Movement(MoveError::OutOfRange {
+                unit,
+                from,
+                distance,
+            }) => RuleViolation::OutOfRange {
+                unit: *unit,
+                from: *from,
+                distance: *distance,
+            },
+            CommandError::Movement(MoveError::FleetPool { ships, fleet }) => {
+                RuleViolation::FleetPoolExceeded {
+                    ships: *ships,
+                    fleet: *fleet,
+                }
+            }
+            CommandError::PlanetExhausted(planet) => RuleViolation::PlanetExhausted {
+                planet: planet.clone(),
+            },
+            CommandError::Production(ProductionError::Blockaded { system }) => {
+                RuleViolation::ProductionBlockaded { system: *system }
+            }
+            CommandError::NoTacticToken => RuleViolation::NoTacticToken,
+            CommandError::Objective(ObjectiveError::NotEnoughCommandTokens {
+                tactic,
+                strategy,
+            }) => RuleViolation::NotEnoughCommandTokens {
+                tactic: *tactic,
+                strategy: *strategy,
+            },
+            CommandError::Transaction(TransactionError::NotNeighbors { player }) => {
+                RuleViolation::NotNeighbors { player: *player }
+            }
+            CommandError::Transaction(TransactionError::AlreadyTransacted { player }) => {
+                RuleViolation::AlreadyTransacted { player: *player }
+            }
+            _ => return None,
+        };
+        Some(violation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::game::hex::Hex;
+    use crate::game::movement::MoveError;
+    use crate::game::objectives::ObjectiveError;
+    use crate::game::tiles::Anomaly;
+    use crate::game::violations::{Rule, RuleViolation};
+    use crate::game::CommandError;
+
+    #[test]
+    fn rule_errors_are_violations_citing_their_rule() {
+        let error = CommandError::Movement(MoveError::OverCapacity {
+            carried: 3,
+            capacity: 2,
+        });
+        let violation = error.violation().unwrap();
+        assert_eq!(
+            violation,
+            RuleViolation::InsufficientCapacity {
+                carried: 3,
+                capacity: 2
+            }
+        );
+        assert_eq!(violation.rule(), Rule::Capacity);
+        assert_eq!(
+            violation.explanation(),
+            "the moving ships can carry 2 units, but 3 were moved with them (LRR 16 Capacity)"
+        );
+        assert_eq!(
+            serde_json::to_value(&violation).unwrap(),
+            json!({"violation": "insufficient_capacity", "carried": 3, "capacity": 2})
+        );
+
+        let blocked = CommandError::Movement(MoveError::Blocked {
+            system: Hex::new(0, 0),
+        });
+        assert_eq!(blocked.violation().unwrap().rule(), Rule::Movement);
+        let nebula = CommandError::Movement(MoveError::CannotMoveThrough {
+            system: Hex::new(0, 0),
+            anomaly: Anomaly::Nebula,
+        });
+        assert_eq!(nebula.violation().unwrap().rule(), Rule::Anomalies);
+        let tokens = CommandError::Objective(ObjectiveError::NotEnoughCommandTokens {
+            tactic: 1,
+            strategy: 0,
+        });
+        assert_eq!(tokens.violation().unwrap().rule(), Rule::CommandTokens);
+        assert_eq!(CommandError::NotYourTurn.violation(), None);
+    }
+}
@@ -1,4 +1,13 @@
-//! The shared library for eventide dominion
+//! The shared library for eventide dominion.
+//!
+//! Everything but the `cli` and `logging` modules compiles for `wasm32-unknown-unknown`, so that
+//! the server and the ui share the same protocol and game model. Those two modules are enabled
+//! by the features of the same name.
 
+#[cfg(feature = "cli")]
 pub mod cli;
+pub mod game;
+pub mod lobby;
+#[cfg(feature = "logging")]
 pub mod logging;
+pub mod protocol;
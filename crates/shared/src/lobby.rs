@@ -0,0 +1,141 @@
+//! Types describing games waiting in the lobby
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use uuid::Uuid;
+
+/// The number of players a game may be configured for
+pub const PLAYER_LIMITS: RangeInclusive<u8> = 3..=8;
+
+/// Uniquely identifies a game
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GameId(pub Uuid);
+
+impl Display for GameId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Uniquely identifies a player
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PlayerId(pub Uuid);
+
+impl Display for PlayerId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Lets a player resume their seat after their connection dropped
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionToken(pub Uuid);
+
+impl Display for SessionToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Who is able to see and join a game
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Listed in the lobby, anyone may join
+    #[default]
+    Open,
+    /// Listed in the lobby, but joining requires the invite code
+    InviteOnly,
+    /// Not listed in the lobby, joining requires the invite code
+    Private,
+}
+
+impl Visibility {
+    /// Checks whether games with this visibility are shown in the lobby
+    pub fn is_listed(&self) -> bool {
+        !matches!(self, Visibility::Private)
+    }
+
+    /// Checks whether joining a game with this visibility requires an invite code
+    pub fn requires_invite(&self) -> bool {
+        !matches!(self, Visibility::Open)
+    }
+}
+
+/// The settings a game is created with
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameSettings {
+    /// The maximum number of players, must be within [`PLAYER_LIMITS`]
+    pub player_limit: u8,
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            player_limit: 6,
+            visibility: Visibility::default(),
+        }
+    }
+}
+
+/// Where a game is in its lifecycle
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStatus {
+    /// Waiting in the lobby for players
+    Waiting,
+    /// The game has been started by the host
+    InProgress,
+}
+
+/// A player sitting at a seat
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Seat {
+    pub player: PlayerId,
+    pub name: String,
+    /// Set while the player has no connection to the server
+    #[serde(default)]
+    pub disconnected: bool,
+}
+
+/// A snapshot of a game as seen from the lobby
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameSummary {
+    pub id: GameId,
+    pub name: String,
+    pub host: PlayerId,
+    pub settings: GameSettings,
+    pub status: GameStatus,
+    /// One entry per seat, `None` if the seat is empty
+    pub seats: Vec<Option<Seat>>,
+}
+
+impl GameSummary {
+    /// Gets the seat index of a player, if they're seated in this game
+    pub fn seat_of(&self, player: PlayerId) -> Option<usize> {
+        self.seats
+            .iter()
+            .position(|seat| matches!(seat, Some(seat) if seat.player == player))
+    }
+
+    /// Gets the number of occupied seats
+    pub fn player_count(&self) -> usize {
+        self.seats.iter().filter(|seat| seat.is_some()).count()
+    }
+}
+
+/// A change in the lobby, pushed to every connected client that can see the game
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LobbyEvent {
+    /// A game was created or changed
+    GameUpdated { game: GameSummary },
+    /// A game was removed from the lobby
+    GameRemoved { id: GameId },
+}
@@ -1,22 +1,94 @@
 //! Provides logging details and functions
 
+use std::path::PathBuf;
+
+#[cfg(feature = "cli")]
 use clap::ValueEnum;
-use std::str::FromStr;
 use thiserror::Error;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{InitError, RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{EnvFilter, ParseError as FilterParseError};
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::util::{SubscriberInitExt, TryInitError};
 use tracing_subscriber::{filter::LevelFilter as TracingLevelFilter, fmt, Layer, Registry};
 
-/// Initializes the logging framework for eventide dominion, using the default
-/// configuration for [Logging](Logging)
-pub fn init_logging(level: LevelFilter) {
-    let layer = fmt::layer().with_filter::<TracingLevelFilter>(level.clone().into());
+/// Initializes the logging framework for eventide dominion, as configured by
+/// `Logging` args of the `cli` module.
+///
+/// The returned guard flushes the log file once dropped, so it must be kept alive for as long
+/// as logs should be written.
+pub fn init_logging(options: &LogOptions) -> Result<LoggingGuard, LoggingError> {
+    let filter = options.filter()?;
+    let mut layers = vec![format_layer(options.format, std::io::stdout, true)];
+    let mut file_guard = None;
+    if let Some(file) = &options.file {
+        let (writer, guard) = tracing_appender::non_blocking(file.appender()?);
+        layers.push(format_layer(options.format, writer, false));
+        file_guard = Some(guard);
+    }
+
+    Registry::default().with(layers).with(filter).try_init()?;
+    Ok(LoggingGuard {
+        _file_guard: file_guard,
+    })
+}
+
+/// How logs are filtered, formatted and written
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LogOptions {
+    /// The level of every module without a directive of its own
+    pub level: LevelFilter,
+    /// Per module filter directives, such as `server::socket=trace,axum=warn`
+    pub directives: Option<String>,
+    pub format: LogFormat,
+    /// A file logs are written to as well
+    pub file: Option<LogFile>,
+}
+
+impl LogOptions {
+    /// Builds the filter selecting which logs are written
+    pub fn filter(&self) -> Result<EnvFilter, LoggingError> {
+        let directives =
+            EnvFilter::builder().parse(self.directives.as_deref().unwrap_or_default())?;
+        Ok(directives.add_directive(TracingLevelFilter::from(self.level).into()))
+    }
+}
+
+/// A rotating log file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LogFile {
+    /// The path of the log file, to which the date is appended when it is rotated
+    pub path: PathBuf,
+    pub rotation: LogRotation,
+}
 
-    Registry::default().with(layer).init()
+impl LogFile {
+    fn appender(&self) -> Result<RollingFileAppender, LoggingError> {
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let prefix = self
+            .path
+            .file_name()
+            .ok_or_else(|| LoggingError::InvalidFile(self.path.clone()))?;
+        Ok(RollingFileAppender::builder()
+            .rotation(self.rotation.into())
+            .filename_prefix(prefix.to_string_lossy())
+            .build(directory)?)
+    }
+}
+
+/// Guards the logging framework, flushing the logs once dropped
+#[must_use = "logs may be lost once the guard is dropped"]
+pub struct LoggingGuard {
+    _file_guard: Option<WorkerGuard>,
 }
 
 /// Level filter
-#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
 pub enum LevelFilter {
     Trace,
     Debug,
@@ -26,6 +98,28 @@ pub enum LevelFilter {
     Off,
 }
 
+/// The format of every log line
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum LogFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// One JSON object per line, including the spans each event occurred in
+    Json,
+}
+
+/// How often a log file is rotated
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
 /// An error occurred while parsing a level filter
 #[derive(Debug, Error)]
 #[error("Unknown level: {unknown}")]
@@ -33,6 +127,19 @@ pub struct ParseError {
     unknown: String,
 }
 
+/// An error occurred while initializing the logging framework
+#[derive(Debug, Error)]
+pub enum LoggingError {
+    #[error("invalid log filter: {0}")]
+    Filter(#[from] FilterParseError),
+    #[error("log file path {0:?} has no file name")]
+    InvalidFile(PathBuf),
+    #[error("could not open log file: {0}")]
+    File(#[from] InitError),
+    #[error("logging was already initialized: {0}")]
+    AlreadyInitialized(#[from] TryInitError),
+}
+
 impl From<LevelFilter> for TracingLevelFilter {
     fn from(value: LevelFilter) -> Self {
         match value {
@@ -45,3 +152,59 @@ impl From<LevelFilter> for TracingLevelFilter {
         }
     }
 }
+
+impl From<LogRotation> for Rotation {
+    fn from(value: LogRotation) -> Self {
+        match value {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn format_layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Text => fmt::layer().with_ansi(ansi).with_writer(writer).boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logging::{LevelFilter, LogFormat, LogOptions};
+
+    fn options(directives: &str) -> LogOptions {
+        LogOptions {
+            level: LevelFilter::Info,
+            directives: Some(directives.to_string()),
+            format: LogFormat::Text,
+            file: None,
+        }
+    }
+
+    #[test]
+    fn directives_extend_level() {
+        let filter = options("server::socket=trace,axum=warn").filter().unwrap();
+        let filter = filter.to_string();
+        assert!(filter.contains("server::socket=trace"), "{filter}");
+        assert!(filter.contains("axum=warn"), "{filter}");
+        assert!(filter.contains("info"), "{filter}");
+    }
+
+    #[test]
+    fn invalid_directives_are_rejected() {
+        assert!(options("server::socket=loud").filter().is_err());
+    }
+}
@@ -0,0 +1,341 @@
+//! The message protocol spoken between clients and the server over the `/ws` web socket.
+//!
+//! Every frame is a JSON text frame containing either a [`ClientMessage`] or a
+//! [`ServerMessage`]. A connection starts with a handshake, where the client sends
+//! [`ClientMessage::Hello`] and the server replies with [`ServerMessage::Welcome`]. After
+//! the handshake, the client sends [`ClientMessage::Request`]s tagged with a [`RequestId`],
+//! and the server answers each one with either a [`ServerMessage::Response`] or a
+//! [`ServerMessage::Error`] carrying the same id.
+//!
+//! Joining a game issues a [`SessionToken`]. A client whose connection dropped can send it in
+//! its next hello to get its seat back, along with every game update it missed.
+
+use crate::game::objectives::ObjectiveId;
+use crate::game::payment::{Currency, Payment};
+use crate::game::view::GameView;
+use crate::game::violations::{Citation, RuleViolation};
+use crate::game::{GameCommand, GameUpdate};
+use crate::lobby::{GameId, GameSettings, GameSummary, LobbyEvent, PlayerId, SessionToken};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+/// The current version of the protocol.
+///
+/// Clients announcing a different version during the handshake are rejected.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Identifies a request so that its reply can be correlated with it.
+///
+/// Ids are chosen by the client and only need to be unique within a single connection.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RequestId(pub u64);
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A message sent from a client to the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Starts the handshake. Must be the first message sent on a connection.
+    Hello {
+        /// The protocol version the client speaks
+        version: u32,
+        /// A free form description of the client
+        client: String,
+        /// Resumes the session with this token instead of starting a new one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session: Option<SessionToken>,
+        /// The sequence number of the last game update the client saw, used when resuming
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_sequence: Option<u64>,
+    },
+    /// A request that expects a reply with the same id
+    Request { id: RequestId, request: Request },
+    /// Keepalive probe, answered with a [`ServerMessage::Pong`]
+    Ping { nonce: u64 },
+    /// Answer to a [`ServerMessage::Ping`]
+    Pong { nonce: u64 },
+}
+
+/// A message sent from the server to a client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Completes the handshake
+    Welcome {
+        /// The protocol version the server speaks
+        version: u32,
+        /// A free form description of the server
+        server: String,
+        /// The id the server assigned to this client
+        player: PlayerId,
+        /// Whether a previous session was resumed
+        resumed: bool,
+    },
+    /// A successful reply to a [`ClientMessage::Request`]
+    Response { id: RequestId, response: Response },
+    /// Something went wrong, either with a specific request or with the connection as a whole
+    Error(ErrorFrame),
+    /// A change in the lobby
+    Lobby { event: LobbyEvent },
+    /// A change in the game the client is playing
+    Game { update: GameUpdate },
+    /// The game the client is playing as the player may see it, sent in place of updates when
+    /// the client missed too many of them to catch up
+    GameState { state: Box<GameView> },
+    /// Keepalive probe, answered with a [`ClientMessage::Pong`]
+    Ping { nonce: u64 },
+    /// Answer to a [`ClientMessage::Ping`]
+    Pong { nonce: u64 },
+}
+
+/// The body of a [`ClientMessage::Request`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Request {
+    /// Asks the server to send back the given message
+    Echo { message: String },
+    /// Lists every game visible in the lobby
+    ListGames,
+    /// Creates a new game, with the requesting player as its host
+    CreateGame {
+        name: String,
+        player_name: String,
+        settings: GameSettings,
+    },
+    /// Takes a seat in a game
+    JoinGame {
+        game: GameId,
+        player_name: String,
+        /// The seat to take, or the first empty seat if not set
+        #[serde(default)]
+        seat: Option<usize>,
+        /// Required for games that aren't open
+        #[serde(default)]
+        invite_code: Option<String>,
+    },
+    /// Leaves the game the requesting player is seated in
+    LeaveGame,
+    /// Starts the game the requesting player is hosting
+    StartGame,
+    /// Issues a command in the game the requesting player is playing
+    Command { command: GameCommand },
+    /// Gets the full state of the game the requesting player is playing
+    GetGameState,
+    /// Suggests how the requesting player could pay a cost, wasting the least
+    SuggestPayment { cost: u8, currency: Currency },
+    /// Lists the objectives the requesting player could score right now
+    QualifyingObjectives,
+}
+
+/// The body of a [`ServerMessage::Response`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Response {
+    /// Reply to [`Request::Echo`]
+    Echo { message: String },
+    /// Reply to [`Request::ListGames`]
+    Games { games: Vec<GameSummary> },
+    /// Reply to [`Request::CreateGame`]
+    GameCreated {
+        game: GameSummary,
+        /// The code other players need to join, if the game isn't open
+        invite_code: Option<String>,
+        session: SessionToken,
+    },
+    /// Reply to [`Request::JoinGame`]
+    Joined {
+        game: GameSummary,
+        session: SessionToken,
+    },
+    /// Reply to requests that change a single game
+    Game { game: GameSummary },
+    /// Reply to [`Request::LeaveGame`]
+    Left { game: GameId },
+    /// Reply to [`Request::Command`]. The resulting events are sent as a [`ServerMessage::Game`].
+    CommandApplied { sequence: u64 },
+    /// Reply to [`Request::GetGameState`], with what the player may see of the game
+    GameState { state: Box<GameView> },
+    /// Reply to [`Request::SuggestPayment`], without a payment if the player can't afford it
+    PaymentSuggested { payment: Option<Payment> },
+    /// Reply to [`Request::QualifyingObjectives`]
+    QualifyingObjectives { objectives: Vec<ObjectiveId> },
+}
+
+/// A structured error sent to a client
+#[derive(Debug, Clone, PartialEq, Error, Serialize, Deserialize)]
+#[error("{code}: {message}")]
+pub struct ErrorFrame {
+    /// The request that caused this error, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<RequestId>,
+    /// Machine readable error kind
+    pub code: ErrorCode,
+    /// Human readable explanation
+    pub message: String,
+    /// The rule of the game a rejected command broke, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub violation: Option<RuleViolation>,
+    /// Where the rule broken is explained, along with the violation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citation: Option<Citation>,
+}
+
+impl ErrorFrame {
+    /// Creates a new error frame that isn't tied to a request
+    pub fn new(code: ErrorCode, message: impl ToString) -> Self {
+        Self {
+            request_id: None,
+            code,
+            message: message.to_string(),
+            violation: None,
+            citation: None,
+        }
+    }
+
+    /// Attaches the rule a rejected command broke, explaining it in the message
+    pub fn with_violation(mut self, violation: RuleViolation) -> Self {
+        self.message = violation.explanation();
+        self.citation = Some(violation.rule().citation());
+        self.violation = Some(violation);
+        self
+    }
+
+    /// Ties this error to the given request
+    pub fn with_request_id(mut self, id: RequestId) -> Self {
+        self.request_id = Some(id);
+        self
+    }
+
+    /// Checks whether this error ends the connection
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self.code,
+            ErrorCode::UnsupportedVersion
+                | ErrorCode::HandshakeRequired
+                | ErrorCode::SessionReplaced
+        )
+    }
+}
+
+/// The kind of error contained in an [`ErrorFrame`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The client speaks a different protocol version
+    UnsupportedVersion,
+    /// The client sent something before completing the handshake
+    HandshakeRequired,
+    /// The frame could not be decoded
+    Malformed,
+    /// The request was valid but could not be carried out
+    Rejected,
+    /// The session to resume has expired or never existed
+    SessionExpired,
+    /// The request needs a session token, but none was sent
+    Unauthenticated,
+    /// The session was resumed by another connection
+    SessionReplaced,
+    /// The server failed unexpectedly
+    Internal,
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ErrorCode::UnsupportedVersion => "unsupported version",
+            ErrorCode::HandshakeRequired => "handshake required",
+            ErrorCode::Malformed => "malformed message",
+            ErrorCode::Rejected => "rejected",
+            ErrorCode::SessionExpired => "session expired",
+            ErrorCode::Unauthenticated => "unauthenticated",
+            ErrorCode::SessionReplaced => "session replaced",
+            ErrorCode::Internal => "internal error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// An error occurred while encoding or decoding a message
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct CodecError(#[from] serde_json::Error);
+
+/// Encodes a message as a JSON text frame
+pub fn encode<T: Serialize>(message: &T) -> Result<String, CodecError> {
+    Ok(serde_json::to_string(message)?)
+}
+
+/// Decodes a message from a JSON text frame
+pub fn decode<'de, T: Deserialize<'de>>(frame: &'de str) -> Result<T, CodecError> {
+    Ok(serde_json::from_str(frame)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::violations::RuleViolation;
+    use crate::protocol::{
+        decode, encode, ClientMessage, ErrorCode, ErrorFrame, Request, RequestId, ServerMessage,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn client_request_is_tagged() {
+        let message = ClientMessage::Request {
+            id: RequestId(7),
+            request: Request::Echo {
+                message: "hi".to_string(),
+            },
+        };
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "type": "request",
+                "id": 7,
+                "request": { "kind": "echo", "message": "hi" }
+            })
+        );
+    }
+
+    #[test]
+    fn error_frame_round_trip() {
+        let message = ServerMessage::Error(
+            ErrorFrame::new(ErrorCode::Malformed, "bad json").with_request_id(RequestId(3)),
+        );
+        let frame = encode(&message).unwrap();
+        let decoded: ServerMessage = decode(&frame).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    fn error_frame_carries_violation() {
+        let frame = ErrorFrame::new(ErrorCode::Rejected, "no")
+            .with_violation(RuleViolation::PlanetExhausted {
+                planet: "Mecatol Rex".to_string(),
+            })
+            .with_request_id(RequestId(4));
+        assert_eq!(
+            serde_json::to_value(ServerMessage::Error(frame)).unwrap(),
+            json!({
+                "type": "error",
+                "request_id": 4,
+                "code": "rejected",
+                "message": "Mecatol Rex is exhausted, so it can't be spent until it is readied (LRR 34 Exhausted)",
+                "violation": { "violation": "planet_exhausted", "planet": "Mecatol Rex" },
+                "citation": { "section": 34, "title": "Exhausted" }
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_message_is_rejected() {
+        assert!(decode::<ClientMessage>(r#"{"type": "launch_missiles"}"#).is_err());
+    }
+}
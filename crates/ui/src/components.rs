@@ -1,4 +1,5 @@
 //! Reusable happiness
 
 pub mod layout;
+pub mod lobby;
 pub mod surfaces;
@@ -0,0 +1,48 @@
+//! Contains the lobby
+
+use shared::lobby::{GameStatus, GameSummary};
+use yew::platform::spawn_local;
+use yew::{function_component, html, use_effect_with, use_state, Html};
+
+use crate::components::surfaces::Card;
+use crate::Request;
+
+/// Lists the games in the lobby, as the server describes them
+#[function_component]
+pub fn GameList() -> Html {
+    let games = use_state(Vec::<GameSummary>::new);
+    {
+        let games = games.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let response = Request::get("/games").send().await;
+                match response {
+                    Ok(response) => match response.json::<Vec<GameSummary>>().await {
+                        Ok(result) => games.set(result),
+                        Err(error) => log::error!("failed to read the games: {error}"),
+                    },
+                    Err(error) => log::error!("failed to list the games: {error}"),
+                }
+            });
+        });
+    }
+
+    html! {
+        <Card>
+            if games.is_empty() {
+                <p>{"No games yet"}</p>
+            }
+            {for games.iter().map(|game| html! {
+                <p key={game.id.to_string()}>
+                    {&game.name}{" ("}
+                    {game.player_count()}{"/"}{game.settings.player_limit}
+                    {match game.status {
+                        GameStatus::Waiting => ", waiting",
+                        GameStatus::InProgress => ", in progress",
+                    }}
+                    {")"}
+                </p>
+            })}
+        </Card>
+    }
+}
@@ -4,6 +4,7 @@ use yew::platform::spawn_local;
 use yew::{classes, function_component, html, use_state, Callback, Html};
 
 use happiness::{system::StylingBox, CssBaseline, ThemeProvider};
+use ui::components::lobby::GameList;
 use ui::components::surfaces::*;
 use ui::Request;
 #[function_component]
@@ -38,6 +39,7 @@ fn App() -> Html {
                         <Sheet>
                             <button onclick={handle_click}>{"Generate new Id!"}</button>
                         </Sheet>
+                        <GameList />
                     </Stack>
                 </Card>
             </StylingBox>